@@ -1,9 +1,22 @@
 use anyhow::{Result, anyhow};
 use clap::{Args, Parser, Subcommand};
 use dbkp_core::{
-    databases::{ConnectionType, DatabaseConfig},
-    storage::provider::{LocalStorageConfig, S3StorageConfig, StorageConfig},
+    checksum::ChecksumAlgorithm,
+    compression::CompressionFormat,
+    databases::{
+        ConnectionType, DatabaseConfig, DumpContent, DumpFilters, DumpFormat,
+        ssh::{SshAuth, SshConfig},
+    },
+    encryption::EncryptionConfig,
+    masking::MaskingConfig,
+    retention::RetentionPolicy,
+    storage::provider::{
+        LocalStorageConfig, S3StorageConfig, SftpStorageConfig, StorageConfig, StorageCredentials,
+        WebDavStorageConfig,
+    },
 };
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 
 mod tests;
 
@@ -19,8 +32,12 @@ pub enum Commands {
     TUI,
     Backup(BackupArgs),
     Restore(RestoreArgs),
+    Copy(CopyArgs),
     List(ListArgs),
     Cleanup(CleanupArgs),
+    Verify(VerifyArgs),
+    Run(RunArgs),
+    Daemon(DaemonArgs),
 }
 
 #[derive(Args, Debug)]
@@ -33,6 +50,54 @@ pub struct BackupArgs {
 
     #[arg(short, long, help = "Retention period (e.g. '30d', '1w', '6m')")]
     pub retention: Option<String>,
+
+    #[command(flatten)]
+    pub retention_policy: RetentionPolicyArgs,
+
+    #[arg(
+        long,
+        help = "Compression format ('gzip', 'zlib', 'deflate', 'zstd', 'xz', 'bzip2' or 'none')"
+    )]
+    pub compression: Option<CompressionFormat>,
+
+    #[arg(
+        long,
+        help = "Compression level, within the range supported by the format"
+    )]
+    pub compression_level: Option<u32>,
+
+    #[arg(long, help = "Threads compressing zstd backups [default: 1]")]
+    pub compression_threads: Option<u32>,
+
+    #[arg(long, help = "Checksum algorithm ('sha256' or 'blake3')")]
+    pub checksum: Option<ChecksumAlgorithm>,
+
+    #[arg(
+        long,
+        help = "PostgreSQL dump format ('plain', 'custom' or 'directory'), defaults to 'plain'"
+    )]
+    pub dump_format: Option<DumpFormat>,
+
+    #[arg(
+        long,
+        help = "Parallel dump jobs, only for the 'directory' dump format"
+    )]
+    pub jobs: Option<u16>,
+
+    #[command(flatten)]
+    pub filters: FilterArgs,
+
+    #[command(flatten)]
+    pub masking: MaskingArgs,
+
+    #[command(flatten)]
+    pub encryption: EncryptionArgs,
+
+    #[arg(
+        long,
+        help = "node_exporter textfile to record the backup metrics in, e.g. /var/lib/node_exporter/textfile/dbkp.prom"
+    )]
+    pub metrics_textfile: Option<PathBuf>,
 }
 
 #[derive(Args, Debug)]
@@ -43,9 +108,30 @@ pub struct RestoreArgs {
     #[arg(long)]
     pub drop_database: bool,
 
+    #[arg(
+        long,
+        help = "Parallel restore jobs for PostgreSQL 'custom' and 'directory' dumps"
+    )]
+    pub jobs: Option<u16>,
+
     #[arg(long)]
     pub latest: bool,
 
+    #[arg(
+        long,
+        help = "Database to restore into instead of --database, created when missing"
+    )]
+    pub target_database: Option<String>,
+
+    #[command(flatten)]
+    pub target_server: TargetServerArgs,
+
+    #[command(flatten)]
+    pub masking: MaskingArgs,
+
+    #[command(flatten)]
+    pub encryption: EncryptionArgs,
+
     #[command(flatten)]
     pub database_config: DatabaseArgs,
 
@@ -53,6 +139,62 @@ pub struct RestoreArgs {
     pub storage_config: StorageArgs,
 }
 
+#[derive(Args, Debug)]
+pub struct CopyArgs {
+    #[command(flatten)]
+    pub database_config: DatabaseArgs,
+
+    #[command(flatten)]
+    pub target: TargetArgs,
+
+    #[arg(long, help = "Drop the target database before the copy")]
+    pub drop_database: bool,
+
+    #[command(flatten)]
+    pub filters: FilterArgs,
+
+    #[command(flatten)]
+    pub masking: MaskingArgs,
+
+    #[arg(
+        long,
+        help = "Compression of the stored backup ('gzip', 'zlib', 'deflate', 'zstd', 'xz', 'bzip2' or 'none')"
+    )]
+    pub compression: Option<CompressionFormat>,
+
+    #[command(flatten)]
+    pub encryption: EncryptionArgs,
+
+    // The copy is also stored as a backup when a storage is given
+    #[command(flatten)]
+    pub storage_config: StorageArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct TargetArgs {
+    #[arg(long, help = "Database to copy into, created when missing")]
+    pub target_database: String,
+
+    #[command(flatten)]
+    pub server: TargetServerArgs,
+}
+
+/// Another server to restore or copy into, the one of the database flags when not set.
+#[derive(Args, Debug)]
+pub struct TargetServerArgs {
+    #[arg(long, help = "Server to restore or copy into [default: --host]")]
+    pub target_host: Option<String>,
+
+    #[arg(long, help = "[default: --port]")]
+    pub target_port: Option<u16>,
+
+    #[arg(long, help = "[default: --username]")]
+    pub target_username: Option<String>,
+
+    #[arg(long, env = "DBKP_TARGET_PASSWORD", help = "[default: --password]")]
+    pub target_password: Option<String>,
+}
+
 #[derive(Args, Debug)]
 pub struct ListArgs {
     #[arg(short, long)]
@@ -71,7 +213,10 @@ pub struct ListArgs {
 #[derive(Args, Debug)]
 pub struct CleanupArgs {
     #[arg(short, long, help = "Retention period (e.g. '30d', '1w', '6m')")]
-    pub retention: String,
+    pub retention: Option<String>,
+
+    #[command(flatten)]
+    pub retention_policy: RetentionPolicyArgs,
 
     #[arg(
         long,
@@ -86,24 +231,113 @@ pub struct CleanupArgs {
     pub storage: StorageArgs,
 }
 
+#[derive(Args, Debug)]
+pub struct VerifyArgs {
+    #[arg(long, help = "Name of the backup to verify")]
+    pub name: String,
+
+    #[arg(
+        long,
+        help = "Restore the backup into a scratch database and compare row counts"
+    )]
+    pub restore: bool,
+
+    #[command(flatten)]
+    pub encryption: EncryptionArgs,
+
+    #[command(flatten)]
+    pub database_config: DatabaseArgs,
+
+    #[command(flatten)]
+    pub storage: StorageArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct RunArgs {
+    #[arg(help = "TOML file describing the backup jobs")]
+    pub file: PathBuf,
+
+    #[arg(
+        long = "job",
+        help = "Only run the job with this name (repeatable), every job by default"
+    )]
+    pub jobs: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct DaemonArgs {
+    #[arg(help = "TOML file describing the backup jobs and their schedules")]
+    pub file: PathBuf,
+
+    #[arg(
+        long = "job",
+        help = "Only schedule the job with this name (repeatable), every scheduled job by default"
+    )]
+    pub jobs: Vec<String>,
+
+    #[arg(
+        long,
+        help = "File keeping the last run of each job [default: <data dir>/dbkp/daemon_state.json]"
+    )]
+    pub state: Option<PathBuf>,
+
+    #[arg(long, help = "Print the last and next run of each job and exit")]
+    pub status: bool,
+
+    #[arg(
+        long,
+        help = "Address to serve Prometheus metrics on, e.g. 0.0.0.0:9187"
+    )]
+    pub metrics_listen: Option<SocketAddr>,
+}
+
 #[derive(Args, Clone, Debug)]
 pub struct SshArgs {
-    #[arg(long)]
+    #[arg(long, help = "SSH bastion to reach the database through")]
     pub ssh_host: Option<String>,
 
-    #[arg(long)]
-    ssh_username: Option<String>,
+    #[arg(long, help = "SSH port of the bastion [default: 22]")]
+    pub ssh_port: Option<u16>,
 
     #[arg(long)]
-    ssh_key_path: Option<String>,
+    pub ssh_username: Option<String>,
+
+    #[arg(
+        long,
+        help = "Private key used to authenticate, ssh-agent is used when not set"
+    )]
+    pub ssh_key_path: Option<String>,
+
+    #[arg(
+        long,
+        env = "DBKP_SSH_PASSPHRASE",
+        hide_env_values = true,
+        help = "Passphrase of the SSH private key"
+    )]
+    pub ssh_key_passphrase: Option<String>,
+
+    #[arg(
+        long,
+        help = "Add the key of a bastion missing from ~/.ssh/known_hosts instead of rejecting it"
+    )]
+    pub ssh_accept_new_host_key: bool,
 }
 
 #[derive(Args, Clone, Debug)]
 pub struct DatabaseArgs {
-    #[arg(long, help = "Database type ('postgresql' or 'mysql')")]
+    #[arg(
+        long = "db",
+        help = "Saved database configuration, by name or id, the other database flags override its fields"
+    )]
+    pub saved_database: Option<String>,
+
+    #[arg(
+        long,
+        help = "Database type ('postgresql', 'mysql', 'mariadb', 'sqlite', 'mongodb' or 'redis')"
+    )]
     pub database_type: Option<String>,
 
-    #[arg(long)]
+    #[arg(long, help = "Database name, or the database file path for SQLite")]
     pub database: Option<String>,
 
     #[arg(long)]
@@ -122,12 +356,120 @@ pub struct DatabaseArgs {
     pub ssh: Option<SshArgs>,
 }
 
+#[derive(Args, Clone, Debug)]
+pub struct RetentionPolicyArgs {
+    #[arg(long, help = "Keep the N most recent backups")]
+    pub keep_last: Option<u32>,
+
+    #[arg(long, help = "Keep the newest backup of each of the last N days")]
+    pub keep_daily: Option<u32>,
+
+    #[arg(long, help = "Keep the newest backup of each of the last N weeks")]
+    pub keep_weekly: Option<u32>,
+
+    #[arg(long, help = "Keep the newest backup of each of the last N months")]
+    pub keep_monthly: Option<u32>,
+
+    #[arg(long, help = "Keep the newest backup of each of the last N years")]
+    pub keep_yearly: Option<u32>,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct EncryptionArgs {
+    #[arg(
+        long,
+        env = "DBKP_PASSPHRASE",
+        hide_env_values = true,
+        help = "Passphrase used to encrypt or decrypt backups"
+    )]
+    pub passphrase: Option<String>,
+
+    #[arg(
+        long = "recipient",
+        env = "DBKP_RECIPIENTS",
+        value_delimiter = ',',
+        help = "age public key to encrypt backups for (repeatable)"
+    )]
+    pub recipients: Vec<String>,
+
+    #[arg(
+        long = "identity",
+        env = "DBKP_IDENTITIES",
+        hide_env_values = true,
+        value_delimiter = ',',
+        help = "age secret key or identity file used to decrypt backups (repeatable)"
+    )]
+    pub identities: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct FilterArgs {
+    #[arg(
+        long = "include-table",
+        help = "Only dump this table, a pattern for PostgreSQL (repeatable)"
+    )]
+    pub include_tables: Vec<String>,
+
+    #[arg(long = "exclude-table", help = "Don't dump this table (repeatable)")]
+    pub exclude_tables: Vec<String>,
+
+    #[arg(
+        long = "include-schema",
+        help = "Only dump this PostgreSQL schema (repeatable)"
+    )]
+    pub include_schemas: Vec<String>,
+
+    #[arg(
+        long = "exclude-schema",
+        help = "Don't dump this PostgreSQL schema (repeatable)"
+    )]
+    pub exclude_schemas: Vec<String>,
+
+    #[arg(
+        long = "exclude-table-data",
+        help = "Dump the definition of this table without its rows (repeatable)"
+    )]
+    pub exclude_table_data: Vec<String>,
+
+    #[arg(long, conflicts_with = "data_only", help = "Only dump the definitions")]
+    pub schema_only: bool,
+
+    #[arg(long, help = "Only dump the rows")]
+    pub data_only: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct MaskingArgs {
+    #[arg(
+        long,
+        help = "JSON file of the column masking rules, for plain SQL dumps"
+    )]
+    pub masking_config: Option<String>,
+
+    #[arg(
+        long,
+        env = "DBKP_MASKING_SEED",
+        hide_env_values = true,
+        help = "Seed of the masked values, overrides the one of the masking config"
+    )]
+    pub masking_seed: Option<String>,
+}
+
 #[derive(Args, Debug)]
 pub struct StorageArgs {
-    #[arg(long, default_value = "local")]
+    #[arg(
+        long = "storage",
+        help = "Saved storage configuration, by name or id, the other storage flags override its fields"
+    )]
+    pub saved_storage: Option<String>,
+
+    #[arg(
+        long,
+        help = "Storage type ('local', 's3', 'sftp' or 'webdav') [default: local]"
+    )]
     pub storage_type: Option<String>,
 
-    #[arg(long, default_value = "default")]
+    #[arg(long, help = "[default: default]")]
     pub storage_name: Option<String>,
 
     #[arg(long)]
@@ -136,7 +478,7 @@ pub struct StorageArgs {
     #[arg(long, env = "S3_BUCKET")]
     pub bucket: Option<String>,
 
-    #[arg(long, env = "S3_REGION", default_value = "us-east-1")]
+    #[arg(long, env = "S3_REGION", help = "[default: us-east-1]")]
     pub region: Option<String>,
 
     #[arg(long, env = "S3_ENDPOINT")]
@@ -147,6 +489,59 @@ pub struct StorageArgs {
 
     #[arg(long, env = "S3_SECRET_ACCESS_KEY", env = "S3_SECRET_KEY")]
     pub secret_key: Option<String>,
+
+    #[command(flatten)]
+    pub sftp: Option<SftpArgs>,
+
+    #[command(flatten)]
+    pub webdav: Option<WebDavArgs>,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct WebDavArgs {
+    #[arg(
+        long,
+        help = "WebDAV endpoint, e.g. https://cloud.example.com/remote.php/dav/files/<user>"
+    )]
+    pub webdav_endpoint: Option<String>,
+
+    #[arg(long)]
+    pub webdav_username: Option<String>,
+
+    #[arg(long, env = "DBKP_WEBDAV_PASSWORD", hide_env_values = true)]
+    pub webdav_password: Option<String>,
+}
+
+#[derive(Args, Clone, Debug)]
+pub struct SftpArgs {
+    #[arg(long, help = "SFTP server to store backups on")]
+    pub sftp_host: Option<String>,
+
+    #[arg(long, help = "SFTP port [default: 22]")]
+    pub sftp_port: Option<u16>,
+
+    #[arg(long)]
+    pub sftp_username: Option<String>,
+
+    #[arg(long, env = "DBKP_SFTP_PASSWORD", hide_env_values = true)]
+    pub sftp_password: Option<String>,
+
+    #[arg(long, help = "Private key used to authenticate instead of a password")]
+    pub sftp_key_path: Option<String>,
+
+    #[arg(
+        long,
+        env = "DBKP_SFTP_PASSPHRASE",
+        hide_env_values = true,
+        help = "Passphrase of the SFTP private key"
+    )]
+    pub sftp_key_passphrase: Option<String>,
+
+    #[arg(
+        long,
+        help = "Add the key of an SFTP server missing from ~/.ssh/known_hosts instead of rejecting it"
+    )]
+    pub sftp_accept_new_host_key: bool,
 }
 
 pub fn parse_retention(retention: &str) -> Result<u64> {
@@ -172,6 +567,131 @@ pub fn parse_retention(retention: &str) -> Result<u64> {
     }
 }
 
+pub fn retention_policy_from_cli(args: &RetentionPolicyArgs) -> Option<RetentionPolicy> {
+    if args.keep_last.is_none()
+        && args.keep_daily.is_none()
+        && args.keep_weekly.is_none()
+        && args.keep_monthly.is_none()
+        && args.keep_yearly.is_none()
+    {
+        return None;
+    }
+
+    Some(RetentionPolicy {
+        keep_last: args.keep_last.unwrap_or(0),
+        keep_daily: args.keep_daily.unwrap_or(0),
+        keep_weekly: args.keep_weekly.unwrap_or(0),
+        keep_monthly: args.keep_monthly.unwrap_or(0),
+        keep_yearly: args.keep_yearly.unwrap_or(0),
+    })
+}
+
+pub fn encryption_from_cli(args: &EncryptionArgs) -> Option<EncryptionConfig> {
+    let encryption_config = EncryptionConfig {
+        passphrase: args.passphrase.clone(),
+        recipients: args.recipients.clone(),
+        identities: args.identities.clone(),
+    };
+
+    if encryption_config.is_empty() {
+        None
+    } else {
+        Some(encryption_config)
+    }
+}
+
+/// Overrides a saved encryption configuration with the flags that were given. A passphrase or
+/// recipients replace the saved encryption key, identities the saved identities.
+pub fn override_encryption_config(config: &mut EncryptionConfig, args: &EncryptionArgs) {
+    if args.passphrase.is_some() || !args.recipients.is_empty() {
+        config.passphrase = args.passphrase.clone();
+        config.recipients = args.recipients.clone();
+    }
+    if !args.identities.is_empty() {
+        config.identities = args.identities.clone();
+    }
+}
+
+/// Server to copy into, `None` when the copy stays on the source server. Its database is the
+/// source one, the target database is created through it.
+pub fn target_from_cli(source: &DatabaseConfig, args: &TargetServerArgs) -> Option<DatabaseConfig> {
+    if args.target_host.is_none()
+        && args.target_port.is_none()
+        && args.target_username.is_none()
+        && args.target_password.is_none()
+    {
+        return None;
+    }
+
+    let mut target = source.clone();
+
+    if let Some(host) = &args.target_host {
+        target.host = host.clone();
+        // The tunnel only reaches the source server
+        target.ssh = None;
+    }
+    if let Some(port) = args.target_port {
+        target.port = port;
+    }
+    if let Some(username) = &args.target_username {
+        target.username = username.clone();
+    }
+    if let Some(password) = &args.target_password {
+        target.password = Some(password.clone());
+    }
+
+    Some(target)
+}
+
+pub fn filters_from_cli(args: &FilterArgs) -> Option<DumpFilters> {
+    let content = if args.schema_only {
+        DumpContent::SchemaOnly
+    } else if args.data_only {
+        DumpContent::DataOnly
+    } else {
+        DumpContent::All
+    };
+
+    let filters = DumpFilters {
+        include_tables: args.include_tables.clone(),
+        exclude_tables: args.exclude_tables.clone(),
+        include_schemas: args.include_schemas.clone(),
+        exclude_schemas: args.exclude_schemas.clone(),
+        exclude_table_data: args.exclude_table_data.clone(),
+        content,
+    };
+
+    if filters.is_empty() {
+        None
+    } else {
+        Some(filters)
+    }
+}
+
+pub fn masking_from_cli(args: &MaskingArgs) -> Result<Option<MaskingConfig>> {
+    let path = match &args.masking_config {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+
+    let mut masking_config = MaskingConfig::from_file(Path::new(path))?;
+    if args.masking_seed.is_some() {
+        masking_config.seed = args.masking_seed.clone();
+    }
+    if masking_config
+        .seed
+        .as_deref()
+        .unwrap_or_default()
+        .is_empty()
+    {
+        return Err(anyhow!(
+            "The masking config has no seed, add one or use --masking-seed"
+        ));
+    }
+
+    Ok(Some(masking_config))
+}
+
 pub fn storage_from_cli(args: &StorageArgs) -> Result<StorageConfig> {
     let default_storage_type = "local".to_string();
     let storage_type = args.storage_type.as_ref().unwrap_or(&default_storage_type);
@@ -197,7 +717,7 @@ pub fn storage_from_cli(args: &StorageArgs) -> Result<StorageConfig> {
             let region = args
                 .region
                 .clone()
-                .ok_or_else(|| anyhow!("S3 storage requires --region parameter"))?;
+                .unwrap_or_else(|| "us-east-1".to_string());
 
             Ok(StorageConfig::S3(S3StorageConfig {
                 name: args
@@ -227,10 +747,313 @@ pub fn storage_from_cli(args: &StorageArgs) -> Result<StorageConfig> {
                 .clone()
                 .ok_or_else(|| anyhow!("Location is required"))?,
         })),
+        "sftp" => {
+            let sftp_args = args
+                .sftp
+                .as_ref()
+                .ok_or_else(|| anyhow!("SFTP storage requires --sftp-host parameter"))?;
+            let host = sftp_args
+                .sftp_host
+                .clone()
+                .ok_or_else(|| anyhow!("SFTP storage requires --sftp-host parameter"))?;
+            let username = sftp_args
+                .sftp_username
+                .clone()
+                .ok_or_else(|| anyhow!("SFTP storage requires --sftp-username parameter"))?;
+
+            let credentials = match (&sftp_args.sftp_key_path, &sftp_args.sftp_password) {
+                (Some(key_path), _) => StorageCredentials::PrivateKey {
+                    username,
+                    key_path: key_path.clone(),
+                    passphrase: sftp_args.sftp_key_passphrase.clone(),
+                },
+                (None, Some(password)) => StorageCredentials::Basic {
+                    username,
+                    password: password.clone(),
+                },
+                (None, None) => {
+                    return Err(anyhow!(
+                        "SFTP storage requires --sftp-password or --sftp-key-path parameter"
+                    ));
+                }
+            };
+
+            Ok(StorageConfig::Sftp(SftpStorageConfig {
+                name: args
+                    .storage_name
+                    .clone()
+                    .unwrap_or_else(|| "default".to_string()),
+                id: "".into(),
+                host,
+                port: sftp_args.sftp_port.unwrap_or(22),
+                credentials,
+                location: args
+                    .location
+                    .clone()
+                    .ok_or_else(|| anyhow!("Location is required"))?,
+                accept_new_host_key: sftp_args.sftp_accept_new_host_key,
+            }))
+        }
+        "webdav" => {
+            let webdav_args = args
+                .webdav
+                .as_ref()
+                .ok_or_else(|| anyhow!("WebDAV storage requires --webdav-endpoint parameter"))?;
+            let endpoint = webdav_args
+                .webdav_endpoint
+                .clone()
+                .ok_or_else(|| anyhow!("WebDAV storage requires --webdav-endpoint parameter"))?;
+
+            let credentials = match (&webdav_args.webdav_username, &webdav_args.webdav_password) {
+                (Some(username), Some(password)) => StorageCredentials::Basic {
+                    username: username.clone(),
+                    password: password.clone(),
+                },
+                (None, None) => StorageCredentials::None,
+                (Some(_), None) => {
+                    return Err(anyhow!(
+                        "WebDAV storage requires --webdav-password parameter"
+                    ));
+                }
+                (None, Some(_)) => {
+                    return Err(anyhow!(
+                        "WebDAV storage requires --webdav-username parameter"
+                    ));
+                }
+            };
+
+            Ok(StorageConfig::WebDav(WebDavStorageConfig {
+                name: args
+                    .storage_name
+                    .clone()
+                    .unwrap_or_else(|| "default".to_string()),
+                id: "".into(),
+                endpoint,
+                credentials,
+                location: args
+                    .location
+                    .clone()
+                    .ok_or_else(|| anyhow!("Location is required"))?,
+            }))
+        }
         _ => Err(anyhow!("Unsupported storage type: {}", storage_type)),
     }
 }
 
+pub fn ssh_config_from_cli(args: &SshArgs) -> Result<Option<SshConfig>> {
+    let host = match &args.ssh_host {
+        Some(host) => host,
+        None => return Ok(None),
+    };
+
+    let username = args
+        .ssh_username
+        .as_ref()
+        .ok_or_else(|| anyhow!("SSH username is required"))?;
+
+    let auth = match &args.ssh_key_path {
+        Some(key_path) => SshAuth::PrivateKey {
+            key_path: key_path.clone(),
+            passphrase: args.ssh_key_passphrase.clone(),
+        },
+        None => SshAuth::Agent,
+    };
+
+    Ok(Some(SshConfig {
+        host: host.clone(),
+        port: args.ssh_port.unwrap_or(22),
+        username: username.clone(),
+        auth,
+        accept_new_host_key: args.ssh_accept_new_host_key,
+    }))
+}
+
+/// Overrides the fields of a saved database configuration with the flags that were given.
+pub fn override_database_config(config: &mut DatabaseConfig, args: &DatabaseArgs) -> Result<()> {
+    if let Some(database_type) = &args.database_type {
+        let connection_type = match database_type.as_str() {
+            "postgresql" => ConnectionType::PostgreSql,
+            "mysql" => ConnectionType::MySql,
+            "mariadb" => ConnectionType::MariaDB,
+            "sqlite" => ConnectionType::Sqlite,
+            "mongodb" => ConnectionType::MongoDb,
+            "redis" => ConnectionType::Redis,
+            _ => return Err(anyhow!("Unsupported database type: {}", database_type)),
+        };
+
+        if connection_type != config.connection_type {
+            return Err(anyhow!(
+                "--database-type {} doesn't match the saved {:?} configuration '{}'",
+                database_type,
+                config.connection_type,
+                config.name
+            ));
+        }
+    }
+
+    if let Some(database) = &args.database {
+        config.database = database.clone();
+    }
+    if let Some(host) = &args.host {
+        config.host = host.clone();
+    }
+    if let Some(port) = args.port {
+        config.port = port;
+    }
+    if let Some(username) = &args.username {
+        config.username = username.clone();
+    }
+    if let Some(password) = &args.password {
+        config.password = Some(password.clone());
+    }
+    if let Some(ssh_args) = &args.ssh {
+        match ssh_config_from_cli(ssh_args)? {
+            Some(ssh_config) => config.ssh = Some(ssh_config),
+            None => {
+                if let Some(ssh_config) = &mut config.ssh {
+                    ssh_config.accept_new_host_key |= ssh_args.ssh_accept_new_host_key;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Overrides the fields of a saved storage configuration with the flags that were given.
+pub fn override_storage_config(config: &mut StorageConfig, args: &StorageArgs) -> Result<()> {
+    let storage_type = match config {
+        StorageConfig::Local(_) => "local",
+        StorageConfig::S3(_) => "s3",
+        StorageConfig::Sftp(_) => "sftp",
+        StorageConfig::WebDav(_) => "webdav",
+    };
+    if let Some(requested) = &args.storage_type
+        && requested != storage_type
+    {
+        return Err(anyhow!(
+            "--storage-type {} doesn't match the saved {} storage",
+            requested,
+            storage_type
+        ));
+    }
+
+    let (name, location) = match config {
+        StorageConfig::Local(config) => (&mut config.name, &mut config.location),
+        StorageConfig::S3(config) => (&mut config.name, &mut config.location),
+        StorageConfig::Sftp(config) => (&mut config.name, &mut config.location),
+        StorageConfig::WebDav(config) => (&mut config.name, &mut config.location),
+    };
+    if let Some(storage_name) = &args.storage_name {
+        *name = storage_name.clone();
+    }
+    if let Some(new_location) = &args.location {
+        *location = new_location.clone();
+    }
+
+    match config {
+        StorageConfig::Local(_) => {}
+        StorageConfig::S3(config) => {
+            if let Some(bucket) = &args.bucket {
+                config.bucket = bucket.clone();
+            }
+            if let Some(region) = &args.region {
+                config.region = region.clone();
+            }
+            if let Some(endpoint) = &args.endpoint {
+                config.endpoint = Some(endpoint.clone());
+            }
+            if let Some(access_key) = &args.access_key {
+                config.access_key = access_key.clone();
+            }
+            if let Some(secret_key) = &args.secret_key {
+                config.secret_key = secret_key.clone();
+            }
+        }
+        StorageConfig::Sftp(config) => {
+            if let Some(sftp_args) = &args.sftp {
+                if let Some(host) = &sftp_args.sftp_host {
+                    config.host = host.clone();
+                }
+                if let Some(port) = sftp_args.sftp_port {
+                    config.port = port;
+                }
+                config.accept_new_host_key |= sftp_args.sftp_accept_new_host_key;
+                config.credentials = override_credentials(
+                    &config.credentials,
+                    sftp_args.sftp_username.as_ref(),
+                    sftp_args.sftp_password.as_ref(),
+                    sftp_args.sftp_key_path.as_ref(),
+                    sftp_args.sftp_key_passphrase.as_ref(),
+                );
+            }
+        }
+        StorageConfig::WebDav(config) => {
+            if let Some(webdav_args) = &args.webdav {
+                if let Some(endpoint) = &webdav_args.webdav_endpoint {
+                    config.endpoint = endpoint.clone();
+                }
+                config.credentials = override_credentials(
+                    &config.credentials,
+                    webdav_args.webdav_username.as_ref(),
+                    webdav_args.webdav_password.as_ref(),
+                    None,
+                    None,
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// A key path switches to key authentication and a password to password authentication, a
+/// username alone keeps the saved authentication.
+fn override_credentials(
+    credentials: &StorageCredentials,
+    username: Option<&String>,
+    password: Option<&String>,
+    key_path: Option<&String>,
+    passphrase: Option<&String>,
+) -> StorageCredentials {
+    let saved_username = match credentials {
+        StorageCredentials::Basic { username, .. }
+        | StorageCredentials::PrivateKey { username, .. } => Some(username),
+        _ => None,
+    };
+    let username = username.or(saved_username).cloned().unwrap_or_default();
+
+    match (key_path, password, credentials) {
+        (Some(key_path), _, _) => StorageCredentials::PrivateKey {
+            username,
+            key_path: key_path.clone(),
+            passphrase: passphrase.cloned(),
+        },
+        (None, Some(password), _) => StorageCredentials::Basic {
+            username,
+            password: password.clone(),
+        },
+        (None, None, StorageCredentials::Basic { password, .. }) => StorageCredentials::Basic {
+            username,
+            password: password.clone(),
+        },
+        (
+            None,
+            None,
+            StorageCredentials::PrivateKey {
+                key_path,
+                passphrase: saved_passphrase,
+                ..
+            },
+        ) => StorageCredentials::PrivateKey {
+            username,
+            key_path: key_path.clone(),
+            passphrase: passphrase.or(saved_passphrase.as_ref()).cloned(),
+        },
+        (None, None, credentials) => credentials.clone(),
+    }
+}
+
 pub fn database_config_from_cli(args: &DatabaseArgs) -> Result<DatabaseConfig> {
     let database_type = args
         .database_type
@@ -240,15 +1063,43 @@ pub fn database_config_from_cli(args: &DatabaseArgs) -> Result<DatabaseConfig> {
         .database
         .as_ref()
         .ok_or_else(|| anyhow!("Database name is required"))?;
+
+    // SQLite databases are local files, there is no server to connect to
+    if database_type == "sqlite" {
+        let name = Path::new(database)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| database.clone());
+
+        return Ok(DatabaseConfig {
+            connection_type: ConnectionType::Sqlite,
+            database: database.clone(),
+            id: "".into(),
+            name,
+            host: "".into(),
+            port: 0,
+            username: "".into(),
+            password: None,
+            ssh: None,
+        });
+    }
+
     let host = args
         .host
         .as_ref()
         .ok_or_else(|| anyhow!("Host is required"))?;
     let port = args.port.ok_or_else(|| anyhow!("Port is required"))?;
-    let username = args
-        .username
-        .as_ref()
-        .ok_or_else(|| anyhow!("Username is required"))?;
+    // Redis servers without ACL users only check the password
+    let username = match (&args.username, database_type.as_str()) {
+        (Some(username), _) => username,
+        (None, "redis") => &String::new(),
+        (None, _) => return Err(anyhow!("Username is required")),
+    };
+
+    let ssh_config = match &args.ssh {
+        Some(ssh_args) => ssh_config_from_cli(ssh_args)?,
+        None => None,
+    };
 
     match database_type.as_str() {
         "postgresql" => Ok(DatabaseConfig {
@@ -260,6 +1111,7 @@ pub fn database_config_from_cli(args: &DatabaseArgs) -> Result<DatabaseConfig> {
             port,
             username: username.clone(),
             password: args.password.clone(),
+            ssh: ssh_config.clone(),
         }),
         "mysql" => Ok(DatabaseConfig {
             connection_type: ConnectionType::MySql,
@@ -270,6 +1122,40 @@ pub fn database_config_from_cli(args: &DatabaseArgs) -> Result<DatabaseConfig> {
             port,
             username: username.clone(),
             password: args.password.clone(),
+            ssh: ssh_config.clone(),
+        }),
+        "mariadb" => Ok(DatabaseConfig {
+            connection_type: ConnectionType::MariaDB,
+            database: database.clone(),
+            id: "".into(),
+            name: database.clone(),
+            host: host.clone(),
+            port,
+            username: username.clone(),
+            password: args.password.clone(),
+            ssh: ssh_config.clone(),
+        }),
+        "mongodb" => Ok(DatabaseConfig {
+            connection_type: ConnectionType::MongoDb,
+            database: database.clone(),
+            id: "".into(),
+            name: database.clone(),
+            host: host.clone(),
+            port,
+            username: username.clone(),
+            password: args.password.clone(),
+            ssh: ssh_config.clone(),
+        }),
+        "redis" => Ok(DatabaseConfig {
+            connection_type: ConnectionType::Redis,
+            database: database.clone(),
+            id: "".into(),
+            name: database.clone(),
+            host: host.clone(),
+            port,
+            username: username.clone(),
+            password: args.password.clone(),
+            ssh: ssh_config.clone(),
         }),
         _ => Err(anyhow!("Unsupported database type: {}", database_type)),
     }
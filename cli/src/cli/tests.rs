@@ -1,14 +1,22 @@
 #[cfg(test)]
 mod cli_test {
-    use dbkp_core::databases::ConnectionType;
+    use dbkp_core::{
+        databases::{ConnectionType, DumpContent, ssh::SshAuth},
+        encryption::EncryptionConfig,
+        storage::provider::{SftpStorageConfig, StorageConfig, StorageCredentials},
+    };
 
     use crate::cli::{
-        database_config_from_cli, storage_from_cli, DatabaseArgs, SshArgs, StorageArgs,
+        DatabaseArgs, EncryptionArgs, FilterArgs, MaskingArgs, RetentionPolicyArgs, SftpArgs,
+        SshArgs, StorageArgs, TargetServerArgs, WebDavArgs, database_config_from_cli,
+        filters_from_cli, masking_from_cli, override_database_config, override_encryption_config,
+        override_storage_config, retention_policy_from_cli, storage_from_cli, target_from_cli,
     };
 
     #[test]
     fn test_01_parse_backup_command() {
         let database_args = DatabaseArgs {
+            saved_database: None,
             database_type: Some("postgresql".into()),
             database: Some("test".into()),
             host: Some("localhost".into()),
@@ -17,8 +25,11 @@ mod cli_test {
             password: Some("password".into()),
             ssh: Some(SshArgs {
                 ssh_host: Some("ssh_host".into()),
+                ssh_port: None,
                 ssh_username: Some("ssh_username".into()),
                 ssh_key_path: Some("ssh_key_path".into()),
+                ssh_key_passphrase: Some("ssh_key_passphrase".into()),
+                ssh_accept_new_host_key: false,
             }),
         };
 
@@ -31,11 +42,24 @@ mod cli_test {
         assert_eq!(database_config.port, 5432);
         assert_eq!(database_config.username, "username");
         assert_eq!(database_config.password.clone().unwrap(), "password");
+
+        let ssh_config = database_config.ssh.expect("Missing SSH config");
+        assert_eq!(ssh_config.host, "ssh_host");
+        assert_eq!(ssh_config.port, 22);
+        assert_eq!(ssh_config.username, "ssh_username");
+        assert_eq!(
+            ssh_config.auth,
+            SshAuth::PrivateKey {
+                key_path: "ssh_key_path".into(),
+                passphrase: Some("ssh_key_passphrase".into()),
+            }
+        );
     }
 
     #[test]
     fn test_02_parse_storage_config() {
         let storage_args = StorageArgs {
+            saved_storage: None,
             storage_type: Some("s3".into()),
             storage_name: Some("test".into()),
             location: Some("".into()),
@@ -44,10 +68,476 @@ mod cli_test {
             endpoint: Some("endpoint".into()),
             access_key: Some("access_key".into()),
             secret_key: Some("access_key".into()),
+            sftp: None,
+            webdav: None,
         };
 
         let storage_config = storage_from_cli(&storage_args);
 
         println!("{:?}", storage_config);
     }
+
+    #[test]
+    fn test_04_parse_sftp_storage_config() {
+        let mut storage_args = StorageArgs {
+            saved_storage: None,
+            storage_type: Some("sftp".into()),
+            storage_name: Some("test".into()),
+            location: Some("/backups".into()),
+            bucket: None,
+            region: None,
+            endpoint: None,
+            access_key: None,
+            secret_key: None,
+            sftp: Some(SftpArgs {
+                sftp_host: Some("sftp_host".into()),
+                sftp_port: Some(2222),
+                sftp_username: Some("sftp_username".into()),
+                sftp_password: Some("sftp_password".into()),
+                sftp_key_path: None,
+                sftp_key_passphrase: None,
+                sftp_accept_new_host_key: false,
+            }),
+            webdav: None,
+        };
+
+        match storage_from_cli(&storage_args).expect("Failed to parse storage args") {
+            StorageConfig::Sftp(config) => {
+                assert_eq!(config.host, "sftp_host");
+                assert_eq!(config.port, 2222);
+                assert_eq!(config.location, "/backups");
+                assert!(matches!(
+                    config.credentials,
+                    StorageCredentials::Basic { ref username, ref password }
+                        if username == "sftp_username" && password == "sftp_password"
+                ));
+            }
+            config => panic!("Unexpected storage config {:?}", config),
+        }
+
+        // A private key takes precedence over the password
+        if let Some(sftp_args) = storage_args.sftp.as_mut() {
+            sftp_args.sftp_key_path = Some("~/.ssh/id_ed25519".into());
+        }
+
+        match storage_from_cli(&storage_args).expect("Failed to parse storage args") {
+            StorageConfig::Sftp(config) => assert!(matches!(
+                config.credentials,
+                StorageCredentials::PrivateKey { ref key_path, .. } if key_path == "~/.ssh/id_ed25519"
+            )),
+            config => panic!("Unexpected storage config {:?}", config),
+        }
+
+        if let Some(sftp_args) = storage_args.sftp.as_mut() {
+            sftp_args.sftp_key_path = None;
+            sftp_args.sftp_password = None;
+        }
+
+        assert!(storage_from_cli(&storage_args).is_err());
+    }
+
+    #[test]
+    fn test_03_parse_retention_policy() {
+        let mut retention_args = RetentionPolicyArgs {
+            keep_last: None,
+            keep_daily: None,
+            keep_weekly: None,
+            keep_monthly: None,
+            keep_yearly: None,
+        };
+
+        assert!(retention_policy_from_cli(&retention_args).is_none());
+
+        retention_args.keep_daily = Some(7);
+        retention_args.keep_monthly = Some(12);
+
+        let policy = retention_policy_from_cli(&retention_args).expect("Missing retention policy");
+
+        assert_eq!(policy.keep_last, 0);
+        assert_eq!(policy.keep_daily, 7);
+        assert_eq!(policy.keep_monthly, 12);
+    }
+
+    #[test]
+    fn test_05_parse_webdav_storage_config() {
+        let mut storage_args = StorageArgs {
+            saved_storage: None,
+            storage_type: Some("webdav".into()),
+            storage_name: Some("test".into()),
+            location: Some("backups".into()),
+            bucket: None,
+            region: None,
+            endpoint: None,
+            access_key: None,
+            secret_key: None,
+            sftp: None,
+            webdav: Some(WebDavArgs {
+                webdav_endpoint: Some("https://cloud.example.com/remote.php/dav/files/dbkp".into()),
+                webdav_username: Some("dbkp".into()),
+                webdav_password: Some("app-password".into()),
+            }),
+        };
+
+        match storage_from_cli(&storage_args).expect("Failed to parse storage args") {
+            StorageConfig::WebDav(config) => {
+                assert_eq!(
+                    config.endpoint,
+                    "https://cloud.example.com/remote.php/dav/files/dbkp"
+                );
+                assert_eq!(config.location, "backups");
+                assert!(matches!(
+                    config.credentials,
+                    StorageCredentials::Basic { ref username, ref password }
+                        if username == "dbkp" && password == "app-password"
+                ));
+            }
+            config => panic!("Unexpected storage config {:?}", config),
+        }
+
+        // A username without a password is rejected
+        if let Some(webdav_args) = storage_args.webdav.as_mut() {
+            webdav_args.webdav_password = None;
+        }
+
+        assert!(storage_from_cli(&storage_args).is_err());
+    }
+
+    #[test]
+    fn test_06_parse_mariadb_database_config() {
+        let database_args = DatabaseArgs {
+            saved_database: None,
+            database_type: Some("mariadb".into()),
+            database: Some("test".into()),
+            host: Some("localhost".into()),
+            port: Some(3306),
+            username: Some("root".into()),
+            password: Some("password".into()),
+            ssh: None,
+        };
+
+        let database_config =
+            database_config_from_cli(&database_args).expect("Failed to parse database args");
+
+        assert_eq!(database_config.connection_type, ConnectionType::MariaDB);
+        assert_eq!(database_config.port, 3306);
+        assert!(database_config.ssh.is_none());
+    }
+
+    #[test]
+    fn test_07_parse_sqlite_database_config() {
+        let database_args = DatabaseArgs {
+            saved_database: None,
+            database_type: Some("sqlite".into()),
+            database: Some("/var/lib/app/app.db".into()),
+            host: None,
+            port: None,
+            username: None,
+            password: None,
+            ssh: None,
+        };
+
+        let database_config =
+            database_config_from_cli(&database_args).expect("Failed to parse database args");
+
+        assert_eq!(database_config.connection_type, ConnectionType::Sqlite);
+        assert_eq!(database_config.database, "/var/lib/app/app.db");
+        assert_eq!(database_config.name, "app");
+    }
+
+    #[test]
+    fn test_08_parse_mongodb_database_config() {
+        let database_args = DatabaseArgs {
+            saved_database: None,
+            database_type: Some("mongodb".into()),
+            database: Some("test".into()),
+            host: Some("localhost".into()),
+            port: Some(27017),
+            username: Some("root".into()),
+            password: Some("password".into()),
+            ssh: None,
+        };
+
+        let database_config =
+            database_config_from_cli(&database_args).expect("Failed to parse database args");
+
+        assert_eq!(database_config.connection_type, ConnectionType::MongoDb);
+        assert_eq!(database_config.port, 27017);
+        assert_eq!(database_config.name, "test");
+    }
+
+    #[test]
+    fn test_09_parse_redis_database_config() {
+        let database_args = DatabaseArgs {
+            saved_database: None,
+            database_type: Some("redis".into()),
+            database: Some("cache".into()),
+            host: Some("localhost".into()),
+            port: Some(6379),
+            username: None,
+            password: Some("password".into()),
+            ssh: None,
+        };
+
+        let database_config =
+            database_config_from_cli(&database_args).expect("Failed to parse database args");
+
+        assert_eq!(database_config.connection_type, ConnectionType::Redis);
+        assert_eq!(database_config.username, "");
+        assert_eq!(database_config.name, "cache");
+
+        let database_args = DatabaseArgs {
+            saved_database: None,
+            database_type: Some("mysql".into()),
+            ..database_args
+        };
+        assert!(database_config_from_cli(&database_args).is_err());
+    }
+
+    #[test]
+    fn test_10_parse_backup_filters() {
+        let mut filter_args = FilterArgs {
+            include_tables: vec![],
+            exclude_tables: vec![],
+            include_schemas: vec![],
+            exclude_schemas: vec![],
+            exclude_table_data: vec![],
+            schema_only: false,
+            data_only: false,
+        };
+
+        assert!(filters_from_cli(&filter_args).is_none());
+
+        filter_args.exclude_tables = vec!["audit_log".into()];
+        filter_args.data_only = true;
+
+        let filters = filters_from_cli(&filter_args).expect("Missing filters");
+
+        assert_eq!(filters.exclude_tables, vec!["audit_log".to_string()]);
+        assert_eq!(filters.content, DumpContent::DataOnly);
+    }
+
+    #[test]
+    fn test_11_parse_masking_config() {
+        let mut masking_args = MaskingArgs {
+            masking_config: None,
+            masking_seed: Some("seed".into()),
+        };
+
+        assert!(
+            masking_from_cli(&masking_args)
+                .expect("Failed to parse masking args")
+                .is_none()
+        );
+
+        let config_file = tempfile::NamedTempFile::new().expect("Failed to create config file");
+        std::fs::write(
+            config_file.path(),
+            r#"{ "seed": "file", "tables": { "users": { "email": "email" } } }"#,
+        )
+        .expect("Failed to write config file");
+        masking_args.masking_config = Some(config_file.path().to_str().unwrap().into());
+
+        let masking_config = masking_from_cli(&masking_args)
+            .expect("Failed to parse masking args")
+            .expect("Missing masking config");
+
+        assert_eq!(masking_config.seed.as_deref(), Some("seed"));
+        assert!(masking_config.tables.contains_key("users"));
+
+        masking_args.masking_config = Some("/nonexistent/masking.json".into());
+        assert!(masking_from_cli(&masking_args).is_err());
+    }
+
+    #[test]
+    fn test_12_parse_copy_target() {
+        let source = database_config_from_cli(&DatabaseArgs {
+            saved_database: None,
+            database_type: Some("postgresql".into()),
+            database: Some("prod_app".into()),
+            host: Some("db.internal".into()),
+            port: Some(5432),
+            username: Some("app".into()),
+            password: Some("secret".into()),
+            ssh: Some(SshArgs {
+                ssh_host: Some("bastion".into()),
+                ssh_port: None,
+                ssh_username: Some("ssh_username".into()),
+                ssh_key_path: Some("ssh_key_path".into()),
+                ssh_key_passphrase: None,
+                ssh_accept_new_host_key: false,
+            }),
+        })
+        .expect("Failed to parse database args");
+
+        let mut target_args = TargetServerArgs {
+            target_host: None,
+            target_port: None,
+            target_username: None,
+            target_password: None,
+        };
+
+        assert!(
+            target_from_cli(&source, &target_args).is_none(),
+            "The copy should stay on the source server"
+        );
+
+        target_args.target_host = Some("localhost".into());
+        target_args.target_password = Some("local".into());
+
+        let target = target_from_cli(&source, &target_args).expect("Missing target");
+
+        assert_eq!(target.host, "localhost");
+        assert_eq!(target.port, 5432);
+        assert_eq!(target.username, "app");
+        assert_eq!(target.password.as_deref(), Some("local"));
+        assert_eq!(target.database, "prod_app");
+        assert!(target.ssh.is_none());
+    }
+
+    #[test]
+    fn test_13_override_saved_configs() {
+        let empty_database_args = DatabaseArgs {
+            saved_database: Some("prod".into()),
+            database_type: None,
+            database: None,
+            host: None,
+            port: None,
+            username: None,
+            password: None,
+            ssh: None,
+        };
+        let mut database_config = database_config_from_cli(&DatabaseArgs {
+            saved_database: None,
+            database_type: Some("postgresql".into()),
+            database: Some("app".into()),
+            host: Some("db.internal".into()),
+            port: Some(5432),
+            username: Some("app".into()),
+            password: Some("secret".into()),
+            ssh: None,
+        })
+        .expect("Failed to parse database args");
+
+        override_database_config(
+            &mut database_config,
+            &DatabaseArgs {
+                database: Some("app_reporting".into()),
+                password: Some("rotated".into()),
+                ..empty_database_args.clone()
+            },
+        )
+        .expect("Failed to override database config");
+
+        assert_eq!(database_config.database, "app_reporting");
+        assert_eq!(database_config.host, "db.internal");
+        assert_eq!(database_config.password.as_deref(), Some("rotated"));
+
+        assert!(
+            override_database_config(
+                &mut database_config,
+                &DatabaseArgs {
+                    database_type: Some("mysql".into()),
+                    ..empty_database_args
+                },
+            )
+            .is_err(),
+            "The database type of a saved config can't change"
+        );
+
+        let mut storage_config = StorageConfig::Sftp(SftpStorageConfig {
+            id: "id".into(),
+            name: "nas".into(),
+            host: "nas.local".into(),
+            port: 22,
+            credentials: StorageCredentials::PrivateKey {
+                username: "backup".into(),
+                key_path: "~/.ssh/id_ed25519".into(),
+                passphrase: None,
+            },
+            location: "/backups".into(),
+            accept_new_host_key: false,
+        });
+        let storage_args = StorageArgs {
+            saved_storage: Some("nas".into()),
+            storage_type: None,
+            storage_name: None,
+            location: Some("/backups/nightly".into()),
+            bucket: None,
+            region: None,
+            endpoint: None,
+            access_key: None,
+            secret_key: None,
+            sftp: Some(SftpArgs {
+                sftp_host: None,
+                sftp_port: Some(2222),
+                sftp_username: Some("cron".into()),
+                sftp_password: None,
+                sftp_key_path: None,
+                sftp_key_passphrase: None,
+                sftp_accept_new_host_key: true,
+            }),
+            webdav: None,
+        };
+
+        override_storage_config(&mut storage_config, &storage_args)
+            .expect("Failed to override storage config");
+
+        match &storage_config {
+            StorageConfig::Sftp(config) => {
+                assert_eq!(config.host, "nas.local");
+                assert_eq!(config.port, 2222);
+                assert_eq!(config.location, "/backups/nightly");
+                assert!(config.accept_new_host_key);
+                assert!(matches!(
+                    &config.credentials,
+                    StorageCredentials::PrivateKey { username, key_path, .. }
+                        if username == "cron" && key_path == "~/.ssh/id_ed25519"
+                ));
+            }
+            _ => panic!("Unexpected storage type"),
+        }
+
+        assert!(
+            override_storage_config(
+                &mut storage_config,
+                &StorageArgs {
+                    storage_type: Some("s3".into()),
+                    ..storage_args
+                },
+            )
+            .is_err()
+        );
+
+        let saved_encryption = EncryptionConfig {
+            passphrase: None,
+            recipients: vec!["age1saved".into()],
+            identities: vec!["/saved/identity.txt".into()],
+        };
+
+        // Identities given to decrypt keep the saved recipients for the next backups
+        let mut encryption_config = saved_encryption.clone();
+        override_encryption_config(
+            &mut encryption_config,
+            &EncryptionArgs {
+                passphrase: None,
+                recipients: vec![],
+                identities: vec!["/other/identity.txt".into()],
+            },
+        );
+        assert_eq!(encryption_config.recipients, vec!["age1saved"]);
+        assert_eq!(encryption_config.identities, vec!["/other/identity.txt"]);
+
+        let mut encryption_config = saved_encryption;
+        override_encryption_config(
+            &mut encryption_config,
+            &EncryptionArgs {
+                passphrase: Some("secret".into()),
+                recipients: vec![],
+                identities: vec![],
+            },
+        );
+        assert_eq!(encryption_config.passphrase.as_deref(), Some("secret"));
+        assert!(encryption_config.recipients.is_empty());
+        assert_eq!(encryption_config.identities, vec!["/saved/identity.txt"]);
+    }
 }
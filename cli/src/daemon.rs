@@ -0,0 +1,525 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, Local, Utc};
+use colored::*;
+use cron::Schedule;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    net::TcpListener,
+    task::{Id, JoinError, JoinSet},
+};
+
+use crate::{
+    jobs::{CatchUp, JobFile},
+    metrics::{self, Metrics},
+};
+
+/// Longest sleep between two checks of the schedules, so a changed clock is noticed.
+const MAX_SLEEP: Duration = Duration::from_secs(60);
+
+/// Parses a cron expression, the seconds field is optional and defaults to 0.
+pub fn parse_schedule(expression: &str) -> Result<Schedule> {
+    let expression = match expression.split_whitespace().count() {
+        5 => format!("0 {}", expression),
+        _ => expression.to_string(),
+    };
+
+    Schedule::from_str(&expression).map_err(|e| anyhow!("Invalid schedule '{}': {}", expression, e))
+}
+
+pub fn default_state_path() -> Result<PathBuf> {
+    Ok(dirs::data_local_dir()
+        .ok_or_else(|| anyhow!("Could not determine data directory"))?
+        .join("dbkp")
+        .join("daemon_state.json"))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// Last run of a job.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JobState {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub status: RunStatus,
+    pub backup: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Last run of every job, kept on disk so missed runs can be caught up after a restart.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DaemonState {
+    pub jobs: BTreeMap<String, JobState>,
+}
+
+impl DaemonState {
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read daemon state {}", path.display()))?;
+
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid daemon state {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&temp_path, path)
+            .with_context(|| format!("Failed to save daemon state {}", path.display()))
+    }
+
+    /// Runs still marked as running were stopped with the daemon.
+    fn mark_interrupted(&mut self) {
+        for state in self.jobs.values_mut() {
+            if state.status == RunStatus::Running {
+                state.status = RunStatus::Failed;
+                state.error = Some("Interrupted by the daemon stopping".into());
+            }
+        }
+    }
+}
+
+/// Whether a run was scheduled between the last run of a job and `now`. A job that never ran
+/// has nothing to catch up.
+pub fn missed_run(schedule: &Schedule, last_run: Option<&JobState>, now: DateTime<Local>) -> bool {
+    let Some(last_run) = last_run else {
+        return false;
+    };
+
+    schedule
+        .after(&last_run.started_at.with_timezone(&Local))
+        .next()
+        .is_some_and(|next| next <= now)
+}
+
+struct ScheduledJob {
+    index: usize,
+    schedule: Schedule,
+    next_run: Option<DateTime<Local>>,
+}
+
+fn scheduled_jobs(job_file: &JobFile, names: &[String]) -> Result<Vec<ScheduledJob>> {
+    let mut scheduled = vec![];
+
+    for job in job_file.select(names)? {
+        let Some(expression) = &job.schedule else {
+            if !names.is_empty() {
+                return Err(anyhow!("Job '{}' has no schedule", job.name));
+            }
+            continue;
+        };
+
+        let schedule =
+            parse_schedule(expression).with_context(|| format!("Invalid job '{}'", job.name))?;
+        // Catches invalid flags on startup rather than on the first run
+        job_file.backup_args(job)?;
+
+        scheduled.push(ScheduledJob {
+            index: job_file
+                .jobs
+                .iter()
+                .position(|j| j.name == job.name)
+                .unwrap(),
+            next_run: schedule.after(&Local::now()).next(),
+            schedule,
+        });
+    }
+
+    if scheduled.is_empty() {
+        return Err(anyhow!("No job has a schedule"));
+    }
+
+    Ok(scheduled)
+}
+
+fn log(message: impl std::fmt::Display) {
+    println!("[{}] {}", Local::now().format("%Y-%m-%d %H:%M:%S"), message);
+}
+
+fn format_time(time: Option<DateTime<Local>>) -> String {
+    time.map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| "-".into())
+}
+
+/// Prints the last and next run of the scheduled jobs.
+pub fn print_status(job_file: &JobFile, names: &[String], state_path: &Path) -> Result<()> {
+    let scheduled = scheduled_jobs(job_file, names)?;
+    let state = DaemonState::load(state_path)?;
+
+    println!("{}:", "Scheduled jobs".green().bold());
+    for scheduled_job in &scheduled {
+        let job = &job_file.jobs[scheduled_job.index];
+
+        let last_run = match state.jobs.get(&job.name) {
+            None => "never run".to_string(),
+            Some(last) => {
+                let started_at = format_time(Some(last.started_at.with_timezone(&Local)));
+                match last.status {
+                    RunStatus::Running => {
+                        format!("{} running since {}", "RUNNING".cyan(), started_at)
+                    }
+                    RunStatus::Succeeded => format!(
+                        "{} {} | {}",
+                        "OK    ".green(),
+                        started_at,
+                        last.backup.as_deref().unwrap_or("-")
+                    ),
+                    RunStatus::Failed => format!(
+                        "{} {} | {}",
+                        "FAILED".red(),
+                        started_at,
+                        last.error.as_deref().unwrap_or("-")
+                    ),
+                }
+            }
+        };
+
+        println!(
+            "  {} ({})\n    Last run: {}\n    Next run: {}",
+            job.name.bold(),
+            job.schedule.as_deref().unwrap_or_default(),
+            last_run,
+            format_time(scheduled_job.next_run)
+        );
+    }
+
+    Ok(())
+}
+
+/// Runs the scheduled jobs until interrupted. Each job runs in its own task so a slow one doesn't
+/// delay the others, but a job never starts while its previous run is still going.
+pub async fn run(
+    job_file: JobFile,
+    names: &[String],
+    state_path: &Path,
+    metrics_listen: Option<SocketAddr>,
+) -> Result<()> {
+    let mut scheduled = scheduled_jobs(&job_file, names)?;
+
+    let metrics = match metrics_listen {
+        Some(address) => {
+            let listener = TcpListener::bind(address)
+                .await
+                .with_context(|| format!("Failed to listen on {}", address))?;
+            log(format!("Serving metrics on http://{}/metrics", address));
+
+            let metrics = Metrics::default();
+            tokio::spawn(metrics::serve(listener, metrics.clone()));
+            Some(metrics)
+        }
+        None => None,
+    };
+
+    let mut state = DaemonState::load(state_path)?;
+    state.mark_interrupted();
+    state.save(state_path)?;
+
+    let mut daemon = Daemon {
+        job_file: Arc::new(job_file),
+        state,
+        state_path,
+        running: HashMap::new(),
+        tasks: JoinSet::new(),
+        metrics,
+    };
+
+    let job_file = daemon.job_file.clone();
+    let now = Local::now();
+    for scheduled_job in &scheduled {
+        let job = &job_file.jobs[scheduled_job.index];
+        log(format!(
+            "Scheduled job '{}' ({}), next run {}",
+            job.name,
+            job.schedule.as_deref().unwrap_or_default(),
+            format_time(scheduled_job.next_run)
+        ));
+
+        if job.catch_up == CatchUp::Once
+            && missed_run(
+                &scheduled_job.schedule,
+                daemon.state.jobs.get(&job.name),
+                now,
+            )
+        {
+            log(format!("Catching up the missed run of job '{}'", job.name));
+            daemon.start(scheduled_job.index);
+        }
+    }
+
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+
+    loop {
+        let sleep = scheduled
+            .iter()
+            .filter_map(|scheduled_job| scheduled_job.next_run)
+            .min()
+            .map(|next_run| (next_run - Local::now()).to_std().unwrap_or_default())
+            .unwrap_or(MAX_SLEEP)
+            .min(MAX_SLEEP);
+
+        tokio::select! {
+            _ = tokio::time::sleep(sleep) => {
+                let now = Local::now();
+                for scheduled_job in scheduled.iter_mut() {
+                    if scheduled_job.next_run.is_some_and(|next_run| next_run <= now) {
+                        daemon.start(scheduled_job.index);
+                        scheduled_job.next_run = scheduled_job.schedule.after(&now).next();
+                    }
+                }
+            }
+            Some(finished) = daemon.tasks.join_next_with_id() => {
+                daemon.finish(finished);
+            }
+            _ = &mut shutdown => {
+                log(format!("Stopping, waiting for {} running jobs", daemon.tasks.len()));
+                while let Some(finished) = daemon.tasks.join_next_with_id().await {
+                    daemon.finish(finished);
+                }
+
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Resolves on Ctrl-C, or on SIGTERM which `docker stop` sends.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+            }
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+struct Daemon<'a> {
+    job_file: Arc<JobFile>,
+    state: DaemonState,
+    state_path: &'a Path,
+    /// Names of the jobs running in `tasks`, a panicked task only leaves its id.
+    running: HashMap<Id, String>,
+    tasks: JoinSet<Result<String>>,
+    metrics: Option<Metrics>,
+}
+
+impl Daemon<'_> {
+    fn start(&mut self, index: usize) {
+        let name = self.job_file.jobs[index].name.clone();
+
+        if self.running.values().any(|running| *running == name) {
+            log(format!(
+                "{} Job '{}' is still running, skipping this run",
+                "[WARN]".yellow(),
+                name
+            ));
+            return;
+        }
+
+        log(format!("Starting job '{}'", name));
+        self.state.jobs.insert(
+            name.clone(),
+            JobState {
+                started_at: Utc::now(),
+                finished_at: None,
+                status: RunStatus::Running,
+                backup: None,
+                error: None,
+            },
+        );
+        self.save_state();
+
+        let job_file = self.job_file.clone();
+        let metrics = self.metrics.clone();
+        let task = self.tasks.spawn(async move {
+            crate::run_job(&job_file, &job_file.jobs[index], metrics.as_ref()).await
+        });
+        self.running.insert(task.id(), name);
+    }
+
+    fn finish(&mut self, finished: Result<(Id, Result<String>), JoinError>) {
+        let (id, result) = match finished {
+            Ok((id, result)) => (id, result),
+            Err(e) => (e.id(), Err(anyhow!("Job task failed: {}", e))),
+        };
+        let Some(name) = self.running.remove(&id) else {
+            return;
+        };
+
+        if let Some(job_state) = self.state.jobs.get_mut(&name) {
+            job_state.finished_at = Some(Utc::now());
+
+            match result {
+                Ok(backup) => {
+                    log(format!(
+                        "{} Job '{}' stored {}",
+                        "[SUCCESS]".green(),
+                        name,
+                        backup
+                    ));
+                    job_state.status = RunStatus::Succeeded;
+                    job_state.backup = Some(backup);
+                }
+                Err(e) => {
+                    log(format!(
+                        "{} Job '{}' failed: {:#}",
+                        "[ERROR]".red(),
+                        name,
+                        e
+                    ));
+                    job_state.status = RunStatus::Failed;
+                    job_state.error = Some(e.to_string());
+                }
+            }
+        }
+
+        self.save_state();
+    }
+
+    /// The state is only used by `--status` and catch-up runs, failing to save it doesn't stop
+    /// the jobs.
+    fn save_state(&self) {
+        if let Err(e) = self.state.save(self.state_path) {
+            log(format!("{} {:#}", "[ERROR]".red(), e));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+
+    fn last_run(started_at: DateTime<Local>) -> JobState {
+        JobState {
+            started_at: started_at.with_timezone(&Utc),
+            finished_at: None,
+            status: RunStatus::Running,
+            backup: None,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_schedule() {
+        let schedule = parse_schedule("30 2 * * *").expect("Five fields should be accepted");
+        let after = Local.with_ymd_and_hms(2025, 1, 15, 12, 0, 0).unwrap();
+        assert_eq!(
+            schedule.after(&after).next(),
+            Some(Local.with_ymd_and_hms(2025, 1, 16, 2, 30, 0).unwrap())
+        );
+
+        assert!(parse_schedule("15 30 2 * * *").is_ok());
+        assert!(parse_schedule("every night").is_err());
+    }
+
+    #[test]
+    fn test_missed_run() {
+        let schedule = parse_schedule("0 2 * * *").unwrap();
+        let now = Local.with_ymd_and_hms(2025, 1, 15, 12, 0, 0).unwrap();
+
+        assert!(!missed_run(&schedule, None, now));
+        assert!(!missed_run(
+            &schedule,
+            Some(&last_run(now - Duration::hours(9))),
+            now
+        ));
+        assert!(missed_run(
+            &schedule,
+            Some(&last_run(now - Duration::hours(11))),
+            now
+        ));
+        assert!(missed_run(
+            &schedule,
+            Some(&last_run(now - Duration::days(3))),
+            now
+        ));
+    }
+
+    #[test]
+    fn test_daemon_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dbkp").join("daemon_state.json");
+
+        let mut state = DaemonState::load(&path).expect("A missing state should be empty");
+        state.jobs.insert("shop".into(), last_run(Local::now()));
+        state.save(&path).expect("Failed to save state");
+
+        let mut state = DaemonState::load(&path).expect("Failed to load state");
+        state.mark_interrupted();
+        let shop = &state.jobs["shop"];
+        assert_eq!(shop.status, RunStatus::Failed);
+        assert!(shop.error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_panicked_job() {
+        let dir = tempfile::tempdir().unwrap();
+        // A file where the state directory should be, so saving the state fails
+        let blocked = dir.path().join("dbkp");
+        fs::write(&blocked, "").unwrap();
+        let state_path = blocked.join("daemon_state.json");
+
+        let mut daemon = Daemon {
+            job_file: Arc::new(JobFile::parse("[[job]]\nname = \"shop\"\n").unwrap()),
+            state: DaemonState::default(),
+            state_path: &state_path,
+            running: HashMap::new(),
+            tasks: JoinSet::new(),
+            metrics: None,
+        };
+        daemon
+            .state
+            .jobs
+            .insert("shop".into(), last_run(Local::now()));
+        let task = daemon.tasks.spawn(async { panic!("Job panicked") });
+        daemon.running.insert(task.id(), "shop".into());
+
+        let finished = daemon.tasks.join_next_with_id().await.unwrap();
+        daemon.finish(finished);
+
+        assert!(daemon.running.is_empty(), "The job should run again");
+        let shop = &daemon.state.jobs["shop"];
+        assert_eq!(shop.status, RunStatus::Failed);
+        assert!(shop.error.as_deref().unwrap().contains("panicked"));
+    }
+}
@@ -0,0 +1,283 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use anyhow::{Context, Result, anyhow};
+use clap::Parser;
+use serde::Deserialize;
+use tokio::process::Command;
+
+use crate::cli::{BackupArgs, Cli, Commands};
+
+/// Backup jobs described in TOML with the flags of `dbkp backup`, e.g.
+///
+/// ```toml
+/// [defaults]
+/// storage = "nas"
+///
+/// [[job]]
+/// name = "shop"
+/// db = "shop"
+/// compression = "zstd"
+/// exclude_table = ["audit_log"]
+/// keep_daily = 7
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct JobFile {
+    /// Flags shared by every job, a job overrides them with its own.
+    #[serde(default)]
+    pub defaults: BTreeMap<String, toml::Value>,
+    #[serde(default, rename = "job")]
+    pub jobs: Vec<Job>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Job {
+    pub name: String,
+    /// Cron expression `dbkp daemon` runs the job on, in local time.
+    #[serde(default)]
+    pub schedule: Option<String>,
+    #[serde(default)]
+    pub catch_up: CatchUp,
+    /// Shell commands run before the backup, the job fails without a backup if one fails.
+    #[serde(default)]
+    pub before: Vec<String>,
+    /// Shell commands run after a successful backup, with its name in `DBKP_BACKUP`.
+    #[serde(default)]
+    pub after: Vec<String>,
+    /// Shell commands run when the job fails, with the error in `DBKP_ERROR`.
+    #[serde(default)]
+    pub on_failure: Vec<String>,
+    /// Every other key is a `dbkp backup` flag in snake case.
+    #[serde(flatten)]
+    pub flags: BTreeMap<String, toml::Value>,
+}
+
+/// What `dbkp daemon` does about the runs of a job missed while it was stopped.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CatchUp {
+    /// Wait for the next scheduled run.
+    #[default]
+    Skip,
+    /// Run the job once on startup, however many runs were missed.
+    Once,
+}
+
+impl JobFile {
+    pub fn load(path: &Path) -> Result<Self> {
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "yml" || extension == "yaml")
+        {
+            return Err(anyhow!(
+                "Job files are written in TOML, {} looks like YAML",
+                path.display()
+            ));
+        }
+
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read job file {}", path.display()))?;
+
+        Self::parse(&content).with_context(|| format!("Invalid job file {}", path.display()))
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let job_file: Self = toml::from_str(content)?;
+
+        let mut names: Vec<&str> = job_file.jobs.iter().map(|job| job.name.as_str()).collect();
+        names.sort();
+        if let Some(duplicate) = names.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(anyhow!("Several jobs are named '{}'", duplicate[0]));
+        }
+
+        Ok(job_file)
+    }
+
+    /// Every job when no name is given, otherwise the named ones in the order of the file.
+    pub fn select(&self, names: &[String]) -> Result<Vec<&Job>> {
+        if let Some(unknown) = names
+            .iter()
+            .find(|name| !self.jobs.iter().any(|job| &job.name == *name))
+        {
+            return Err(anyhow!("No job named '{}'", unknown));
+        }
+
+        Ok(self
+            .jobs
+            .iter()
+            .filter(|job| names.is_empty() || names.contains(&job.name))
+            .collect())
+    }
+
+    /// Parses the flags of a job like `dbkp backup` would, so defaults, environment variables and
+    /// validation are the same as on the command line.
+    pub fn backup_args(&self, job: &Job) -> Result<BackupArgs> {
+        let mut flags = self.defaults.clone();
+        flags.extend(job.flags.clone());
+
+        let mut argv = vec!["dbkp".to_string(), "backup".to_string()];
+        for (key, value) in &flags {
+            push_flag(&mut argv, key, value)
+                .with_context(|| format!("Invalid job '{}'", job.name))?;
+        }
+
+        match Cli::try_parse_from(argv) {
+            Ok(Cli {
+                command: Some(Commands::Backup(args)),
+            }) => Ok(args),
+            Ok(_) => Err(anyhow!("Invalid job '{}'", job.name)),
+            Err(e) => {
+                let message = e.to_string();
+                Err(anyhow!(
+                    "Invalid job '{}': {}",
+                    job.name,
+                    message
+                        .lines()
+                        .next()
+                        .unwrap_or_default()
+                        .trim_start_matches("error: ")
+                ))
+            }
+        }
+    }
+}
+
+fn push_flag(argv: &mut Vec<String>, key: &str, value: &toml::Value) -> Result<()> {
+    let flag = format!("--{}", key.replace('_', "-"));
+
+    match value {
+        toml::Value::Boolean(true) => argv.push(flag),
+        toml::Value::Boolean(false) => {}
+        toml::Value::Array(values) => {
+            for value in values {
+                push_flag(argv, key, value)?;
+            }
+        }
+        // Joined to the flag so values starting with `-` aren't taken for flags
+        toml::Value::String(value) => argv.push(format!("{}={}", flag, value)),
+        toml::Value::Integer(value) => argv.push(format!("{}={}", flag, value)),
+        _ => return Err(anyhow!("Unsupported value for '{}': {}", key, value)),
+    }
+
+    Ok(())
+}
+
+/// Runs the hook commands in order with `sh -c`, stopping at the first failure.
+pub async fn run_hooks(commands: &[String], env: &[(&str, &str)]) -> Result<()> {
+    for command in commands {
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .envs(env.iter().copied())
+            .status()
+            .await
+            .with_context(|| format!("Failed to run hook '{}'", command))?;
+
+        if !status.success() {
+            return Err(anyhow!("Hook '{}' failed with {}", command, status));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use dbkp_core::compression::CompressionFormat;
+
+    use super::*;
+
+    const JOB_FILE: &str = r#"
+        [defaults]
+        storage = "nas"
+        compression = "gzip"
+
+        [[job]]
+        name = "shop"
+        db = "shop"
+        compression = "zstd"
+        exclude_table = ["audit_log", "sessions"]
+        schema_only = true
+        keep_daily = 7
+        before = ["true"]
+
+        [[job]]
+        name = "blog"
+        db = "blog"
+        password = "-starts-with-a-dash"
+        location = "/backups/blog"
+    "#;
+
+    #[test]
+    fn test_parse_job_file() {
+        let job_file = JobFile::parse(JOB_FILE).expect("Failed to parse job file");
+
+        let jobs = job_file.select(&[]).unwrap();
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs[0].before, vec!["true".to_string()]);
+
+        let shop = job_file.backup_args(jobs[0]).expect("Invalid shop job");
+        assert_eq!(shop.database_config.saved_database.as_deref(), Some("shop"));
+        assert_eq!(shop.storage_config.saved_storage.as_deref(), Some("nas"));
+        assert_eq!(shop.compression, Some(CompressionFormat::Zstd));
+        assert_eq!(shop.filters.exclude_tables, vec!["audit_log", "sessions"]);
+        assert!(shop.filters.schema_only);
+        assert_eq!(shop.retention_policy.keep_daily, Some(7));
+
+        let blog = job_file.backup_args(jobs[1]).expect("Invalid blog job");
+        assert_eq!(blog.compression, Some(CompressionFormat::Gzip));
+        assert_eq!(
+            blog.database_config.password.as_deref(),
+            Some("-starts-with-a-dash")
+        );
+        assert_eq!(
+            blog.storage_config.location.as_deref(),
+            Some("/backups/blog")
+        );
+
+        let selected = job_file.select(&["blog".into()]).unwrap();
+        assert_eq!(selected.len(), 1);
+        assert!(job_file.select(&["missing".into()]).is_err());
+    }
+
+    #[test]
+    fn test_invalid_job_file() {
+        let job_file = JobFile::parse(
+            r#"
+            [[job]]
+            name = "shop"
+            exclude_tables = ["audit_log"]
+            "#,
+        )
+        .unwrap();
+        let error = job_file.backup_args(&job_file.jobs[0]).unwrap_err();
+        assert!(
+            error.to_string().contains("--exclude-tables"),
+            "Unknown keys should be rejected, got: {}",
+            error
+        );
+
+        assert!(
+            JobFile::parse("[[job]]\nname = \"a\"\n[[job]]\nname = \"a\"\n").is_err(),
+            "Job names should be unique"
+        );
+        assert!(JobFile::parse("[other]\n").is_err());
+        assert!(JobFile::load(Path::new("jobs.yaml")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_run_hooks() {
+        run_hooks(
+            &["test \"$DBKP_JOB\" = shop".into()],
+            &[("DBKP_JOB", "shop")],
+        )
+        .await
+        .expect("Hook should see the job name");
+
+        assert!(
+            run_hooks(&["true".into(), "exit 3".into()], &[])
+                .await
+                .is_err()
+        );
+    }
+}
@@ -1,21 +1,38 @@
 use anyhow::{Result, anyhow};
 use clap::Parser;
-use cli::{Cli, Commands, database_config_from_cli, parse_retention, storage_from_cli};
+use cli::{
+    Cli, Commands, database_config_from_cli, encryption_from_cli, filters_from_cli,
+    masking_from_cli, override_database_config, override_encryption_config,
+    override_storage_config, parse_retention, retention_policy_from_cli, storage_from_cli,
+    target_from_cli,
+};
 use colored::*;
 use dbkp_core::{
-    DbBkp, RestoreOptions,
+    BackupOptions, DbBkp, RestoreOptions, VerifyOptions,
+    checksum::verify_checksum,
+    copy::{CopyOptions, copy},
     databases::DatabaseConnection,
-    storage::provider::{ListOptions, StorageProvider},
+    retention::{RetentionDecision, RetentionPolicy},
+    storage::provider::{
+        CleanupOptions, ListOptions, RetentionOptions, StorageConfig, StorageProvider,
+    },
 };
 
 mod cli;
+mod daemon;
+mod jobs;
+mod metrics;
 mod spinner;
 mod tests;
 mod tui;
 
+use jobs::{Job, JobFile, run_hooks};
+use metrics::{BackupRun, Metrics, StorageUsage, record_to_textfile};
 use spinner::Spinner;
+use std::path::Path;
+use std::time::{Duration, Instant};
 
-use crate::tui::app::App;
+use crate::tui::{app::App, configs::Configs};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -32,68 +49,85 @@ async fn main() -> Result<()> {
         }
 
         Commands::Backup(args) => {
-            let mut spinner = Spinner::new("Resolving configuration...");
-            spinner.start();
+            let textfile = args.metrics_textfile.clone();
+            let mut run = BackupRun::default();
 
-            let (database_config, storage_config) = match resolve_configs_for_backup(&args).await {
-                Ok(configs) => {
-                    spinner.update_message("Configuration resolved, connecting to database...");
-                    configs
-                }
-                Err(e) => {
-                    spinner.error("Failed to resolve configuration");
-                    return Err(e);
-                }
-            };
+            let started_at = Instant::now();
+            let result = run_backup(args, textfile.is_some().then_some(&mut run)).await;
+            record_metrics(
+                None,
+                textfile.as_deref(),
+                &run,
+                started_at.elapsed(),
+                result.is_ok(),
+            );
 
-            let database_connection = match DatabaseConnection::new(database_config).await {
-                Ok(conn) => {
-                    spinner.update_message("Database connected, connecting to storage...");
-                    conn
-                }
-                Err(e) => {
-                    spinner.error("Failed to connect to database");
-                    return Err(e);
-                }
-            };
+            result?;
+        }
+        Commands::Run(args) => {
+            let job_file = JobFile::load(&args.file)?;
+            let jobs = job_file.select(&args.jobs)?;
+            let mut results = vec![];
 
-            let storage_provider = match StorageProvider::new(storage_config) {
-                Ok(provider) => {
-                    spinner.update_message("Storage connected, testing connections...");
-                    provider
-                }
-                Err(e) => {
-                    spinner.error("Failed to connect to storage");
-                    return Err(e);
+            for job in &jobs {
+                println!("\n{} {}", "Job".bold(), job.name.bold());
+
+                let started_at = Instant::now();
+                let result = run_job(&job_file, job, None).await;
+                if let Err(e) = &result {
+                    println!(
+                        "{}",
+                        format!("[ERROR] Job '{}' failed: {:#}", job.name, e).red()
+                    );
                 }
-            };
 
-            let core = DbBkp::new(database_connection, storage_provider);
+                results.push((job.name.clone(), started_at.elapsed(), result));
+            }
 
-            // Test database & storage connection
-            match core.test().await {
-                Ok(_) => spinner.update_message("Connections verified, starting backup..."),
-                Err(e) => {
-                    spinner.error("Connection test failed");
-                    return Err(e);
+            println!("\n{}:", "Job summary".green().bold());
+
+            let failed = results
+                .iter()
+                .filter(|(_, _, result)| result.is_err())
+                .count();
+            for (name, elapsed, result) in &results {
+                let duration = format!("{:.1}s", elapsed.as_secs_f64());
+                match result {
+                    Ok(backup_file) => println!(
+                        "  {} {} | {} | {}",
+                        "OK    ".green(),
+                        name,
+                        duration,
+                        backup_file
+                    ),
+                    Err(e) => println!("  {} {} | {} | {}", "FAILED".red(), name, duration, e),
                 }
             }
 
-            match core.backup().await {
-                Ok(backup_file) => {
-                    spinner.success(format!("Backup completed successfully: {}", backup_file));
-                }
-                Err(e) => {
-                    spinner.error("Backup failed");
-                    return Err(e);
-                }
+            if failed > 0 {
+                return Err(anyhow!("{} of {} jobs failed", failed, results.len()));
+            }
+        }
+        Commands::Daemon(args) => {
+            let job_file = JobFile::load(&args.file)?;
+            let state_path = match args.state {
+                Some(state_path) => state_path,
+                None => daemon::default_state_path()?,
+            };
+
+            if args.status {
+                daemon::print_status(&job_file, &args.jobs, &state_path)?;
+            } else {
+                // Jobs run side by side, their spinners would overwrite each other
+                spinner::disable_animation();
+                daemon::run(job_file, &args.jobs, &state_path, args.metrics_listen).await?;
             }
         }
         Commands::List(args) => {
             let mut spinner = Spinner::new("Resolving storage configuration...");
             spinner.start();
 
-            let storage_config = match resolve_storage_config(&Some(args.storage)).await {
+            let storage_config = match resolve_storage_config(&args.storage, "list") {
                 Ok(config) => {
                     spinner.update_message("Storage configuration resolved, connecting...");
                     config
@@ -127,6 +161,7 @@ async fn main() -> Result<()> {
                 .list_with_options(ListOptions {
                     latest_only: Some(args.latest_only),
                     limit: args.limit,
+                    database: args.database,
                 })
                 .await
             {
@@ -148,7 +183,7 @@ async fn main() -> Result<()> {
             println!("\n{}:", "Available backups".green().bold());
 
             for (index, entry) in entries.iter().enumerate() {
-                let filename = &entry.metadata.name;
+                let filename = &entry.path;
                 let size = entry.metadata.content_length;
                 let size_str = if size < 1024 {
                     format!("{}B", size)
@@ -160,10 +195,9 @@ async fn main() -> Result<()> {
                     format!("{:.2}GB", size as f64 / (1024.0 * 1024.0 * 1024.0))
                 };
 
-                // Try to extract and format timestamp
-                let date_str = match dbkp_core::common::extract_timestamp_from_filename(filename) {
-                    Ok(timestamp) => timestamp.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
-                    Err(_) => "Unknown date".to_string(),
+                let date_str = match entry.timestamp() {
+                    Some(timestamp) => timestamp.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+                    None => "Unknown date".to_string(),
                 };
 
                 println!(
@@ -190,17 +224,27 @@ async fn main() -> Result<()> {
                 }
             };
 
-            let backup_name = match resolve_backup_name(&args, &storage_config).await {
-                Ok(name) => {
-                    spinner.update_message("Backup identified, connecting to database...");
-                    name
-                }
+            let masking = match masking_from_cli(&args.masking) {
+                Ok(masking) => masking,
                 Err(e) => {
-                    spinner.error("Failed to resolve backup name");
+                    spinner.error("Invalid masking config");
                     return Err(e);
                 }
             };
 
+            let backup_name =
+                match resolve_backup_name(&args, &database_config, &storage_config).await {
+                    Ok(name) => {
+                        spinner.update_message("Backup identified, connecting to database...");
+                        name
+                    }
+                    Err(e) => {
+                        spinner.error("Failed to resolve backup name");
+                        return Err(e);
+                    }
+                };
+            let target = target_from_cli(&database_config, &args.target_server);
+
             let database_connection = match DatabaseConnection::new(database_config).await {
                 Ok(conn) => {
                     spinner.update_message("Database connected, connecting to storage...");
@@ -242,6 +286,11 @@ async fn main() -> Result<()> {
                     name: backup_name.clone(),
                     compression_format: None,
                     drop_database_first: Some(args.drop_database),
+                    encryption: resolve_encryption_config(&args.encryption),
+                    jobs: args.jobs,
+                    masking,
+                    target,
+                    target_database: args.target_database.clone(),
                 })
                 .await
             {
@@ -254,11 +303,95 @@ async fn main() -> Result<()> {
                 }
             }
         }
+        Commands::Copy(args) => {
+            let mut spinner = Spinner::new("Resolving configuration...");
+            spinner.start();
+
+            let database_config = match resolve_database_config(&args.database_config, "copy") {
+                Ok(config) => config,
+                Err(e) => {
+                    spinner.error("Failed to resolve configuration");
+                    return Err(e);
+                }
+            };
+            let target = target_from_cli(&database_config, &args.target.server);
+
+            let masking = match masking_from_cli(&args.masking) {
+                Ok(masking) => masking,
+                Err(e) => {
+                    spinner.error("Invalid masking config");
+                    return Err(e);
+                }
+            };
+
+            let storage_config = if args.storage_config.saved_storage.is_some()
+                || has_storage_config(&args.storage_config)
+            {
+                Some(resolve_storage_config(&args.storage_config, "copy")?)
+            } else {
+                None
+            };
+
+            let database_connection = match DatabaseConnection::new(database_config).await {
+                Ok(conn) => {
+                    spinner.update_message(format!(
+                        "Database connected, copying into '{}'...",
+                        args.target.target_database
+                    ));
+                    conn
+                }
+                Err(e) => {
+                    spinner.error("Failed to connect to database");
+                    return Err(e);
+                }
+            };
+
+            let options = CopyOptions {
+                target,
+                target_database: Some(args.target.target_database.clone()),
+                drop_database_first: Some(args.drop_database),
+                filters: filters_from_cli(&args.filters),
+                masking,
+            };
+
+            let copied = match storage_config {
+                Some(storage_config) => {
+                    let storage_provider = StorageProvider::new(storage_config)?;
+                    let core = DbBkp::new(database_connection, storage_provider);
+
+                    core.copy(
+                        options,
+                        Some(BackupOptions {
+                            compression_format: args.compression,
+                            encryption: resolve_encryption_config(&args.encryption),
+                            ..Default::default()
+                        }),
+                    )
+                    .await
+                    .map(Some)
+                }
+                None => copy(&database_connection, options).await.map(|_| None),
+            };
+
+            match copied {
+                Ok(Some(backup_file)) => spinner.success(format!(
+                    "Copied into '{}', backup stored: {}",
+                    args.target.target_database, backup_file
+                )),
+                Ok(None) => {
+                    spinner.success(format!("Copied into '{}'", args.target.target_database))
+                }
+                Err(e) => {
+                    spinner.error("Copy failed");
+                    return Err(e);
+                }
+            }
+        }
         Commands::Cleanup(args) => {
             let mut spinner = Spinner::new("Resolving storage configuration...");
             spinner.start();
 
-            let storage_config = match resolve_storage_config(&Some(args.storage)).await {
+            let storage_config = match resolve_storage_config(&args.storage, "cleanup") {
                 Ok(config) => {
                     spinner.update_message("Storage configuration resolved, connecting...");
                     config
@@ -296,25 +429,182 @@ async fn main() -> Result<()> {
                 }
             }
 
-            match storage
-                .cleanup(parse_retention(&args.retention)?, args.dry_run)
-                .await
-            {
-                Ok((entries_deleted, storage_reclaimed)) => {
-                    if args.dry_run {
-                        spinner.success(format!(
-                            "Dry run completed: {} entries would be deleted, {} storage would be reclaimed",
-                            entries_deleted, storage_reclaimed
-                        ));
-                    } else {
-                        spinner.success(format!(
-                            "Cleanup completed: {} entries deleted, {} storage reclaimed",
-                            entries_deleted, storage_reclaimed
+            let retention = match resolve_retention(
+                &args.retention,
+                &args.retention_policy,
+                args.database.as_deref(),
+            ) {
+                Ok(Some(retention)) => retention,
+                Ok(None) => {
+                    spinner.error("Missing retention");
+                    return Err(anyhow!(
+                        "Either --retention or one of the --keep-* options is required"
+                    ));
+                }
+                Err(e) => {
+                    spinner.error("Invalid retention");
+                    return Err(e);
+                }
+            };
+
+            run_retention(
+                &storage,
+                retention,
+                args.dry_run,
+                args.database,
+                &mut spinner,
+            )
+            .await?;
+        }
+        Commands::Verify(args) => {
+            let mut spinner = Spinner::new("Resolving storage configuration...");
+            spinner.start();
+
+            let storage_config = match resolve_storage_config(&args.storage, "verify") {
+                Ok(config) => {
+                    spinner.update_message("Storage configuration resolved, connecting...");
+                    config
+                }
+                Err(e) => {
+                    spinner.error("Failed to resolve storage configuration");
+                    return Err(e);
+                }
+            };
+
+            let storage = match StorageProvider::new(storage_config) {
+                Ok(provider) => {
+                    spinner
+                        .update_message(format!("Storage connected, verifying '{}'...", args.name));
+                    provider
+                }
+                Err(e) => {
+                    spinner.error("Failed to connect to storage");
+                    return Err(e);
+                }
+            };
+
+            if args.restore {
+                let database_config = match resolve_database_config(&args.database_config, "verify")
+                {
+                    Ok(config) => config,
+                    Err(e) => {
+                        spinner.error("Missing database configuration");
+                        return Err(e);
+                    }
+                };
+
+                let database_connection = match DatabaseConnection::new(database_config).await {
+                    Ok(conn) => {
+                        spinner.update_message(format!(
+                            "Database connected, restoring '{}' into a scratch database...",
+                            args.name
                         ));
+                        conn
                     }
+                    Err(e) => {
+                        spinner.error("Failed to connect to database");
+                        return Err(e);
+                    }
+                };
+
+                let core = DbBkp::new(database_connection, storage);
+
+                let report = match core
+                    .verify(VerifyOptions {
+                        name: args.name.clone(),
+                        target: None,
+                        encryption: resolve_encryption_config(&args.encryption),
+                    })
+                    .await
+                {
+                    Ok(report) => report,
+                    Err(e) => {
+                        spinner.error("Verification failed");
+                        return Err(e);
+                    }
+                };
+
+                if report.is_valid() {
+                    spinner.success(format!("Backup verified: {}", report.name));
+                } else {
+                    spinner.error("Backup verification failed");
+                }
+
+                if let Some(checksum) = &report.checksum {
+                    let status = if checksum.is_valid() {
+                        "OK".green()
+                    } else {
+                        "MISMATCH".red()
+                    };
+                    println!(
+                        "  Checksum ({:?}): {} {}",
+                        checksum.algorithm, status, checksum.actual_checksum
+                    );
+                }
+
+                if !report.has_table_stats {
+                    println!(
+                        "{}",
+                        "[INFO] No row counts recorded for this backup, only the restore was checked"
+                            .cyan()
+                    );
+                }
+
+                for table in &report.tables {
+                    let status = if table.is_missing() {
+                        "MISSING".red()
+                    } else if table.rows_match() {
+                        "OK".green()
+                    } else {
+                        "MISMATCH".red()
+                    };
+                    let rows = |rows: Option<u64>| match rows {
+                        Some(rows) => rows.to_string(),
+                        None => "missing".to_string(),
+                    };
+
+                    println!(
+                        "  {} | {} | expected {} | restored {}",
+                        status,
+                        table.name,
+                        rows(table.expected_rows),
+                        rows(table.actual_rows)
+                    );
+                }
+
+                if !report.is_valid() {
+                    return Err(anyhow!(
+                        "Backup {} failed verification: {} table(s) missing, {} with a different row count",
+                        report.name,
+                        report.missing_tables().len(),
+                        report.mismatched_tables().len()
+                    ));
+                }
+
+                return Ok(());
+            }
+
+            match verify_checksum(&storage, &args.name).await {
+                Ok(verification) if verification.is_valid() => {
+                    spinner.success(format!(
+                        "Checksum verified: {} ({:?} {})",
+                        args.name, verification.algorithm, verification.actual_checksum
+                    ));
+                }
+                Ok(verification) => {
+                    spinner.error("Checksum mismatch");
+                    return Err(anyhow!(
+                        "Backup {} is corrupted: expected {:?} {} ({} bytes), got {} ({} bytes)",
+                        args.name,
+                        verification.algorithm,
+                        verification.expected_checksum,
+                        verification.expected_size,
+                        verification.actual_checksum,
+                        verification.actual_size
+                    ));
                 }
                 Err(e) => {
-                    spinner.error("Cleanup failed");
+                    spinner.error("Verification failed");
                     return Err(e);
                 }
             }
@@ -324,32 +614,242 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+/// Runs the hooks and the backup of a job, the failure hooks run whatever step failed.
+async fn run_job(job_file: &JobFile, job: &Job, metrics: Option<&Metrics>) -> Result<String> {
+    let mut run = BackupRun {
+        job: job.name.clone(),
+        ..Default::default()
+    };
+    let mut textfile = None;
+
+    let started_at = Instant::now();
+    let result: Result<String> = async {
+        let args = job_file.backup_args(job)?;
+        textfile = args.metrics_textfile.clone();
+        let collect_metrics = metrics.is_some() || textfile.is_some();
+
+        run_hooks(&job.before, &[("DBKP_JOB", &job.name)]).await?;
+        let backup_file = run_backup(args, collect_metrics.then_some(&mut run)).await?;
+        run_hooks(
+            &job.after,
+            &[("DBKP_JOB", &job.name), ("DBKP_BACKUP", &backup_file)],
+        )
+        .await?;
+
+        Ok(backup_file)
+    }
+    .await;
+
+    if let Err(e) = &result {
+        let error = e.to_string();
+        if let Err(hook_error) = run_hooks(
+            &job.on_failure,
+            &[("DBKP_JOB", &job.name), ("DBKP_ERROR", &error)],
+        )
+        .await
+        {
+            println!("{}", format!("[WARN] {}", hook_error).yellow());
+        }
+    }
+
+    record_metrics(
+        metrics,
+        textfile.as_deref(),
+        &run,
+        started_at.elapsed(),
+        result.is_ok(),
+    );
+
+    result
+}
+
+/// Records a backup run in the daemon metrics and the metrics textfile, whichever are enabled.
+fn record_metrics(
+    metrics: Option<&Metrics>,
+    textfile: Option<&Path>,
+    run: &BackupRun,
+    duration: Duration,
+    succeeded: bool,
+) {
+    if let Some(metrics) = metrics {
+        metrics.record(run, duration, succeeded);
+    }
+
+    if let Some(textfile) = textfile
+        && let Err(e) = record_to_textfile(textfile, run, duration, succeeded)
+    {
+        println!(
+            "{}",
+            format!("[WARN] Failed to write metrics: {:#}", e).yellow()
+        );
+    }
+}
+
+/// Runs a backup and applies the retention, returns the name of the backup. What the metrics need
+/// is collected into `run` when given.
+async fn run_backup(args: cli::BackupArgs, mut run: Option<&mut BackupRun>) -> Result<String> {
+    let mut spinner = Spinner::new("Resolving configuration...");
+    spinner.start();
+
+    let (database_config, storage_config) = match resolve_configs_for_backup(&args).await {
+        Ok(configs) => {
+            spinner.update_message("Configuration resolved, connecting to database...");
+            configs
+        }
+        Err(e) => {
+            spinner.error("Failed to resolve configuration");
+            return Err(e);
+        }
+    };
+
+    let database_name = database_config.name.clone();
+    if let Some(run) = run.as_deref_mut() {
+        run.database = database_name.clone();
+        run.storage = match &storage_config {
+            StorageConfig::Local(config) => config.name.clone(),
+            StorageConfig::S3(config) => config.name.clone(),
+            StorageConfig::Sftp(config) => config.name.clone(),
+            StorageConfig::WebDav(config) => config.name.clone(),
+        };
+    }
+
+    // Fail on an invalid retention before running the backup
+    let retention = match resolve_retention(
+        &args.retention,
+        &args.retention_policy,
+        Some(&database_name),
+    ) {
+        Ok(Some(Retention::MaxAge(0))) => {
+            spinner.error("Invalid retention period");
+            return Err(anyhow!(
+                "Retention period must be at least one day, the new backup would be deleted"
+            ));
+        }
+        Ok(retention) => retention,
+        Err(e) => {
+            spinner.error("Invalid retention");
+            return Err(e);
+        }
+    };
+
+    let masking = match masking_from_cli(&args.masking) {
+        Ok(masking) => masking,
+        Err(e) => {
+            spinner.error("Invalid masking config");
+            return Err(e);
+        }
+    };
+
+    let database_connection = match DatabaseConnection::new(database_config).await {
+        Ok(conn) => {
+            spinner.update_message("Database connected, connecting to storage...");
+            conn
+        }
+        Err(e) => {
+            spinner.error("Failed to connect to database");
+            return Err(e);
+        }
+    };
+
+    let storage_provider = match StorageProvider::new(storage_config) {
+        Ok(provider) => {
+            spinner.update_message("Storage connected, testing connections...");
+            provider
+        }
+        Err(e) => {
+            spinner.error("Failed to connect to storage");
+            return Err(e);
+        }
+    };
+
+    let core = DbBkp::new(database_connection, storage_provider.clone());
+
+    // Test database & storage connection
+    match core.test().await {
+        Ok(_) => spinner.update_message("Connections verified, starting backup..."),
+        Err(e) => {
+            spinner.error("Connection test failed");
+            return Err(e);
+        }
+    }
+
+    let backup_file = match core
+        .backup_with(Some(BackupOptions {
+            name: None,
+            compression_format: args.compression,
+            compression_level: args.compression_level,
+            compression_threads: args.compression_threads,
+            encryption: resolve_encryption_config(&args.encryption),
+            checksum_algorithm: args.checksum,
+            dump_format: args.dump_format,
+            jobs: args.jobs,
+            filters: filters_from_cli(&args.filters),
+            masking,
+        }))
+        .await
+    {
+        Ok(backup_file) => {
+            spinner.success(format!("Backup completed successfully: {}", backup_file));
+            backup_file
+        }
+        Err(e) => {
+            spinner.error("Backup failed");
+            return Err(e);
+        }
+    };
+
+    let mut decisions = None;
+    if let Some(retention) = retention {
+        let mut spinner = Spinner::new(format!(
+            "Applying retention to backups of '{}'...",
+            database_name
+        ));
+        spinner.start();
+
+        let is_policy = matches!(retention, Retention::Policy(_));
+        let retention_decisions = run_retention(
+            &storage_provider,
+            retention,
+            false,
+            Some(database_name.clone()),
+            &mut spinner,
+        )
+        .await?;
+        decisions = is_policy.then_some(retention_decisions);
+    }
+
+    // Metrics are best effort, a backup that went through isn't failed for them
+    if let Some(run) = run {
+        run.manifest = storage_provider
+            .read_manifest(&backup_file)
+            .await
+            .unwrap_or_default();
+        // The retention policy already listed the backups of the database
+        run.storage_usage = match decisions {
+            Some(decisions) => Some(StorageUsage::from_decisions(&decisions)),
+            None => storage_provider
+                .list_with_options(ListOptions {
+                    latest_only: None,
+                    limit: None,
+                    database: Some(database_name),
+                })
+                .await
+                .ok()
+                .map(|entries| StorageUsage::from_entries(&entries)),
+        };
+    }
+
+    Ok(backup_file)
+}
+
 async fn resolve_configs_for_backup(
     args: &cli::BackupArgs,
 ) -> Result<(
     dbkp_core::databases::DatabaseConfig,
     dbkp_core::storage::provider::StorageConfig,
 )> {
-    // Check if we have direct CLI parameters
-    let database_config = if has_database_config(&args.database_config) {
-        database_config_from_cli(&args.database_config)?
-    } else {
-        return Err(anyhow!(
-            "Database configuration parameters are required.\n\
-                Database parameters: --database-type, --database, --host, --port, --username\n\
-                Use 'dbkp backup --help' for more details."
-        ));
-    };
-
-    let storage_config = if has_storage_config(&args.storage_config) {
-        storage_from_cli(&args.storage_config)?
-    } else {
-        return Err(anyhow!(
-            "Storage configuration parameters are required.\n\
-                Storage parameters: --storage-type, --location (and for S3: --bucket, --endpoint, --access-key, --secret-key)\n\
-                Use 'dbkp backup --help' for more details."
-        ));
-    };
+    let database_config = resolve_database_config(&args.database_config, "backup")?;
+    let storage_config = resolve_storage_config(&args.storage_config, "backup")?;
 
     Ok((database_config, storage_config))
 }
@@ -360,72 +860,235 @@ async fn resolve_configs_for_restore(
     dbkp_core::databases::DatabaseConfig,
     dbkp_core::storage::provider::StorageConfig,
 )> {
-    // Check if we have direct CLI parameters
-    let database_config = if has_database_config(&args.database_config) {
-        database_config_from_cli(&args.database_config)?
-    } else {
-        return Err(anyhow!(
-            "Database configuration parameters are required.\n\
-                Database parameters: --database-type, --database, --host, --port, --username\n\
-                Use 'dbkp restore --help' for more details."
-        ));
+    let database_config = resolve_database_config(&args.database_config, "restore")?;
+    let storage_config = resolve_storage_config(&args.storage_config, "restore")?;
+
+    Ok((database_config, storage_config))
+}
+
+enum Retention {
+    MaxAge(u64),
+    Policy(RetentionPolicy),
+}
+
+/// Retention flags take precedence over the policy saved for the database.
+fn resolve_retention(
+    retention: &Option<String>,
+    policy_args: &cli::RetentionPolicyArgs,
+    database: Option<&str>,
+) -> Result<Option<Retention>> {
+    let policy = retention_policy_from_cli(policy_args);
+
+    match (retention, policy) {
+        (Some(_), Some(_)) => Err(anyhow!(
+            "--retention cannot be combined with the --keep-* options"
+        )),
+        (Some(retention), None) => Ok(Some(Retention::MaxAge(parse_retention(retention)?))),
+        (None, Some(policy)) => Ok(Some(Retention::Policy(policy))),
+        (None, None) => Ok(database
+            .and_then(|database| {
+                Configs::load()
+                    .ok()
+                    .and_then(|configs| configs.get_retention_policy(database))
+            })
+            .map(Retention::Policy)),
+    }
+}
+
+/// Applies the retention, returns the decisions of a retention policy and none for a maximum age.
+async fn run_retention(
+    storage_provider: &StorageProvider,
+    retention: Retention,
+    dry_run: bool,
+    database: Option<String>,
+    spinner: &mut Spinner,
+) -> Result<Vec<RetentionDecision>> {
+    let mut decisions = vec![];
+
+    let (entries_deleted, storage_reclaimed) = match retention {
+        Retention::MaxAge(retention_days) => {
+            match storage_provider
+                .cleanup_with_options(CleanupOptions {
+                    retention_days,
+                    dry_run,
+                    database,
+                })
+                .await
+            {
+                Ok(result) => result,
+                Err(e) => {
+                    spinner.error("Cleanup failed");
+                    return Err(e);
+                }
+            }
+        }
+        Retention::Policy(policy) => {
+            decisions = match storage_provider
+                .apply_retention(RetentionOptions {
+                    policy,
+                    dry_run,
+                    database,
+                })
+                .await
+            {
+                Ok(decisions) => decisions,
+                Err(e) => {
+                    spinner.error("Cleanup failed");
+                    return Err(e);
+                }
+            };
+
+            let deleted: Vec<&RetentionDecision> = decisions
+                .iter()
+                .filter(|d| !d.is_kept() && d.delete_error.is_none())
+                .collect();
+
+            (deleted.len(), deleted.iter().map(|d| d.size).sum())
+        }
     };
 
-    let storage_config = if has_storage_config(&args.storage_config) {
-        storage_from_cli(&args.storage_config)?
+    let failed = decisions
+        .iter()
+        .filter(|d| d.delete_error.is_some())
+        .count();
+
+    if dry_run {
+        spinner.success(format!(
+            "Dry run completed: {} entries would be deleted, {} storage would be reclaimed",
+            entries_deleted, storage_reclaimed
+        ));
+    } else if failed > 0 {
+        spinner.error(format!(
+            "Cleanup failed: {} entries deleted, {} could not be deleted",
+            entries_deleted, failed
+        ));
     } else {
-        return Err(anyhow!(
-            "Storage configuration parameters are required.\n\
-                Storage parameters: --storage-type, --location (and for S3: --bucket, --endpoint, --access-key, --secret-key)\n\
-                Use 'dbkp restore --help' for more details."
+        spinner.success(format!(
+            "Cleanup completed: {} entries deleted, {} storage reclaimed",
+            entries_deleted, storage_reclaimed
         ));
-    };
+    }
 
-    Ok((database_config, storage_config))
+    print_retention_decisions(&decisions, dry_run);
+
+    if failed > 0 {
+        return Err(anyhow!("Failed to delete {} backup(s)", failed));
+    }
+
+    Ok(decisions)
 }
 
-async fn resolve_storage_config(
-    storage_args: &Option<cli::StorageArgs>,
-) -> Result<dbkp_core::storage::provider::StorageConfig> {
-    if let Some(storage_config) = storage_args {
-        if has_storage_config(storage_config) {
-            storage_from_cli(storage_config)
+fn print_retention_decisions(decisions: &[RetentionDecision], dry_run: bool) {
+    let delete_label = if dry_run {
+        "DELETE (dry run)"
+    } else {
+        "DELETE"
+    };
+
+    println!();
+
+    for decision in decisions {
+        if decision.is_kept() {
+            let reasons: Vec<String> = decision.reasons.iter().map(|r| r.to_string()).collect();
+            println!(
+                "  {} {} ({})",
+                "KEEP".green(),
+                decision.path,
+                reasons.join(", ")
+            );
+        } else if let Some(error) = &decision.delete_error {
+            println!("  {} {} ({})", "FAILED".red(), decision.path, error);
         } else {
-            Err(anyhow!(
-                "Storage configuration parameters are required.\n\
-                    Storage parameters: --storage-type, --location (and for S3: --bucket, --endpoint, --access-key, --secret-key)\n\
-                    Use command --help for more details."
-            ))
+            println!("  {} {}", delete_label.red(), decision.path);
         }
+    }
+}
+
+/// The saved config with the encryption flags and environment variables overriding its fields,
+/// or else the flags alone.
+fn resolve_encryption_config(
+    args: &cli::EncryptionArgs,
+) -> Option<dbkp_core::encryption::EncryptionConfig> {
+    match Configs::load()
+        .ok()
+        .and_then(|configs| configs.get_encryption_config())
+    {
+        Some(mut config) => {
+            override_encryption_config(&mut config, args);
+            Some(config)
+        }
+        None => encryption_from_cli(args),
+    }
+}
+
+/// A saved configuration selected with `--db`, with the other flags overriding its fields, or else
+/// the flags alone.
+fn resolve_database_config(
+    args: &cli::DatabaseArgs,
+    command: &str,
+) -> Result<dbkp_core::databases::DatabaseConfig> {
+    if let Some(saved_database) = &args.saved_database {
+        let mut config = Configs::load()?.find_database_config(saved_database)?;
+        override_database_config(&mut config, args)?;
+
+        Ok(config)
+    } else if has_database_config(args) {
+        database_config_from_cli(args)
+    } else {
+        Err(anyhow!(
+            "Database configuration parameters are required.\n\
+                Database parameters: --db with a saved configuration, or --database-type, --database, --host, --port, --username (only --database-type and --database for SQLite)\n\
+                Use 'dbkp {} --help' for more details.",
+            command
+        ))
+    }
+}
+
+/// A saved configuration selected with `--storage`, with the other flags overriding its fields,
+/// or else the flags alone.
+fn resolve_storage_config(
+    args: &cli::StorageArgs,
+    command: &str,
+) -> Result<dbkp_core::storage::provider::StorageConfig> {
+    if let Some(saved_storage) = &args.saved_storage {
+        let mut config = Configs::load()?.find_storage_config(saved_storage)?;
+        override_storage_config(&mut config, args)?;
+
+        Ok(config)
+    } else if has_storage_config(args) {
+        storage_from_cli(args)
     } else {
         Err(anyhow!(
             "Storage configuration parameters are required.\n\
-                Storage parameters: --storage-type, --location (and for S3: --bucket, --endpoint, --access-key, --secret-key)\n\
-                Use command --help for more details."
+                Storage parameters: --storage with a saved configuration, or --storage-type, --location (and for S3: --bucket, --endpoint, --access-key, --secret-key, for SFTP: --sftp-host, --sftp-username, --sftp-password or --sftp-key-path, for WebDAV: --webdav-endpoint)\n\
+                Use 'dbkp {} --help' for more details.",
+            command
         ))
     }
 }
 
 async fn resolve_backup_name(
     args: &cli::RestoreArgs,
+    database_config: &dbkp_core::databases::DatabaseConfig,
     storage_config: &dbkp_core::storage::provider::StorageConfig,
 ) -> Result<String> {
     if let Some(name) = &args.name {
         Ok(name.clone())
     } else if args.latest {
-        // Get the latest backup
+        // Get the latest backup of the database being restored
         let storage_provider = StorageProvider::new(storage_config.clone())?;
         let entries = storage_provider
             .list_with_options(ListOptions {
                 latest_only: Some(true),
                 limit: Some(1),
+                database: Some(database_config.name.clone()),
             })
             .await?;
 
         if let Some(entry) = entries.first() {
-            Ok(entry.metadata.name.clone())
+            Ok(entry.path.clone())
         } else {
-            Err(anyhow!("No backups found"))
+            Err(anyhow!("No backups found for {}", database_config.name))
         }
     } else {
         Err(anyhow!("Either --name or --latest must be specified"))
@@ -433,11 +1096,19 @@ async fn resolve_backup_name(
 }
 
 fn has_database_config(args: &cli::DatabaseArgs) -> bool {
+    // SQLite only needs the database file path
+    if args.database_type.as_deref() == Some("sqlite") {
+        return args.database.is_some();
+    }
+
+    // Redis servers without ACL users only check the password
+    let has_username = args.username.is_some() || args.database_type.as_deref() == Some("redis");
+
     args.database_type.is_some()
         && args.database.is_some()
         && args.host.is_some()
         && args.port.is_some()
-        && args.username.is_some()
+        && has_username
 }
 
 fn has_storage_config(args: &cli::StorageArgs) -> bool {
@@ -451,6 +1122,19 @@ fn has_storage_config(args: &cli::StorageArgs) -> bool {
             && args.access_key.is_some()
             && args.secret_key.is_some()
             && args.location.is_some()
+    } else if args.storage_type.as_deref() == Some("sftp") {
+        // For SFTP, we need host, username, a password or a key, and location
+        args.sftp.as_ref().is_some_and(|sftp| {
+            sftp.sftp_host.is_some()
+                && sftp.sftp_username.is_some()
+                && (sftp.sftp_password.is_some() || sftp.sftp_key_path.is_some())
+        }) && args.location.is_some()
+    } else if args.storage_type.as_deref() == Some("webdav") {
+        // For WebDAV, we need endpoint and location, credentials are optional
+        args.webdav
+            .as_ref()
+            .is_some_and(|webdav| webdav.webdav_endpoint.is_some())
+            && args.location.is_some()
     } else {
         false
     }
@@ -0,0 +1,489 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::{Context, Result, anyhow};
+use chrono::Utc;
+use dbkp_core::{manifest::BackupManifest, retention::RetentionDecision, storage::Entry};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+const LAST_SUCCESS: &str = "dbkp_backup_last_success_timestamp_seconds";
+const LAST_FAILURE: &str = "dbkp_backup_last_failure_timestamp_seconds";
+const LAST_DURATION: &str = "dbkp_backup_last_duration_seconds";
+const LAST_SIZE: &str = "dbkp_backup_last_size_bytes";
+const LAST_UNCOMPRESSED_SIZE: &str = "dbkp_backup_last_uncompressed_size_bytes";
+const LAST_COMPRESSION_RATIO: &str = "dbkp_backup_last_compression_ratio";
+const SUCCESSES: &str = "dbkp_backup_successes_total";
+const FAILURES: &str = "dbkp_backup_failures_total";
+const STORAGE_BACKUPS: &str = "dbkp_storage_backups";
+const STORAGE_SIZE: &str = "dbkp_storage_size_bytes";
+
+/// Name, type and help of every metric, in the order they're rendered.
+const FAMILIES: &[(&str, &str, &str)] = &[
+    (
+        LAST_SUCCESS,
+        "gauge",
+        "Unix time the last successful backup finished.",
+    ),
+    (
+        LAST_FAILURE,
+        "gauge",
+        "Unix time the last failed backup finished.",
+    ),
+    (
+        LAST_DURATION,
+        "gauge",
+        "Duration of the last backup with its retention, successful or not.",
+    ),
+    (
+        LAST_SIZE,
+        "gauge",
+        "Bytes written to the storage by the last successful backup.",
+    ),
+    (
+        LAST_UNCOMPRESSED_SIZE,
+        "gauge",
+        "Size of the dump of the last successful backup before compression.",
+    ),
+    (
+        LAST_COMPRESSION_RATIO,
+        "gauge",
+        "Uncompressed size divided by the stored size of the last successful backup.",
+    ),
+    (SUCCESSES, "counter", "Successful backups."),
+    (FAILURES, "counter", "Failed backups."),
+    (
+        STORAGE_BACKUPS,
+        "gauge",
+        "Backups of the database kept by the storage, counted after its last backup.",
+    ),
+    (
+        STORAGE_SIZE,
+        "gauge",
+        "Bytes of the backups of the database kept by the storage, counted after its last backup.",
+    ),
+];
+
+/// Most a request head can be, the endpoint only serves small GET requests.
+const MAX_REQUEST_SIZE: usize = 8192;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+type Labels = Vec<(String, String)>;
+
+/// What a backup run reports to the metrics, filled in as the backup goes.
+#[derive(Default)]
+pub struct BackupRun {
+    /// Name of the job, empty outside of job files.
+    pub job: String,
+    pub database: String,
+    pub storage: String,
+    pub manifest: Option<BackupManifest>,
+    /// Backups of the database in the storage once the retention is applied.
+    pub storage_usage: Option<StorageUsage>,
+}
+
+/// Number and size of the backups of a database in a storage.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StorageUsage {
+    pub backups: u64,
+    pub size: u64,
+}
+
+impl StorageUsage {
+    pub fn from_entries(entries: &[Entry]) -> Self {
+        Self {
+            backups: entries.len() as u64,
+            size: entries
+                .iter()
+                .map(|entry| entry.metadata.content_length)
+                .sum(),
+        }
+    }
+
+    /// The backups kept by the retention and those it failed to delete.
+    pub fn from_decisions(decisions: &[RetentionDecision]) -> Self {
+        let remaining = decisions
+            .iter()
+            .filter(|decision| decision.is_kept() || decision.delete_error.is_some());
+
+        remaining.fold(Self::default(), |usage, decision| Self {
+            backups: usage.backups + 1,
+            size: usage.size + decision.size,
+        })
+    }
+}
+
+/// Prometheus metrics of backup runs, shared by the daemon and its `/metrics` endpoint.
+#[derive(Clone, Default)]
+pub struct Metrics {
+    series: Arc<Mutex<BTreeMap<&'static str, BTreeMap<Labels, f64>>>>,
+}
+
+impl Metrics {
+    pub fn record(&self, run: &BackupRun, duration: Duration, succeeded: bool) {
+        let mut series = self.series.lock().unwrap();
+        let mut set = |family: &'static str, labels: &Labels, value: f64| {
+            series
+                .entry(family)
+                .or_default()
+                .insert(labels.clone(), value);
+        };
+
+        let labels: Labels = vec![
+            ("job_name".into(), run.job.clone()),
+            ("database".into(), run.database.clone()),
+            ("storage".into(), run.storage.clone()),
+        ];
+        let now = Utc::now().timestamp() as f64;
+
+        set(LAST_DURATION, &labels, duration.as_secs_f64());
+        if succeeded {
+            set(LAST_SUCCESS, &labels, now);
+            if let Some(manifest) = &run.manifest {
+                set(LAST_SIZE, &labels, manifest.compressed_size as f64);
+                set(
+                    LAST_UNCOMPRESSED_SIZE,
+                    &labels,
+                    manifest.uncompressed_size as f64,
+                );
+                if manifest.compressed_size > 0 {
+                    set(
+                        LAST_COMPRESSION_RATIO,
+                        &labels,
+                        manifest.uncompressed_size as f64 / manifest.compressed_size as f64,
+                    );
+                }
+            }
+        } else {
+            set(LAST_FAILURE, &labels, now);
+        }
+
+        if let Some(usage) = &run.storage_usage {
+            let labels: Labels = vec![
+                ("database".into(), run.database.clone()),
+                ("storage".into(), run.storage.clone()),
+            ];
+            set(STORAGE_BACKUPS, &labels, usage.backups as f64);
+            set(STORAGE_SIZE, &labels, usage.size as f64);
+        }
+
+        // Both counters exist from the first run so `increase()` sees the first failure
+        for (family, increment) in [(SUCCESSES, succeeded), (FAILURES, !succeeded)] {
+            *series
+                .entry(family)
+                .or_default()
+                .entry(labels.clone())
+                .or_default() += if increment { 1.0 } else { 0.0 };
+        }
+    }
+
+    /// The metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let series = self.series.lock().unwrap();
+        let mut output = String::new();
+
+        for (family, kind, help) in FAMILIES {
+            let Some(family_series) = series.get(family).filter(|series| !series.is_empty()) else {
+                continue;
+            };
+
+            output.push_str(&format!(
+                "# HELP {} {}\n# TYPE {} {}\n",
+                family, help, family, kind
+            ));
+            for (labels, value) in family_series {
+                let labels: Vec<String> = labels
+                    .iter()
+                    .map(|(name, value)| format!("{}=\"{}\"", name, escape_label(value)))
+                    .collect();
+                output.push_str(&format!("{}{{{}}} {}\n", family, labels.join(","), value));
+            }
+        }
+
+        output
+    }
+
+    /// Reads the metrics back from a textfile written by `write_textfile`, so counters and the
+    /// last success keep going across runs of the CLI.
+    pub fn load_textfile(path: &Path) -> Result<Self> {
+        let metrics = Self::default();
+        if !path.exists() {
+            return Ok(metrics);
+        }
+
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read metrics textfile {}", path.display()))?;
+
+        {
+            let mut series = metrics.series.lock().unwrap();
+            for line in content.lines().filter(|line| !line.starts_with('#')) {
+                let Some((family, labels, value)) = parse_sample(line) else {
+                    continue;
+                };
+                // Metrics of older versions are dropped
+                if let Some((family, _, _)) = FAMILIES.iter().find(|(name, _, _)| *name == family) {
+                    series.entry(family).or_default().insert(labels, value);
+                }
+            }
+        }
+
+        Ok(metrics)
+    }
+
+    /// Writes the metrics for the textfile collector of node_exporter, through a temporary file
+    /// so it never reads a partial one.
+    pub fn write_textfile(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let temp_path = path.with_extension("prom.tmp");
+        fs::write(&temp_path, self.render())?;
+        fs::rename(&temp_path, path)
+            .with_context(|| format!("Failed to write metrics textfile {}", path.display()))
+    }
+}
+
+/// Adds a run to the metrics textfile at `path`.
+pub fn record_to_textfile(
+    path: &Path,
+    run: &BackupRun,
+    duration: Duration,
+    succeeded: bool,
+) -> Result<()> {
+    // Jobs of the daemon can share a textfile, its update mustn't interleave
+    static TEXTFILE_LOCK: Mutex<()> = Mutex::new(());
+    let _lock = TEXTFILE_LOCK.lock().unwrap();
+
+    let metrics = Metrics::load_textfile(path)?;
+    metrics.record(run, duration, succeeded);
+    metrics.write_textfile(path)
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Parses a `family{name="value",...} value` line.
+fn parse_sample(line: &str) -> Option<(&str, Labels, f64)> {
+    let (family, rest) = line.split_once('{')?;
+    let mut labels = vec![];
+    let mut chars = rest.char_indices();
+
+    loop {
+        let (start, c) = chars.next()?;
+        if c == '}' {
+            let value = rest[start + 1..].trim().parse().ok()?;
+            return Some((family, labels, value));
+        }
+        if c == ',' {
+            continue;
+        }
+
+        let name_end = rest[start..].find("=\"")? + start;
+        let name = rest[start..name_end].to_string();
+        chars.nth(name_end - start);
+
+        let mut value = String::new();
+        loop {
+            match chars.next()?.1 {
+                '"' => break,
+                '\\' => match chars.next()?.1 {
+                    'n' => value.push('\n'),
+                    c => value.push(c),
+                },
+                c => value.push(c),
+            }
+        }
+        labels.push((name, value));
+    }
+}
+
+/// Serves the metrics on `/metrics` until the task is dropped.
+pub async fn serve(listener: TcpListener, metrics: Metrics) {
+    loop {
+        let Ok((stream, _)) = listener.accept().await else {
+            continue;
+        };
+
+        let metrics = metrics.clone();
+        tokio::spawn(async move {
+            let _ = tokio::time::timeout(REQUEST_TIMEOUT, respond(stream, &metrics)).await;
+        });
+    }
+}
+
+async fn respond(mut stream: TcpStream, metrics: &Metrics) -> Result<()> {
+    let mut request = vec![];
+    let mut buffer = [0; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let n = stream.read(&mut buffer).await?;
+        if n == 0 || request.len() + n > MAX_REQUEST_SIZE {
+            return Err(anyhow!("Invalid request"));
+        }
+        request.extend_from_slice(&buffer[..n]);
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut request_line = request.lines().next().unwrap_or_default().split(' ');
+    let method = request_line.next().unwrap_or_default();
+    let path = request_line.next().unwrap_or_default();
+    let path = path.split('?').next().unwrap_or_default();
+
+    let (status, body) = match (method, path) {
+        ("GET", "/metrics") => ("200 OK", metrics.render()),
+        ("GET", _) => (
+            "404 Not Found",
+            "Not found, metrics are on /metrics\n".into(),
+        ),
+        _ => ("405 Method Not Allowed", "Method not allowed\n".into()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(job: &str) -> BackupRun {
+        BackupRun {
+            job: job.into(),
+            database: "shop".into(),
+            storage: "nas".into(),
+            manifest: None,
+            storage_usage: None,
+        }
+    }
+
+    #[test]
+    fn test_render_metrics() {
+        let metrics = Metrics::default();
+        metrics.record(&run("nightly"), Duration::from_secs(3), false);
+        metrics.record(&run("nightly"), Duration::from_secs(2), true);
+        metrics.record(&run("hourly \"quick\""), Duration::from_secs(1), true);
+
+        let output = metrics.render();
+        let labels = r#"{job_name="nightly",database="shop",storage="nas"}"#;
+        assert!(output.contains(&format!("dbkp_backup_successes_total{} 1\n", labels)));
+        assert!(output.contains(&format!("dbkp_backup_failures_total{} 1\n", labels)));
+        assert!(output.contains(&format!("dbkp_backup_last_duration_seconds{} 2\n", labels)));
+        assert!(output.contains("# TYPE dbkp_backup_failures_total counter\n"));
+        assert!(output.contains(r#"job_name="hourly \"quick\"""#));
+        assert!(
+            !output.contains("dbkp_backup_last_size_bytes"),
+            "Sizes are only known from a manifest"
+        );
+    }
+
+    #[test]
+    fn test_storage_usage() {
+        let metrics = Metrics::default();
+        let mut shop = run("nightly");
+        shop.storage_usage = Some(StorageUsage {
+            backups: 3,
+            size: 300,
+        });
+        let mut blog = run("nightly");
+        blog.database = "blog".into();
+        blog.storage_usage = Some(StorageUsage {
+            backups: 1,
+            size: 50,
+        });
+
+        metrics.record(&shop, Duration::from_secs(1), true);
+        metrics.record(&blog, Duration::from_secs(1), true);
+        shop.storage_usage = Some(StorageUsage {
+            backups: 2,
+            size: 200,
+        });
+        metrics.record(&shop, Duration::from_secs(1), true);
+
+        // Each run only updates the usage of its own database
+        let output = metrics.render();
+        let labels = r#"{database="shop",storage="nas"}"#;
+        assert!(output.contains(&format!("dbkp_storage_backups{} 2\n", labels)));
+        assert!(output.contains(&format!("dbkp_storage_size_bytes{} 200\n", labels)));
+        assert!(output.contains(r#"dbkp_storage_backups{database="blog",storage="nas"} 1"#));
+    }
+
+    #[test]
+    fn test_metrics_textfile() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dbkp.prom");
+
+        record_to_textfile(&path, &run("nightly"), Duration::from_secs(2), true).unwrap();
+        let first = Metrics::load_textfile(&path).unwrap().render();
+        assert_eq!(first, fs::read_to_string(&path).unwrap());
+
+        record_to_textfile(
+            &path,
+            &run("hourly \"quick\"\n"),
+            Duration::from_secs(1),
+            false,
+        )
+        .unwrap();
+        record_to_textfile(&path, &run("nightly"), Duration::from_secs(2), false).unwrap();
+
+        let output = fs::read_to_string(&path).unwrap();
+        let labels = r#"{job_name="nightly",database="shop",storage="nas"}"#;
+        assert!(output.contains(&format!("dbkp_backup_successes_total{} 1\n", labels)));
+        assert!(output.contains(&format!("dbkp_backup_failures_total{} 1\n", labels)));
+        assert!(
+            output.contains(&format!(
+                "dbkp_backup_last_success_timestamp_seconds{}",
+                labels
+            )),
+            "A failure shouldn't forget the last success"
+        );
+        assert!(output.contains(r#"job_name="hourly \"quick\"\n""#));
+    }
+
+    #[tokio::test]
+    async fn test_serve_metrics() {
+        let metrics = Metrics::default();
+        metrics.record(&run("nightly"), Duration::from_secs(2), true);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(serve(listener, metrics));
+
+        let get = |path: &'static str| async move {
+            let mut stream = TcpStream::connect(address).await.unwrap();
+            stream
+                .write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).as_bytes())
+                .await
+                .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response
+        };
+
+        let response = get("/metrics").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("dbkp_backup_successes_total{"));
+
+        assert!(get("/").await.starts_with("HTTP/1.1 404"));
+
+        server.abort();
+    }
+}
@@ -5,6 +5,13 @@ use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
 
+static ANIMATION_DISABLED: AtomicBool = AtomicBool::new(false);
+
+/// Stop animating spinners, only their final message is printed
+pub fn disable_animation() {
+    ANIMATION_DISABLED.store(true, Ordering::Relaxed);
+}
+
 /// Animated spinner with color changes
 pub struct Spinner {
     running: Arc<AtomicBool>,
@@ -24,8 +31,8 @@ impl Spinner {
 
     /// Start the spinner animation
     pub fn start(&mut self) {
-        if self.running.load(Ordering::Relaxed) {
-            return; // Already running
+        if self.running.load(Ordering::Relaxed) || ANIMATION_DISABLED.load(Ordering::Relaxed) {
+            return; // Already running or disabled
         }
 
         self.running.store(true, Ordering::Relaxed);
@@ -4,11 +4,12 @@ use anyhow::{Result, anyhow};
 use async_trait::async_trait;
 use crossterm::event::{Event as CrosstermEvent, KeyCode};
 use dbkp_core::{
-    DbBkp,
-    databases::{DatabaseConfig, DatabaseConnection},
+    BackupOptions, DbBkp,
+    databases::{DatabaseConfig, DatabaseConnection, DumpContent, DumpFilters},
     storage::provider::{StorageConfig, StorageProvider},
 };
 use tokio::sync::mpsc;
+use tui_input::{Input, backend::crossterm::EventHandler};
 
 use crate::tui::{
     backup::view::BackupView,
@@ -20,10 +21,32 @@ use crate::tui::{
     view::View,
 };
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum SelectionMode {
     DB,
     Storage,
+    IncludeTables,
+    ExcludeTables,
+    ExcludeTableData,
+    IncludeSchemas,
+    ExcludeSchemas,
+    Content,
+}
+
+impl SelectionMode {
+    fn is_filter_input(&self) -> bool {
+        !matches!(self, SelectionMode::DB | SelectionMode::Storage)
+    }
+}
+
+/// Comma separated list of an input, blank entries are dropped.
+fn split_list(input: &Input) -> Vec<String> {
+    input
+        .value()
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
 }
 
 #[derive(Clone, Debug)]
@@ -36,6 +59,12 @@ pub struct BackupModel {
     pub highlight_storage_id: String,
     pub selected_database_id: Option<String>,
     pub selected_storage_id: Option<String>,
+    pub include_tables_input: Input,
+    pub exclude_tables_input: Input,
+    pub exclude_table_data_input: Input,
+    pub include_schemas_input: Input,
+    pub exclude_schemas_input: Input,
+    pub content_input: Input,
     pub event_sender: mpsc::UnboundedSender<Event>,
 }
 
@@ -53,6 +82,8 @@ impl BackupModel {
                 let storage_id = match storage_config {
                     StorageConfig::Local(config) => config.id.clone(),
                     StorageConfig::S3(config) => config.id.clone(),
+                    StorageConfig::Sftp(config) => config.id.clone(),
+                    StorageConfig::WebDav(config) => config.id.clone(),
                 };
 
                 return Ok(BackupModel {
@@ -64,6 +95,12 @@ impl BackupModel {
                     highlight_storage_id: storage_id,
                     selected_database_id: None,
                     selected_storage_id: None,
+                    include_tables_input: Input::default(),
+                    exclude_tables_input: Input::default(),
+                    exclude_table_data_input: Input::default(),
+                    include_schemas_input: Input::default(),
+                    exclude_schemas_input: Input::default(),
+                    content_input: Input::new("all".to_string()),
                     event_sender,
                 });
             }
@@ -92,6 +129,8 @@ impl BackupModel {
                     .position(|config| match config {
                         StorageConfig::Local(config) => config.id == self.highlight_storage_id,
                         StorageConfig::S3(config) => config.id == self.highlight_storage_id,
+                        StorageConfig::Sftp(config) => config.id == self.highlight_storage_id,
+                        StorageConfig::WebDav(config) => config.id == self.highlight_storage_id,
                     })
                     .unwrap();
 
@@ -99,8 +138,11 @@ impl BackupModel {
                 self.highlight_storage_id = match &storage_configs[next_config_index] {
                     StorageConfig::Local(config) => config.id.clone(),
                     StorageConfig::S3(config) => config.id.clone(),
+                    StorageConfig::Sftp(config) => config.id.clone(),
+                    StorageConfig::WebDav(config) => config.id.clone(),
                 }
             }
+            _ => {}
         }
     }
 
@@ -129,6 +171,8 @@ impl BackupModel {
                     .position(|config| match config {
                         StorageConfig::Local(config) => config.id == self.highlight_storage_id,
                         StorageConfig::S3(config) => config.id == self.highlight_storage_id,
+                        StorageConfig::Sftp(config) => config.id == self.highlight_storage_id,
+                        StorageConfig::WebDav(config) => config.id == self.highlight_storage_id,
                     })
                     .unwrap();
 
@@ -141,15 +185,79 @@ impl BackupModel {
                 self.highlight_storage_id = match &storage_configs[previous_config_index] {
                     StorageConfig::Local(config) => config.id.clone(),
                     StorageConfig::S3(config) => config.id.clone(),
+                    StorageConfig::Sftp(config) => config.id.clone(),
+                    StorageConfig::WebDav(config) => config.id.clone(),
                 }
             }
+            _ => {}
         }
     }
 
     pub fn cycle_through_columns(&mut self, _direction: bool) {
         self.selection_mode = match self.selection_mode {
-            SelectionMode::Storage => SelectionMode::DB,
             SelectionMode::DB => SelectionMode::Storage,
+            _ => SelectionMode::DB,
+        }
+    }
+
+    /// Tab walks through both lists and then the filter inputs.
+    pub fn cycle_through_inputs(&mut self, forward: bool) {
+        let modes = [
+            SelectionMode::DB,
+            SelectionMode::Storage,
+            SelectionMode::IncludeTables,
+            SelectionMode::ExcludeTables,
+            SelectionMode::ExcludeTableData,
+            SelectionMode::IncludeSchemas,
+            SelectionMode::ExcludeSchemas,
+            SelectionMode::Content,
+        ];
+
+        let index = modes
+            .iter()
+            .position(|mode| *mode == self.selection_mode)
+            .unwrap();
+
+        let next_index = if forward {
+            (index + 1) % modes.len()
+        } else {
+            (index + modes.len() - 1) % modes.len()
+        };
+
+        self.selection_mode = modes[next_index].clone();
+    }
+
+    fn current_input(&mut self) -> Option<&mut Input> {
+        match self.selection_mode {
+            SelectionMode::DB | SelectionMode::Storage => None,
+            SelectionMode::IncludeTables => Some(&mut self.include_tables_input),
+            SelectionMode::ExcludeTables => Some(&mut self.exclude_tables_input),
+            SelectionMode::ExcludeTableData => Some(&mut self.exclude_table_data_input),
+            SelectionMode::IncludeSchemas => Some(&mut self.include_schemas_input),
+            SelectionMode::ExcludeSchemas => Some(&mut self.exclude_schemas_input),
+            SelectionMode::Content => Some(&mut self.content_input),
+        }
+    }
+
+    pub fn filters(&self) -> Result<Option<DumpFilters>> {
+        let content = match self.content_input.value().trim() {
+            "" => DumpContent::All,
+            content => content.parse()?,
+        };
+
+        let filters = DumpFilters {
+            include_tables: split_list(&self.include_tables_input),
+            exclude_tables: split_list(&self.exclude_tables_input),
+            include_schemas: split_list(&self.include_schemas_input),
+            exclude_schemas: split_list(&self.exclude_schemas_input),
+            exclude_table_data: split_list(&self.exclude_table_data_input),
+            content,
+        };
+
+        if filters.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(filters))
         }
     }
 
@@ -172,14 +280,19 @@ impl BackupModel {
                     .find(|config| match config {
                         StorageConfig::Local(config) => config.id == self.highlight_storage_id,
                         StorageConfig::S3(config) => config.id == self.highlight_storage_id,
+                        StorageConfig::Sftp(config) => config.id == self.highlight_storage_id,
+                        StorageConfig::WebDav(config) => config.id == self.highlight_storage_id,
                     })
                     .unwrap();
 
                 self.selected_storage_id = match selected_config {
                     StorageConfig::Local(config) => Some(config.id.clone()),
                     StorageConfig::S3(config) => Some(config.id.clone()),
+                    StorageConfig::Sftp(config) => Some(config.id.clone()),
+                    StorageConfig::WebDav(config) => Some(config.id.clone()),
                 };
             }
+            _ => {}
         };
 
         let database_config = match &self.selected_database_id {
@@ -190,6 +303,8 @@ impl BackupModel {
         let storage_config = match &self.selected_storage_id {
             Some(id) => storage_configs.iter().find(|config| match config {
                 StorageConfig::S3(config) => config.id == *id,
+                StorageConfig::Sftp(config) => config.id == *id,
+                StorageConfig::WebDav(config) => config.id == *id,
                 StorageConfig::Local(config) => config.id == *id,
             }),
             None => None,
@@ -209,8 +324,22 @@ impl BackupModel {
         database_config: DatabaseConfig,
         storage_config: StorageConfig,
     ) -> Result<()> {
-        self.in_progress = true;
         let sender = self.event_sender.clone();
+        let filters = match self.filters() {
+            Ok(filters) => filters,
+            Err(e) => {
+                let error_view = ErrorView::new(ErrorModel::new(
+                    sender.clone(),
+                    Some("Invalid Backup Filters".to_string()),
+                    e.to_string(),
+                ));
+                let _ = sender.send(Event::View(Box::new(error_view)));
+                return Ok(());
+            }
+        };
+
+        self.in_progress = true;
+        let encryption = self.configs.get_encryption_config();
 
         let home_view = HomeView::new(HomeModel::new(sender.clone())?);
 
@@ -258,7 +387,14 @@ impl BackupModel {
 
             let db_bkp = DbBkp::new(database_connection, storage_provider);
 
-            match db_bkp.backup().await {
+            match db_bkp
+                .backup_with(Some(BackupOptions {
+                    encryption,
+                    filters,
+                    ..Default::default()
+                }))
+                .await
+            {
                 Ok(_) => {
                     let _ = sender.send(Event::View(Box::new(home_view))).unwrap();
                 }
@@ -291,10 +427,38 @@ impl Model for BackupModel {
 
     async fn handle_event(&mut self, event: &CrosstermEvent) -> Result<()> {
         if let CrosstermEvent::Key(key) = event {
+            if self.selection_mode.is_filter_input() {
+                match key.code {
+                    KeyCode::Esc => {
+                        self.exit = true;
+                    }
+                    KeyCode::Tab | KeyCode::Down | KeyCode::Enter => {
+                        self.cycle_through_inputs(true);
+                    }
+                    KeyCode::BackTab | KeyCode::Up => {
+                        self.cycle_through_inputs(false);
+                    }
+                    _ => {
+                        if let Some(input) = self.current_input() {
+                            input.handle_event(event);
+                        }
+                    }
+                }
+
+                self.event_sender.send(Event::Tick)?;
+                return Ok(());
+            }
+
             match key.code {
                 KeyCode::Esc => {
                     self.exit = true;
                 }
+                KeyCode::Tab => {
+                    self.cycle_through_inputs(true);
+                }
+                KeyCode::BackTab => {
+                    self.cycle_through_inputs(false);
+                }
                 KeyCode::Down => {
                     self.select_next();
                 }
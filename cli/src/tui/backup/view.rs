@@ -9,7 +9,7 @@ use ratatui::{
 use crate::tui::{
     backup::model::{BackupModel, SelectionMode},
     model::Model,
-    utils::{ListItem, create_list},
+    utils::{ListItem, create_list, render_input},
     view::View,
 };
 
@@ -51,6 +51,8 @@ impl View for BackupView {
                 let config_id = match config {
                     StorageConfig::Local(config) => &config.id,
                     StorageConfig::S3(config) => &config.id,
+                    StorageConfig::Sftp(config) => &config.id,
+                    StorageConfig::WebDav(config) => &config.id,
                 };
 
                 Some(config_id.clone()) == self.backup_model.selected_storage_id
@@ -62,6 +64,8 @@ impl View for BackupView {
                 let storage_name = match storage_config {
                     StorageConfig::Local(config) => &config.name,
                     StorageConfig::S3(config) => &config.name,
+                    StorageConfig::Sftp(config) => &config.name,
+                    StorageConfig::WebDav(config) => &config.name,
                 };
 
                 let text = format!(
@@ -75,10 +79,17 @@ impl View for BackupView {
             }
         }
 
+        let [lists, filters1, filters2] = Layout::vertical([
+            Constraint::Min(0),
+            Constraint::Length(3),
+            Constraint::Length(3),
+        ])
+        .areas(frame.area());
+
         let [column1, column2] =
             Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
                 .flex(Flex::Center)
-                .areas(frame.area());
+                .areas(lists);
 
         let database_items: Vec<ListItem> = databse_configs
             .iter()
@@ -112,11 +123,15 @@ impl View for BackupView {
                 let current_id = match config {
                     StorageConfig::Local(config) => config.id.clone(),
                     StorageConfig::S3(config) => config.id.clone(),
+                    StorageConfig::Sftp(config) => config.id.clone(),
+                    StorageConfig::WebDav(config) => config.id.clone(),
                 };
 
                 let current_name = match config {
                     StorageConfig::Local(config) => config.name.clone(),
                     StorageConfig::S3(config) => config.name.clone(),
+                    StorageConfig::Sftp(config) => config.name.clone(),
+                    StorageConfig::WebDav(config) => config.name.clone(),
                 };
 
                 let active = matches!(self.backup_model.selection_mode, SelectionMode::Storage);
@@ -141,5 +156,65 @@ impl View for BackupView {
             .border_set(symbols::border::ROUNDED);
         let list = create_list(storage_items).block(block);
         frame.render_widget(list, column2);
+
+        let [include_tables, exclude_tables, exclude_table_data] =
+            Layout::horizontal([Constraint::Ratio(1, 3); 3]).areas(filters1);
+        let [include_schemas, exclude_schemas, content] =
+            Layout::horizontal([Constraint::Ratio(1, 3); 3]).areas(filters2);
+
+        let model = &self.backup_model;
+        let inputs = [
+            (
+                &model.include_tables_input,
+                "Include Tables (comma separated)",
+                SelectionMode::IncludeTables,
+                include_tables,
+            ),
+            (
+                &model.exclude_tables_input,
+                "Exclude Tables",
+                SelectionMode::ExcludeTables,
+                exclude_tables,
+            ),
+            (
+                &model.exclude_table_data_input,
+                "Exclude Table Data",
+                SelectionMode::ExcludeTableData,
+                exclude_table_data,
+            ),
+            (
+                &model.include_schemas_input,
+                "Include Schemas",
+                SelectionMode::IncludeSchemas,
+                include_schemas,
+            ),
+            (
+                &model.exclude_schemas_input,
+                "Exclude Schemas",
+                SelectionMode::ExcludeSchemas,
+                exclude_schemas,
+            ),
+            (
+                &model.content_input,
+                "Content (all, schema-only, data-only)",
+                SelectionMode::Content,
+                content,
+            ),
+        ];
+
+        for (input, title, mode, area) in inputs {
+            let width = area.width.max(3) - 3;
+            let scroll = input.visual_scroll(width as usize);
+
+            render_input(
+                frame,
+                input,
+                title,
+                model.selection_mode == mode,
+                area,
+                scroll,
+                false,
+            );
+        }
     }
 }
@@ -1,13 +1,21 @@
 use anyhow::{Result, anyhow};
-use dbkp_core::{databases::DatabaseConfig, storage::provider::StorageConfig};
+use dbkp_core::{
+    databases::DatabaseConfig, encryption::EncryptionConfig, retention::RetentionPolicy,
+    storage::provider::StorageConfig,
+};
 use serde::{Deserialize, Serialize};
-use std::{borrow::Borrow, fs, path::PathBuf};
+use std::{borrow::Borrow, collections::HashMap, fs, path::PathBuf};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Configs {
     pub config_path: PathBuf,
     database_configs: Vec<DatabaseConfig>,
     storage_configs: Vec<StorageConfig>,
+    #[serde(default)]
+    encryption: Option<EncryptionConfig>,
+    /// Retention policies keyed by database configuration name.
+    #[serde(default)]
+    retention_policies: HashMap<String, RetentionPolicy>,
 }
 
 impl Configs {
@@ -30,6 +38,8 @@ impl Configs {
                 config_path: config_path.clone(),
                 database_configs: vec![],
                 storage_configs: vec![],
+                encryption: None,
+                retention_policies: HashMap::new(),
             };
 
             let content = serde_json::to_string_pretty(&app_storage)?;
@@ -72,4 +82,64 @@ impl Configs {
 
         Ok(())
     }
+
+    pub fn get_encryption_config(&self) -> Option<EncryptionConfig> {
+        self.encryption.clone()
+    }
+
+    pub fn get_retention_policy(&self, database: &str) -> Option<RetentionPolicy> {
+        self.retention_policies.get(database).cloned()
+    }
+
+    /// Saved database configuration with this id, or else the only one with this name.
+    pub fn find_database_config(&self, name_or_id: &str) -> Result<DatabaseConfig> {
+        find_config(
+            &self.database_configs,
+            name_or_id,
+            |config| (config.id.as_str(), config.name.as_str()),
+            "database",
+        )
+    }
+
+    /// Saved storage configuration with this id, or else the only one with this name.
+    pub fn find_storage_config(&self, name_or_id: &str) -> Result<StorageConfig> {
+        find_config(
+            &self.storage_configs,
+            name_or_id,
+            |config| match config {
+                StorageConfig::Local(config) => (config.id.as_str(), config.name.as_str()),
+                StorageConfig::S3(config) => (config.id.as_str(), config.name.as_str()),
+                StorageConfig::Sftp(config) => (config.id.as_str(), config.name.as_str()),
+                StorageConfig::WebDav(config) => (config.id.as_str(), config.name.as_str()),
+            },
+            "storage",
+        )
+    }
+}
+
+fn find_config<T: Clone>(
+    configs: &[T],
+    name_or_id: &str,
+    id_and_name: impl Fn(&T) -> (&str, &str),
+    kind: &str,
+) -> Result<T> {
+    if let Some(config) = configs.iter().find(|c| id_and_name(c).0 == name_or_id) {
+        return Ok(config.clone());
+    }
+
+    let mut named = configs.iter().filter(|c| id_and_name(c).1 == name_or_id);
+
+    match (named.next(), named.next()) {
+        (Some(config), None) => Ok(config.clone()),
+        (Some(_), Some(_)) => Err(anyhow!(
+            "Several saved {} configurations are named '{}', select one by its id",
+            kind,
+            name_or_id
+        )),
+        (None, _) => Err(anyhow!(
+            "No saved {} configuration named '{}'",
+            kind,
+            name_or_id
+        )),
+    }
 }
@@ -1,9 +1,9 @@
-use anyhow::{anyhow, Result};
+use anyhow::{Result, anyhow};
 use async_trait::async_trait;
 use crossterm::event::{Event as CrosstermEvent, KeyCode};
 use dbkp_core::databases::{ConnectionType, DatabaseConfig};
 use tokio::sync::mpsc;
-use tui_input::{backend::crossterm::EventHandler, Input};
+use tui_input::{Input, backend::crossterm::EventHandler};
 
 use crate::tui::{
     configs::Configs,
@@ -81,12 +81,25 @@ impl DatabaseModel {
         };
     }
 
+    fn is_sqlite(&self) -> bool {
+        self.type_input.value() == "sqlite"
+    }
+
+    /// Redis servers without ACL users only check the password.
+    fn needs_username(&self) -> bool {
+        self.type_input.value() != "redis"
+    }
+
     fn input_filled(&self) -> bool {
+        if self.is_sqlite() {
+            return !self.name_input.value().is_empty() && !self.database_input.value().is_empty();
+        }
+
         !self.name_input.value().is_empty()
             && !self.database_input.value().is_empty()
             && !self.host_input.value().is_empty()
             && !self.port_input.value().is_empty()
-            && !self.username_input.value().is_empty()
+            && (!self.needs_username() || !self.username_input.value().is_empty())
             && !self.password_input.value().is_empty()
     }
 
@@ -99,11 +112,16 @@ impl DatabaseModel {
             return Err(anyhow!("Database is required"));
         }
 
+        // SQLite only needs the path of the database file
+        if self.is_sqlite() {
+            return Ok(());
+        }
+
         if self.host_input.value().is_empty() {
             return Err(anyhow!("Host is required"));
         }
 
-        if self.username_input.value().is_empty() {
+        if self.needs_username() && self.username_input.value().is_empty() {
             return Err(anyhow!("Username is required"));
         }
 
@@ -126,12 +144,31 @@ impl DatabaseModel {
 
         self.validate_inputs()?;
 
+        if self.is_sqlite() {
+            config.add_database_config(DatabaseConfig {
+                id,
+                name: self.name_input.value().to_string(),
+                connection_type: ConnectionType::Sqlite,
+                database: self.database_input.value().to_string(),
+                host: "".into(),
+                port: 0,
+                username: "".into(),
+                password: None,
+                ssh: None,
+            })?;
+
+            return Ok(());
+        }
+
         let new_database_config = DatabaseConfig {
             id,
             name: self.name_input.value().to_string(),
             connection_type: match self.type_input.value() {
                 "postgresql" => ConnectionType::PostgreSql,
                 "mysql" => ConnectionType::MySql,
+                "mariadb" => ConnectionType::MariaDB,
+                "mongodb" => ConnectionType::MongoDb,
+                "redis" => ConnectionType::Redis,
                 _ => return Err(anyhow!("Invalid database type")),
             },
             database: self.database_input.value().to_string(),
@@ -139,6 +176,7 @@ impl DatabaseModel {
             port: self.port_input.value().parse::<u16>().unwrap(),
             username: self.username_input.value().to_string(),
             password: Some(self.password_input.value().to_string()),
+            ssh: None,
         };
 
         config.add_database_config(new_database_config)?;
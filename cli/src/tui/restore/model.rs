@@ -1,12 +1,12 @@
 use std::time::Duration;
 
-use anyhow::{anyhow, Result};
+use anyhow::{Result, anyhow};
 use async_trait::async_trait;
 use crossterm::event::{Event as CrosstermEvent, KeyCode};
 use dbkp_core::{
-    databases::DatabaseConnection,
-    storage::provider::{StorageConfig, StorageProvider},
     DbBkp, RestoreOptions,
+    databases::DatabaseConnection,
+    storage::provider::{ListOptions, StorageConfig, StorageProvider},
 };
 use tokio::sync::mpsc;
 
@@ -58,6 +58,8 @@ impl RestoreModel {
             let storage_id = match storage_config {
                 StorageConfig::Local(config) => config.id.clone(),
                 StorageConfig::S3(config) => config.id.clone(),
+                StorageConfig::Sftp(config) => config.id.clone(),
+                StorageConfig::WebDav(config) => config.id.clone(),
             };
 
             if let Some(database_config) = first_database_config {
@@ -93,6 +95,8 @@ impl RestoreModel {
                     .position(|config| match config {
                         StorageConfig::Local(config) => config.id == self.highlight_storage_id,
                         StorageConfig::S3(config) => config.id == self.highlight_storage_id,
+                        StorageConfig::Sftp(config) => config.id == self.highlight_storage_id,
+                        StorageConfig::WebDav(config) => config.id == self.highlight_storage_id,
                     })
                     .unwrap_or(0);
 
@@ -100,6 +104,8 @@ impl RestoreModel {
                 self.highlight_storage_id = match &storage_configs[next_index] {
                     StorageConfig::Local(config) => config.id.clone(),
                     StorageConfig::S3(config) => config.id.clone(),
+                    StorageConfig::Sftp(config) => config.id.clone(),
+                    StorageConfig::WebDav(config) => config.id.clone(),
                 };
             }
             SelectionMode::Database => {
@@ -139,6 +145,8 @@ impl RestoreModel {
                     .position(|config| match config {
                         StorageConfig::Local(config) => config.id == self.highlight_storage_id,
                         StorageConfig::S3(config) => config.id == self.highlight_storage_id,
+                        StorageConfig::Sftp(config) => config.id == self.highlight_storage_id,
+                        StorageConfig::WebDav(config) => config.id == self.highlight_storage_id,
                     })
                     .unwrap_or(0);
                 let previous_index = if current_index as i8 - 1 < 0 {
@@ -149,6 +157,8 @@ impl RestoreModel {
                 self.highlight_storage_id = match &storage_configs[previous_index] {
                     StorageConfig::Local(config) => config.id.clone(),
                     StorageConfig::S3(config) => config.id.clone(),
+                    StorageConfig::Sftp(config) => config.id.clone(),
+                    StorageConfig::WebDav(config) => config.id.clone(),
                 };
             }
             SelectionMode::Database => {
@@ -216,14 +226,29 @@ impl RestoreModel {
         let storage_config = match &self.selected_storage_id {
             Some(id) => storage_configs.iter().find(|config| match config {
                 StorageConfig::S3(config) => config.id == *id,
+                StorageConfig::Sftp(config) => config.id == *id,
+                StorageConfig::WebDav(config) => config.id == *id,
                 StorageConfig::Local(config) => config.id == *id,
             }),
             None => None,
         };
 
+        // Only the backups of the chosen database can be restored into it
+        let database_id = self
+            .selected_database_id
+            .as_ref()
+            .unwrap_or(&self.highlight_database_id);
+        let database_name = self
+            .configs
+            .get_database_configs()
+            .iter()
+            .find(|config| config.id == *database_id)
+            .map(|config| config.name.clone());
+
         if storage_config.is_some() {
             let sender = self.event_sender.clone();
             let storage_config = storage_config.unwrap().clone();
+            self.selected_backup_id = None;
             let mut restore_model = self.clone();
 
             tokio::spawn(async move {
@@ -240,7 +265,14 @@ impl RestoreModel {
                     }
                 };
 
-                let entries = match storage_provider.list().await {
+                let entries = match storage_provider
+                    .list_with_options(ListOptions {
+                        latest_only: None,
+                        limit: None,
+                        database: database_name,
+                    })
+                    .await
+                {
                     Ok(entries) => entries,
                     Err(e) => {
                         let error_view = ErrorView::new(ErrorModel::new(
@@ -253,10 +285,7 @@ impl RestoreModel {
                     }
                 };
 
-                let backups: Vec<String> = entries
-                    .iter()
-                    .map(|entry| entry.metadata.name.clone())
-                    .collect();
+                let backups: Vec<String> = entries.iter().map(|entry| entry.path.clone()).collect();
 
                 restore_model.highlighted_backup_id = backups.first().cloned();
                 restore_model.backups = backups;
@@ -285,6 +314,8 @@ impl RestoreModel {
         let storage_config = match &self.selected_storage_id {
             Some(id) => storage_configs.iter().find(|config| match config {
                 StorageConfig::S3(config) => config.id == *id,
+                StorageConfig::Sftp(config) => config.id == *id,
+                StorageConfig::WebDav(config) => config.id == *id,
                 StorageConfig::Local(config) => config.id == *id,
             }),
             None => None,
@@ -300,6 +331,7 @@ impl RestoreModel {
             let database_config = database_config.unwrap().clone();
             let storage_config = storage_config.unwrap().clone();
             let backup_id = self.selected_backup_id.clone().unwrap();
+            let encryption = self.configs.get_encryption_config();
 
             tokio::spawn(async move {
                 let database_connection_result = tokio::time::timeout(
@@ -350,6 +382,11 @@ impl RestoreModel {
                         name: backup_id,
                         compression_format: None,
                         drop_database_first: Some(true),
+                        encryption,
+                        jobs: None,
+                        masking: None,
+                        target: None,
+                        target_database: None,
                     })
                     .await
                 {
@@ -379,6 +416,9 @@ impl RestoreModel {
             }
             SelectionMode::Database => {
                 self.selected_database_id = Some(self.highlight_database_id.clone());
+                if self.selected_storage_id.is_some() {
+                    self.load_backups()?;
+                }
             }
             SelectionMode::Backup => {
                 self.selected_backup_id = self.highlighted_backup_id.clone();
@@ -87,11 +87,15 @@ impl View for RestoreView {
                 let current_id = match config {
                     StorageConfig::Local(config) => config.id.clone(),
                     StorageConfig::S3(config) => config.id.clone(),
+                    StorageConfig::Sftp(config) => config.id.clone(),
+                    StorageConfig::WebDav(config) => config.id.clone(),
                 };
 
                 let current_name = match config {
                     StorageConfig::Local(config) => config.name.clone(),
                     StorageConfig::S3(config) => config.name.clone(),
+                    StorageConfig::Sftp(config) => config.name.clone(),
+                    StorageConfig::WebDav(config) => config.name.clone(),
                 };
 
                 let active = matches!(self.model.selection_mode, SelectionMode::Storage);
@@ -3,13 +3,18 @@ use crate::tui::{
     event::Event,
     home::{model::HomeModel, view::HomeView},
     model::Model,
-    storage::view::{LocalStorageView, S3StorageView, StorageView},
+    storage::view::{
+        LocalStorageView, S3StorageView, SftpStorageView, StorageView, WebDavStorageView,
+    },
     view::View,
 };
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use crossterm::event::{Event as CrosstermEvent, KeyCode};
-use dbkp_core::storage::provider::{LocalStorageConfig, S3StorageConfig, StorageConfig};
+use dbkp_core::storage::provider::{
+    LocalStorageConfig, S3StorageConfig, SftpStorageConfig, StorageConfig, StorageCredentials,
+    WebDavStorageConfig,
+};
 use tokio::sync::mpsc;
 use tui_input::{backend::crossterm::EventHandler, Input};
 
@@ -23,6 +28,17 @@ pub enum CurrentInput {
     S3Endpoint,
     S3AccessKey,
     S3SecretKey,
+    SftpLocation,
+    SftpHost,
+    SftpPort,
+    SftpUsername,
+    SftpPassword,
+    SftpKeyPath,
+    SftpKeyPassphrase,
+    WebDavLocation,
+    WebDavEndpoint,
+    WebDavUsername,
+    WebDavPassword,
 }
 
 #[derive(Clone, Debug)]
@@ -41,6 +57,17 @@ pub struct StorageModel {
     pub s3_input_access_key: Input,
     pub s3_input_secret_key: Input,
     pub s3_input_location: Input,
+    pub sftp_input_location: Input,
+    pub sftp_input_host: Input,
+    pub sftp_input_port: Input,
+    pub sftp_input_username: Input,
+    pub sftp_input_password: Input,
+    pub sftp_input_key_path: Input,
+    pub sftp_input_key_passphrase: Input,
+    pub webdav_input_location: Input,
+    pub webdav_input_endpoint: Input,
+    pub webdav_input_username: Input,
+    pub webdav_input_password: Input,
 }
 
 impl StorageModel {
@@ -49,7 +76,12 @@ impl StorageModel {
             event_sender,
             exit: false,
             current_input: CurrentInput::ConfigName,
-            storage_type_options: vec!["S3".to_string(), "Local".to_string()],
+            storage_type_options: vec![
+                "S3".to_string(),
+                "Local".to_string(),
+                "SFTP".to_string(),
+                "WebDAV".to_string(),
+            ],
             highlighted_option_index: 0,
             current_storage_config: None,
             local_input_location: Input::new("".to_string()),
@@ -60,6 +92,17 @@ impl StorageModel {
             s3_input_access_key: Input::new("".to_string()),
             s3_input_secret_key: Input::new("".to_string()),
             s3_input_location: Input::new("".to_string()),
+            sftp_input_location: Input::new("".to_string()),
+            sftp_input_host: Input::new("".to_string()),
+            sftp_input_port: Input::new("22".to_string()),
+            sftp_input_username: Input::new("".to_string()),
+            sftp_input_password: Input::new("".to_string()),
+            sftp_input_key_path: Input::new("".to_string()),
+            sftp_input_key_passphrase: Input::new("".to_string()),
+            webdav_input_location: Input::new("".to_string()),
+            webdav_input_endpoint: Input::new("".to_string()),
+            webdav_input_username: Input::new("".to_string()),
+            webdav_input_password: Input::new("".to_string()),
         }
     }
 
@@ -97,6 +140,25 @@ impl StorageModel {
                     CurrentInput::S3SecretKey => CurrentInput::ConfigName,
                     _ => CurrentInput::ConfigName,
                 },
+                StorageConfig::Sftp(_) => match self.current_input {
+                    CurrentInput::ConfigName => CurrentInput::SftpLocation,
+                    CurrentInput::SftpLocation => CurrentInput::SftpHost,
+                    CurrentInput::SftpHost => CurrentInput::SftpPort,
+                    CurrentInput::SftpPort => CurrentInput::SftpUsername,
+                    CurrentInput::SftpUsername => CurrentInput::SftpPassword,
+                    CurrentInput::SftpPassword => CurrentInput::SftpKeyPath,
+                    CurrentInput::SftpKeyPath => CurrentInput::SftpKeyPassphrase,
+                    CurrentInput::SftpKeyPassphrase => CurrentInput::ConfigName,
+                    _ => CurrentInput::ConfigName,
+                },
+                StorageConfig::WebDav(_) => match self.current_input {
+                    CurrentInput::ConfigName => CurrentInput::WebDavLocation,
+                    CurrentInput::WebDavLocation => CurrentInput::WebDavEndpoint,
+                    CurrentInput::WebDavEndpoint => CurrentInput::WebDavUsername,
+                    CurrentInput::WebDavUsername => CurrentInput::WebDavPassword,
+                    CurrentInput::WebDavPassword => CurrentInput::ConfigName,
+                    _ => CurrentInput::ConfigName,
+                },
             };
         }
     }
@@ -119,6 +181,25 @@ impl StorageModel {
                     CurrentInput::S3SecretKey => CurrentInput::S3AccessKey,
                     _ => CurrentInput::ConfigName,
                 },
+                StorageConfig::Sftp(_) => match self.current_input {
+                    CurrentInput::ConfigName => CurrentInput::SftpKeyPassphrase,
+                    CurrentInput::SftpLocation => CurrentInput::ConfigName,
+                    CurrentInput::SftpHost => CurrentInput::SftpLocation,
+                    CurrentInput::SftpPort => CurrentInput::SftpHost,
+                    CurrentInput::SftpUsername => CurrentInput::SftpPort,
+                    CurrentInput::SftpPassword => CurrentInput::SftpUsername,
+                    CurrentInput::SftpKeyPath => CurrentInput::SftpPassword,
+                    CurrentInput::SftpKeyPassphrase => CurrentInput::SftpKeyPath,
+                    _ => CurrentInput::ConfigName,
+                },
+                StorageConfig::WebDav(_) => match self.current_input {
+                    CurrentInput::ConfigName => CurrentInput::WebDavPassword,
+                    CurrentInput::WebDavLocation => CurrentInput::ConfigName,
+                    CurrentInput::WebDavEndpoint => CurrentInput::WebDavLocation,
+                    CurrentInput::WebDavUsername => CurrentInput::WebDavEndpoint,
+                    CurrentInput::WebDavPassword => CurrentInput::WebDavUsername,
+                    _ => CurrentInput::ConfigName,
+                },
             };
         }
     }
@@ -142,6 +223,32 @@ impl StorageModel {
                         || config.secret_key.is_empty();
                     return !is_empty;
                 }
+                StorageConfig::Sftp(config) => {
+                    // The key passphrase is optional, Tab reaches it before Enter saves
+                    let has_credentials = match &config.credentials {
+                        StorageCredentials::Basic { username, password } => {
+                            !username.is_empty() && !password.is_empty()
+                        }
+                        StorageCredentials::PrivateKey {
+                            username, key_path, ..
+                        } => !username.is_empty() && !key_path.is_empty(),
+                        _ => false,
+                    };
+                    let is_empty = config.id.is_empty()
+                        || config.name.is_empty()
+                        || config.host.is_empty()
+                        || config.location.is_empty()
+                        || !has_credentials;
+                    return !is_empty;
+                }
+                StorageConfig::WebDav(config) => {
+                    let is_empty = config.id.is_empty()
+                        || config.name.is_empty()
+                        || config.endpoint.is_empty()
+                        || config.location.is_empty()
+                        || !matches!(config.credentials, StorageCredentials::Basic { .. });
+                    return !is_empty;
+                }
             }
         }
 
@@ -188,6 +295,52 @@ impl StorageModel {
                         return Err(anyhow!("ID is required"));
                     }
                 }
+                StorageConfig::Sftp(config) => {
+                    if config.name.is_empty() {
+                        return Err(anyhow!("Config name is required"));
+                    }
+                    if config.host.is_empty() {
+                        return Err(anyhow!("Host is required"));
+                    }
+                    if let Err(e) = self.sftp_input_port.value().parse::<u16>() {
+                        return Err(anyhow!("Invalid port: {}", e));
+                    }
+                    if self.sftp_input_username.value().is_empty() {
+                        return Err(anyhow!("Username is required"));
+                    }
+                    if matches!(
+                        &config.credentials,
+                        StorageCredentials::Basic { password, .. } if password.is_empty()
+                    ) {
+                        return Err(anyhow!("Password or private key is required"));
+                    }
+                    if config.location.is_empty() {
+                        return Err(anyhow!("Location is required"));
+                    }
+                    if config.id.is_empty() {
+                        return Err(anyhow!("ID is required"));
+                    }
+                }
+                StorageConfig::WebDav(config) => {
+                    if config.name.is_empty() {
+                        return Err(anyhow!("Config name is required"));
+                    }
+                    if config.endpoint.is_empty() {
+                        return Err(anyhow!("Endpoint is required"));
+                    }
+                    if self.webdav_input_username.value().is_empty() {
+                        return Err(anyhow!("Username is required"));
+                    }
+                    if self.webdav_input_password.value().is_empty() {
+                        return Err(anyhow!("Password is required"));
+                    }
+                    if config.location.is_empty() {
+                        return Err(anyhow!("Location is required"));
+                    }
+                    if config.id.is_empty() {
+                        return Err(anyhow!("ID is required"));
+                    }
+                }
             }
         } else {
             return Err(anyhow!("Storage config is required"));
@@ -215,6 +368,44 @@ impl StorageModel {
                 config.location = self.s3_input_location.value().to_string();
                 config.name = self.input_config_name.value().to_string();
             }
+            Some(StorageConfig::Sftp(config)) => {
+                let username = self.sftp_input_username.value().to_string();
+
+                config.host = self.sftp_input_host.value().to_string();
+                config.port = self.sftp_input_port.value().parse::<u16>().unwrap_or(22);
+                config.credentials = if self.sftp_input_key_path.value().is_empty() {
+                    StorageCredentials::Basic {
+                        username,
+                        password: self.sftp_input_password.value().to_string(),
+                    }
+                } else {
+                    StorageCredentials::PrivateKey {
+                        username,
+                        key_path: self.sftp_input_key_path.value().to_string(),
+                        passphrase: match self.sftp_input_key_passphrase.value() {
+                            "" => None,
+                            passphrase => Some(passphrase.to_string()),
+                        },
+                    }
+                };
+                config.location = self.sftp_input_location.value().to_string();
+                config.name = self.input_config_name.value().to_string();
+            }
+            Some(StorageConfig::WebDav(config)) => {
+                config.endpoint = self.webdav_input_endpoint.value().to_string();
+                config.credentials = match (
+                    self.webdav_input_username.value(),
+                    self.webdav_input_password.value(),
+                ) {
+                    ("", "") => StorageCredentials::None,
+                    (username, password) => StorageCredentials::Basic {
+                        username: username.to_string(),
+                        password: password.to_string(),
+                    },
+                };
+                config.location = self.webdav_input_location.value().to_string();
+                config.name = self.input_config_name.value().to_string();
+            }
             None => {}
         }
     }
@@ -245,6 +436,12 @@ impl Model for StorageModel {
                 StorageConfig::S3(_) => {
                     return Ok(Some(Box::new(S3StorageView::new(self.clone()))));
                 }
+                StorageConfig::Sftp(_) => {
+                    return Ok(Some(Box::new(SftpStorageView::new(self.clone()))));
+                }
+                StorageConfig::WebDav(_) => {
+                    return Ok(Some(Box::new(WebDavStorageView::new(self.clone()))));
+                }
             }
         }
 
@@ -277,6 +474,39 @@ impl Model for StorageModel {
             CurrentInput::S3SecretKey => {
                 self.s3_input_secret_key.handle_event(event);
             }
+            CurrentInput::SftpLocation => {
+                self.sftp_input_location.handle_event(event);
+            }
+            CurrentInput::SftpHost => {
+                self.sftp_input_host.handle_event(event);
+            }
+            CurrentInput::SftpPort => {
+                self.sftp_input_port.handle_event(event);
+            }
+            CurrentInput::SftpUsername => {
+                self.sftp_input_username.handle_event(event);
+            }
+            CurrentInput::SftpPassword => {
+                self.sftp_input_password.handle_event(event);
+            }
+            CurrentInput::SftpKeyPath => {
+                self.sftp_input_key_path.handle_event(event);
+            }
+            CurrentInput::SftpKeyPassphrase => {
+                self.sftp_input_key_passphrase.handle_event(event);
+            }
+            CurrentInput::WebDavLocation => {
+                self.webdav_input_location.handle_event(event);
+            }
+            CurrentInput::WebDavEndpoint => {
+                self.webdav_input_endpoint.handle_event(event);
+            }
+            CurrentInput::WebDavUsername => {
+                self.webdav_input_username.handle_event(event);
+            }
+            CurrentInput::WebDavPassword => {
+                self.webdav_input_password.handle_event(event);
+            }
         }
 
         self.update_current_config();
@@ -284,7 +514,10 @@ impl Model for StorageModel {
         if let CrosstermEvent::Key(key) = event {
             if let Some(current_config) = &self.current_storage_config {
                 match current_config {
-                    StorageConfig::Local(_) | StorageConfig::S3(_) => match key.code {
+                    StorageConfig::Local(_)
+                    | StorageConfig::S3(_)
+                    | StorageConfig::Sftp(_)
+                    | StorageConfig::WebDav(_) => match key.code {
                         KeyCode::Esc | KeyCode::Left => {
                             self.current_storage_config = None;
                         }
@@ -326,7 +559,30 @@ impl Model for StorageModel {
                     }
                     KeyCode::Enter | KeyCode::Right => {
                         if let Some(option) = selected_option {
-                            if option == "Local" {
+                            if option == "WebDAV" {
+                                self.current_storage_config =
+                                    Some(StorageConfig::WebDav(WebDavStorageConfig {
+                                        id: cuid2::create_id(),
+                                        name: "".into(),
+                                        endpoint: "".into(),
+                                        credentials: StorageCredentials::None,
+                                        location: "".into(),
+                                    }));
+                            } else if option == "SFTP" {
+                                self.current_storage_config =
+                                    Some(StorageConfig::Sftp(SftpStorageConfig {
+                                        id: cuid2::create_id(),
+                                        name: "".into(),
+                                        host: "".into(),
+                                        port: 22,
+                                        credentials: StorageCredentials::Basic {
+                                            username: "".into(),
+                                            password: "".into(),
+                                        },
+                                        location: "".into(),
+                                        accept_new_host_key: false,
+                                    }));
+                            } else if option == "Local" {
                                 self.current_storage_config =
                                     Some(StorageConfig::Local(LocalStorageConfig {
                                         id: cuid2::create_id(),
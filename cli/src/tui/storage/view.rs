@@ -239,3 +239,240 @@ impl View for S3StorageView {
         );
     }
 }
+
+#[derive(Clone, Debug)]
+pub struct SftpStorageView {
+    storage_model: StorageModel,
+}
+
+impl SftpStorageView {
+    pub fn new(storage_model: StorageModel) -> Self {
+        SftpStorageView { storage_model }
+    }
+}
+
+impl View for SftpStorageView {
+    fn clone_box(&self) -> Box<dyn View> {
+        Box::new(self.clone())
+    }
+
+    fn get_model(&self) -> Box<dyn Model> {
+        Box::new(self.storage_model.clone())
+    }
+
+    fn render(&self, frame: &mut ratatui::Frame) {
+        let inputs_layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+            ])
+            .split(frame.area());
+
+        let width = inputs_layout[0].width.max(3) - 3;
+        let scroll = self
+            .storage_model
+            .sftp_input_location
+            .visual_scroll(width as usize);
+
+        render_input(
+            frame,
+            &self.storage_model.input_config_name,
+            "Config Name",
+            matches!(&self.storage_model.current_input, CurrentInput::ConfigName),
+            inputs_layout[0],
+            scroll,
+            false,
+        );
+
+        render_input(
+            frame,
+            &self.storage_model.sftp_input_location,
+            "Location",
+            matches!(
+                &self.storage_model.current_input,
+                CurrentInput::SftpLocation
+            ),
+            inputs_layout[1],
+            scroll,
+            false,
+        );
+
+        render_input(
+            frame,
+            &self.storage_model.sftp_input_host,
+            "Host",
+            matches!(&self.storage_model.current_input, CurrentInput::SftpHost),
+            inputs_layout[2],
+            scroll,
+            false,
+        );
+
+        render_input(
+            frame,
+            &self.storage_model.sftp_input_port,
+            "Port",
+            matches!(&self.storage_model.current_input, CurrentInput::SftpPort),
+            inputs_layout[3],
+            scroll,
+            false,
+        );
+
+        render_input(
+            frame,
+            &self.storage_model.sftp_input_username,
+            "Username",
+            matches!(
+                &self.storage_model.current_input,
+                CurrentInput::SftpUsername
+            ),
+            inputs_layout[4],
+            scroll,
+            false,
+        );
+
+        render_input(
+            frame,
+            &self.storage_model.sftp_input_password,
+            "Password",
+            matches!(
+                &self.storage_model.current_input,
+                CurrentInput::SftpPassword
+            ),
+            inputs_layout[5],
+            scroll,
+            true,
+        );
+
+        render_input(
+            frame,
+            &self.storage_model.sftp_input_key_path,
+            "Private Key Path (instead of password)",
+            matches!(&self.storage_model.current_input, CurrentInput::SftpKeyPath),
+            inputs_layout[6],
+            scroll,
+            false,
+        );
+
+        render_input(
+            frame,
+            &self.storage_model.sftp_input_key_passphrase,
+            "Private Key Passphrase",
+            matches!(
+                &self.storage_model.current_input,
+                CurrentInput::SftpKeyPassphrase
+            ),
+            inputs_layout[7],
+            scroll,
+            true,
+        );
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct WebDavStorageView {
+    storage_model: StorageModel,
+}
+
+impl WebDavStorageView {
+    pub fn new(storage_model: StorageModel) -> Self {
+        WebDavStorageView { storage_model }
+    }
+}
+
+impl View for WebDavStorageView {
+    fn clone_box(&self) -> Box<dyn View> {
+        Box::new(self.clone())
+    }
+
+    fn get_model(&self) -> Box<dyn Model> {
+        Box::new(self.storage_model.clone())
+    }
+
+    fn render(&self, frame: &mut ratatui::Frame) {
+        let inputs_layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+            ])
+            .split(frame.area());
+
+        let width = inputs_layout[0].width.max(3) - 3;
+        let scroll = self
+            .storage_model
+            .webdav_input_location
+            .visual_scroll(width as usize);
+
+        render_input(
+            frame,
+            &self.storage_model.input_config_name,
+            "Config Name",
+            matches!(&self.storage_model.current_input, CurrentInput::ConfigName),
+            inputs_layout[0],
+            scroll,
+            false,
+        );
+
+        render_input(
+            frame,
+            &self.storage_model.webdav_input_location,
+            "Location",
+            matches!(
+                &self.storage_model.current_input,
+                CurrentInput::WebDavLocation
+            ),
+            inputs_layout[1],
+            scroll,
+            false,
+        );
+
+        render_input(
+            frame,
+            &self.storage_model.webdav_input_endpoint,
+            "Endpoint",
+            matches!(
+                &self.storage_model.current_input,
+                CurrentInput::WebDavEndpoint
+            ),
+            inputs_layout[2],
+            scroll,
+            false,
+        );
+
+        render_input(
+            frame,
+            &self.storage_model.webdav_input_username,
+            "Username",
+            matches!(
+                &self.storage_model.current_input,
+                CurrentInput::WebDavUsername
+            ),
+            inputs_layout[3],
+            scroll,
+            false,
+        );
+
+        render_input(
+            frame,
+            &self.storage_model.webdav_input_password,
+            "Password",
+            matches!(
+                &self.storage_model.current_input,
+                CurrentInput::WebDavPassword
+            ),
+            inputs_layout[4],
+            scroll,
+            true,
+        );
+    }
+}
@@ -35,14 +35,23 @@ impl ArchiveInstaller {
     async fn get_archive_url(&self) -> Result<String> {
         let metadata = self.get_database_archives_metadata().await?;
 
-        let (major_version, _, string_version) = match &self.database_version {
-            Version::PostgreSQL(version) => (version.major, version.major, version.to_string()),
-            Version::MySql(version) => (version.major, version.minor, version.to_string()),
+        // MariaDB clients are released per series, so its archives also match the minor version
+        let (major_version, minor_version, string_version) = match &self.database_version {
+            Version::PostgreSQL(version) => (version.major, None, version.to_string()),
+            Version::MySql(version) => (version.major, None, version.to_string()),
+            Version::MariaDB(version) => (version.major, Some(version.minor), version.to_string()),
+            Version::Sqlite(_) => return Err(anyhow!("SQLite doesn't need client binaries")),
+            Version::MongoDb(version) => (version.major, None, version.to_string()),
+            Version::Redis(_) => return Err(anyhow!("Redis doesn't need client binaries")),
         };
 
         let database_name = match self.database_version {
             Version::PostgreSQL(_) => "postgresql",
             Version::MySql(_) => "mysql",
+            Version::MariaDB(_) => "mariadb",
+            Version::Sqlite(_) => "sqlite",
+            Version::MongoDb(_) => "mongodb",
+            Version::Redis(_) => "redis",
         };
 
         let databases = match metadata
@@ -59,11 +68,10 @@ impl ArchiveInstaller {
             }
         };
 
-        let archive = match databases
-            .archives
-            .iter()
-            .find(|item| item.version.major == major_version as u32)
-        {
+        let archive = match databases.archives.iter().find(|item| {
+            item.version.major == major_version as u32
+                && minor_version.is_none_or(|minor| item.version.minor == Some(minor as u32))
+        }) {
             Some(archive) => archive,
             None => return Err(anyhow!("Archive not found for version: {}", string_version)),
         };
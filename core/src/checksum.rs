@@ -0,0 +1,219 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::{self, Read, Write};
+
+use crate::{manifest::BackupManifest, storage::provider::StorageProvider};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChecksumAlgorithm {
+    #[default]
+    Sha256,
+    Blake3,
+}
+
+impl std::str::FromStr for ChecksumAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sha256" | "sha-256" => Ok(ChecksumAlgorithm::Sha256),
+            "blake3" => Ok(ChecksumAlgorithm::Blake3),
+            _ => Err(anyhow!(
+                "Unsupported checksum algorithm: {}. Use sha256 or blake3",
+                s
+            )),
+        }
+    }
+}
+
+enum Hasher {
+    Sha256(Sha256),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    fn new(algorithm: ChecksumAlgorithm) -> Self {
+        match algorithm {
+            ChecksumAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            ChecksumAlgorithm::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+        }
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        match self {
+            Hasher::Sha256(hasher) => hasher.update(bytes),
+            Hasher::Blake3(hasher) => {
+                hasher.update(bytes);
+            }
+        }
+    }
+
+    fn finalize(self) -> String {
+        match self {
+            Hasher::Sha256(hasher) => format!("{:x}", hasher.finalize()),
+            Hasher::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+        }
+    }
+}
+
+/// Counts the bytes going through a writer.
+pub struct CountingWriter<W: Write + Send + Unpin> {
+    inner: W,
+    bytes_written: u64,
+}
+
+impl<W: Write + Send + Unpin> CountingWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            bytes_written: 0,
+        }
+    }
+
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write + Send + Unpin> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.bytes_written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Computes a digest of the bytes going through a writer.
+pub struct HashingWriter<W: Write + Send + Unpin> {
+    inner: W,
+    hasher: Hasher,
+    bytes_written: u64,
+}
+
+impl<W: Write + Send + Unpin> HashingWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self::with_algorithm(inner, ChecksumAlgorithm::Sha256)
+    }
+
+    pub fn with_algorithm(inner: W, algorithm: ChecksumAlgorithm) -> Self {
+        Self {
+            inner,
+            hasher: Hasher::new(algorithm),
+            bytes_written: 0,
+        }
+    }
+
+    /// Returns the inner writer, the number of bytes written and the hex encoded digest.
+    pub fn finish(self) -> (W, u64, String) {
+        let digest = self.hasher.finalize();
+        (self.inner, self.bytes_written, digest)
+    }
+}
+
+impl<W: Write + Send + Unpin> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        self.bytes_written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChecksumVerification {
+    pub algorithm: ChecksumAlgorithm,
+    pub expected_checksum: String,
+    pub actual_checksum: String,
+    pub expected_size: u64,
+    pub actual_size: u64,
+}
+
+impl ChecksumVerification {
+    pub fn is_valid(&self) -> bool {
+        self.expected_checksum == self.actual_checksum && self.expected_size == self.actual_size
+    }
+}
+
+/// Streams a backup back from storage and compares its digest with the one of its manifest.
+pub async fn verify_checksum(
+    storage_provider: &StorageProvider,
+    name: &str,
+) -> Result<ChecksumVerification> {
+    let manifest: BackupManifest = storage_provider
+        .read_manifest(name)
+        .await?
+        .ok_or_else(|| anyhow!("No manifest found for {}, nothing to verify against", name))?;
+
+    let mut reader = storage_provider.create_reader(name).await?;
+    let mut hasher = Hasher::new(manifest.checksum_algorithm);
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut actual_size = 0u64;
+
+    loop {
+        let n = reader.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+        actual_size += n as u64;
+    }
+
+    Ok(ChecksumVerification {
+        algorithm: manifest.checksum_algorithm,
+        expected_checksum: manifest.checksum,
+        actual_checksum: hasher.finalize(),
+        expected_size: manifest.compressed_size,
+        actual_size,
+    })
+}
+
+#[cfg(test)]
+mod checksum_test {
+    use std::io::Write;
+
+    use super::{ChecksumAlgorithm, CountingWriter, HashingWriter};
+
+    #[test]
+    fn hash_and_count() {
+        let mut writer = CountingWriter::new(HashingWriter::new(Vec::new()));
+        writer.write_all(b"hello world").expect("Failed to write");
+
+        assert_eq!(writer.bytes_written(), 11);
+
+        let (bytes, size, digest) = writer.into_inner().finish();
+
+        assert_eq!(bytes, b"hello world");
+        assert_eq!(size, 11);
+        assert_eq!(
+            digest,
+            "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+        );
+    }
+
+    #[test]
+    fn hash_blake3() {
+        let mut writer = HashingWriter::with_algorithm(Vec::new(), ChecksumAlgorithm::Blake3);
+        writer.write_all(b"hello world").expect("Failed to write");
+
+        let (_, size, digest) = writer.finish();
+
+        assert_eq!(size, 11);
+        assert_eq!(
+            digest,
+            "d74981efa70a0c880b8d8c1985d075dbcbf679b99a5f9914e5aaf96b831a9e24"
+        );
+    }
+}
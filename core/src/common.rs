@@ -5,6 +5,7 @@ use regex::Regex;
 use std::{
     borrow::Borrow,
     env,
+    ffi::OsStr,
     path::{Path, PathBuf},
 };
 
@@ -13,6 +14,7 @@ use uuid::Uuid;
 use crate::{
     compression::CompressionFormat,
     databases::{version::Version, DatabaseConfig},
+    encryption::EncryptionFormat,
 };
 
 pub fn slugify(input: &str) -> String {
@@ -39,6 +41,7 @@ pub fn slugify(input: &str) -> String {
 pub fn get_default_backup_name<B>(
     database_config: B,
     compression_format: &CompressionFormat,
+    encryption_format: Option<&EncryptionFormat>,
 ) -> String
 where
     B: Borrow<DatabaseConfig>,
@@ -49,12 +52,13 @@ where
     let uuid_string = Uuid::new_v4().to_string();
     let uuid = uuid_string.split('-').next().unwrap_or("backup");
 
-    let extension = match compression_format {
-        CompressionFormat::Zlib => "zip",
-        CompressionFormat::Deflate => "zz",
-        CompressionFormat::Gzip => "gz",
-        CompressionFormat::None => "",
-    };
+    let mut extension = compression_format.extension().to_string();
+    if let Some(encryption_format) = encryption_format {
+        if !extension.is_empty() {
+            extension.push('.');
+        }
+        extension.push_str(encryption_format.extension());
+    }
 
     format!(
         "{}-{}-{}.{}",
@@ -62,6 +66,24 @@ where
     )
 }
 
+/// Backups of each database are stored under their own prefix, e.g. `my-app/my_app-...gz`.
+pub fn get_default_backup_path<B>(
+    database_config: B,
+    compression_format: &CompressionFormat,
+    encryption_format: Option<&EncryptionFormat>,
+) -> String
+where
+    B: Borrow<DatabaseConfig>,
+{
+    let borrowed_config: &DatabaseConfig = database_config.borrow();
+    let name = get_default_backup_name(borrowed_config, compression_format, encryption_format);
+
+    match slugify(&borrowed_config.name).as_str() {
+        "" => name,
+        prefix => format!("{}/{}", prefix, name),
+    }
+}
+
 pub fn get_binaries_base_path(version: &Version) -> PathBuf {
     let db_name = get_db_name(&version);
     let version_name = get_version_name(&version);
@@ -77,6 +99,10 @@ pub fn get_db_name(version: &Version) -> String {
     match version {
         Version::PostgreSQL(_) => "postgresql".into(),
         Version::MySql(_) => "mysql".into(),
+        Version::MariaDB(_) => "mariadb".into(),
+        Version::Sqlite(_) => "sqlite".into(),
+        Version::MongoDb(_) => "mongodb".into(),
+        Version::Redis(_) => "redis".into(),
     }
 }
 
@@ -84,12 +110,17 @@ pub fn get_version_name(version: &Version) -> String {
     match version {
         Version::PostgreSQL(version) => version.to_string(),
         Version::MySql(version) => version.to_string(),
+        Version::MariaDB(version) => version.to_string(),
+        Version::Sqlite(version) => version.to_string(),
+        Version::MongoDb(version) => version.to_string(),
+        Version::Redis(version) => version.to_string(),
     }
 }
 
 pub fn extract_timestamp_from_filename(filename: &str) -> Result<DateTime<Utc>> {
-    let re = Regex::new(r"(\d{4}-\d{2}-\d{2}-\d{6})-[a-f0-9]+\.(gz|dump|tar|zip|sql)$")
-        .map_err(|e| anyhow!("Failed to compile regex: {}", e))?;
+    let re =
+        Regex::new(r"(\d{4}-\d{2}-\d{2}-\d{6})-[a-f0-9]+\.(gz|zz|zip|zst|xz|bz2|dump|tar|sql|enc|age)?(\.(enc|age))?$")
+            .map_err(|e| anyhow!("Failed to compile regex: {}", e))?;
 
     let caps = re.captures(filename).ok_or_else(|| {
         anyhow!(
@@ -111,6 +142,31 @@ pub fn extract_timestamp_from_filename(filename: &str) -> Result<DateTime<Utc>>
     Ok(datetime)
 }
 
+pub fn extract_database_name_from_filename(filename: &str) -> Result<String> {
+    let re =
+        Regex::new(r"^(.+)-\d{4}-\d{2}-\d{2}-\d{6}-[a-f0-9]+\.(gz|zz|zip|zst|xz|bz2|dump|tar|sql|enc|age)?(\.(enc|age))?$")
+            .map_err(|e| anyhow!("Failed to compile regex: {}", e))?;
+
+    let caps = re.captures(filename).ok_or_else(|| {
+        anyhow!(
+            "Filename doesn't match expected backup name format: {}",
+            filename
+        )
+    })?;
+
+    let database_name = caps
+        .get(1)
+        .ok_or_else(|| {
+            anyhow!(
+                "Failed to extract database name from filename: {}",
+                filename
+            )
+        })?
+        .as_str();
+
+    Ok(database_name.to_string())
+}
+
 pub fn get_arch() -> Result<String> {
     // Get system architecture using std
     let arch = std::env::consts::ARCH;
@@ -168,3 +224,27 @@ pub fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> Result<()>
     }
     Ok(())
 }
+
+/// Looks `names` up in the directories of a `PATH`-like `search_path`, earlier names win over
+/// later ones wherever they are found.
+pub fn find_in_path(names: &[&str], search_path: &OsStr) -> Option<PathBuf> {
+    names.iter().find_map(|name| {
+        env::split_paths(search_path)
+            .map(|dir| dir.join(name))
+            .find(|path| is_executable(path))
+    })
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata()
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
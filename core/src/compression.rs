@@ -1,31 +1,168 @@
+use bzip2::read::BzDecoder;
+use bzip2::write::BzEncoder;
 use flate2::write::{DeflateEncoder, GzEncoder, ZlibEncoder};
 use flate2::Compression;
 use serde::{Deserialize, Serialize};
-use std::io::{self, Seek, SeekFrom, Write};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom, Write};
+use std::ops::RangeInclusive;
+use xz2::read::XzDecoder;
+use xz2::write::XzEncoder;
 
-#[derive(Clone, Serialize, Deserialize)]
+use crate::encryption::EncryptionFormat;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum CompressionFormat {
     Gzip,
     Zlib,
     Deflate,
+    Zstd,
+    Xz,
+    Bzip2,
     None,
 }
 
+impl CompressionFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            CompressionFormat::Zlib => "zip",
+            CompressionFormat::Deflate => "zz",
+            CompressionFormat::Gzip => "gz",
+            CompressionFormat::Zstd => "zst",
+            CompressionFormat::Xz => "xz",
+            CompressionFormat::Bzip2 => "bz2",
+            CompressionFormat::None => "",
+        }
+    }
+
+    pub fn default_level(&self) -> u32 {
+        match self {
+            CompressionFormat::Gzip | CompressionFormat::Zlib | CompressionFormat::Deflate => 9,
+            CompressionFormat::Zstd => 3,
+            CompressionFormat::Xz => 6,
+            CompressionFormat::Bzip2 => 9,
+            CompressionFormat::None => 0,
+        }
+    }
+
+    pub fn levels(&self) -> RangeInclusive<u32> {
+        match self {
+            CompressionFormat::Gzip | CompressionFormat::Zlib | CompressionFormat::Deflate => 0..=9,
+            CompressionFormat::Zstd => 1..=22,
+            CompressionFormat::Xz => 0..=9,
+            CompressionFormat::Bzip2 => 1..=9,
+            CompressionFormat::None => 0..=0,
+        }
+    }
+
+    /// Rejects a level outside of the range supported by the format.
+    pub fn check_level(&self, level: u32) -> anyhow::Result<u32> {
+        let levels = self.levels();
+        if !levels.contains(&level) {
+            return Err(anyhow::anyhow!(
+                "Compression level {} is not supported by {:?}, use {} to {}",
+                level,
+                self,
+                levels.start(),
+                levels.end()
+            ));
+        }
+
+        Ok(level)
+    }
+
+    /// Guesses the format from the extension of a backup file name.
+    pub fn from_extension(path: &str) -> Option<CompressionFormat> {
+        let path = EncryptionFormat::strip_extension(path);
+        let extension = path.rsplit_once('.').map(|(_, extension)| extension)?;
+
+        match extension {
+            "gz" => Some(CompressionFormat::Gzip),
+            "zip" => Some(CompressionFormat::Zlib),
+            "zz" => Some(CompressionFormat::Deflate),
+            "zst" => Some(CompressionFormat::Zstd),
+            "xz" => Some(CompressionFormat::Xz),
+            "bz2" => Some(CompressionFormat::Bzip2),
+            "" | "sql" | "dump" | "tar" => Some(CompressionFormat::None),
+            _ => None,
+        }
+    }
+
+    /// Recognizes the formats that start with a magic number.
+    /// Raw deflate streams have no header and can't be detected this way.
+    pub fn from_signature(signature: &[u8]) -> Option<CompressionFormat> {
+        match signature {
+            [0x1F, 0x8B, ..] => Some(CompressionFormat::Gzip),
+            [0x78, 0x01 | 0x5E | 0x9C | 0xDA, ..] => Some(CompressionFormat::Zlib),
+            [0x28, 0xB5, 0x2F, 0xFD, ..] => Some(CompressionFormat::Zstd),
+            [0xFD, b'7', b'z', b'X', b'Z', 0x00, ..] => Some(CompressionFormat::Xz),
+            [b'B', b'Z', b'h', ..] => Some(CompressionFormat::Bzip2),
+            _ => None,
+        }
+    }
+}
+
+impl std::str::FromStr for CompressionFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "gzip" | "gz" => Ok(CompressionFormat::Gzip),
+            "zlib" => Ok(CompressionFormat::Zlib),
+            "deflate" => Ok(CompressionFormat::Deflate),
+            "zstd" | "zst" => Ok(CompressionFormat::Zstd),
+            "xz" => Ok(CompressionFormat::Xz),
+            "bzip2" | "bz2" => Ok(CompressionFormat::Bzip2),
+            "none" => Ok(CompressionFormat::None),
+            _ => Err(anyhow::anyhow!(
+                "Unsupported compression format: {}. Use gzip, zlib, deflate, zstd, xz, bzip2 or none",
+                s
+            )),
+        }
+    }
+}
+
 pub enum Compressor<W: Write + Send + Unpin> {
     Gzip(GzEncoder<W>),
     Zlib(ZlibEncoder<W>),
     Deflate(DeflateEncoder<W>),
+    Zstd(zstd::stream::write::Encoder<'static, W>),
+    Xz(XzEncoder<W>),
+    Bzip2(BzEncoder<W>),
     None(W),
 }
 
 impl<W: Write + Send + Unpin> Compressor<W> {
-    pub fn new(writer: W, format: CompressionFormat, level: Compression) -> Self {
-        match format {
-            CompressionFormat::Gzip => Compressor::Gzip(GzEncoder::new(writer, level)),
-            CompressionFormat::Zlib => Compressor::Zlib(ZlibEncoder::new(writer, level)),
-            CompressionFormat::Deflate => Compressor::Deflate(DeflateEncoder::new(writer, level)),
+    /// Creates a compressor, only zstd uses more than one of the `threads`.
+    pub fn new(writer: W, format: CompressionFormat, level: u32, threads: u32) -> io::Result<Self> {
+        let level = format
+            .check_level(level)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+
+        let compressor = match format {
+            CompressionFormat::Gzip => {
+                Compressor::Gzip(GzEncoder::new(writer, Compression::new(level)))
+            }
+            CompressionFormat::Zlib => {
+                Compressor::Zlib(ZlibEncoder::new(writer, Compression::new(level)))
+            }
+            CompressionFormat::Deflate => {
+                Compressor::Deflate(DeflateEncoder::new(writer, Compression::new(level)))
+            }
+            CompressionFormat::Zstd => {
+                let mut encoder = zstd::stream::write::Encoder::new(writer, level as i32)?;
+                if threads > 1 {
+                    encoder.multithread(threads)?;
+                }
+                Compressor::Zstd(encoder)
+            }
+            CompressionFormat::Xz => Compressor::Xz(XzEncoder::new(writer, level)),
+            CompressionFormat::Bzip2 => {
+                Compressor::Bzip2(BzEncoder::new(writer, bzip2::Compression::new(level)))
+            }
             CompressionFormat::None => Compressor::None(writer),
-        }
+        };
+
+        Ok(compressor)
     }
 
     pub fn finish(self) -> io::Result<W> {
@@ -33,6 +170,9 @@ impl<W: Write + Send + Unpin> Compressor<W> {
             Compressor::Gzip(encoder) => encoder.finish(),
             Compressor::Zlib(encoder) => encoder.finish(),
             Compressor::Deflate(encoder) => encoder.finish(),
+            Compressor::Zstd(encoder) => encoder.finish(),
+            Compressor::Xz(encoder) => encoder.finish(),
+            Compressor::Bzip2(encoder) => encoder.finish(),
             Compressor::None(writer) => Ok(writer),
         }
     }
@@ -44,6 +184,9 @@ impl<W: Write + Send + Unpin> Write for Compressor<W> {
             Compressor::Gzip(ref mut encoder) => encoder.write(buf),
             Compressor::Zlib(ref mut encoder) => encoder.write(buf),
             Compressor::Deflate(ref mut encoder) => encoder.write(buf),
+            Compressor::Zstd(ref mut encoder) => encoder.write(buf),
+            Compressor::Xz(ref mut encoder) => encoder.write(buf),
+            Compressor::Bzip2(ref mut encoder) => encoder.write(buf),
             Compressor::None(ref mut writer) => writer.write(buf),
         }
     }
@@ -53,6 +196,9 @@ impl<W: Write + Send + Unpin> Write for Compressor<W> {
             Compressor::Gzip(ref mut encoder) => encoder.flush(),
             Compressor::Zlib(ref mut encoder) => encoder.flush(),
             Compressor::Deflate(ref mut encoder) => encoder.flush(),
+            Compressor::Zstd(ref mut encoder) => encoder.flush(),
+            Compressor::Xz(ref mut encoder) => encoder.flush(),
+            Compressor::Bzip2(ref mut encoder) => encoder.flush(),
             Compressor::None(ref mut writer) => writer.flush(),
         }
     }
@@ -65,41 +211,51 @@ pub enum Decompressor<R: Read + Send + Unpin> {
     Gzip(GzDecoder<R>),
     Zlib(ZlibDecoder<R>),
     Deflate(DeflateDecoder<R>),
+    Zstd(zstd::stream::read::Decoder<'static, BufReader<R>>),
+    Xz(XzDecoder<R>),
+    Bzip2(BzDecoder<R>),
     None(R),
 }
 
 impl<R: Read + Send + Unpin> Decompressor<R> {
-    pub fn new(reader: R, format: CompressionFormat) -> Self {
-        match format {
+    pub fn new(reader: R, format: CompressionFormat) -> io::Result<Self> {
+        let decompressor = match format {
             CompressionFormat::Gzip => Decompressor::Gzip(GzDecoder::new(reader)),
             CompressionFormat::Zlib => Decompressor::Zlib(ZlibDecoder::new(reader)),
             CompressionFormat::Deflate => Decompressor::Deflate(DeflateDecoder::new(reader)),
+            CompressionFormat::Zstd => {
+                Decompressor::Zstd(zstd::stream::read::Decoder::new(reader)?)
+            }
+            CompressionFormat::Xz => Decompressor::Xz(XzDecoder::new(reader)),
+            CompressionFormat::Bzip2 => Decompressor::Bzip2(BzDecoder::new(reader)),
             CompressionFormat::None => Decompressor::None(reader),
-        }
+        };
+
+        Ok(decompressor)
     }
 
     pub fn detect_format(mut reader: R) -> io::Result<(CompressionFormat, R)>
     where
         R: Read + Seek,
     {
-        let mut signature = [0u8; 3];
+        let mut signature = [0u8; 6];
         let start_pos = reader.stream_position()?;
         let bytes_read = reader.read(&mut signature)?;
         reader.seek(SeekFrom::Start(start_pos))?;
 
-        if bytes_read < 2 {
-            return Ok((CompressionFormat::None, reader));
-        }
+        let format = CompressionFormat::from_signature(&signature[..bytes_read])
+            .unwrap_or(CompressionFormat::None);
 
-        if signature[0] == 0x1F && signature[1] == 0x8B {
-            Ok((CompressionFormat::Gzip, reader))
-        } else if signature[0] == 0x78
-            && (signature[1] == 0x01 || signature[1] == 0x9C || signature[1] == 0xDA)
-        {
-            Ok((CompressionFormat::Zlib, reader))
-        } else {
-            Ok((CompressionFormat::None, reader))
-        }
+        Ok((format, reader))
+    }
+
+    /// Peeks at the buffered bytes of the reader to detect the format without consuming them.
+    pub fn sniff_format(reader: &mut R) -> io::Result<Option<CompressionFormat>>
+    where
+        R: BufRead,
+    {
+        let signature = reader.fill_buf()?;
+        Ok(CompressionFormat::from_signature(signature))
     }
 
     pub fn into_inner(self) -> R {
@@ -107,6 +263,9 @@ impl<R: Read + Send + Unpin> Decompressor<R> {
             Decompressor::Gzip(decoder) => decoder.into_inner(),
             Decompressor::Zlib(decoder) => decoder.into_inner(),
             Decompressor::Deflate(decoder) => decoder.into_inner(),
+            Decompressor::Zstd(decoder) => decoder.finish().into_inner(),
+            Decompressor::Xz(decoder) => decoder.into_inner(),
+            Decompressor::Bzip2(decoder) => decoder.into_inner(),
             Decompressor::None(reader) => reader,
         }
     }
@@ -118,6 +277,9 @@ impl<R: Read + Send + Unpin> Read for Decompressor<R> {
             Decompressor::Gzip(ref mut decoder) => decoder.read(buf),
             Decompressor::Zlib(ref mut decoder) => decoder.read(buf),
             Decompressor::Deflate(ref mut decoder) => decoder.read(buf),
+            Decompressor::Zstd(ref mut decoder) => decoder.read(buf),
+            Decompressor::Xz(ref mut decoder) => decoder.read(buf),
+            Decompressor::Bzip2(ref mut decoder) => decoder.read(buf),
             Decompressor::None(ref mut reader) => reader.read(buf),
         }
     }
@@ -125,9 +287,7 @@ impl<R: Read + Send + Unpin> Read for Decompressor<R> {
 
 #[cfg(test)]
 mod compression_test {
-    use std::io::{Cursor, Read, Write};
-
-    use flate2::Compression;
+    use std::io::{BufReader, Cursor, Read, Write};
 
     use crate::compression::{CompressionFormat, Decompressor};
 
@@ -137,7 +297,8 @@ mod compression_test {
     fn compress() {
         let message = "Ceci est un texte test";
         let bytes = vec![];
-        let mut compressor = Compressor::new(bytes, CompressionFormat::Zlib, Compression::best());
+        let mut compressor = Compressor::new(bytes, CompressionFormat::Zlib, 9, 1)
+            .expect("Failed to create compressor");
 
         compressor
             .write_all(message.as_bytes())
@@ -147,7 +308,8 @@ mod compression_test {
         res.flush().expect("Failed to flush");
 
         let reader = Cursor::new(res);
-        let mut decompressor = Decompressor::new(reader, CompressionFormat::Zlib);
+        let mut decompressor = Decompressor::new(reader, CompressionFormat::Zlib)
+            .expect("Failed to create decompressor");
 
         let mut buf = [0u8; 512];
         let n = decompressor.read(&mut buf).expect("Failed to read bytes");
@@ -155,4 +317,80 @@ mod compression_test {
 
         assert_eq!(message.as_bytes(), decompressed_bytes);
     }
+
+    #[test]
+    fn detect() {
+        for format in [
+            CompressionFormat::Gzip,
+            CompressionFormat::Zlib,
+            CompressionFormat::Zstd,
+            CompressionFormat::Xz,
+            CompressionFormat::Bzip2,
+        ] {
+            let mut compressor = Compressor::new(vec![], format, format.default_level(), 1)
+                .expect("Failed to create compressor");
+            compressor
+                .write_all(b"SELECT 1;")
+                .expect("Failed to write bytes");
+            let bytes = compressor.finish().expect("Unable to finish compressor");
+
+            let mut reader = BufReader::new(Cursor::new(bytes));
+            let detected = Decompressor::sniff_format(&mut reader).expect("Failed to sniff");
+            assert_eq!(detected, Some(format));
+
+            let mut decompressor =
+                Decompressor::new(reader, format).expect("Failed to create decompressor");
+            let mut restored = String::new();
+            decompressor
+                .read_to_string(&mut restored)
+                .expect("Failed to read bytes");
+            assert_eq!(restored, "SELECT 1;");
+        }
+
+        let mut reader = BufReader::new(Cursor::new(b"SELECT 1;".to_vec()));
+        let detected = Decompressor::sniff_format(&mut reader).expect("Failed to sniff");
+        assert_eq!(detected, None);
+
+        assert_eq!(
+            CompressionFormat::from_extension("db-2025-01-01-000000-abcd.zz"),
+            Some(CompressionFormat::Deflate)
+        );
+        assert_eq!(
+            CompressionFormat::from_extension("db-2025-01-01-000000-abcd."),
+            Some(CompressionFormat::None)
+        );
+        assert_eq!(
+            CompressionFormat::from_extension("db-2025-01-01-000000-abcd.zst"),
+            Some(CompressionFormat::Zstd)
+        );
+    }
+
+    #[test]
+    fn check_level() {
+        assert!(CompressionFormat::Gzip.check_level(19).is_err());
+        assert_eq!(CompressionFormat::Zstd.check_level(19).unwrap(), 19);
+        assert!(CompressionFormat::Zstd.check_level(0).is_err());
+        assert!(CompressionFormat::Bzip2.check_level(0).is_err());
+        assert_eq!(CompressionFormat::Xz.check_level(0).unwrap(), 0);
+
+        assert!(Compressor::new(vec![], CompressionFormat::Gzip, 10, 1).is_err());
+    }
+
+    #[test]
+    fn zstd_threads() {
+        let plaintext: Vec<u8> = (0..4 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+        let mut compressor = Compressor::new(vec![], CompressionFormat::Zstd, 3, 4)
+            .expect("Failed to create compressor");
+        compressor
+            .write_all(&plaintext)
+            .expect("Failed to write bytes");
+        let bytes = compressor.finish().expect("Unable to finish compressor");
+
+        let mut restored = vec![];
+        Decompressor::new(Cursor::new(bytes), CompressionFormat::Zstd)
+            .expect("Failed to create decompressor")
+            .read_to_end(&mut restored)
+            .expect("Failed to read bytes");
+        assert_eq!(restored, plaintext);
+    }
 }
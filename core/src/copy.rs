@@ -0,0 +1,389 @@
+use std::{
+    future::Future,
+    io::{self, Read, Write},
+    sync::mpsc::{sync_channel, Receiver, SyncSender},
+};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use tokio::runtime::Handle;
+
+use crate::{
+    databases::{
+        self, ConnectionType, DatabaseConfig, DatabaseConnection, DumpFilters, DumpFormat,
+    },
+    masking::{sql::SqlDialect, Masker, MaskingConfig, MaskingWriter},
+};
+
+/// Chunks buffered between the dump and the restore before the dump waits.
+const PIPE_CAPACITY: usize = 64;
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct CopyOptions {
+    /// Server to copy into, the one of the source database when not set.
+    pub target: Option<DatabaseConfig>,
+    /// Database to copy into, created when missing. Defaults to the one of the target.
+    pub target_database: Option<String>,
+    pub drop_database_first: Option<bool>,
+    /// Everything is copied when not set.
+    pub filters: Option<DumpFilters>,
+    /// Masks the dump on its way to the target.
+    pub masking: Option<MaskingConfig>,
+}
+
+/// Connection to the database a backup is restored or copied into, the source database itself
+/// when neither a server nor a database is given.
+pub async fn connect_target(
+    source: &DatabaseConnection,
+    target: Option<&DatabaseConfig>,
+    target_database: Option<&str>,
+) -> Result<DatabaseConnection> {
+    let server_connection = match target {
+        Some(target) => source.connect_server(target.clone()).await?,
+        None => source.clone(),
+    };
+
+    match target_database {
+        Some(database) => server_connection.connect_database(database).await,
+        None => Ok(server_connection),
+    }
+}
+
+/// Copies a plain dump of `source` into the target database without storing it.
+pub async fn copy(source: &DatabaseConnection, options: CopyOptions) -> Result<()> {
+    let target = connect_target(
+        source,
+        options.target.as_ref(),
+        options.target_database.as_deref(),
+    )
+    .await?;
+    check_copy_target(&source.config, &target.config)?;
+
+    let masker = match options.masking {
+        Some(masking) => Some(Masker::new(
+            masking,
+            SqlDialect::for_connection_type(&source.config.connection_type)?,
+        )?),
+        None => None,
+    };
+    let dump_options = databases::BackupOptions {
+        format: DumpFormat::Plain,
+        jobs: None,
+        filters: options.filters.unwrap_or_default(),
+    };
+
+    let (mut writer, reader) = pipe();
+    let source_connection = source.connection.clone();
+    let dump = async move {
+        match masker {
+            Some(masker) => {
+                let mut masking_writer = MaskingWriter::new(&mut writer, masker);
+                source_connection
+                    .backup_with_options(&mut masking_writer, dump_options)
+                    .await?;
+                masking_writer.finish()?;
+            }
+            None => {
+                source_connection
+                    .backup_with_options(&mut writer, dump_options)
+                    .await?
+            }
+        }
+        writer.close();
+
+        Ok(())
+    };
+
+    stream_into(
+        &target,
+        databases::RestoreOptions {
+            drop_database_first: options.drop_database_first.unwrap_or(false),
+            jobs: None,
+        },
+        reader,
+        dump,
+    )
+    .await
+}
+
+/// A dump can only be restored by the same kind of server, MySQL and MariaDB dumps are compatible.
+pub(crate) fn check_copy_target(source: &DatabaseConfig, target: &DatabaseConfig) -> Result<()> {
+    let is_mysql = |connection_type: &ConnectionType| {
+        matches!(
+            connection_type,
+            ConnectionType::MySql | ConnectionType::MariaDB
+        )
+    };
+
+    if source.connection_type != target.connection_type
+        && !(is_mysql(&source.connection_type) && is_mysql(&target.connection_type))
+    {
+        return Err(anyhow!(
+            "Can't copy a {:?} database into a {:?} one",
+            source.connection_type,
+            target.connection_type
+        ));
+    }
+
+    if source.host != target.host || source.port != target.port {
+        return Ok(());
+    }
+
+    // A Redis copy restores the whole instance, `database` only names its backups
+    if source.connection_type == ConnectionType::Redis {
+        return Err(anyhow!(
+            "The source and target are the same Redis instance, use another target instance"
+        ));
+    }
+
+    if source.database == target.database {
+        return Err(anyhow!(
+            "The source and target are the same database, use another target database"
+        ));
+    }
+
+    Ok(())
+}
+
+/// Runs `dump` and the restore of its output into `target` side by side. Both ends of the pipe
+/// block, so each side gets its own thread and neither can stall the other's runtime.
+pub(crate) async fn stream_into<T, F>(
+    target: &DatabaseConnection,
+    options: databases::RestoreOptions,
+    mut reader: PipeReader,
+    dump: F,
+) -> Result<T>
+where
+    T: Send + 'static,
+    F: Future<Output = Result<T>> + Send + 'static,
+{
+    let handle = Handle::current();
+    let dumped = tokio::task::spawn_blocking({
+        let handle = handle.clone();
+        move || handle.block_on(dump)
+    });
+
+    let target_connection = target.connection.clone();
+    let restored = tokio::task::spawn_blocking(move || {
+        handle.block_on(target_connection.restore_with_options(&mut reader, options))
+    });
+
+    let dumped = dumped
+        .await
+        .map_err(|e| anyhow!("Dump task failed: {}", e))?;
+    let restored = restored
+        .await
+        .map_err(|e| anyhow!("Restore task failed: {}", e))?;
+
+    match (dumped, restored) {
+        (Ok(value), Ok(())) => Ok(value),
+        (Err(e), Ok(())) => Err(e),
+        (Ok(_), Err(e)) => Err(e),
+        (Err(dump_error), Err(restore_error)) => Err(anyhow!(
+            "Copy failed, dump: {}, restore: {}",
+            dump_error,
+            restore_error
+        )),
+    }
+}
+
+/// In-memory pipe from a dump to a restore. The reader sees the end of the dump only once the
+/// writer is closed, a writer dropped before that fails the restore instead of truncating it.
+pub fn pipe() -> (PipeWriter, PipeReader) {
+    let (sender, receiver) = sync_channel(PIPE_CAPACITY);
+
+    (
+        PipeWriter {
+            sender: Some(sender),
+        },
+        PipeReader {
+            receiver,
+            chunk: vec![],
+            position: 0,
+            finished: false,
+        },
+    )
+}
+
+pub struct PipeWriter {
+    sender: Option<SyncSender<Option<Vec<u8>>>>,
+}
+
+impl PipeWriter {
+    /// Marks the dump as complete.
+    pub fn close(&mut self) {
+        if let Some(sender) = self.sender.take() {
+            let _ = sender.send(None);
+        }
+    }
+}
+
+impl Write for PipeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let sender = self
+            .sender
+            .as_ref()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::BrokenPipe, "Pipe has been closed"))?;
+
+        sender.send(Some(buf.to_vec())).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "The restore stopped reading the dump",
+            )
+        })?;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub struct PipeReader {
+    receiver: Receiver<Option<Vec<u8>>>,
+    chunk: Vec<u8>,
+    position: usize,
+    finished: bool,
+}
+
+impl Read for PipeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.chunk.len() {
+            if self.finished {
+                return Ok(0);
+            }
+
+            match self.receiver.recv() {
+                Ok(Some(chunk)) => {
+                    self.chunk = chunk;
+                    self.position = 0;
+                }
+                Ok(None) => self.finished = true,
+                Err(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "The dump stopped before it was complete",
+                    ))
+                }
+            }
+        }
+
+        let n = buf.len().min(self.chunk.len() - self.position);
+        buf[..n].copy_from_slice(&self.chunk[self.position..self.position + n]);
+        self.position += n;
+
+        Ok(n)
+    }
+}
+
+/// Writes everything to both writers, used to store a backup while it's copied.
+pub struct TeeWriter<A: Write, B: Write> {
+    first: A,
+    second: B,
+}
+
+impl<A: Write, B: Write> TeeWriter<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        Self { first, second }
+    }
+}
+
+impl<A: Write, B: Write> Write for TeeWriter<A, B> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.first.write_all(buf)?;
+        self.second.write_all(buf)?;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.first.flush()?;
+        self.second.flush()
+    }
+}
+
+#[cfg(test)]
+mod copy_test {
+    use std::io::{Read, Write};
+
+    use crate::databases::{ConnectionType, DatabaseConfig};
+
+    use super::{check_copy_target, pipe, TeeWriter};
+
+    fn config(connection_type: ConnectionType, database: &str) -> DatabaseConfig {
+        DatabaseConfig {
+            id: "test".into(),
+            name: "test".into(),
+            connection_type,
+            host: "localhost".into(),
+            port: 5432,
+            username: "user".into(),
+            password: None,
+            database: database.into(),
+            ssh: None,
+        }
+    }
+
+    #[test]
+    fn pipe_and_tee() {
+        let (writer, mut reader) = pipe();
+
+        let dump = std::thread::spawn(move || {
+            let mut tee = TeeWriter::new(vec![], writer);
+            for i in 0..1000 {
+                writeln!(tee, "line {}", i).expect("Failed to write");
+            }
+            let TeeWriter {
+                first: copy,
+                second: mut writer,
+            } = tee;
+            writer.close();
+            copy
+        });
+
+        let mut restored = vec![];
+        reader.read_to_end(&mut restored).expect("Failed to read");
+
+        assert_eq!(dump.join().unwrap(), restored);
+        assert!(restored.ends_with(b"line 999\n"));
+
+        let (mut writer, mut reader) = pipe();
+        writer.write_all(b"partial").unwrap();
+        drop(writer);
+        let mut restored = vec![];
+        assert!(
+            reader.read_to_end(&mut restored).is_err(),
+            "A dump that stopped early shouldn't look complete"
+        );
+
+        let (mut writer, reader) = pipe();
+        drop(reader);
+        assert!(writer.write_all(b"data").is_err());
+    }
+
+    #[test]
+    fn copy_target() {
+        let source = config(ConnectionType::PostgreSql, "app");
+
+        assert!(check_copy_target(&source, &source).is_err());
+        assert!(check_copy_target(&source, &config(ConnectionType::PostgreSql, "app_dev")).is_ok());
+        assert!(check_copy_target(&source, &config(ConnectionType::MySql, "app_dev")).is_err());
+        assert!(check_copy_target(
+            &config(ConnectionType::MySql, "app"),
+            &config(ConnectionType::MariaDB, "app_dev")
+        )
+        .is_ok());
+
+        let redis = config(ConnectionType::Redis, "cache");
+        assert!(check_copy_target(&redis, &config(ConnectionType::Redis, "cache_dev")).is_err());
+        assert!(check_copy_target(
+            &redis,
+            &DatabaseConfig {
+                port: redis.port + 1,
+                ..redis.clone()
+            }
+        )
+        .is_ok());
+    }
+}
@@ -0,0 +1,94 @@
+use std::{
+    io::{Read, Write},
+    sync::Arc,
+};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+
+use crate::databases::{
+    mysql::connection::MySqlConnection, ssh::tunnel::SshTunnel, version::Version, BackupOptions,
+    DatabaseConfig, DatabaseConnectionTrait, DatabaseMetadata, RestoreOptions, TableStats,
+};
+
+/// MariaDB server, reached over the MySQL protocol but dumped and restored with the
+/// MariaDB clients matching its release series.
+pub struct MariaDBConnection {
+    connection: MySqlConnection,
+}
+
+impl MariaDBConnection {
+    pub async fn new(config: DatabaseConfig) -> Result<Self> {
+        Self::check(MySqlConnection::new(config).await?).await
+    }
+
+    /// Connects through an open tunnel, `config` already points at its local end.
+    pub(crate) async fn with_tunnel(
+        config: DatabaseConfig,
+        tunnel: Option<Arc<SshTunnel>>,
+    ) -> Result<Self> {
+        Self::check(MySqlConnection::with_tunnel(config, tunnel).await?).await
+    }
+
+    async fn check(connection: MySqlConnection) -> Result<Self> {
+        match connection.get_metadata().await?.version {
+            Version::MariaDB(_) => Ok(Self { connection }),
+            _ => Err(anyhow!(
+                "{}:{} is not a MariaDB server, use the MySQL database type",
+                connection.config.host,
+                connection.config.port
+            )),
+        }
+    }
+}
+
+#[async_trait]
+impl DatabaseConnectionTrait for MariaDBConnection {
+    async fn get_metadata(&self) -> Result<DatabaseMetadata> {
+        self.connection.get_metadata().await
+    }
+
+    async fn test(&self) -> Result<bool> {
+        self.connection.test().await
+    }
+
+    async fn backup(&self, writer: &mut (dyn Write + Send + Unpin)) -> Result<()> {
+        self.connection.backup(writer).await
+    }
+
+    async fn backup_with_options(
+        &self,
+        writer: &mut (dyn Write + Send + Unpin),
+        options: BackupOptions,
+    ) -> Result<()> {
+        self.connection.backup_with_options(writer, options).await
+    }
+
+    async fn restore_with_options(
+        &self,
+        reader: &mut (dyn Read + Send + Unpin),
+        options: RestoreOptions,
+    ) -> Result<()> {
+        self.connection.restore_with_options(reader, options).await
+    }
+
+    async fn restore(&self, reader: &mut (dyn Read + Send + Unpin)) -> Result<()> {
+        self.connection.restore(reader).await
+    }
+
+    async fn get_table_stats(&self) -> Result<Vec<TableStats>> {
+        self.connection.get_table_stats().await
+    }
+
+    async fn database_exists(&self, name: &str) -> Result<bool> {
+        self.connection.database_exists(name).await
+    }
+
+    async fn create_database(&self, name: &str) -> Result<()> {
+        self.connection.create_database(name).await
+    }
+
+    async fn drop_database(&self, name: &str) -> Result<()> {
+        self.connection.drop_database(name).await
+    }
+}
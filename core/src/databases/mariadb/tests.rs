@@ -0,0 +1,138 @@
+#[cfg(test)]
+mod mariadb_connection_tests {
+    use std::env;
+
+    use crate::databases::{
+        mariadb::{connection::MariaDBConnection, version::MariaDBVersion},
+        version::{Version, VersionTrait},
+        ConnectionType, DatabaseConfig, DatabaseConnectionTrait,
+    };
+    use anyhow::Result;
+    use dotenv::dotenv;
+
+    fn get_mariadb_config() -> Result<DatabaseConfig> {
+        dotenv().ok();
+
+        let port: u16 = env::var("MARIADB_PORT").unwrap_or("0".into()).parse()?;
+        let password = env::var("MARIADB_PASSWORD").unwrap_or_default();
+
+        let config = DatabaseConfig {
+            id: "test".to_string(),
+            name: "test".to_string(),
+            connection_type: ConnectionType::MariaDB,
+            host: env::var("MARIADB_HOST").unwrap_or_default(),
+            password: Some(password),
+            ssh: None,
+            username: env::var("MARIADB_USERNAME").unwrap_or_default(),
+            database: env::var("MARIADB_NAME").unwrap_or_default(),
+            port,
+        };
+
+        Ok(config)
+    }
+
+    #[test]
+    fn test_01_parse_version() {
+        let version =
+            MariaDBVersion::parse_string_version("10.11.6-MariaDB-1:10.11.6+maria~ubu2204")
+                .expect("Failed to parse version");
+        assert_eq!((version.major, version.minor, version.patch), (10, 11, 6));
+        assert_eq!(version.to_string(), "10.11");
+
+        let version = MariaDBVersion::parse_string_version("5.5.5-11.4.2-MariaDB-log")
+            .expect("Failed to parse version");
+        assert_eq!((version.major, version.minor, version.patch), (11, 4, 2));
+
+        assert!(MariaDBVersion::parse_string_version("8.0.36").is_none());
+        assert!(MariaDBVersion::parse_string_version("9.3.0").is_none());
+    }
+
+    #[ignore]
+    #[tokio::test]
+    async fn test_02_mariadb_connection() {
+        let config = get_mariadb_config().expect("Failed to get config");
+        let connection = MariaDBConnection::new(config)
+            .await
+            .expect("Failed to get connection");
+
+        let is_connected = connection.test().await.expect("Failed to test connection");
+
+        assert!(is_connected);
+
+        let metadata = connection
+            .get_metadata()
+            .await
+            .expect("Failed to get metadata");
+
+        let version = match &metadata.version {
+            Version::MariaDB(version) => Some(version),
+            _ => None,
+        };
+
+        assert!(version.is_some());
+
+        let version = version.unwrap();
+
+        assert_eq!(version.to_string(), "10.11");
+    }
+
+    #[ignore]
+    #[tokio::test]
+    async fn test_03_mariadb_backup() {
+        let config = get_mariadb_config().expect("Failed to get config");
+
+        let connection = MariaDBConnection::new(config)
+            .await
+            .expect("Failed to get connection");
+
+        let mut buffer = Vec::new();
+        connection
+            .backup(&mut buffer)
+            .await
+            .expect("Failed to backup database");
+
+        assert!(!buffer.is_empty());
+        assert!(String::from_utf8_lossy(&buffer).contains("MariaDB dump"));
+    }
+
+    #[test]
+    fn test_04_resolve_command() {
+        use std::{fs, os::unix::fs::PermissionsExt};
+
+        use crate::databases::mariadb::utilities::MariaDBUtilities;
+
+        let dir = tempfile::tempdir().expect("Failed to create directory");
+        let install = |name: &str| {
+            let path = dir.path().join(name);
+            fs::write(&path, "#!/bin/sh\n").expect("Failed to write client");
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755))
+                .expect("Failed to make client executable");
+            path
+        };
+
+        // No archive is ever installed for this version
+        let utilities = MariaDBUtilities::new(MariaDBVersion {
+            major: 0,
+            minor: 0,
+            patch: 0,
+        });
+        let search_path = env::join_paths([dir.path()]).unwrap();
+        let find = |bin_name| {
+            utilities
+                .find_bin(bin_name, Some(&search_path))
+                .expect("Failed to resolve client")
+        };
+
+        assert_eq!(find("mysqldump"), None);
+        fs::write(dir.path().join("mysql"), "not executable").unwrap();
+        assert_eq!(find("mysql"), None);
+
+        let mysqldump = install("mysqldump");
+        assert_eq!(find("mysqldump"), Some(mysqldump));
+
+        let mariadb_dump = install("mariadb-dump");
+        let mariadb = install("mariadb");
+        assert_eq!(find("mysqldump"), Some(mariadb_dump));
+        assert_eq!(find("mysql"), Some(mariadb));
+    }
+}
@@ -0,0 +1,101 @@
+use std::{env, ffi::OsStr, path::PathBuf};
+
+use crate::{
+    archives::installer::ArchiveInstaller,
+    common::{find_in_path, get_binaries_base_path},
+    databases::{version::Version, UtilitiesTrait},
+};
+use anyhow::{anyhow, Result};
+use log::debug;
+use tokio::process::Command;
+
+use super::version::MariaDBVersion;
+use async_trait::async_trait;
+
+/// MariaDB 11 no longer ships the `mysql*` names of its clients.
+fn get_bin_name(bin_name: &str) -> &str {
+    match bin_name {
+        "mysqldump" => "mariadb-dump",
+        "mysql" => "mariadb",
+        _ => bin_name,
+    }
+}
+
+/// Names to look for on `PATH`, older installs only have the `mysql*` ones.
+fn get_path_names(bin_name: &str) -> Vec<&str> {
+    match bin_name {
+        "mysqldump" => vec!["mariadb-dump", "mysqldump"],
+        "mysql" => vec!["mariadb", "mysql"],
+        _ => vec![bin_name],
+    }
+}
+
+pub struct MariaDBUtilities {
+    version: MariaDBVersion,
+}
+
+impl MariaDBUtilities {
+    pub fn new(version: MariaDBVersion) -> Self {
+        MariaDBUtilities { version }
+    }
+
+    pub async fn install(&self) -> Result<()> {
+        let archives_installer = ArchiveInstaller::new(Version::MariaDB(self.version.clone()));
+        let path = archives_installer.download_and_install().await?;
+
+        debug!(
+            "Successfully installed MariaDB utilities at {}",
+            path.display()
+        );
+
+        Ok(())
+    }
+
+    /// The installed client if there is one, otherwise the first one found in `search_path`.
+    pub fn find_bin(&self, bin_name: &str, search_path: Option<&OsStr>) -> Result<Option<PathBuf>> {
+        let bin_path = self.get_base_path()?.join(get_bin_name(bin_name));
+        if bin_path.exists() {
+            return Ok(Some(bin_path));
+        }
+
+        Ok(
+            search_path
+                .and_then(|search_path| find_in_path(&get_path_names(bin_name), search_path)),
+        )
+    }
+}
+
+#[async_trait]
+impl UtilitiesTrait for MariaDBUtilities {
+    fn get_base_path(&self) -> Result<PathBuf> {
+        let path = get_binaries_base_path(&Version::MariaDB(self.version.clone())).join("bin");
+        Ok(path)
+    }
+
+    /// Accepts the MySQL client names, `mysqldump` runs `mariadb-dump`. Falls back to the clients
+    /// on `PATH` before trying to install them.
+    async fn get_command(&self, bin_name: &str) -> Result<Command> {
+        if let Some(bin_path) = self.find_bin(bin_name, env::var_os("PATH").as_deref())? {
+            return Ok(Command::new(bin_path));
+        }
+
+        debug!("MariaDB utilities not found, attempting to download and install");
+        if let Err(e) = self.install().await {
+            return Err(anyhow!(
+                "{} not found on PATH and the MariaDB utilities could not be installed: {}",
+                get_bin_name(bin_name),
+                e
+            ));
+        }
+
+        let bin_path = self.get_base_path()?.join(get_bin_name(bin_name));
+        if !bin_path.exists() {
+            return Err(anyhow!(
+                "Binary {} not found after installation",
+                get_bin_name(bin_name)
+            ));
+        }
+
+        Ok(Command::new(bin_path))
+    }
+}
@@ -0,0 +1,78 @@
+use std::{fmt, str::FromStr};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::databases::version::VersionTrait;
+
+/// Prefix MariaDB 10 adds to its version for clients that expect MySQL 5.
+const REPLICATION_PREFIX: &str = "5.5.5-";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MariaDBVersion {
+    pub major: u16,
+    pub minor: u16,
+    pub patch: u16,
+}
+
+impl MariaDBVersion {
+    /// MariaDB speaks the MySQL protocol, only `SELECT version()` tells them apart.
+    pub fn is_mariadb(version_string: &str) -> bool {
+        version_string.to_lowercase().contains("mariadb")
+    }
+}
+
+impl VersionTrait for MariaDBVersion {
+    fn from_str(string: &str) -> Option<Self> {
+        let res: Vec<&str> = string.split(".").collect();
+
+        let major = res.first()?.parse::<u16>().ok()?;
+        let minor = res.get(1)?.parse::<u16>().ok()?;
+        let patch = res.get(2)?.parse::<u16>().ok()?;
+
+        Some(MariaDBVersion {
+            major,
+            minor,
+            patch,
+        })
+    }
+
+    fn parse_string_version(version_string: &str) -> Option<Self> {
+        if !Self::is_mariadb(version_string) {
+            return None;
+        }
+
+        let version_string = version_string
+            .strip_prefix(REPLICATION_PREFIX)
+            .unwrap_or(version_string);
+
+        let regex = Regex::new(r"(\d+)\.(\d+)\.(\d+)").ok()?;
+        let captures = regex.captures(version_string)?;
+
+        let major = captures.get(1)?.as_str().parse::<u16>().ok()?;
+        let minor = captures.get(2)?.as_str().parse::<u16>().ok()?;
+        let patch = captures.get(3)?.as_str().parse::<u16>().ok()?;
+
+        Some(MariaDBVersion {
+            major,
+            minor,
+            patch,
+        })
+    }
+}
+
+/// MariaDB releases are series like 10.11 or 11.4, the client archives follow them.
+impl fmt::Display for MariaDBVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+impl FromStr for MariaDBVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        <MariaDBVersion as VersionTrait>::from_str(s)
+            .ok_or_else(|| format!("Unsupported MariaDB version: {}", s))
+    }
+}
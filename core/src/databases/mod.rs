@@ -1,33 +1,165 @@
 use std::{
+    fmt,
     io::{Read, Write},
     path::PathBuf,
+    str::FromStr,
     sync::Arc,
 };
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
+use mariadb::connection::MariaDBConnection;
+use mongo::connection::MongoDbConnection;
 use mysql::connection::MySqlConnection;
 use postgres::connection::PostgreSqlConnection;
+use redis::connection::RedisConnection;
 use serde::{Deserialize, Serialize};
+use sqlite::connection::SqliteConnection;
+use ssh::{open_tunnel, through_tunnel, tunnel::SshTunnel, SshConfig};
 use tokio::process::Command;
 use version::Version;
 
+pub mod mariadb;
+pub mod mongo;
 pub mod mysql;
 pub mod postgres;
+pub mod redis;
+pub mod sqlite;
+pub mod ssh;
 pub mod version;
 
+/// Output format of the database dump. Archive formats are only produced by PostgreSQL.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DumpFormat {
+    /// SQL script, the only format of the other databases.
+    #[default]
+    Plain,
+    /// `pg_dump` custom archive, restored with `pg_restore`.
+    Custom,
+    /// `pg_dump` directory archive, dumped in parallel and stored as a tar archive.
+    Directory,
+}
+
+impl DumpFormat {
+    /// Archives are recognized by their magic bytes, anything else is a SQL script.
+    pub fn sniff(header: &[u8]) -> DumpFormat {
+        if header.starts_with(b"PGDMP") {
+            DumpFormat::Custom
+        } else if header.get(257..262) == Some(b"ustar") {
+            DumpFormat::Directory
+        } else {
+            DumpFormat::Plain
+        }
+    }
+}
+
+impl fmt::Display for DumpFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DumpFormat::Plain => write!(f, "plain"),
+            DumpFormat::Custom => write!(f, "custom"),
+            DumpFormat::Directory => write!(f, "directory"),
+        }
+    }
+}
+
+impl FromStr for DumpFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "plain" => Ok(DumpFormat::Plain),
+            "custom" => Ok(DumpFormat::Custom),
+            "directory" => Ok(DumpFormat::Directory),
+            _ => Err(anyhow!(
+                "Unsupported dump format: {}. Use plain, custom or directory",
+                s
+            )),
+        }
+    }
+}
+
+/// Part of the selected objects kept in the dump.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DumpContent {
+    /// Definitions and rows.
+    #[default]
+    All,
+    SchemaOnly,
+    DataOnly,
+}
+
+impl fmt::Display for DumpContent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DumpContent::All => write!(f, "all"),
+            DumpContent::SchemaOnly => write!(f, "schema-only"),
+            DumpContent::DataOnly => write!(f, "data-only"),
+        }
+    }
+}
+
+impl FromStr for DumpContent {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "all" => Ok(DumpContent::All),
+            "schema-only" => Ok(DumpContent::SchemaOnly),
+            "data-only" => Ok(DumpContent::DataOnly),
+            _ => Err(anyhow!(
+                "Unsupported dump content: {}. Use all, schema-only or data-only",
+                s
+            )),
+        }
+    }
+}
+
+/// Tables and schemas a dump is restricted to. Patterns are handed to the dump tool as they are,
+/// `pg_dump` understands wildcards while `mysqldump` only takes exact table names.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DumpFilters {
+    pub include_tables: Vec<String>,
+    pub exclude_tables: Vec<String>,
+    pub include_schemas: Vec<String>,
+    pub exclude_schemas: Vec<String>,
+    /// Tables dumped with their definition but without their rows.
+    pub exclude_table_data: Vec<String>,
+    pub content: DumpContent,
+}
+
+impl DumpFilters {
+    pub fn is_empty(&self) -> bool {
+        *self == DumpFilters::default()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct BackupOptions {
-    // compression: Option<u16>,
+    pub format: DumpFormat,
+    /// Parallel dump jobs, only used by the directory format.
+    pub jobs: Option<u16>,
+    pub filters: DumpFilters,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RestoreOptions {
     pub drop_database_first: bool,
+    /// Parallel restore jobs, only used for PostgreSQL archives.
+    #[serde(default)]
+    pub jobs: Option<u16>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DatabaseMetadata {
-    version: Version,
+    pub version: Version,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableStats {
+    pub name: String,
+    pub rows: u64,
 }
 
 #[async_trait]
@@ -35,12 +167,29 @@ pub trait DatabaseConnectionTrait: Send + Sync + Unpin {
     async fn test(&self) -> Result<bool>;
     async fn get_metadata(&self) -> Result<DatabaseMetadata>;
     async fn backup(&self, writer: &mut (dyn Write + Send + Unpin)) -> Result<()>;
+    /// Databases without dump options only produce plain dumps of everything.
+    async fn backup_with_options(
+        &self,
+        writer: &mut (dyn Write + Send + Unpin),
+        options: BackupOptions,
+    ) -> Result<()> {
+        if !options.filters.is_empty() {
+            return Err(anyhow!("Backup filters aren't supported by this database"));
+        }
+
+        self.backup(writer).await
+    }
     async fn restore(&self, reader: &mut (dyn Read + Send + Unpin)) -> Result<()>;
     async fn restore_with_options(
         &self,
         reader: &mut (dyn Read + Send + Unpin),
         options: RestoreOptions,
     ) -> Result<()>;
+    /// Exact row count of every table of the configured database.
+    async fn get_table_stats(&self) -> Result<Vec<TableStats>>;
+    async fn database_exists(&self, name: &str) -> Result<bool>;
+    async fn create_database(&self, name: &str) -> Result<()>;
+    async fn drop_database(&self, name: &str) -> Result<()>;
 }
 
 #[async_trait]
@@ -53,7 +202,10 @@ pub trait UtilitiesTrait: Send + Sync + Unpin {
 pub enum ConnectionType {
     PostgreSql,
     MySql,
-    // MariaDB,
+    MariaDB,
+    Sqlite,
+    MongoDb,
+    Redis,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -66,22 +218,83 @@ pub struct DatabaseConfig {
     pub database: String,
     pub username: String,
     pub password: Option<String>,
+    #[serde(default)]
+    pub ssh: Option<SshConfig>,
 }
 
+#[derive(Clone)]
 pub struct DatabaseConnection {
     pub config: DatabaseConfig,
     pub connection: Arc<dyn DatabaseConnectionTrait>,
+    /// Shared with the connections to the other databases of the same server.
+    tunnel: Option<Arc<SshTunnel>>,
 }
 
 impl DatabaseConnection {
     pub async fn new(config: DatabaseConfig) -> Result<Self> {
+        // SQLite refuses SSH configurations itself
+        let tunnel = match config.connection_type {
+            ConnectionType::Sqlite => None,
+            _ => open_tunnel(&config)?,
+        };
+
+        Self::with_tunnel(config, tunnel).await
+    }
+
+    async fn with_tunnel(config: DatabaseConfig, tunnel: Option<Arc<SshTunnel>>) -> Result<Self> {
+        let tunneled_config = through_tunnel(&config, tunnel.as_deref());
         let connection: Arc<dyn DatabaseConnectionTrait> = match config.connection_type {
             ConnectionType::PostgreSql => {
-                Arc::new(PostgreSqlConnection::new(config.clone()).await?)
+                Arc::new(PostgreSqlConnection::with_tunnel(tunneled_config, tunnel.clone()).await?)
+            }
+            ConnectionType::MySql => {
+                Arc::new(MySqlConnection::with_tunnel(tunneled_config, tunnel.clone()).await?)
+            }
+            ConnectionType::MariaDB => {
+                Arc::new(MariaDBConnection::with_tunnel(tunneled_config, tunnel.clone()).await?)
+            }
+            ConnectionType::Sqlite => Arc::new(SqliteConnection::new(config.clone()).await?),
+            ConnectionType::MongoDb => {
+                Arc::new(MongoDbConnection::with_tunnel(tunneled_config, tunnel.clone()).await?)
+            }
+            ConnectionType::Redis => {
+                Arc::new(RedisConnection::with_tunnel(tunneled_config, tunnel.clone()).await?)
             }
-            ConnectionType::MySql => Arc::new(MySqlConnection::new(config.clone()).await?),
         };
 
-        Ok(Self { config, connection })
+        Ok(Self {
+            config,
+            connection,
+            tunnel,
+        })
+    }
+
+    /// Connects with another configuration, through the tunnel of this connection when both
+    /// reach the same server through the same bastion.
+    pub async fn connect_server(&self, config: DatabaseConfig) -> Result<DatabaseConnection> {
+        let same_server = config.host == self.config.host
+            && config.port == self.config.port
+            && config.ssh == self.config.ssh;
+
+        match (same_server, &self.tunnel) {
+            (true, Some(tunnel)) => Self::with_tunnel(config, Some(tunnel.clone())).await,
+            _ => Self::new(config).await,
+        }
+    }
+
+    /// Connects to another database of the same server, created when it doesn't exist.
+    pub async fn connect_database(&self, database: &str) -> Result<DatabaseConnection> {
+        if database == self.config.database {
+            return Ok(self.clone());
+        }
+
+        if !self.connection.database_exists(database).await? {
+            self.connection.create_database(database).await?;
+        }
+
+        let mut config = self.config.clone();
+        config.database = database.to_string();
+
+        Self::with_tunnel(config, self.tunnel.clone()).await
     }
 }
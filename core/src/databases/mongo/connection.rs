@@ -0,0 +1,403 @@
+use std::{
+    io::{Read, Write},
+    process::Stdio,
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::databases::{
+    ssh::{connect_through_tunnel, tunnel::SshTunnel},
+    version::{Version, VersionTrait},
+    BackupOptions, DatabaseConfig, DatabaseConnectionTrait, DatabaseMetadata, DumpContent,
+    DumpFilters, RestoreOptions, TableStats, UtilitiesTrait,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use mongodb::{
+    bson::{doc, Document},
+    options::{ClientOptions, Credential, ServerAddress},
+    Client,
+};
+use tempfile::NamedTempFile;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    process::Command,
+};
+
+use super::{utilities::MongoDbUtilities, version::MongoDbVersion};
+
+/// Database users are usually created in `admin`, it's also the driver default.
+const AUTHENTICATION_DATABASE: &str = "admin";
+
+pub struct MongoDbConnection {
+    /// Points at the local end of the SSH tunnel when there is one.
+    pub config: DatabaseConfig,
+    pub client: Client,
+    _tunnel: Option<Arc<SshTunnel>>,
+}
+
+fn get_client_options(config: &DatabaseConfig) -> ClientOptions {
+    let credential = match config.username.is_empty() {
+        true => None,
+        false => Some(
+            Credential::builder()
+                .username(config.username.clone())
+                .password(config.password.clone())
+                .source(AUTHENTICATION_DATABASE.to_string())
+                .build(),
+        ),
+    };
+
+    // Replica set members advertise addresses that aren't reachable through a tunnel
+    ClientOptions::builder()
+        .hosts(vec![ServerAddress::Tcp {
+            host: config.host.clone(),
+            port: Some(config.port),
+        }])
+        .direct_connection(true)
+        .server_selection_timeout(Duration::from_secs(30))
+        .credential(credential)
+        .build()
+}
+
+/// YAML single quoted string, the only escape is a doubled quote.
+fn quote_yaml(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// `mongodump` collection filters, it dumps either a single collection or all but the excluded
+/// ones.
+pub(crate) fn get_filter_args(filters: &DumpFilters) -> Result<Vec<String>> {
+    if !filters.include_schemas.is_empty() || !filters.exclude_schemas.is_empty() {
+        return Err(anyhow!(
+            "MongoDB databases have no schemas to filter, use collection filters instead"
+        ));
+    }
+
+    if !filters.exclude_table_data.is_empty() || filters.content != DumpContent::All {
+        return Err(anyhow!(
+            "MongoDB backups always contain the documents of the dumped collections"
+        ));
+    }
+
+    match filters.include_tables.as_slice() {
+        [] => Ok(filters
+            .exclude_tables
+            .iter()
+            .map(|collection| format!("--excludeCollection={}", collection))
+            .collect()),
+        [collection] if filters.exclude_tables.is_empty() => {
+            Ok(vec![format!("--collection={}", collection)])
+        }
+        _ => Err(anyhow!(
+            "mongodump can only include a single collection, without excluded collections"
+        )),
+    }
+}
+
+impl MongoDbConnection {
+    pub async fn new(config: DatabaseConfig) -> Result<Self> {
+        let (config, tunnel) = connect_through_tunnel(&config)?;
+
+        Self::with_tunnel(config, tunnel).await
+    }
+
+    /// Connects through an open tunnel, `config` already points at its local end.
+    pub(crate) async fn with_tunnel(
+        config: DatabaseConfig,
+        tunnel: Option<Arc<SshTunnel>>,
+    ) -> Result<Self> {
+        let client = Client::with_options(get_client_options(&config))?;
+
+        client
+            .database(AUTHENTICATION_DATABASE)
+            .run_command(doc! { "ping": 1 })
+            .await
+            .map_err(|e| anyhow!("Failed to connect to MongoDB: {}", e))?;
+
+        Ok(Self {
+            config,
+            client,
+            _tunnel: tunnel,
+        })
+    }
+
+    /// The database tools read the password from a config file rather than the command line,
+    /// where other users could see it. The file is removed when dropped.
+    fn write_password_file(&self) -> Result<Option<NamedTempFile>> {
+        match &self.config.password {
+            Some(password) => {
+                let mut file = NamedTempFile::new()?;
+                writeln!(file, "password: {}", quote_yaml(password))?;
+                file.flush()?;
+                Ok(Some(file))
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn get_command(
+        &self,
+        bin_name: &str,
+        password_file: Option<&NamedTempFile>,
+    ) -> Result<Command> {
+        let metadata = self.get_metadata().await?;
+        let version = match metadata.version {
+            Version::MongoDb(version) => version,
+            _ => return Err(anyhow!("Wrong version type")),
+        };
+
+        let utilities = MongoDbUtilities::new(version);
+        let mut cmd = utilities.get_command(bin_name).await?;
+
+        cmd.arg(format!("--host={}", self.config.host))
+            .arg(format!("--port={}", self.config.port))
+            .arg("--quiet");
+
+        if !self.config.username.is_empty() {
+            cmd.arg(format!("--username={}", self.config.username))
+                .arg(format!(
+                    "--authenticationDatabase={}",
+                    AUTHENTICATION_DATABASE
+                ));
+        }
+
+        if let Some(password_file) = password_file {
+            cmd.arg(format!("--config={}", password_file.path().display()));
+        }
+
+        Ok(cmd)
+    }
+}
+
+#[async_trait]
+impl DatabaseConnectionTrait for MongoDbConnection {
+    async fn get_metadata(&self) -> Result<DatabaseMetadata> {
+        let build_info = self
+            .client
+            .database(AUTHENTICATION_DATABASE)
+            .run_command(doc! { "buildInfo": 1 })
+            .await
+            .map_err(|e| anyhow!("Failed to get database version: {}", e))?;
+
+        let version_string = build_info
+            .get_str("version")
+            .map_err(|e| anyhow!("Failed to get database version: {}", e))?;
+
+        let version = match MongoDbVersion::parse_string_version(version_string) {
+            Some(version) => version,
+            None => return Err(anyhow!("Failed to parse MongoDB version string")),
+        };
+
+        Ok(DatabaseMetadata {
+            version: Version::MongoDb(version),
+        })
+    }
+
+    async fn test(&self) -> Result<bool> {
+        self.client
+            .database(AUTHENTICATION_DATABASE)
+            .run_command(doc! { "ping": 1 })
+            .await
+            .map(|_| true)
+            .map_err(|e| anyhow!("Connection test failed: {}", e))
+    }
+
+    async fn backup(&self, writer: &mut (dyn Write + Send + Unpin)) -> Result<()> {
+        self.backup_with_options(writer, BackupOptions::default())
+            .await
+    }
+
+    async fn backup_with_options(
+        &self,
+        writer: &mut (dyn Write + Send + Unpin),
+        options: BackupOptions,
+    ) -> Result<()> {
+        let filter_args = get_filter_args(&options.filters)?;
+        let password_file = self.write_password_file()?;
+        let mut cmd = self
+            .get_command("mongodump", password_file.as_ref())
+            .await?;
+
+        cmd.arg("--archive")
+            .arg(format!("--db={}", self.config.database))
+            .args(filter_args);
+
+        let mut child = cmd
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| anyhow!("Failed to start mongodump: {}", e))?;
+
+        let mut stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow!("Failed to capture mongodump stdout"))?;
+
+        let mut buffer = [0u8; 16384];
+
+        loop {
+            match stdout.read(&mut buffer).await {
+                Ok(0) => break, // EOF
+                Ok(n) => {
+                    writer
+                        .write_all(&buffer[..n])
+                        .map_err(|e| anyhow!("Failed to write backup data: {}", e))?;
+                }
+                Err(e) => {
+                    return Err(anyhow!("Failed to read from mongodump: {}", e));
+                }
+            }
+        }
+
+        let status = child
+            .wait()
+            .await
+            .map_err(|e| anyhow!("mongodump process failed: {}", e))?;
+
+        if !status.success() {
+            let mut stderr = child
+                .stderr
+                .take()
+                .ok_or_else(|| anyhow!("Failed to capture mongodump stderr"))?;
+
+            let mut error_message = String::new();
+            stderr
+                .read_to_string(&mut error_message)
+                .await
+                .map_err(|e| anyhow!("Failed to read mongodump stderr: {}", e))?;
+
+            return Err(anyhow!("mongodump failed: {}", error_message));
+        }
+
+        Ok(())
+    }
+
+    async fn restore(&self, reader: &mut (dyn Read + Send + Unpin)) -> Result<()> {
+        self.restore_with_options(
+            reader,
+            RestoreOptions {
+                drop_database_first: true,
+                jobs: None,
+            },
+        )
+        .await
+    }
+
+    /// Collections are restored into the configured database whatever database they were
+    /// dumped from, which is how `verify` restores into its scratch database.
+    async fn restore_with_options(
+        &self,
+        reader: &mut (dyn Read + Send + Unpin),
+        options: RestoreOptions,
+    ) -> Result<()> {
+        if options.drop_database_first {
+            self.drop_database(&self.config.database).await?;
+        }
+
+        let password_file = self.write_password_file()?;
+        let mut cmd = self
+            .get_command("mongorestore", password_file.as_ref())
+            .await?;
+
+        cmd.arg("--archive")
+            .arg("--drop")
+            .arg("--nsFrom=$db$.$collection$")
+            .arg(format!("--nsTo={}.$collection$", self.config.database));
+
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| anyhow!("Failed to start mongorestore: {}", e))?;
+
+        let mut stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow!("Failed to capture mongorestore stdin"))?;
+
+        let mut buffer = [0u8; 16384];
+
+        loop {
+            match reader.read(&mut buffer) {
+                Ok(0) => break, // EOF
+                Ok(n) => {
+                    stdin.write_all(&buffer[..n]).await?;
+                }
+                Err(e) => {
+                    // Killed while its input is still open, at the end of it mongorestore
+                    // would restore the part of the archive it got
+                    let _ = child.kill().await;
+                    return Err(anyhow!("Failed to read backup data: {}", e));
+                }
+            }
+        }
+
+        drop(stdin);
+
+        let output = child
+            .wait_with_output()
+            .await
+            .map_err(|e| anyhow!("mongorestore process failed: {}", e))?;
+
+        if !output.status.success() {
+            return Err(anyhow!(
+                "mongorestore failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+
+        Ok(())
+    }
+
+    async fn get_table_stats(&self) -> Result<Vec<TableStats>> {
+        let database = self.client.database(&self.config.database);
+
+        let mut collections = database
+            .list_collection_names()
+            .filter(doc! { "type": "collection" })
+            .await
+            .map_err(|e| anyhow!("Failed to list collections: {}", e))?;
+        collections.retain(|collection| !collection.starts_with("system."));
+        collections.sort();
+
+        let mut stats = vec![];
+
+        for collection in collections {
+            let rows = database
+                .collection::<Document>(&collection)
+                .count_documents(doc! {})
+                .await
+                .map_err(|e| anyhow!("Failed to count documents of {}: {}", collection, e))?;
+
+            stats.push(TableStats {
+                name: collection,
+                rows,
+            });
+        }
+
+        Ok(stats)
+    }
+
+    /// Always true, MongoDB creates databases on their first write.
+    async fn database_exists(&self, _name: &str) -> Result<bool> {
+        Ok(true)
+    }
+
+    /// MongoDB creates databases on their first write.
+    async fn create_database(&self, _name: &str) -> Result<()> {
+        Ok(())
+    }
+
+    async fn drop_database(&self, name: &str) -> Result<()> {
+        self.client
+            .database(name)
+            .drop()
+            .await
+            .map_err(|e| anyhow!("Failed to drop database {}: {}", name, e))?;
+
+        Ok(())
+    }
+}
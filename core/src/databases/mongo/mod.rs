@@ -0,0 +1,4 @@
+pub mod connection;
+mod tests;
+pub mod utilities;
+pub mod version;
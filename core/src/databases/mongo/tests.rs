@@ -0,0 +1,186 @@
+#[cfg(test)]
+mod mongodb_connection_tests {
+    use std::env;
+
+    use crate::databases::{
+        mongo::{
+            connection::{get_filter_args, MongoDbConnection},
+            version::MongoDbVersion,
+        },
+        version::{Version, VersionTrait},
+        ConnectionType, DatabaseConfig, DatabaseConnectionTrait, DumpContent, DumpFilters,
+    };
+    use anyhow::Result;
+    use dotenv::dotenv;
+    use mongodb::bson::{doc, Document};
+
+    fn get_mongodb_config() -> Result<DatabaseConfig> {
+        dotenv().ok();
+
+        let port: u16 = env::var("MONGODB_PORT").unwrap_or("0".into()).parse()?;
+        let password = env::var("MONGODB_PASSWORD").unwrap_or_default();
+
+        let config = DatabaseConfig {
+            id: "test".to_string(),
+            name: "test".to_string(),
+            connection_type: ConnectionType::MongoDb,
+            host: env::var("MONGODB_HOST").unwrap_or_default(),
+            password: Some(password),
+            ssh: None,
+            username: env::var("MONGODB_USERNAME").unwrap_or_default(),
+            database: env::var("MONGODB_NAME").unwrap_or_default(),
+            port,
+        };
+
+        Ok(config)
+    }
+
+    #[test]
+    fn test_01_parse_version() {
+        let version = MongoDbVersion::parse_string_version("7.0.12").expect("Failed to parse");
+        assert_eq!((version.major, version.minor, version.patch), (7, 0, 12));
+        assert_eq!(version.to_string(), "7");
+
+        assert!(MongoDbVersion::parse_string_version("unknown").is_none());
+    }
+
+    #[ignore]
+    #[tokio::test]
+    async fn test_02_mongodb_connection() {
+        let config = get_mongodb_config().expect("Failed to get config");
+        let connection = MongoDbConnection::new(config)
+            .await
+            .expect("Failed to get connection");
+
+        assert!(connection.test().await.expect("Failed to test connection"));
+
+        let metadata = connection
+            .get_metadata()
+            .await
+            .expect("Failed to get metadata");
+
+        assert!(matches!(metadata.version, Version::MongoDb(_)));
+    }
+
+    #[ignore]
+    #[tokio::test]
+    async fn test_03_mongodb_backup_and_restore() {
+        let config = get_mongodb_config().expect("Failed to get config");
+        let collection_name = format!("test_restore_{}", chrono::Utc::now().timestamp());
+
+        let connection = MongoDbConnection::new(config)
+            .await
+            .expect("Failed to get connection");
+
+        let collection = connection
+            .client
+            .database(&connection.config.database)
+            .collection::<Document>(&collection_name);
+
+        collection
+            .insert_many(vec![
+                doc! { "name": "test1", "value": 100 },
+                doc! { "name": "test2", "value": 200 },
+                doc! { "name": "test3", "value": 300 },
+            ])
+            .await
+            .expect("Failed to insert test data");
+
+        let mut backup_buffer = Vec::new();
+        connection
+            .backup(&mut backup_buffer)
+            .await
+            .expect("Failed to backup database");
+
+        assert!(!backup_buffer.is_empty(), "Backup should not be empty");
+
+        collection
+            .delete_one(doc! { "name": "test3" })
+            .await
+            .expect("Failed to delete test data");
+
+        connection
+            .restore(&mut std::io::Cursor::new(backup_buffer))
+            .await
+            .expect("Failed to restore database");
+
+        let stats = connection
+            .get_table_stats()
+            .await
+            .expect("Failed to get table stats");
+
+        let restored = stats
+            .iter()
+            .find(|stats| stats.name == collection_name)
+            .expect("Should have restored the test collection");
+        assert_eq!(restored.rows, 3, "Should have 3 documents after restore");
+    }
+
+    #[test]
+    fn test_04_filter_args() {
+        let filters = DumpFilters {
+            exclude_tables: vec!["logs".into(), "sessions".into()],
+            ..Default::default()
+        };
+        assert_eq!(
+            get_filter_args(&filters).unwrap(),
+            vec!["--excludeCollection=logs", "--excludeCollection=sessions"]
+        );
+
+        let filters = DumpFilters {
+            include_tables: vec!["orders".into()],
+            ..Default::default()
+        };
+        assert_eq!(
+            get_filter_args(&filters).unwrap(),
+            vec!["--collection=orders"]
+        );
+
+        for filters in [
+            DumpFilters {
+                include_tables: vec!["orders".into(), "users".into()],
+                ..Default::default()
+            },
+            DumpFilters {
+                content: DumpContent::SchemaOnly,
+                ..Default::default()
+            },
+        ] {
+            assert!(get_filter_args(&filters).is_err());
+        }
+    }
+
+    #[test]
+    fn test_05_resolve_command() {
+        use std::{fs, os::unix::fs::PermissionsExt};
+
+        use crate::databases::mongo::utilities::MongoDbUtilities;
+
+        let dir = tempfile::tempdir().expect("Failed to create directory");
+        let mongodump = dir.path().join("mongodump");
+        fs::write(&mongodump, "#!/bin/sh\n").expect("Failed to write tool");
+        fs::set_permissions(&mongodump, fs::Permissions::from_mode(0o755))
+            .expect("Failed to make tool executable");
+
+        // No archive is ever installed for this version
+        let utilities = MongoDbUtilities::new(MongoDbVersion {
+            major: 0,
+            minor: 0,
+            patch: 0,
+        });
+        let search_path = env::join_paths([dir.path()]).unwrap();
+
+        assert_eq!(
+            utilities
+                .find_bin("mongodump", Some(&search_path))
+                .expect("Failed to resolve tool"),
+            Some(mongodump)
+        );
+        assert_eq!(
+            utilities
+                .find_bin("mongorestore", Some(&search_path))
+                .expect("Failed to resolve tool"),
+            None
+        );
+    }
+}
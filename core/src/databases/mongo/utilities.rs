@@ -0,0 +1,76 @@
+use std::{env, ffi::OsStr, path::PathBuf};
+
+use crate::{
+    archives::installer::ArchiveInstaller,
+    common::{find_in_path, get_binaries_base_path},
+    databases::{version::Version, UtilitiesTrait},
+};
+use anyhow::{anyhow, Result};
+use log::debug;
+use tokio::process::Command;
+
+use super::version::MongoDbVersion;
+use async_trait::async_trait;
+
+pub struct MongoDbUtilities {
+    version: MongoDbVersion,
+}
+
+impl MongoDbUtilities {
+    pub fn new(version: MongoDbVersion) -> Self {
+        MongoDbUtilities { version }
+    }
+
+    pub async fn install(&self) -> Result<()> {
+        let archives_installer = ArchiveInstaller::new(Version::MongoDb(self.version.clone()));
+        let path = archives_installer.download_and_install().await?;
+
+        debug!(
+            "Successfully installed MongoDB utilities at {}",
+            path.display()
+        );
+
+        Ok(())
+    }
+
+    /// The installed tool if there is one, otherwise the one found in `search_path`.
+    pub fn find_bin(&self, bin_name: &str, search_path: Option<&OsStr>) -> Result<Option<PathBuf>> {
+        let bin_path = self.get_base_path()?.join(bin_name);
+        if bin_path.exists() {
+            return Ok(Some(bin_path));
+        }
+
+        Ok(search_path.and_then(|search_path| find_in_path(&[bin_name], search_path)))
+    }
+}
+
+#[async_trait]
+impl UtilitiesTrait for MongoDbUtilities {
+    fn get_base_path(&self) -> Result<PathBuf> {
+        let path = get_binaries_base_path(&Version::MongoDb(self.version.clone())).join("bin");
+        Ok(path)
+    }
+
+    /// Falls back to the database tools on `PATH` before trying to install them.
+    async fn get_command(&self, bin_name: &str) -> Result<Command> {
+        if let Some(bin_path) = self.find_bin(bin_name, env::var_os("PATH").as_deref())? {
+            return Ok(Command::new(bin_path));
+        }
+
+        debug!("MongoDB utilities not found, attempting to download and install");
+        if let Err(e) = self.install().await {
+            return Err(anyhow!(
+                "{} not found on PATH and the MongoDB database tools could not be installed: {}",
+                bin_name,
+                e
+            ));
+        }
+
+        let bin_path = self.get_base_path()?.join(bin_name);
+        if !bin_path.exists() {
+            return Err(anyhow!("Binary {} not found after installation", bin_name));
+        }
+
+        Ok(Command::new(bin_path))
+    }
+}
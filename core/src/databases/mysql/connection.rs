@@ -1,12 +1,16 @@
 use std::{
     io::{Read, Write},
     process::Stdio,
+    sync::Arc,
     time::Duration,
 };
 
 use crate::databases::{
+    mariadb::{utilities::MariaDBUtilities, version::MariaDBVersion},
+    ssh::{connect_through_tunnel, tunnel::SshTunnel},
     version::{Version, VersionTrait},
-    DatabaseConfig, DatabaseConnectionTrait, DatabaseMetadata, RestoreOptions, UtilitiesTrait,
+    BackupOptions, DatabaseConfig, DatabaseConnectionTrait, DatabaseMetadata, DumpContent,
+    DumpFilters, RestoreOptions, TableStats, UtilitiesTrait,
 };
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
@@ -21,13 +25,79 @@ use tokio::{
 
 use super::{utilities::MySqlUtilities, version::MySqlVersion};
 
+fn quote_identifier(identifier: &str) -> String {
+    format!("`{}`", identifier.replace('`', "``"))
+}
+
 pub struct MySqlConnection {
+    /// Points at the local end of the SSH tunnel when there is one.
     pub config: DatabaseConfig,
     pub pool: Pool<MySql>,
+    _tunnel: Option<Arc<SshTunnel>>,
+}
+
+/// Arguments of the `mysqldump` runs of a backup. Tables without their rows are dumped by a
+/// second run with `--no-data`, appended to the same script.
+pub(crate) fn get_dump_runs(database: &str, filters: &DumpFilters) -> Result<Vec<Vec<String>>> {
+    if !filters.include_schemas.is_empty() || !filters.exclude_schemas.is_empty() {
+        return Err(anyhow!(
+            "MySQL databases have no schemas to filter, use table filters instead"
+        ));
+    }
+
+    let ignore_table = |table: &String| format!("--ignore-table={}.{}", database, table);
+    let is_selected = |table: &&String| {
+        (filters.include_tables.is_empty() || filters.include_tables.contains(table))
+            && !filters.exclude_tables.contains(table)
+    };
+
+    let mut args: Vec<String> = filters.exclude_tables.iter().map(ignore_table).collect();
+    let mut runs = vec![];
+
+    match filters.content {
+        DumpContent::All => {
+            let without_data: Vec<String> = filters
+                .exclude_table_data
+                .iter()
+                .filter(is_selected)
+                .cloned()
+                .collect();
+
+            if !without_data.is_empty() {
+                args.extend(without_data.iter().map(ignore_table));
+                runs.push(
+                    std::iter::once("--no-data".to_string())
+                        .chain(without_data)
+                        .collect(),
+                );
+            }
+        }
+        DumpContent::SchemaOnly => args.push("--no-data".into()),
+        // Rows of the excluded tables are left out with the rest of their table
+        DumpContent::DataOnly => {
+            args.extend(filters.exclude_table_data.iter().map(ignore_table));
+            args.push("--no-create-info".into());
+        }
+    }
+
+    args.extend(filters.include_tables.iter().cloned());
+    runs.insert(0, args);
+
+    Ok(runs)
 }
 
 impl MySqlConnection {
     pub async fn new(config: DatabaseConfig) -> Result<Self> {
+        let (config, tunnel) = connect_through_tunnel(&config)?;
+
+        Self::with_tunnel(config, tunnel).await
+    }
+
+    /// Connects through an open tunnel, `config` already points at its local end.
+    pub(crate) async fn with_tunnel(
+        config: DatabaseConfig,
+        tunnel: Option<Arc<SshTunnel>>,
+    ) -> Result<Self> {
         let mut connect_options = MySqlConnectOptions::new()
             .host(&config.host)
             .username(&config.username)
@@ -45,20 +115,24 @@ impl MySqlConnection {
             .connect_with(connect_options)
             .await?;
 
-        Ok(Self { config, pool })
+        Ok(Self {
+            config,
+            pool,
+            _tunnel: tunnel,
+        })
     }
 
     async fn get_base_command(&self, bin_name: &str) -> Result<Command> {
         let metadata = self.get_metadata().await?;
 
-        let version = match metadata.version {
-            Version::MySql(version) => version,
+        let mut cmd = match metadata.version {
+            Version::MySql(version) => MySqlUtilities::new(version).get_command(bin_name).await?,
+            Version::MariaDB(version) => {
+                MariaDBUtilities::new(version).get_command(bin_name).await?
+            }
             _ => return Err(anyhow!("Wrong version type")),
         };
 
-        let utilities = MySqlUtilities::new(version);
-        let mut cmd = utilities.get_command(bin_name).await?;
-
         if let Some(password) = &self.config.password {
             cmd.env("MYSQL_PWD", password.as_str());
         }
@@ -77,35 +151,9 @@ impl MySqlConnection {
 
         Ok(cmd)
     }
-}
-
-#[async_trait]
-impl DatabaseConnectionTrait for MySqlConnection {
-    async fn get_metadata(&self) -> Result<DatabaseMetadata> {
-        let version_string: (String,) = sqlx::query_as("SELECT version()")
-            .fetch_one(&self.pool)
-            .await
-            .map_err(|e| anyhow!("Failed to get database version: {}", e))?;
-
-        let version = match MySqlVersion::parse_string_version(version_string.0.as_str()) {
-            Some(version) => version,
-            None => return Err(anyhow!("Failed to parse MySQL version string")),
-        };
-
-        Ok(DatabaseMetadata {
-            version: Version::MySql(version),
-        })
-    }
 
-    async fn test(&self) -> Result<bool> {
-        sqlx::query("SELECT 1")
-            .execute(&self.pool)
-            .await
-            .map(|_| true)
-            .map_err(|e| anyhow!("Connection test failed: {}", e))
-    }
-
-    async fn backup(&self, writer: &mut (dyn Write + Send + Unpin)) -> Result<()> {
+    /// Streams one `mysqldump` run, `args` come after the database name so they may list tables.
+    async fn dump(&self, args: &[String], writer: &mut (dyn Write + Send + Unpin)) -> Result<()> {
         let mut cmd = self.get_command("mysqldump").await?;
 
         cmd.arg("--opt")
@@ -115,7 +163,8 @@ impl DatabaseConnectionTrait for MySqlConnection {
             .arg("--add-drop-database")
             .arg("--add-drop-table")
             .arg("--no-tablespaces")
-            .arg("--skip-triggers");
+            .arg("--skip-triggers")
+            .args(args);
 
         let mut child = cmd
             .stdout(Stdio::piped())
@@ -166,6 +215,61 @@ impl DatabaseConnectionTrait for MySqlConnection {
 
         Ok(())
     }
+}
+
+#[async_trait]
+impl DatabaseConnectionTrait for MySqlConnection {
+    async fn get_metadata(&self) -> Result<DatabaseMetadata> {
+        let version_string: (String,) = sqlx::query_as("SELECT version()")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to get database version: {}", e))?;
+
+        if MariaDBVersion::is_mariadb(&version_string.0) {
+            let version = match MariaDBVersion::parse_string_version(version_string.0.as_str()) {
+                Some(version) => version,
+                None => return Err(anyhow!("Failed to parse MariaDB version string")),
+            };
+
+            return Ok(DatabaseMetadata {
+                version: Version::MariaDB(version),
+            });
+        }
+
+        let version = match MySqlVersion::parse_string_version(version_string.0.as_str()) {
+            Some(version) => version,
+            None => return Err(anyhow!("Failed to parse MySQL version string")),
+        };
+
+        Ok(DatabaseMetadata {
+            version: Version::MySql(version),
+        })
+    }
+
+    async fn test(&self) -> Result<bool> {
+        sqlx::query("SELECT 1")
+            .execute(&self.pool)
+            .await
+            .map(|_| true)
+            .map_err(|e| anyhow!("Connection test failed: {}", e))
+    }
+
+    async fn backup(&self, writer: &mut (dyn Write + Send + Unpin)) -> Result<()> {
+        self.backup_with_options(writer, BackupOptions::default())
+            .await
+    }
+
+    async fn backup_with_options(
+        &self,
+        writer: &mut (dyn Write + Send + Unpin),
+        options: BackupOptions,
+    ) -> Result<()> {
+        for args in get_dump_runs(&self.config.database, &options.filters)? {
+            self.dump(&args, writer).await?;
+        }
+
+        Ok(())
+    }
 
     async fn restore_with_options(
         &self,
@@ -202,7 +306,11 @@ impl DatabaseConnectionTrait for MySqlConnection {
         }
 
         let mut cmd = self.get_command("mysql").await?;
-        let mut child = cmd.stdin(Stdio::piped()).stdout(Stdio::piped()).spawn()?;
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
 
         let mut stdin = child
             .stdin
@@ -218,7 +326,10 @@ impl DatabaseConnectionTrait for MySqlConnection {
                     stdin.write_all(&buffer[..n]).await?;
                 }
                 Err(e) => {
-                    return Err(anyhow!("Failed to read from pg_dump: {}", e));
+                    // Killed while its input is still open, at the end of it mysql would run
+                    // the part of the backup it got
+                    let _ = child.kill().await;
+                    return Err(anyhow!("Failed to read backup data: {}", e));
                 }
             }
         }
@@ -245,8 +356,72 @@ impl DatabaseConnectionTrait for MySqlConnection {
             reader,
             RestoreOptions {
                 drop_database_first: true,
+                jobs: None,
             },
         )
         .await
     }
+
+    async fn get_table_stats(&self) -> Result<Vec<TableStats>> {
+        let tables: Vec<(String,)> = sqlx::query_as(
+            "SELECT CAST(table_name AS CHAR) FROM information_schema.tables
+            WHERE table_schema = DATABASE() AND table_type = 'BASE TABLE'
+            ORDER BY table_name",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to list tables: {}", e))?;
+
+        let mut stats = vec![];
+
+        for (table,) in tables {
+            let (rows,): (i64,) = sqlx::query_as(&format!(
+                "SELECT COUNT(*) FROM {}",
+                quote_identifier(&table)
+            ))
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to count rows of {}: {}", table, e))?;
+
+            stats.push(TableStats {
+                name: table,
+                rows: rows as u64,
+            });
+        }
+
+        Ok(stats)
+    }
+
+    async fn database_exists(&self, name: &str) -> Result<bool> {
+        let (count,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM information_schema.schemata WHERE schema_name = ?",
+        )
+        .bind(name)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to look up database {}: {}", name, e))?;
+
+        Ok(count > 0)
+    }
+
+    async fn create_database(&self, name: &str) -> Result<()> {
+        sqlx::query(&format!("CREATE DATABASE {}", quote_identifier(name)))
+            .execute(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to create database {}: {}", name, e))?;
+
+        Ok(())
+    }
+
+    async fn drop_database(&self, name: &str) -> Result<()> {
+        sqlx::query(&format!(
+            "DROP DATABASE IF EXISTS {}",
+            quote_identifier(name)
+        ))
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to drop database {}: {}", name, e))?;
+
+        Ok(())
+    }
 }
@@ -3,8 +3,9 @@ mod mysql_connection_tests {
     use std::{env, thread::sleep, time::Duration};
 
     use crate::databases::{
-        mysql::connection::MySqlConnection, version::Version, ConnectionType, DatabaseConfig,
-        DatabaseConnectionTrait,
+        mysql::connection::{get_dump_runs, MySqlConnection},
+        version::Version,
+        ConnectionType, DatabaseConfig, DatabaseConnectionTrait, DumpContent, DumpFilters,
     };
     use anyhow::Result;
     use dotenv::dotenv;
@@ -21,6 +22,7 @@ mod mysql_connection_tests {
             connection_type: ConnectionType::MySql,
             host: env::var("MYSQL_HOST").unwrap_or_default(),
             password: Some(password),
+            ssh: None,
             username: env::var("MYSQL_USERNAME").unwrap_or_default(),
             database: env::var("MYSQL_NAME").unwrap_or_default(),
             port,
@@ -190,4 +192,41 @@ mod mysql_connection_tests {
         let test3_exists = restored_rows.iter().any(|(name, _)| name == "test3");
         assert!(test3_exists, "test3 should be restored");
     }
+
+    #[test]
+    fn test_04_dump_runs() {
+        assert_eq!(
+            get_dump_runs("shop", &DumpFilters::default()).unwrap(),
+            vec![Vec::<String>::new()]
+        );
+
+        let filters = DumpFilters {
+            include_tables: vec!["orders".into(), "sessions".into()],
+            exclude_table_data: vec!["sessions".into(), "logs".into()],
+            ..Default::default()
+        };
+        assert_eq!(
+            get_dump_runs("shop", &filters).unwrap(),
+            vec![
+                vec!["--ignore-table=shop.sessions", "orders", "sessions"],
+                vec!["--no-data", "sessions"],
+            ]
+        );
+
+        let filters = DumpFilters {
+            exclude_tables: vec!["logs".into()],
+            content: DumpContent::DataOnly,
+            ..Default::default()
+        };
+        assert_eq!(
+            get_dump_runs("shop", &filters).unwrap(),
+            vec![vec!["--ignore-table=shop.logs", "--no-create-info"]]
+        );
+
+        let filters = DumpFilters {
+            include_schemas: vec!["public".into()],
+            ..Default::default()
+        };
+        assert!(get_dump_runs("shop", &filters).is_err());
+    }
 }
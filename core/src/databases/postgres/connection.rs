@@ -1,12 +1,15 @@
 use std::{
-    io::{Read, Write},
-    process::Stdio,
+    io::{self, Cursor, Read, Write},
+    process::{Output, Stdio},
+    sync::Arc,
     time::Duration,
 };
 
 use crate::databases::{
+    ssh::{connect_through_tunnel, tunnel::SshTunnel},
     version::{Version, VersionTrait},
-    DatabaseConfig, DatabaseConnectionTrait, DatabaseMetadata, RestoreOptions, UtilitiesTrait,
+    BackupOptions, DatabaseConfig, DatabaseConnectionTrait, DatabaseMetadata, DumpContent,
+    DumpFilters, DumpFormat, RestoreOptions, TableStats, UtilitiesTrait,
 };
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
@@ -14,6 +17,7 @@ use sqlx::{
     postgres::{PgConnectOptions, PgPoolOptions},
     Pool, Postgres,
 };
+use tempfile::{tempdir, NamedTempFile};
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
     process::Command,
@@ -22,22 +26,88 @@ use tokio::{
 use super::{utilities::PostgreSqlUtilities, version::PostgreSQLVersion};
 
 pub struct PostgreSqlConnection {
+    /// Points at the local end of the SSH tunnel when there is one.
     pub config: DatabaseConfig,
     pub pool: Pool<Postgres>,
+    _tunnel: Option<Arc<SshTunnel>>,
+}
+
+fn get_connect_options(config: &DatabaseConfig, database: &str) -> PgConnectOptions {
+    let connect_options = PgConnectOptions::new()
+        .host(&config.host)
+        .username(&config.username)
+        .database(database)
+        .port(config.port);
+
+    match &config.password {
+        Some(password) => connect_options.password(password),
+        None => connect_options,
+    }
+}
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+fn check_restore_output(bin_name: &str, output: Output) -> Result<()> {
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let exit_code = output.status.code().unwrap_or(-1);
+
+        return Err(anyhow!(
+            "{} restore failed with exit code {}.\nStderr: {}\nStdout: {}",
+            bin_name,
+            exit_code,
+            stderr.trim(),
+            stdout.trim()
+        ));
+    }
+
+    Ok(())
+}
+
+/// `pg_dump` selection arguments, patterns keep their `pg_dump` wildcard meaning.
+pub(crate) fn get_filter_args(filters: &DumpFilters) -> Vec<String> {
+    let patterns = [
+        ("--table", &filters.include_tables),
+        ("--exclude-table", &filters.exclude_tables),
+        ("--schema", &filters.include_schemas),
+        ("--exclude-schema", &filters.exclude_schemas),
+        ("--exclude-table-data", &filters.exclude_table_data),
+    ];
+
+    let mut args: Vec<String> = patterns
+        .iter()
+        .flat_map(|(option, patterns)| {
+            patterns
+                .iter()
+                .map(move |pattern| format!("{}={}", option, pattern))
+        })
+        .collect();
+
+    match filters.content {
+        DumpContent::All => {}
+        DumpContent::SchemaOnly => args.push("--schema-only".into()),
+        DumpContent::DataOnly => args.push("--data-only".into()),
+    }
+
+    args
 }
 
 impl PostgreSqlConnection {
     pub async fn new(config: DatabaseConfig) -> Result<Self> {
-        let mut connect_options = PgConnectOptions::new()
-            .host(&config.host)
-            .username(&config.username)
-            .database("postgres")
-            .port(config.port);
-
-        connect_options = match &config.password {
-            Some(password) => connect_options.password(&password),
-            None => connect_options,
-        };
+        let (config, tunnel) = connect_through_tunnel(&config)?;
+
+        Self::with_tunnel(config, tunnel).await
+    }
+
+    /// Connects through an open tunnel, `config` already points at its local end.
+    pub(crate) async fn with_tunnel(
+        config: DatabaseConfig,
+        tunnel: Option<Arc<SshTunnel>>,
+    ) -> Result<Self> {
+        let connect_options = get_connect_options(&config, "postgres");
 
         let pool = PgPoolOptions::new()
             .max_connections(5)
@@ -45,7 +115,11 @@ impl PostgreSqlConnection {
             .connect_with(connect_options)
             .await?;
 
-        Ok(Self { config, pool })
+        Ok(Self {
+            config,
+            pool,
+            _tunnel: tunnel,
+        })
     }
 
     async fn get_base_command(&self, bin_name: &str) -> Result<Command> {
@@ -79,41 +153,11 @@ impl PostgreSqlConnection {
 
         Ok(cmd)
     }
-}
-
-#[async_trait]
-impl DatabaseConnectionTrait for PostgreSqlConnection {
-    async fn get_metadata(&self) -> Result<DatabaseMetadata> {
-        let version_string: (String,) = sqlx::query_as("SELECT version()")
-            .fetch_one(&self.pool)
-            .await
-            .map_err(|e| anyhow!("Failed to get database version: {}", e))?;
 
-        let version = match PostgreSQLVersion::parse_string_version(version_string.0.as_str()) {
-            Some(version) => version,
-            None => return Err(anyhow!("Fauiled to parse PostgreSQL version string")),
-        };
-
-        Ok(DatabaseMetadata {
-            version: Version::PostgreSQL(version),
-        })
-    }
-
-    async fn test(&self) -> Result<bool> {
-        sqlx::query("SELECT 1")
-            .execute(&self.pool)
-            .await
-            .map(|_| true)
-            .map_err(|e| anyhow!("Connection test failed: {}", e))
-    }
-
-    async fn backup(&self, writer: &mut (dyn Write + Send + Unpin)) -> Result<()> {
+    async fn get_dump_command(&self) -> Result<Command> {
         let mut cmd = self.get_command("pg_dump").await?;
 
-        cmd.arg("--format=plain")
-            .arg("--encoding=UTF8")
-            .arg("--clean")
-            .arg("--if-exists")
+        cmd.arg("--encoding=UTF8")
             .arg("--no-owner")
             .arg("--blobs")
             .arg("--exclude-schema=information_schema")
@@ -122,6 +166,25 @@ impl DatabaseConnectionTrait for PostgreSqlConnection {
             .arg("--exclude-schema=pg_temp*")
             .arg("--exclude-schema=pg_toast_temp*");
 
+        Ok(cmd)
+    }
+
+    async fn get_restore_command(&self, jobs: u16) -> Result<Command> {
+        let mut cmd = self.get_command("pg_restore").await?;
+
+        cmd.arg("--clean")
+            .arg("--if-exists")
+            .arg("--no-owner")
+            .arg(format!("--jobs={}", jobs));
+
+        Ok(cmd)
+    }
+
+    async fn stream_dump(
+        &self,
+        mut cmd: Command,
+        writer: &mut (dyn Write + Send + Unpin),
+    ) -> Result<()> {
         let mut child = cmd
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
@@ -172,11 +235,206 @@ impl DatabaseConnectionTrait for PostgreSqlConnection {
         Ok(())
     }
 
+    /// `pg_dump` writes directory archives to disk, the directory is then streamed as a tar
+    /// archive.
+    async fn dump_directory(
+        &self,
+        mut cmd: Command,
+        writer: &mut (dyn Write + Send + Unpin),
+        jobs: u16,
+    ) -> Result<()> {
+        let dump_directory = tempdir()?;
+        let dump_path = dump_directory.path().join("dump");
+
+        cmd.arg("--format=directory")
+            .arg("--compress=0")
+            .arg(format!("--jobs={}", jobs))
+            .arg(format!("--file={}", dump_path.display()));
+
+        let output = cmd
+            .output()
+            .await
+            .map_err(|e| anyhow!("Failed to start pg_dump: {}", e))?;
+
+        if !output.status.success() {
+            return Err(anyhow!(
+                "pg_dump failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+
+        let mut archive = tar::Builder::new(writer);
+        archive
+            .append_dir_all(".", &dump_path)
+            .and_then(|_| archive.finish())
+            .map_err(|e| anyhow!("Failed to write backup data: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Feeds the backup to a client reading it from its standard input.
+    async fn pipe_restore(
+        &self,
+        mut cmd: Command,
+        bin_name: &str,
+        reader: &mut (dyn Read + Send + Unpin),
+    ) -> Result<()> {
+        let mut child = cmd
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| anyhow!("Failed to start {}: {}", bin_name, e))?;
+
+        let mut stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow!("Failed to capture {} stdin", bin_name))?;
+
+        let mut buffer = [0u8; 16384];
+
+        loop {
+            match reader.read(&mut buffer) {
+                Ok(0) => break, // EOF
+                Ok(n) => {
+                    stdin.write_all(&buffer[..n]).await?;
+                }
+                Err(e) => {
+                    // Killed while its input is still open, at the end of it the client would
+                    // run the part of the backup it got
+                    let _ = child.kill().await;
+                    return Err(anyhow!("Failed to read backup data: {}", e));
+                }
+            }
+        }
+
+        drop(stdin);
+
+        let output = child
+            .wait_with_output()
+            .await
+            .map_err(|e| anyhow!("{} process failed: {}", bin_name, e))?;
+
+        check_restore_output(bin_name, output)
+    }
+
+    /// `pg_restore` only restores in parallel from a file, the archive is written to a
+    /// temporary one first.
+    async fn restore_custom(
+        &self,
+        reader: &mut (dyn Read + Send + Unpin),
+        jobs: u16,
+    ) -> Result<()> {
+        let mut cmd = self.get_restore_command(jobs).await?;
+
+        if jobs <= 1 {
+            return self.pipe_restore(cmd, "pg_restore", reader).await;
+        }
+
+        let mut archive = NamedTempFile::new()?;
+        io::copy(reader, &mut archive).map_err(|e| anyhow!("Failed to read backup data: {}", e))?;
+        archive.flush()?;
+
+        cmd.arg("--format=custom").arg(archive.path());
+
+        let output = cmd
+            .output()
+            .await
+            .map_err(|e| anyhow!("Failed to start pg_restore: {}", e))?;
+
+        check_restore_output("pg_restore", output)
+    }
+
+    async fn restore_directory(
+        &self,
+        reader: &mut (dyn Read + Send + Unpin),
+        jobs: u16,
+    ) -> Result<()> {
+        let dump_directory = tempdir()?;
+
+        tar::Archive::new(reader)
+            .unpack(dump_directory.path())
+            .map_err(|e| anyhow!("Failed to extract directory archive: {}", e))?;
+
+        let mut cmd = self.get_restore_command(jobs).await?;
+        cmd.arg("--format=directory").arg(dump_directory.path());
+
+        let output = cmd
+            .output()
+            .await
+            .map_err(|e| anyhow!("Failed to start pg_restore: {}", e))?;
+
+        check_restore_output("pg_restore", output)
+    }
+}
+
+#[async_trait]
+impl DatabaseConnectionTrait for PostgreSqlConnection {
+    async fn get_metadata(&self) -> Result<DatabaseMetadata> {
+        let version_string: (String,) = sqlx::query_as("SELECT version()")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| anyhow!("Failed to get database version: {}", e))?;
+
+        let version = match PostgreSQLVersion::parse_string_version(version_string.0.as_str()) {
+            Some(version) => version,
+            None => return Err(anyhow!("Fauiled to parse PostgreSQL version string")),
+        };
+
+        Ok(DatabaseMetadata {
+            version: Version::PostgreSQL(version),
+        })
+    }
+
+    async fn test(&self) -> Result<bool> {
+        sqlx::query("SELECT 1")
+            .execute(&self.pool)
+            .await
+            .map(|_| true)
+            .map_err(|e| anyhow!("Connection test failed: {}", e))
+    }
+
+    async fn backup(&self, writer: &mut (dyn Write + Send + Unpin)) -> Result<()> {
+        self.backup_with_options(writer, BackupOptions::default())
+            .await
+    }
+
+    /// Archives aren't compressed by `pg_dump`, the backup is compressed afterwards.
+    async fn backup_with_options(
+        &self,
+        writer: &mut (dyn Write + Send + Unpin),
+        options: BackupOptions,
+    ) -> Result<()> {
+        let mut cmd = self.get_dump_command().await?;
+        cmd.args(get_filter_args(&options.filters));
+
+        match options.format {
+            DumpFormat::Plain => {
+                cmd.arg("--format=plain");
+                // Data only scripts have no objects to drop, pg_dump refuses --clean with them
+                if options.filters.content != DumpContent::DataOnly {
+                    cmd.arg("--clean").arg("--if-exists");
+                }
+                self.stream_dump(cmd, writer).await
+            }
+            DumpFormat::Custom => {
+                cmd.arg("--format=custom").arg("--compress=0");
+                self.stream_dump(cmd, writer).await
+            }
+            DumpFormat::Directory => {
+                self.dump_directory(cmd, writer, options.jobs.unwrap_or(1))
+                    .await
+            }
+        }
+    }
+
     async fn restore(&self, reader: &mut (dyn Read + Send + Unpin)) -> Result<()> {
         self.restore_with_options(
             reader,
             RestoreOptions {
                 drop_database_first: true,
+                jobs: None,
             },
         )
         .await
@@ -286,61 +544,104 @@ impl DatabaseConnectionTrait for PostgreSqlConnection {
             }
         }
 
-        let mut cmd = self.get_base_command("psql").await?;
+        // The format is told by the magic bytes, backups made before archives were supported
+        // have no manifest entry for it
+        let mut header = vec![];
+        Read::take(&mut *reader, 512)
+            .read_to_end(&mut header)
+            .map_err(|e| anyhow!("Failed to read backup data: {}", e))?;
+        let format = DumpFormat::sniff(&header);
+        let mut reader = Read::chain(Cursor::new(header), reader);
+        let jobs = options.jobs.unwrap_or(1);
+
+        match format {
+            DumpFormat::Plain => {
+                let cmd = self.get_command("psql").await?;
+                self.pipe_restore(cmd, "psql", &mut reader).await
+            }
+            DumpFormat::Custom => self.restore_custom(&mut reader, jobs).await,
+            DumpFormat::Directory => self.restore_directory(&mut reader, jobs).await,
+        }
+    }
 
-        cmd.arg("-h")
-            .arg(&self.config.host)
-            .arg("-p")
-            .arg(self.config.port.to_string())
-            .arg("-U")
-            .arg(&self.config.username)
-            .arg("-d")
-            .arg(&self.config.database);
+    async fn get_table_stats(&self) -> Result<Vec<TableStats>> {
+        // The shared pool is connected to the maintenance database
+        let pool = PgPoolOptions::new()
+            .max_connections(1)
+            .acquire_timeout(Duration::from_secs(30))
+            .connect_with(get_connect_options(&self.config, &self.config.database))
+            .await?;
 
-        let mut child = cmd
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()?;
+        let tables: Vec<(String, String)> = sqlx::query_as(
+            "SELECT table_schema::text, table_name::text FROM information_schema.tables
+            WHERE table_type = 'BASE TABLE'
+            AND table_schema NOT IN ('pg_catalog', 'information_schema')
+            ORDER BY table_schema, table_name",
+        )
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| anyhow!("Failed to list tables: {}", e))?;
 
-        let mut stdin = child
-            .stdin
-            .take()
-            .ok_or_else(|| anyhow!("Failed to capture psql stdin"))?;
+        let mut stats = vec![];
 
-        let mut buffer = [0u8; 16384];
+        for (schema, table) in tables {
+            let (rows,): (i64,) = sqlx::query_as(&format!(
+                "SELECT COUNT(*) FROM {}.{}",
+                quote_identifier(&schema),
+                quote_identifier(&table)
+            ))
+            .fetch_one(&pool)
+            .await
+            .map_err(|e| anyhow!("Failed to count rows of {}.{}: {}", schema, table, e))?;
 
-        loop {
-            match reader.read(&mut buffer) {
-                Ok(0) => break, // EOF
-                Ok(n) => {
-                    stdin.write_all(&buffer[..n]).await?;
-                }
-                Err(e) => {
-                    return Err(anyhow!("Failed to read backup data: {}", e));
-                }
-            }
+            stats.push(TableStats {
+                name: format!("{}.{}", schema, table),
+                rows: rows as u64,
+            });
         }
 
-        drop(stdin);
+        pool.close().await;
 
-        let output = child
-            .wait_with_output()
+        Ok(stats)
+    }
+
+    async fn database_exists(&self, name: &str) -> Result<bool> {
+        let (exists,): (bool,) =
+            sqlx::query_as("SELECT EXISTS (SELECT 1 FROM pg_database WHERE datname = $1)")
+                .bind(name)
+                .fetch_one(&self.pool)
+                .await
+                .map_err(|e| anyhow!("Failed to look up database {}: {}", name, e))?;
+
+        Ok(exists)
+    }
+
+    async fn create_database(&self, name: &str) -> Result<()> {
+        sqlx::query(&format!("CREATE DATABASE {}", quote_identifier(name)))
+            .execute(&self.pool)
             .await
-            .map_err(|e| anyhow!("psql process failed: {}", e))?;
+            .map_err(|e| anyhow!("Failed to create database {}: {}", name, e))?;
 
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            let exit_code = output.status.code().unwrap_or(-1);
+        Ok(())
+    }
 
-            return Err(anyhow!(
-                "psql restore failed with exit code {}.\nStderr: {}\nStdout: {}",
-                exit_code,
-                stderr.trim(),
-                stdout.trim()
-            ));
-        }
+    async fn drop_database(&self, name: &str) -> Result<()> {
+        sqlx::query(
+            "SELECT pg_terminate_backend(pid) FROM pg_stat_activity
+            WHERE datname = $1 AND pid <> pg_backend_pid()",
+        )
+        .bind(name)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to terminate connections to {}: {}", name, e))?;
+
+        sqlx::query(&format!(
+            "DROP DATABASE IF EXISTS {}",
+            quote_identifier(name)
+        ))
+        .execute(&self.pool)
+        .await
+        .map_err(|e| anyhow!("Failed to drop database {}: {}", name, e))?;
 
         Ok(())
     }
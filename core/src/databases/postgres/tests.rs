@@ -1,7 +1,10 @@
 #[cfg(test)]
 mod postgresql_connection_test {
+    use crate::databases::postgres::connection::get_filter_args;
     use crate::databases::version::Version;
-    use crate::databases::{DatabaseConnectionTrait, RestoreOptions};
+    use crate::databases::{
+        DatabaseConnectionTrait, DumpContent, DumpFilters, DumpFormat, RestoreOptions,
+    };
     use crate::test_utils::test_utils::{
         get_postgresql_connection, get_postgresql_pool, initialize_test,
     };
@@ -150,6 +153,7 @@ mod postgresql_connection_test {
                 &mut backup_cursor,
                 RestoreOptions {
                     drop_database_first: false,
+                    jobs: None,
                 },
             )
             .await
@@ -181,4 +185,118 @@ mod postgresql_connection_test {
         let test3_exists = restored_rows.iter().any(|(name, _)| name == "test3");
         assert!(test3_exists, "test3 should be restored");
     }
+
+    #[test]
+    fn test_05_sniff_dump_format() {
+        assert_eq!(DumpFormat::sniff(b"PGDMP\x01\x0e"), DumpFormat::Custom);
+        assert_eq!(
+            DumpFormat::sniff(b"--\n-- PostgreSQL database dump"),
+            DumpFormat::Plain
+        );
+        assert_eq!(DumpFormat::sniff(b""), DumpFormat::Plain);
+
+        let mut tar_header = vec![0u8; 512];
+        tar_header[257..263].copy_from_slice(b"ustar ");
+        assert_eq!(DumpFormat::sniff(&tar_header), DumpFormat::Directory);
+
+        assert_eq!(
+            "Directory".parse::<DumpFormat>().unwrap(),
+            DumpFormat::Directory
+        );
+        assert!("tar".parse::<DumpFormat>().is_err());
+    }
+
+    #[test]
+    fn test_06_filter_args() {
+        assert!(get_filter_args(&DumpFilters::default()).is_empty());
+
+        let filters = DumpFilters {
+            include_tables: vec!["public.orders".into(), "public.order_*".into()],
+            exclude_schemas: vec!["audit".into()],
+            exclude_table_data: vec!["public.sessions".into()],
+            content: DumpContent::SchemaOnly,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            get_filter_args(&filters),
+            vec![
+                "--table=public.orders",
+                "--table=public.order_*",
+                "--exclude-schema=audit",
+                "--exclude-table-data=public.sessions",
+                "--schema-only",
+            ]
+        );
+    }
+
+    /// Yields a dump, then fails as if the storage connection dropped.
+    struct FailingReader {
+        dump: std::io::Cursor<Vec<u8>>,
+    }
+
+    impl std::io::Read for FailingReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match self.dump.read(buf)? {
+                0 => {
+                    // Gives psql the time to run what it got
+                    sleep(Duration::from_millis(500));
+                    Err(std::io::Error::other("Connection reset"))
+                }
+                n => Ok(n),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_07_restore_failing_halfway() {
+        initialize_test();
+        let test_table_name = format!("test_truncated_{}", chrono::Utc::now().timestamp_millis());
+        let db_pool = get_postgresql_pool().await.expect("Failed to get db_pool");
+        let connection = get_postgresql_connection(false)
+            .await
+            .expect("Failed to get connection");
+
+        // Long enough for the format to be sniffed before the read fails
+        let dump = format!(
+            "-- {}\nCREATE TABLE {} (id INTEGER);\nCOPY {} (id) FROM stdin;\n1\n2\n3\n",
+            "-".repeat(600),
+            test_table_name,
+            test_table_name
+        );
+        let mut reader = FailingReader {
+            dump: std::io::Cursor::new(dump.into_bytes()),
+        };
+
+        let result = connection
+            .restore_with_options(
+                &mut reader,
+                RestoreOptions {
+                    drop_database_first: false,
+                    jobs: None,
+                },
+            )
+            .await;
+        assert!(result.is_err(), "The failed read should fail the restore");
+
+        let table: Option<String> =
+            sqlx::query_scalar(format!("SELECT to_regclass('{}')::text", test_table_name).as_str())
+                .fetch_one(&db_pool)
+                .await
+                .expect("Failed to look up test table");
+        if table.is_some() {
+            let (rows,): (i64,) =
+                sqlx::query_as(format!("SELECT count(*) FROM {}", test_table_name).as_str())
+                    .fetch_one(&db_pool)
+                    .await
+                    .expect("Failed to count rows");
+
+            sqlx::query(format!("DROP TABLE {}", test_table_name).as_str())
+                .execute(&db_pool)
+                .await
+                .expect("Failed to drop test table");
+
+            assert_eq!(rows, 0, "The rows of the unfinished copy shouldn't be kept");
+        }
+    }
 }
@@ -0,0 +1,343 @@
+use std::{
+    io::{BufReader, Read, Write},
+    sync::Arc,
+};
+
+use crate::databases::{
+    ssh::{connect_through_tunnel, tunnel::SshTunnel},
+    version::{Version, VersionTrait},
+    DatabaseConfig, DatabaseConnectionTrait, DatabaseMetadata, RestoreOptions, TableStats,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+
+use super::{
+    rdb::{RdbItem, RdbReader},
+    resp::{Reply, RespConnection},
+    version::RedisVersion,
+};
+
+/// User Redis authenticates with the password only form of `AUTH`, the only one before Redis 6.
+const DEFAULT_USER: &str = "default";
+
+/// Commands sent before their replies are read during a restore.
+const PIPELINE_SIZE: usize = 1000;
+
+/// Whole Redis instance, every logical database is part of the snapshot. `database` only
+/// names the backups.
+pub struct RedisConnection {
+    /// Points at the local end of the SSH tunnel when there is one.
+    pub config: DatabaseConfig,
+    _tunnel: Option<Arc<SshTunnel>>,
+}
+
+fn get_info_field(info: &str, field: &str) -> Option<String> {
+    info.lines()
+        .find_map(|line| line.strip_prefix(field)?.strip_prefix(':'))
+        .map(|value| value.trim().to_string())
+}
+
+/// Key counts from the keyspace section of `INFO`, e.g. `db0:keys=3,expires=0,avg_ttl=0`.
+pub(crate) fn parse_keyspace(info: &str) -> Vec<TableStats> {
+    info.lines()
+        .filter_map(|line| {
+            let (database, fields) = line.split_once(':')?;
+            if !database.starts_with("db") {
+                return None;
+            }
+
+            let keys = fields
+                .split(',')
+                .find_map(|field| field.strip_prefix("keys="))?
+                .parse()
+                .ok()?;
+
+            Some(TableStats {
+                name: database.to_string(),
+                rows: keys,
+            })
+        })
+        .collect()
+}
+
+/// Reads the pipelined replies, the first error aborts the restore.
+async fn read_replies(connection: &mut RespConnection, count: usize) -> Result<()> {
+    connection.flush().await?;
+
+    for _ in 0..count {
+        connection
+            .read_reply()
+            .await
+            .map_err(|e| anyhow!("Failed to restore Redis snapshot: {}", e))?;
+    }
+
+    Ok(())
+}
+
+impl RedisConnection {
+    pub async fn new(config: DatabaseConfig) -> Result<Self> {
+        let (config, tunnel) = connect_through_tunnel(&config)?;
+
+        Self::with_tunnel(config, tunnel).await
+    }
+
+    /// Connects through an open tunnel, `config` already points at its local end.
+    pub(crate) async fn with_tunnel(
+        config: DatabaseConfig,
+        tunnel: Option<Arc<SshTunnel>>,
+    ) -> Result<Self> {
+        let connection = Self {
+            config,
+            _tunnel: tunnel,
+        };
+        connection.connect().await?;
+
+        Ok(connection)
+    }
+
+    /// Connections are opened per operation, a backup leaves its connection in replication mode.
+    async fn connect(&self) -> Result<RespConnection> {
+        let mut connection = RespConnection::connect(&self.config.host, self.config.port).await?;
+
+        if let Some(password) = self.config.password.as_ref().filter(|p| !p.is_empty()) {
+            let authenticated = match self.config.username.as_str() {
+                "" | DEFAULT_USER => connection.command(&[b"AUTH", password.as_bytes()]).await,
+                username => {
+                    connection
+                        .command(&[b"AUTH", username.as_bytes(), password.as_bytes()])
+                        .await
+                }
+            };
+            authenticated.map_err(|e| anyhow!("Failed to authenticate to Redis: {}", e))?;
+        }
+
+        Ok(connection)
+    }
+
+    async fn get_info(&self, section: &str) -> Result<String> {
+        let mut connection = self.connect().await?;
+
+        match connection.command(&[b"INFO", section.as_bytes()]).await? {
+            Reply::Bulk(Some(info)) => Ok(String::from_utf8_lossy(&info).to_string()),
+            reply => Err(anyhow!("Unexpected INFO reply: {:?}", reply)),
+        }
+    }
+}
+
+#[async_trait]
+impl DatabaseConnectionTrait for RedisConnection {
+    async fn get_metadata(&self) -> Result<DatabaseMetadata> {
+        let info = self
+            .get_info("server")
+            .await
+            .map_err(|e| anyhow!("Failed to get database version: {}", e))?;
+
+        let version = match get_info_field(&info, "redis_version")
+            .and_then(|version| RedisVersion::parse_string_version(&version))
+        {
+            Some(version) => version,
+            None => return Err(anyhow!("Failed to parse Redis version string")),
+        };
+
+        Ok(DatabaseMetadata {
+            version: Version::Redis(version),
+        })
+    }
+
+    async fn test(&self) -> Result<bool> {
+        let mut connection = self.connect().await?;
+
+        match connection.command(&[b"PING"]).await {
+            Ok(reply) => Ok(reply == Reply::Status("PONG".into())),
+            Err(e) => Err(anyhow!("Connection test failed: {}", e)),
+        }
+    }
+
+    /// Asks for a full resynchronization like a replica would, the server answers with a
+    /// point in time RDB snapshot of the whole instance.
+    async fn backup(&self, writer: &mut (dyn Write + Send + Unpin)) -> Result<()> {
+        let mut connection = self.connect().await?;
+
+        // Since Redis 7 the server doesn't keep streaming writes after the snapshot
+        let _ = connection.command(&[b"REPLCONF", b"rdb-only", b"1"]).await;
+
+        connection.send(&[b"SYNC"]).await?;
+        connection.flush().await?;
+
+        // Newlines keep the connection alive while the server writes the snapshot
+        let mut kind = connection.read_byte().await?;
+        while kind == b'\n' {
+            kind = connection.read_byte().await?;
+        }
+
+        let header = connection.read_line().await?;
+        match kind {
+            b'$' => {}
+            b'-' => {
+                return Err(anyhow!(
+                    "Redis refused to send a snapshot: {}",
+                    String::from_utf8_lossy(&header)
+                ))
+            }
+            _ => return Err(anyhow!("Unexpected reply to SYNC from Redis")),
+        }
+
+        let mut buffer = vec![0u8; 16384];
+
+        // Diskless replication streams a snapshot of unknown size, terminated by a random mark
+        if let Some(mark) = header.strip_prefix(b"EOF:") {
+            let mut pending: Vec<u8> = vec![];
+
+            loop {
+                let n = connection.read(&mut buffer).await?;
+                if n == 0 {
+                    return Err(anyhow!("Redis closed the connection during the backup"));
+                }
+                pending.extend_from_slice(&buffer[..n]);
+
+                if let Some(end) = pending.windows(mark.len()).position(|w| w == mark) {
+                    writer
+                        .write_all(&pending[..end])
+                        .map_err(|e| anyhow!("Failed to write backup data: {}", e))?;
+                    break;
+                }
+
+                // The mark may be split across reads
+                if pending.len() > mark.len() {
+                    let complete = pending.len() - mark.len();
+                    writer
+                        .write_all(&pending[..complete])
+                        .map_err(|e| anyhow!("Failed to write backup data: {}", e))?;
+                    pending.drain(..complete);
+                }
+            }
+
+            return Ok(());
+        }
+
+        let mut remaining: usize = String::from_utf8_lossy(&header)
+            .parse()
+            .map_err(|_| anyhow!("Invalid snapshot size from Redis"))?;
+
+        while remaining > 0 {
+            let size = remaining.min(buffer.len());
+            let n = connection.read(&mut buffer[..size]).await?;
+            if n == 0 {
+                return Err(anyhow!("Redis closed the connection during the backup"));
+            }
+
+            writer
+                .write_all(&buffer[..n])
+                .map_err(|e| anyhow!("Failed to write backup data: {}", e))?;
+            remaining -= n;
+        }
+
+        Ok(())
+    }
+
+    async fn restore(&self, reader: &mut (dyn Read + Send + Unpin)) -> Result<()> {
+        self.restore_with_options(
+            reader,
+            RestoreOptions {
+                drop_database_first: true,
+                jobs: None,
+            },
+        )
+        .await
+    }
+
+    /// A running server can't load an RDB file, every key is sent back with `RESTORE` into the
+    /// logical database it was saved from, replacing existing keys.
+    async fn restore_with_options(
+        &self,
+        reader: &mut (dyn Read + Send + Unpin),
+        options: RestoreOptions,
+    ) -> Result<()> {
+        let mut connection = self.connect().await?;
+
+        if options.drop_database_first {
+            connection
+                .command(&[b"FLUSHALL"])
+                .await
+                .map_err(|e| anyhow!("Failed to flush Redis: {}", e))?;
+        }
+
+        let mut snapshot = RdbReader::new(BufReader::new(reader))?;
+        let now = Utc::now().timestamp_millis() as u64;
+        let mut selected_database = None;
+        let mut pending = 0;
+
+        while let Some(item) = snapshot.next_item()? {
+            match item {
+                RdbItem::Function(code) => {
+                    connection
+                        .send(&[b"FUNCTION", b"LOAD", b"REPLACE", &code])
+                        .await?;
+                    pending += 1;
+                }
+                RdbItem::Entry(entry) => {
+                    // Like Redis loading a snapshot, keys that expired in the meantime are skipped
+                    if entry.expire_at.is_some_and(|expire_at| expire_at <= now) {
+                        continue;
+                    }
+
+                    if selected_database != Some(entry.database) {
+                        connection
+                            .send(&[b"SELECT", entry.database.to_string().as_bytes()])
+                            .await?;
+                        selected_database = Some(entry.database);
+                        pending += 1;
+                    }
+
+                    let expire_at = entry.expire_at.unwrap_or(0).to_string();
+                    connection
+                        .send(&[
+                            b"RESTORE",
+                            &entry.key,
+                            expire_at.as_bytes(),
+                            &entry.payload,
+                            b"REPLACE",
+                            b"ABSTTL",
+                        ])
+                        .await?;
+                    pending += 1;
+                }
+            }
+
+            if pending >= PIPELINE_SIZE {
+                read_replies(&mut connection, pending).await?;
+                pending = 0;
+            }
+        }
+
+        read_replies(&mut connection, pending).await
+    }
+
+    /// Key count of every logical database.
+    async fn get_table_stats(&self) -> Result<Vec<TableStats>> {
+        let info = self
+            .get_info("keyspace")
+            .await
+            .map_err(|e| anyhow!("Failed to get key counts: {}", e))?;
+
+        Ok(parse_keyspace(&info))
+    }
+
+    /// Numbered databases always exist.
+    async fn database_exists(&self, _name: &str) -> Result<bool> {
+        Ok(true)
+    }
+
+    async fn create_database(&self, _name: &str) -> Result<()> {
+        Err(anyhow!(
+            "Redis has a fixed set of numbered databases, backups can't be restored into a scratch database"
+        ))
+    }
+
+    async fn drop_database(&self, _name: &str) -> Result<()> {
+        Err(anyhow!(
+            "Redis has a fixed set of numbered databases, use a restore with drop to flush the instance"
+        ))
+    }
+}
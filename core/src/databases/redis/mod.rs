@@ -0,0 +1,5 @@
+pub mod connection;
+pub mod rdb;
+pub mod resp;
+mod tests;
+pub mod version;
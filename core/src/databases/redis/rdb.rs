@@ -0,0 +1,481 @@
+use std::io::Read;
+
+use anyhow::{anyhow, Result};
+use crc::{Crc, Digest, CRC_64_REDIS};
+
+/// Checksum of RDB files and of `DUMP` payloads.
+pub static RDB_CRC: Crc<u64> = Crc::<u64>::new(&CRC_64_REDIS);
+
+const OPCODE_SLOT_INFO: u8 = 0xF4;
+const OPCODE_FUNCTION2: u8 = 0xF5;
+const OPCODE_FUNCTION_PRE_GA: u8 = 0xF6;
+const OPCODE_MODULE_AUX: u8 = 0xF7;
+const OPCODE_IDLE: u8 = 0xF8;
+const OPCODE_FREQ: u8 = 0xF9;
+const OPCODE_AUX: u8 = 0xFA;
+const OPCODE_RESIZEDB: u8 = 0xFB;
+const OPCODE_EXPIRETIME_MS: u8 = 0xFC;
+const OPCODE_EXPIRETIME: u8 = 0xFD;
+const OPCODE_SELECTDB: u8 = 0xFE;
+const OPCODE_EOF: u8 = 0xFF;
+
+const TYPE_STRING: u8 = 0;
+const TYPE_LIST: u8 = 1;
+const TYPE_SET: u8 = 2;
+const TYPE_ZSET: u8 = 3;
+const TYPE_HASH: u8 = 4;
+const TYPE_ZSET_2: u8 = 5;
+const TYPE_MODULE_2: u8 = 7;
+const TYPE_HASH_ZIPMAP: u8 = 9;
+const TYPE_LIST_ZIPLIST: u8 = 10;
+const TYPE_SET_INTSET: u8 = 11;
+const TYPE_ZSET_ZIPLIST: u8 = 12;
+const TYPE_HASH_ZIPLIST: u8 = 13;
+const TYPE_LIST_QUICKLIST: u8 = 14;
+const TYPE_STREAM_LISTPACKS: u8 = 15;
+const TYPE_HASH_LISTPACK: u8 = 16;
+const TYPE_ZSET_LISTPACK: u8 = 17;
+const TYPE_LIST_QUICKLIST_2: u8 = 18;
+const TYPE_STREAM_LISTPACKS_2: u8 = 19;
+const TYPE_SET_LISTPACK: u8 = 20;
+const TYPE_STREAM_LISTPACKS_3: u8 = 21;
+const TYPE_HASH_METADATA: u8 = 24;
+const TYPE_HASH_LISTPACK_EX: u8 = 25;
+
+const MODULE_OPCODE_EOF: u64 = 0;
+const MODULE_OPCODE_SINT: u64 = 1;
+const MODULE_OPCODE_UINT: u64 = 2;
+const MODULE_OPCODE_FLOAT: u64 = 3;
+const MODULE_OPCODE_DOUBLE: u64 = 4;
+const MODULE_OPCODE_STRING: u64 = 5;
+
+const ENCODING_INT8: u64 = 0;
+const ENCODING_INT16: u64 = 1;
+const ENCODING_INT32: u64 = 2;
+const ENCODING_LZF: u64 = 3;
+
+/// Size of a stream entry ID, two big endian 64 bits integers.
+const STREAM_ID_SIZE: u64 = 16;
+
+pub struct RdbEntry {
+    pub database: u64,
+    pub key: Vec<u8>,
+    /// Unix time in milliseconds.
+    pub expire_at: Option<u64>,
+    /// Value in the `DUMP` format: type, serialized value, RDB version and checksum.
+    pub payload: Vec<u8>,
+}
+
+pub enum RdbItem {
+    /// Source code of a function library.
+    Function(Vec<u8>),
+    Entry(RdbEntry),
+}
+
+/// Reads an RDB snapshot key by key. Values aren't decoded, their serialized bytes are copied
+/// into `DUMP` payloads the server loads with `RESTORE`.
+pub struct RdbReader<R: Read> {
+    reader: R,
+    digest: Digest<'static, u64>,
+    version: u16,
+    database: u64,
+    /// Serialized bytes of the value being read.
+    value: Option<Vec<u8>>,
+}
+
+impl<R: Read> RdbReader<R> {
+    pub fn new(reader: R) -> Result<Self> {
+        let mut rdb = Self {
+            reader,
+            digest: RDB_CRC.digest(),
+            version: 0,
+            database: 0,
+            value: None,
+        };
+
+        let header = rdb.read_bytes(9)?;
+        if !header.starts_with(b"REDIS") {
+            return Err(anyhow!("Backup is not a Redis RDB snapshot"));
+        }
+
+        rdb.version = String::from_utf8_lossy(&header[5..])
+            .parse()
+            .map_err(|_| anyhow!("Invalid RDB version in the snapshot header"))?;
+
+        Ok(rdb)
+    }
+
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    /// `None` once the end of the snapshot is reached and its checksum verified.
+    pub fn next_item(&mut self) -> Result<Option<RdbItem>> {
+        let mut expire_at = None;
+
+        loop {
+            match self.read_u8()? {
+                OPCODE_AUX => {
+                    self.skip_string()?;
+                    self.skip_string()?;
+                }
+                OPCODE_RESIZEDB => {
+                    self.read_length()?;
+                    self.read_length()?;
+                }
+                OPCODE_SLOT_INFO => {
+                    for _ in 0..3 {
+                        self.read_length()?;
+                    }
+                }
+                OPCODE_SELECTDB => self.database = self.read_length()?,
+                OPCODE_EXPIRETIME_MS => expire_at = Some(u64::from_le_bytes(self.read_array()?)),
+                OPCODE_EXPIRETIME => {
+                    expire_at = Some(u32::from_le_bytes(self.read_array()?) as u64 * 1000)
+                }
+                OPCODE_IDLE => {
+                    self.read_length()?;
+                }
+                OPCODE_FREQ => {
+                    self.read_u8()?;
+                }
+                OPCODE_FUNCTION2 => return Ok(Some(RdbItem::Function(self.read_string()?))),
+                OPCODE_FUNCTION_PRE_GA | OPCODE_MODULE_AUX => {
+                    return Err(anyhow!(
+                        "Snapshots with module or pre-release function data aren't supported"
+                    ))
+                }
+                OPCODE_EOF => {
+                    self.verify_checksum()?;
+                    return Ok(None);
+                }
+                value_type => {
+                    let key = self.read_string()?;
+
+                    self.value = Some(vec![value_type]);
+                    let skipped = self.skip_value(value_type);
+                    let mut payload = self.value.take().unwrap_or_default();
+                    skipped?;
+
+                    payload.extend_from_slice(&self.version.to_le_bytes());
+                    let checksum = RDB_CRC.checksum(&payload);
+                    payload.extend_from_slice(&checksum.to_le_bytes());
+
+                    return Ok(Some(RdbItem::Entry(RdbEntry {
+                        database: self.database,
+                        key,
+                        expire_at,
+                        payload,
+                    })));
+                }
+            }
+        }
+    }
+
+    /// Snapshots written with `rdbchecksum no` end with a zero checksum.
+    fn verify_checksum(&mut self) -> Result<()> {
+        if self.version < 5 {
+            return Ok(());
+        }
+
+        let computed = std::mem::replace(&mut self.digest, RDB_CRC.digest()).finalize();
+        let mut expected = [0u8; 8];
+        self.reader
+            .read_exact(&mut expected)
+            .map_err(|e| anyhow!("Failed to read the snapshot checksum: {}", e))?;
+        let expected = u64::from_le_bytes(expected);
+
+        if expected != 0 && expected != computed {
+            return Err(anyhow!(
+                "Snapshot checksum mismatch, the backup is corrupted"
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn skip_value(&mut self, value_type: u8) -> Result<()> {
+        match value_type {
+            TYPE_STRING | TYPE_HASH_ZIPMAP | TYPE_LIST_ZIPLIST | TYPE_SET_INTSET
+            | TYPE_ZSET_ZIPLIST | TYPE_HASH_ZIPLIST | TYPE_HASH_LISTPACK | TYPE_ZSET_LISTPACK
+            | TYPE_SET_LISTPACK => self.skip_string()?,
+            TYPE_LIST | TYPE_SET | TYPE_LIST_QUICKLIST => {
+                for _ in 0..self.read_length()? {
+                    self.skip_string()?;
+                }
+            }
+            TYPE_LIST_QUICKLIST_2 => {
+                for _ in 0..self.read_length()? {
+                    self.read_length()?;
+                    self.skip_string()?;
+                }
+            }
+            TYPE_HASH => {
+                for _ in 0..self.read_length()? {
+                    self.skip_string()?;
+                    self.skip_string()?;
+                }
+            }
+            TYPE_ZSET => {
+                for _ in 0..self.read_length()? {
+                    self.skip_string()?;
+                    // Scores are strings, 253 to 255 stand for NaN and the infinities
+                    let length = self.read_u8()?;
+                    if length < 253 {
+                        self.read_bytes(length as u64)?;
+                    }
+                }
+            }
+            TYPE_ZSET_2 => {
+                for _ in 0..self.read_length()? {
+                    self.skip_string()?;
+                    self.read_bytes(8)?;
+                }
+            }
+            TYPE_HASH_METADATA => {
+                self.read_bytes(8)?;
+                for _ in 0..self.read_length()? {
+                    self.read_length()?;
+                    self.skip_string()?;
+                    self.skip_string()?;
+                }
+            }
+            TYPE_HASH_LISTPACK_EX => {
+                self.read_bytes(8)?;
+                self.skip_string()?;
+            }
+            TYPE_MODULE_2 => self.skip_module_value()?,
+            TYPE_STREAM_LISTPACKS | TYPE_STREAM_LISTPACKS_2 | TYPE_STREAM_LISTPACKS_3 => {
+                self.skip_stream(value_type)?
+            }
+            _ => return Err(anyhow!("Unsupported RDB value type {}", value_type)),
+        }
+
+        Ok(())
+    }
+
+    fn skip_module_value(&mut self) -> Result<()> {
+        // Module type ID
+        self.read_length()?;
+
+        loop {
+            match self.read_length()? {
+                MODULE_OPCODE_EOF => return Ok(()),
+                MODULE_OPCODE_SINT | MODULE_OPCODE_UINT => {
+                    self.read_length()?;
+                }
+                MODULE_OPCODE_FLOAT => {
+                    self.read_bytes(4)?;
+                }
+                MODULE_OPCODE_DOUBLE => {
+                    self.read_bytes(8)?;
+                }
+                MODULE_OPCODE_STRING => self.skip_string()?,
+                opcode => return Err(anyhow!("Unknown module value opcode {}", opcode)),
+            }
+        }
+    }
+
+    fn skip_stream(&mut self, value_type: u8) -> Result<()> {
+        for _ in 0..self.read_length()? {
+            self.skip_string()?;
+            self.skip_string()?;
+        }
+
+        // Length and last ID, then first ID, max deleted ID and entries added since version 2
+        let fields = match value_type {
+            TYPE_STREAM_LISTPACKS => 3,
+            _ => 8,
+        };
+        for _ in 0..fields {
+            self.read_length()?;
+        }
+
+        for _ in 0..self.read_length()? {
+            // Consumer group name and last delivered ID
+            self.skip_string()?;
+            self.read_length()?;
+            self.read_length()?;
+            if value_type != TYPE_STREAM_LISTPACKS {
+                // Entries read
+                self.read_length()?;
+            }
+
+            // Pending entries: ID, delivery time and delivery count
+            for _ in 0..self.read_length()? {
+                self.read_bytes(STREAM_ID_SIZE + 8)?;
+                self.read_length()?;
+            }
+
+            for _ in 0..self.read_length()? {
+                // Consumer name, seen time and active time since version 3
+                self.skip_string()?;
+                self.read_bytes(8)?;
+                if value_type == TYPE_STREAM_LISTPACKS_3 {
+                    self.read_bytes(8)?;
+                }
+
+                for _ in 0..self.read_length()? {
+                    self.read_bytes(STREAM_ID_SIZE)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_exact(&mut self, buffer: &mut [u8]) -> Result<()> {
+        self.reader
+            .read_exact(buffer)
+            .map_err(|e| anyhow!("Failed to read the snapshot: {}", e))?;
+        self.consume(buffer);
+        Ok(())
+    }
+
+    fn consume(&mut self, data: &[u8]) {
+        self.digest.update(data);
+        if let Some(value) = &mut self.value {
+            value.extend_from_slice(data);
+        }
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        let [byte] = self.read_array()?;
+        Ok(byte)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut buffer = [0u8; N];
+        self.read_exact(&mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Lengths come from the snapshot, the buffer only grows as data is actually read.
+    fn read_bytes(&mut self, length: u64) -> Result<Vec<u8>> {
+        let mut buffer = Vec::new();
+        (&mut self.reader)
+            .take(length)
+            .read_to_end(&mut buffer)
+            .map_err(|e| anyhow!("Failed to read the snapshot: {}", e))?;
+
+        if buffer.len() as u64 != length {
+            return Err(anyhow!("Unexpected end of the snapshot"));
+        }
+
+        self.consume(&buffer);
+        Ok(buffer)
+    }
+
+    fn read_length(&mut self) -> Result<u64> {
+        match self.read_length_or_encoding()? {
+            (length, false) => Ok(length),
+            (_, true) => Err(anyhow!("Unexpected string encoding in the snapshot")),
+        }
+    }
+
+    /// Returns the length, or the encoding of a string when the flag is set.
+    fn read_length_or_encoding(&mut self) -> Result<(u64, bool)> {
+        let first = self.read_u8()?;
+
+        match first >> 6 {
+            0 => Ok(((first & 0x3F) as u64, false)),
+            1 => Ok((
+                (((first & 0x3F) as u64) << 8) | self.read_u8()? as u64,
+                false,
+            )),
+            2 => match first {
+                0x80 => Ok((u32::from_be_bytes(self.read_array()?) as u64, false)),
+                0x81 => Ok((u64::from_be_bytes(self.read_array()?), false)),
+                _ => Err(anyhow!("Invalid length in the snapshot")),
+            },
+            _ => Ok(((first & 0x3F) as u64, true)),
+        }
+    }
+
+    fn read_string(&mut self) -> Result<Vec<u8>> {
+        let (length, encoded) = self.read_length_or_encoding()?;
+        if !encoded {
+            return self.read_bytes(length);
+        }
+
+        match length {
+            ENCODING_INT8 => Ok((self.read_u8()? as i8).to_string().into_bytes()),
+            ENCODING_INT16 => Ok(i16::from_le_bytes(self.read_array()?)
+                .to_string()
+                .into_bytes()),
+            ENCODING_INT32 => Ok(i32::from_le_bytes(self.read_array()?)
+                .to_string()
+                .into_bytes()),
+            ENCODING_LZF => {
+                let compressed_length = self.read_length()?;
+                let length = self.read_length()?;
+                let compressed = self.read_bytes(compressed_length)?;
+                lzf_decompress(&compressed, length)
+            }
+            encoding => Err(anyhow!("Unknown string encoding {}", encoding)),
+        }
+    }
+
+    fn skip_string(&mut self) -> Result<()> {
+        let (length, encoded) = self.read_length_or_encoding()?;
+        if !encoded {
+            self.read_bytes(length)?;
+            return Ok(());
+        }
+
+        match length {
+            ENCODING_INT8 => self.read_bytes(1)?,
+            ENCODING_INT16 => self.read_bytes(2)?,
+            ENCODING_INT32 => self.read_bytes(4)?,
+            ENCODING_LZF => {
+                let compressed_length = self.read_length()?;
+                self.read_length()?;
+                self.read_bytes(compressed_length)?
+            }
+            encoding => return Err(anyhow!("Unknown string encoding {}", encoding)),
+        };
+
+        Ok(())
+    }
+}
+
+/// Strings Redis compressed with LZF, keys and function code are needed decompressed.
+pub fn lzf_decompress(input: &[u8], length: u64) -> Result<Vec<u8>> {
+    let invalid = || anyhow!("Invalid LZF compressed string in the snapshot");
+    let mut output = Vec::with_capacity(input.len());
+    let mut position = 0;
+
+    while position < input.len() {
+        let control = input[position] as usize;
+        position += 1;
+
+        if control < 32 {
+            // Literal run
+            let end = position + control + 1;
+            output.extend_from_slice(input.get(position..end).ok_or_else(invalid)?);
+            position = end;
+        } else {
+            // Back reference
+            let mut run = control >> 5;
+            if run == 7 {
+                run += *input.get(position).ok_or_else(invalid)? as usize;
+                position += 1;
+            }
+
+            let offset =
+                ((control & 0x1F) << 8) + *input.get(position).ok_or_else(invalid)? as usize;
+            position += 1;
+
+            let start = output.len().checked_sub(offset + 1).ok_or_else(invalid)?;
+            for index in start..start + run + 2 {
+                output.push(output[index]);
+            }
+        }
+    }
+
+    if output.len() as u64 != length {
+        return Err(invalid());
+    }
+
+    Ok(output)
+}
@@ -0,0 +1,105 @@
+use anyhow::{anyhow, Result};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufStream},
+    net::TcpStream,
+};
+
+/// Replies of the commands sent by dbkp, none of them returns an array.
+#[derive(Debug, PartialEq)]
+pub enum Reply {
+    Status(String),
+    Integer(i64),
+    Bulk(Option<Vec<u8>>),
+}
+
+/// Minimal RESP2 client, enough to authenticate, run a few commands and read a replication
+/// stream without pulling in a full Redis client.
+pub struct RespConnection {
+    stream: BufStream<TcpStream>,
+}
+
+impl RespConnection {
+    pub async fn connect(host: &str, port: u16) -> Result<Self> {
+        let stream = TcpStream::connect((host, port))
+            .await
+            .map_err(|e| anyhow!("Failed to connect to Redis at {}:{}: {}", host, port, e))?;
+
+        Ok(Self {
+            stream: BufStream::new(stream),
+        })
+    }
+
+    /// Queues a command, it's only sent on the next flush so commands can be pipelined.
+    pub async fn send(&mut self, args: &[&[u8]]) -> Result<()> {
+        let mut command = format!("*{}\r\n", args.len()).into_bytes();
+
+        for arg in args {
+            command.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+            command.extend_from_slice(arg);
+            command.extend_from_slice(b"\r\n");
+        }
+
+        self.stream.write_all(&command).await?;
+        Ok(())
+    }
+
+    pub async fn flush(&mut self) -> Result<()> {
+        self.stream.flush().await?;
+        Ok(())
+    }
+
+    pub async fn command(&mut self, args: &[&[u8]]) -> Result<Reply> {
+        self.send(args).await?;
+        self.flush().await?;
+        self.read_reply().await
+    }
+
+    /// Line without its CRLF terminator.
+    pub async fn read_line(&mut self) -> Result<Vec<u8>> {
+        let mut line = Vec::new();
+        self.stream.read_until(b'\n', &mut line).await?;
+
+        if !line.ends_with(b"\r\n") {
+            return Err(anyhow!("Redis closed the connection"));
+        }
+
+        line.truncate(line.len() - 2);
+        Ok(line)
+    }
+
+    /// Server errors are returned as errors.
+    pub async fn read_reply(&mut self) -> Result<Reply> {
+        let line = self.read_line().await?;
+        let (kind, content) = match line.split_first() {
+            Some((kind, content)) => (*kind, String::from_utf8_lossy(content).to_string()),
+            None => return Err(anyhow!("Empty reply from Redis")),
+        };
+
+        match kind {
+            b'+' => Ok(Reply::Status(content)),
+            b'-' => Err(anyhow!("Redis error: {}", content)),
+            b':' => Ok(Reply::Integer(content.parse()?)),
+            b'$' => {
+                let length: i64 = content.parse()?;
+                if length < 0 {
+                    return Ok(Reply::Bulk(None));
+                }
+
+                let mut data = vec![0u8; length as usize + 2];
+                self.stream.read_exact(&mut data).await?;
+                data.truncate(length as usize);
+
+                Ok(Reply::Bulk(Some(data)))
+            }
+            _ => Err(anyhow!("Unexpected reply from Redis: {}", content)),
+        }
+    }
+
+    pub async fn read(&mut self, buffer: &mut [u8]) -> Result<usize> {
+        Ok(self.stream.read(buffer).await?)
+    }
+
+    pub async fn read_byte(&mut self) -> Result<u8> {
+        Ok(self.stream.read_u8().await?)
+    }
+}
@@ -0,0 +1,260 @@
+#[cfg(test)]
+mod redis_connection_tests {
+    use std::{env, io::Cursor};
+
+    use crate::databases::{
+        redis::{
+            connection::{parse_keyspace, RedisConnection},
+            rdb::{lzf_decompress, RdbItem, RdbReader, RDB_CRC},
+            resp::RespConnection,
+            version::RedisVersion,
+        },
+        version::{Version, VersionTrait},
+        ConnectionType, DatabaseConfig, DatabaseConnectionTrait, TableStats,
+    };
+    use anyhow::Result;
+    use dotenv::dotenv;
+
+    fn get_redis_config() -> Result<DatabaseConfig> {
+        dotenv().ok();
+
+        let port: u16 = env::var("REDIS_PORT").unwrap_or("0".into()).parse()?;
+
+        let config = DatabaseConfig {
+            id: "test".to_string(),
+            name: "test".to_string(),
+            connection_type: ConnectionType::Redis,
+            host: env::var("REDIS_HOST").unwrap_or_default(),
+            password: env::var("REDIS_PASSWORD").ok(),
+            ssh: None,
+            username: env::var("REDIS_USERNAME").unwrap_or_default(),
+            database: "0".into(),
+            port,
+        };
+
+        Ok(config)
+    }
+
+    fn rdb_string(value: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![value.len() as u8];
+        bytes.extend_from_slice(value);
+        bytes
+    }
+
+    /// Snapshot with a string expiring at `expire_at` in database 0 and an integer encoded key
+    /// in database 1.
+    fn build_snapshot(expire_at: u64) -> Vec<u8> {
+        let mut rdb = b"REDIS0011".to_vec();
+        rdb.push(0xFA);
+        rdb.extend(rdb_string(b"redis-ver"));
+        rdb.extend(rdb_string(b"7.2.4"));
+        rdb.extend([0xFE, 0]);
+        rdb.extend([0xFB, 1, 1]);
+        rdb.push(0xFC);
+        rdb.extend(expire_at.to_le_bytes());
+        rdb.push(0);
+        rdb.extend(rdb_string(b"session"));
+        rdb.extend(rdb_string(b"abc"));
+        rdb.extend([0xFE, 1]);
+        rdb.push(2);
+        rdb.extend([0xC0, 123]);
+        rdb.push(2);
+        rdb.extend(rdb_string(b"a"));
+        rdb.extend(rdb_string(b"b"));
+        rdb.push(0xFF);
+
+        let checksum = RDB_CRC.checksum(&rdb);
+        rdb.extend(checksum.to_le_bytes());
+        rdb
+    }
+
+    #[test]
+    fn test_01_parse_version() {
+        let info = "# Server\r\nredis_version:7.2.4\r\nredis_git_sha1:00000000\r\n";
+        let version_string = info
+            .lines()
+            .find_map(|line| line.strip_prefix("redis_version:"))
+            .expect("Failed to find version");
+
+        let version = RedisVersion::parse_string_version(version_string).expect("Failed to parse");
+        assert_eq!((version.major, version.minor, version.patch), (7, 2, 4));
+        assert_eq!(version.to_string(), "7");
+    }
+
+    #[test]
+    fn test_02_parse_keyspace() {
+        let info =
+            "# Keyspace\r\ndb0:keys=3,expires=1,avg_ttl=0\r\ndb2:keys=10,expires=0,avg_ttl=0\r\n";
+
+        assert_eq!(
+            parse_keyspace(info),
+            vec![
+                TableStats {
+                    name: "db0".into(),
+                    rows: 3,
+                },
+                TableStats {
+                    name: "db2".into(),
+                    rows: 10,
+                },
+            ]
+        );
+        assert!(parse_keyspace("# Keyspace\r\n").is_empty());
+    }
+
+    #[test]
+    fn test_03_checksum() {
+        // Test vector of the Redis CRC64 implementation
+        assert_eq!(RDB_CRC.checksum(b"123456789"), 0xe9c6d914c4b8d9ca);
+    }
+
+    #[test]
+    fn test_04_lzf_decompress() {
+        // Literal "abc" then a back reference repeating it three times
+        let compressed = [2, b'a', b'b', b'c', 0xE0, 2, 2];
+        assert_eq!(
+            lzf_decompress(&compressed, 14).expect("Failed to decompress"),
+            b"abcabcabcabcab"
+        );
+
+        assert!(lzf_decompress(&compressed, 3).is_err());
+        assert!(lzf_decompress(&[0x20, 0], 2).is_err());
+    }
+
+    #[test]
+    fn test_05_read_snapshot() {
+        let snapshot = build_snapshot(1_700_000_000_000);
+        let mut reader = RdbReader::new(Cursor::new(snapshot)).expect("Failed to read header");
+        assert_eq!(reader.version(), 11);
+
+        let entry = match reader.next_item().expect("Failed to read entry") {
+            Some(RdbItem::Entry(entry)) => entry,
+            _ => panic!("Expected an entry"),
+        };
+        assert_eq!(entry.database, 0);
+        assert_eq!(entry.key, b"session");
+        assert_eq!(entry.expire_at, Some(1_700_000_000_000));
+
+        let (value, checksum) = entry.payload.split_at(entry.payload.len() - 8);
+        assert_eq!(value, [0, 3, b'a', b'b', b'c', 11, 0]);
+        assert_eq!(
+            u64::from_le_bytes(checksum.try_into().unwrap()),
+            RDB_CRC.checksum(value)
+        );
+
+        let entry = match reader.next_item().expect("Failed to read entry") {
+            Some(RdbItem::Entry(entry)) => entry,
+            _ => panic!("Expected an entry"),
+        };
+        assert_eq!(entry.database, 1);
+        assert_eq!(entry.key, b"123");
+        assert_eq!(entry.expire_at, None);
+        assert_eq!(&entry.payload[..6], [2, 2, 1, b'a', 1, b'b']);
+
+        assert!(reader.next_item().expect("Failed to read end").is_none());
+    }
+
+    #[test]
+    fn test_06_reject_corrupted_snapshot() {
+        let mut snapshot = build_snapshot(0);
+        let last = snapshot.len() - 1;
+        snapshot[last] ^= 0xFF;
+
+        let mut reader = RdbReader::new(Cursor::new(snapshot)).expect("Failed to read header");
+        let error = loop {
+            match reader.next_item() {
+                Ok(Some(_)) => continue,
+                Ok(None) => panic!("Corrupted snapshot should be rejected"),
+                Err(e) => break e,
+            }
+        };
+        assert!(error.to_string().contains("checksum"));
+
+        assert!(RdbReader::new(Cursor::new(b"SQLite format 3".to_vec())).is_err());
+    }
+
+    #[ignore]
+    #[tokio::test]
+    async fn test_07_redis_connection() {
+        let config = get_redis_config().expect("Failed to get config");
+        let connection = RedisConnection::new(config)
+            .await
+            .expect("Failed to get connection");
+
+        assert!(connection.test().await.expect("Failed to test connection"));
+
+        let metadata = connection
+            .get_metadata()
+            .await
+            .expect("Failed to get metadata");
+
+        assert!(matches!(metadata.version, Version::Redis(_)));
+    }
+
+    #[ignore]
+    #[tokio::test]
+    async fn test_08_redis_backup_and_restore() {
+        let config = get_redis_config().expect("Failed to get config");
+        let connection = RedisConnection::new(config.clone())
+            .await
+            .expect("Failed to get connection");
+
+        let mut client = RespConnection::connect(&config.host, config.port)
+            .await
+            .expect("Failed to connect");
+        if let Some(password) = &config.password {
+            client
+                .command(&[b"AUTH", password.as_bytes()])
+                .await
+                .expect("Failed to authenticate");
+        }
+
+        client
+            .command(&[b"FLUSHALL"])
+            .await
+            .expect("Failed to flush");
+        client
+            .command(&[b"SET", b"test1", b"100"])
+            .await
+            .expect("Failed to insert test data");
+        client
+            .command(&[b"RPUSH", b"test2", b"a", b"b", b"c"])
+            .await
+            .expect("Failed to insert test data");
+        client
+            .command(&[b"SET", b"test3", b"300", b"EX", b"3600"])
+            .await
+            .expect("Failed to insert test data");
+
+        let mut backup_buffer = Vec::new();
+        connection
+            .backup(&mut backup_buffer)
+            .await
+            .expect("Failed to backup database");
+
+        assert!(backup_buffer.starts_with(b"REDIS"));
+
+        client
+            .command(&[b"DEL", b"test3"])
+            .await
+            .expect("Failed to delete test data");
+
+        connection
+            .restore(&mut Cursor::new(backup_buffer))
+            .await
+            .expect("Failed to restore database");
+
+        let stats = connection
+            .get_table_stats()
+            .await
+            .expect("Failed to get table stats");
+
+        assert_eq!(
+            stats,
+            vec![TableStats {
+                name: "db0".into(),
+                rows: 3,
+            }]
+        );
+    }
+}
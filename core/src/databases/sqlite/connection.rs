@@ -0,0 +1,252 @@
+use std::{
+    fs,
+    io::{self, ErrorKind, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use crate::databases::{
+    version::{Version, VersionTrait},
+    DatabaseConfig, DatabaseConnectionTrait, DatabaseMetadata, RestoreOptions, TableStats,
+};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use sqlx::{sqlite::SqliteConnectOptions, ConnectOptions, Connection};
+use tempfile::{tempdir, NamedTempFile};
+
+use super::version::SqliteVersion;
+
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+/// Files SQLite keeps next to a database while it's in use.
+const SIDE_FILE_SUFFIXES: [&str; 3] = ["-wal", "-shm", "-journal"];
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+/// SQLite database file, `database` is its path. Snapshots and restores go through the
+/// embedded library so no client binaries are downloaded.
+pub struct SqliteConnection {
+    pub config: DatabaseConfig,
+}
+
+impl SqliteConnection {
+    pub async fn new(config: DatabaseConfig) -> Result<Self> {
+        if config.ssh.is_some() {
+            return Err(anyhow!(
+                "SQLite databases are local files and can't be reached through an SSH tunnel"
+            ));
+        }
+
+        let connection = Self { config };
+        connection.connect().await?.close().await?;
+
+        Ok(connection)
+    }
+
+    fn path(&self) -> &Path {
+        Path::new(&self.config.database)
+    }
+
+    /// Connections are opened per operation, a restore replaces the file under them.
+    async fn connect(&self) -> Result<sqlx::SqliteConnection> {
+        SqliteConnectOptions::new()
+            .filename(self.path())
+            .connect()
+            .await
+            .map_err(|e| {
+                anyhow!(
+                    "Failed to open SQLite database {}: {}",
+                    self.path().display(),
+                    e
+                )
+            })
+    }
+}
+
+#[async_trait]
+impl DatabaseConnectionTrait for SqliteConnection {
+    async fn get_metadata(&self) -> Result<DatabaseMetadata> {
+        let mut connection = self.connect().await?;
+        let version_string: (String,) = sqlx::query_as("SELECT sqlite_version()")
+            .fetch_one(&mut connection)
+            .await
+            .map_err(|e| anyhow!("Failed to get database version: {}", e))?;
+        connection.close().await?;
+
+        let version = match SqliteVersion::parse_string_version(version_string.0.as_str()) {
+            Some(version) => version,
+            None => return Err(anyhow!("Failed to parse SQLite version string")),
+        };
+
+        Ok(DatabaseMetadata {
+            version: Version::Sqlite(version),
+        })
+    }
+
+    async fn test(&self) -> Result<bool> {
+        let mut connection = self.connect().await?;
+
+        let result = sqlx::query("SELECT 1")
+            .execute(&mut connection)
+            .await
+            .map(|_| true)
+            .map_err(|e| anyhow!("Connection test failed: {}", e));
+        connection.close().await?;
+
+        result
+    }
+
+    /// `VACUUM INTO` writes a consistent snapshot even while the database is being written to.
+    async fn backup(&self, writer: &mut (dyn Write + Send + Unpin)) -> Result<()> {
+        let snapshot_directory = tempdir()?;
+        let snapshot_path = snapshot_directory.path().join("snapshot.sqlite");
+
+        let mut connection = self.connect().await?;
+        sqlx::query("VACUUM INTO ?")
+            .bind(snapshot_path.to_string_lossy().to_string())
+            .execute(&mut connection)
+            .await
+            .map_err(|e| anyhow!("Failed to snapshot SQLite database: {}", e))?;
+        connection.close().await?;
+
+        let mut snapshot = fs::File::open(&snapshot_path)?;
+        io::copy(&mut snapshot, writer)
+            .map_err(|e| anyhow!("Failed to write backup data: {}", e))?;
+
+        Ok(())
+    }
+
+    /// The backup is written and checked next to the database, then renamed over it so
+    /// readers see either the old or the restored database.
+    async fn restore_with_options(
+        &self,
+        reader: &mut (dyn Read + Send + Unpin),
+        _options: RestoreOptions,
+    ) -> Result<()> {
+        let path = self.path();
+        let directory = match path.parent() {
+            Some(directory) if !directory.as_os_str().is_empty() => directory,
+            _ => Path::new("."),
+        };
+
+        let mut restored = NamedTempFile::new_in(directory)
+            .map_err(|e| anyhow!("Failed to create file in {}: {}", directory.display(), e))?;
+
+        io::copy(reader, &mut restored)
+            .map_err(|e| anyhow!("Failed to read backup data: {}", e))?;
+        restored.as_file().sync_all()?;
+
+        let mut header = [0u8; 16];
+        restored.seek(SeekFrom::Start(0))?;
+        if restored.read_exact(&mut header).is_err() || &header != SQLITE_HEADER {
+            return Err(anyhow!("Backup is not a SQLite database"));
+        }
+
+        let mut connection = SqliteConnectOptions::new()
+            .filename(restored.path())
+            .read_only(true)
+            .connect()
+            .await?;
+        let (integrity,): (String,) = sqlx::query_as("PRAGMA quick_check")
+            .fetch_one(&mut connection)
+            .await
+            .map_err(|e| anyhow!("Failed to check restored database: {}", e))?;
+        connection.close().await?;
+
+        if integrity != "ok" {
+            return Err(anyhow!("Restored database is corrupted: {}", integrity));
+        }
+
+        if let Ok(metadata) = fs::metadata(path) {
+            fs::set_permissions(restored.path(), metadata.permissions())?;
+
+            // Frames left in the write-ahead log would be applied on top of the restored file
+            let mut connection = self.connect().await?;
+            sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+                .execute(&mut connection)
+                .await
+                .map_err(|e| anyhow!("Failed to checkpoint SQLite database: {}", e))?;
+            connection.close().await?;
+        }
+
+        restored
+            .persist(path)
+            .map_err(|e| anyhow!("Failed to replace {}: {}", path.display(), e.error))?;
+
+        Ok(())
+    }
+
+    async fn restore(&self, reader: &mut (dyn Read + Send + Unpin)) -> Result<()> {
+        self.restore_with_options(
+            reader,
+            RestoreOptions {
+                drop_database_first: true,
+                jobs: None,
+            },
+        )
+        .await
+    }
+
+    async fn get_table_stats(&self) -> Result<Vec<TableStats>> {
+        let mut connection = self.connect().await?;
+
+        let tables: Vec<(String,)> = sqlx::query_as(
+            "SELECT name FROM sqlite_master
+            WHERE type = 'table' AND name NOT LIKE 'sqlite_%'
+            ORDER BY name",
+        )
+        .fetch_all(&mut connection)
+        .await
+        .map_err(|e| anyhow!("Failed to list tables: {}", e))?;
+
+        let mut stats = vec![];
+
+        for (table,) in tables {
+            let (rows,): (i64,) = sqlx::query_as(&format!(
+                "SELECT COUNT(*) FROM {}",
+                quote_identifier(&table)
+            ))
+            .fetch_one(&mut connection)
+            .await
+            .map_err(|e| anyhow!("Failed to count rows of {}: {}", table, e))?;
+
+            stats.push(TableStats {
+                name: table,
+                rows: rows as u64,
+            });
+        }
+
+        connection.close().await?;
+
+        Ok(stats)
+    }
+
+    async fn database_exists(&self, name: &str) -> Result<bool> {
+        Ok(Path::new(name).exists())
+    }
+
+    /// An empty file is a valid empty SQLite database.
+    async fn create_database(&self, name: &str) -> Result<()> {
+        fs::File::create_new(name)
+            .map_err(|e| anyhow!("Failed to create database {}: {}", name, e))?;
+
+        Ok(())
+    }
+
+    async fn drop_database(&self, name: &str) -> Result<()> {
+        let side_files = SIDE_FILE_SUFFIXES
+            .iter()
+            .map(|suffix| format!("{}{}", name, suffix));
+
+        for file in std::iter::once(name.to_string()).chain(side_files) {
+            match fs::remove_file(&file) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => return Err(anyhow!("Failed to drop database {}: {}", name, e)),
+            }
+        }
+
+        Ok(())
+    }
+}
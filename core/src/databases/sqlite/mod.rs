@@ -0,0 +1,3 @@
+pub mod connection;
+mod tests;
+pub mod version;
@@ -0,0 +1,225 @@
+#[cfg(test)]
+mod sqlite_connection_tests {
+    use std::path::Path;
+
+    use crate::databases::{
+        sqlite::connection::SqliteConnection, version::Version, ConnectionType, DatabaseConfig,
+        DatabaseConnectionTrait,
+    };
+    use anyhow::Result;
+    use sqlx::{sqlite::SqliteConnectOptions, ConnectOptions, Connection};
+    use tempfile::tempdir;
+
+    fn get_sqlite_config(path: &Path) -> DatabaseConfig {
+        DatabaseConfig {
+            id: "test".to_string(),
+            name: "test".to_string(),
+            connection_type: ConnectionType::Sqlite,
+            host: "".into(),
+            password: None,
+            ssh: None,
+            username: "".into(),
+            database: path.to_string_lossy().to_string(),
+            port: 0,
+        }
+    }
+
+    async fn execute(path: &Path, queries: &[&str]) -> Result<()> {
+        let mut connection = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true)
+            .connect()
+            .await?;
+
+        for query in queries {
+            sqlx::query(query).execute(&mut connection).await?;
+        }
+
+        connection.close().await?;
+        Ok(())
+    }
+
+    async fn fetch_rows(path: &Path) -> Result<Vec<(String, i32)>> {
+        let mut connection = SqliteConnectOptions::new().filename(path).connect().await?;
+        let rows = sqlx::query_as("SELECT name, value FROM backup_test_table ORDER BY id")
+            .fetch_all(&mut connection)
+            .await?;
+        connection.close().await?;
+        Ok(rows)
+    }
+
+    async fn create_test_database(path: &Path) -> Result<()> {
+        execute(
+            path,
+            &[
+                "CREATE TABLE backup_test_table (id INTEGER PRIMARY KEY, name TEXT, value INTEGER)",
+                "INSERT INTO backup_test_table (name, value) VALUES ('test1', 100), ('test2', 200), ('test3', 300)",
+            ],
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_01_sqlite_connection() {
+        let directory = tempdir().expect("Failed to create temp dir");
+        let path = directory.path().join("app.db");
+        create_test_database(&path)
+            .await
+            .expect("Failed to create database");
+
+        let connection = SqliteConnection::new(get_sqlite_config(&path))
+            .await
+            .expect("Failed to get connection");
+
+        assert!(connection.test().await.expect("Failed to test connection"));
+
+        let metadata = connection
+            .get_metadata()
+            .await
+            .expect("Failed to get metadata");
+        assert!(matches!(metadata.version, Version::Sqlite(ref version) if version.major == 3));
+
+        let stats = connection
+            .get_table_stats()
+            .await
+            .expect("Failed to get table stats");
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].name, "backup_test_table");
+        assert_eq!(stats[0].rows, 3);
+
+        // Missing files aren't created
+        let missing = directory.path().join("missing.db");
+        assert!(SqliteConnection::new(get_sqlite_config(&missing))
+            .await
+            .is_err());
+        assert!(!missing.exists());
+    }
+
+    #[tokio::test]
+    async fn test_02_sqlite_backup_and_restore() {
+        let directory = tempdir().expect("Failed to create temp dir");
+        let path = directory.path().join("app.db");
+        create_test_database(&path)
+            .await
+            .expect("Failed to create database");
+
+        // Writes sitting in the write-ahead log are part of the snapshot
+        execute(
+            &path,
+            &[
+                "PRAGMA journal_mode = WAL",
+                "UPDATE backup_test_table SET value = 150 WHERE name = 'test1'",
+            ],
+        )
+        .await
+        .expect("Failed to update database");
+
+        let connection = SqliteConnection::new(get_sqlite_config(&path))
+            .await
+            .expect("Failed to get connection");
+
+        let mut backup_buffer = Vec::new();
+        connection
+            .backup(&mut backup_buffer)
+            .await
+            .expect("Failed to backup database");
+        assert!(backup_buffer.starts_with(b"SQLite format 3\0"));
+
+        execute(
+            &path,
+            &[
+                "UPDATE backup_test_table SET value = 999 WHERE name = 'test1'",
+                "DELETE FROM backup_test_table WHERE name = 'test3'",
+            ],
+        )
+        .await
+        .expect("Failed to modify database");
+
+        connection
+            .restore(&mut std::io::Cursor::new(backup_buffer))
+            .await
+            .expect("Failed to restore database");
+
+        let rows = fetch_rows(&path).await.expect("Failed to fetch rows");
+        assert_eq!(
+            rows,
+            vec![
+                ("test1".to_string(), 150),
+                ("test2".to_string(), 200),
+                ("test3".to_string(), 300)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_03_sqlite_restore_rejects_invalid_backup() {
+        let directory = tempdir().expect("Failed to create temp dir");
+        let path = directory.path().join("app.db");
+        create_test_database(&path)
+            .await
+            .expect("Failed to create database");
+
+        let connection = SqliteConnection::new(get_sqlite_config(&path))
+            .await
+            .expect("Failed to get connection");
+
+        let result = connection
+            .restore(&mut std::io::Cursor::new(
+                b"CREATE TABLE users (id INTEGER);".to_vec(),
+            ))
+            .await;
+        assert!(result.is_err());
+
+        // The database is left untouched and no temporary file remains
+        let rows = fetch_rows(&path).await.expect("Failed to fetch rows");
+        assert_eq!(rows.len(), 3);
+        assert_eq!(
+            std::fs::read_dir(directory.path())
+                .expect("Failed to list directory")
+                .count(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_04_sqlite_create_and_drop_database() {
+        let directory = tempdir().expect("Failed to create temp dir");
+        let path = directory.path().join("app.db");
+        create_test_database(&path)
+            .await
+            .expect("Failed to create database");
+
+        let connection = SqliteConnection::new(get_sqlite_config(&path))
+            .await
+            .expect("Failed to get connection");
+
+        let scratch = directory.path().join("scratch.db");
+        let scratch = scratch.to_string_lossy();
+
+        connection
+            .create_database(&scratch)
+            .await
+            .expect("Failed to create database");
+        assert!(connection.create_database(&scratch).await.is_err());
+
+        let scratch_connection =
+            SqliteConnection::new(get_sqlite_config(Path::new(scratch.as_ref())))
+                .await
+                .expect("Failed to connect to the new database");
+        assert!(scratch_connection
+            .get_table_stats()
+            .await
+            .expect("Failed to get table stats")
+            .is_empty());
+
+        connection
+            .drop_database(&scratch)
+            .await
+            .expect("Failed to drop database");
+        assert!(!Path::new(scratch.as_ref()).exists());
+        connection
+            .drop_database(&scratch)
+            .await
+            .expect("Dropping a missing database should succeed");
+    }
+}
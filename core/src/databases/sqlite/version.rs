@@ -0,0 +1,59 @@
+use std::{fmt, str::FromStr};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::databases::version::VersionTrait;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SqliteVersion {
+    pub major: u16,
+    pub minor: u16,
+    pub patch: u16,
+}
+
+impl VersionTrait for SqliteVersion {
+    fn from_str(string: &str) -> Option<Self> {
+        let res: Vec<&str> = string.split(".").collect();
+
+        let major = res.first()?.parse::<u16>().ok()?;
+        let minor = res.get(1)?.parse::<u16>().ok()?;
+        let patch = res.get(2)?.parse::<u16>().ok()?;
+
+        Some(SqliteVersion {
+            major,
+            minor,
+            patch,
+        })
+    }
+
+    fn parse_string_version(version_string: &str) -> Option<Self> {
+        let regex = Regex::new(r"(\d+)\.(\d+)\.(\d+)").ok()?;
+        let captures = regex.captures(version_string)?;
+
+        let major = captures.get(1)?.as_str().parse::<u16>().ok()?;
+        let minor = captures.get(2)?.as_str().parse::<u16>().ok()?;
+        let patch = captures.get(3)?.as_str().parse::<u16>().ok()?;
+
+        Some(SqliteVersion {
+            major,
+            minor,
+            patch,
+        })
+    }
+}
+
+impl fmt::Display for SqliteVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl FromStr for SqliteVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        <SqliteVersion as VersionTrait>::from_str(s)
+            .ok_or_else(|| format!("Unsupported SQLite version: {}", s))
+    }
+}
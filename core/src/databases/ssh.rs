@@ -0,0 +1,647 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use super::DatabaseConfig;
+
+fn default_ssh_port() -> u16 {
+    22
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SshAuth {
+    /// Identities offered by the running ssh-agent.
+    Agent,
+    PrivateKey {
+        key_path: String,
+        passphrase: Option<String>,
+    },
+}
+
+impl std::fmt::Debug for SshAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SshAuth::Agent => write!(f, "Agent"),
+            SshAuth::PrivateKey {
+                key_path,
+                passphrase,
+            } => f
+                .debug_struct("PrivateKey")
+                .field("key_path", key_path)
+                .field("passphrase", &passphrase.as_ref().map(|_| "<redacted>"))
+                .finish(),
+        }
+    }
+}
+
+/// Bastion the database is reached through.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SshConfig {
+    pub host: String,
+    #[serde(default = "default_ssh_port")]
+    pub port: u16,
+    pub username: String,
+    pub auth: SshAuth,
+    /// Adds the key of a bastion missing from `~/.ssh/known_hosts` instead of rejecting it.
+    #[serde(default)]
+    pub accept_new_host_key: bool,
+}
+
+impl SshConfig {
+    /// Copies the configuration without the key passphrase.
+    pub fn redact(&self) -> SshConfig {
+        let mut ssh_config = self.clone();
+        if let SshAuth::PrivateKey { passphrase, .. } = &mut ssh_config.auth {
+            *passphrase = None;
+        }
+        ssh_config
+    }
+}
+
+/// Opens a tunnel when the database sits behind a bastion and returns the configuration
+/// to connect through it. The tunnel is closed when dropped.
+pub fn connect_through_tunnel(
+    config: &DatabaseConfig,
+) -> Result<(DatabaseConfig, Option<Arc<tunnel::SshTunnel>>)> {
+    let tunnel = open_tunnel(config)?;
+
+    Ok((through_tunnel(config, tunnel.as_deref()), tunnel))
+}
+
+/// Opens a tunnel to the database server when it sits behind a bastion.
+pub fn open_tunnel(config: &DatabaseConfig) -> Result<Option<Arc<tunnel::SshTunnel>>> {
+    match &config.ssh {
+        Some(ssh_config) => Ok(Some(Arc::new(tunnel::SshTunnel::open(
+            ssh_config,
+            &config.host,
+            config.port,
+        )?))),
+        None => Ok(None),
+    }
+}
+
+/// Configuration pointed at the local end of an open tunnel, other databases of the same
+/// server are reached through it too.
+pub fn through_tunnel(
+    config: &DatabaseConfig,
+    tunnel: Option<&tunnel::SshTunnel>,
+) -> DatabaseConfig {
+    let mut tunneled_config = config.clone();
+
+    if let Some(tunnel) = tunnel {
+        tunneled_config.host = "127.0.0.1".into();
+        tunneled_config.port = tunnel.local_port();
+        tunneled_config.ssh = None;
+    }
+
+    tunneled_config
+}
+
+#[cfg(feature = "ssh")]
+pub mod tunnel {
+    use std::{
+        fs::{self, OpenOptions},
+        io::{self, ErrorKind, Read, Write},
+        net::{Shutdown, TcpListener, TcpStream},
+        path::{Path, PathBuf},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        thread::{self, JoinHandle},
+        time::Duration,
+    };
+
+    use anyhow::{anyhow, Result};
+    use log::{debug, warn};
+    use ssh2::{Channel, CheckResult, KnownHostFileKind, KnownHostKeyFormat, KnownHosts, Session};
+
+    use super::{SshAuth, SshConfig};
+
+    const BUFFER_SIZE: usize = 32 * 1024;
+    const CONNECT_TIMEOUT_MS: u32 = 30_000;
+
+    /// Local port forwarded to a remote host and port through an SSH session.
+    pub struct SshTunnel {
+        local_port: u16,
+        shutdown: Arc<AtomicBool>,
+        handle: Option<JoinHandle<()>>,
+    }
+
+    impl SshTunnel {
+        pub fn open(ssh_config: &SshConfig, remote_host: &str, remote_port: u16) -> Result<Self> {
+            let session = connect(ssh_config)?;
+
+            let listener = TcpListener::bind("127.0.0.1:0")?;
+            listener.set_nonblocking(true)?;
+            let local_port = listener.local_addr()?.port();
+
+            debug!(
+                "Forwarding 127.0.0.1:{} to {}:{} through {}",
+                local_port, remote_host, remote_port, ssh_config.host
+            );
+
+            let shutdown = Arc::new(AtomicBool::new(false));
+            let shutdown_clone = shutdown.clone();
+            let remote_host = remote_host.to_string();
+
+            // libssh2 sessions can't be shared between threads, so a single thread
+            // multiplexes every forwarded connection in non-blocking mode
+            let handle = thread::spawn(move || {
+                session.set_blocking(false);
+                forward(
+                    &session,
+                    &listener,
+                    &remote_host,
+                    remote_port,
+                    &shutdown_clone,
+                );
+            });
+
+            Ok(Self {
+                local_port,
+                shutdown,
+                handle: Some(handle),
+            })
+        }
+
+        pub fn local_port(&self) -> u16 {
+            self.local_port
+        }
+    }
+
+    impl Drop for SshTunnel {
+        fn drop(&mut self) {
+            self.shutdown.store(true, Ordering::Relaxed);
+            if let Some(handle) = self.handle.take() {
+                let _ = handle.join();
+            }
+        }
+    }
+
+    pub(crate) fn expand_home(path: &str) -> PathBuf {
+        match (path.strip_prefix("~/"), dirs::home_dir()) {
+            (Some(path), Some(home)) => home.join(path),
+            _ => PathBuf::from(path),
+        }
+    }
+
+    /// Connects and checks the host key, the session still has to be authenticated.
+    pub(crate) fn open_session(
+        host: &str,
+        port: u16,
+        accept_new_host_key: bool,
+    ) -> Result<Session> {
+        let tcp = TcpStream::connect((host, port))
+            .map_err(|e| anyhow!("Failed to connect to SSH host {}:{}: {}", host, port, e))?;
+
+        let mut session = Session::new()?;
+        session.set_tcp_stream(tcp);
+        session.set_timeout(CONNECT_TIMEOUT_MS);
+        session
+            .handshake()
+            .map_err(|e| anyhow!("SSH handshake with {} failed: {}", host, e))?;
+
+        check_host_key(&session, host, port, accept_new_host_key)?;
+
+        Ok(session)
+    }
+
+    fn connect(ssh_config: &SshConfig) -> Result<Session> {
+        let session = open_session(
+            &ssh_config.host,
+            ssh_config.port,
+            ssh_config.accept_new_host_key,
+        )?;
+
+        let auth_result = match &ssh_config.auth {
+            SshAuth::Agent => session.userauth_agent(&ssh_config.username),
+            SshAuth::PrivateKey {
+                key_path,
+                passphrase,
+            } => session.userauth_pubkey_file(
+                &ssh_config.username,
+                None,
+                &expand_home(key_path),
+                passphrase.as_deref(),
+            ),
+        };
+
+        auth_result.map_err(|e| {
+            anyhow!(
+                "SSH authentication as {} on {} failed: {}",
+                ssh_config.username,
+                ssh_config.host,
+                e
+            )
+        })?;
+
+        if !session.authenticated() {
+            return Err(anyhow!(
+                "SSH authentication as {} on {} failed",
+                ssh_config.username,
+                ssh_config.host
+            ));
+        }
+
+        Ok(session)
+    }
+
+    fn check_host_key(
+        session: &Session,
+        host: &str,
+        port: u16,
+        accept_new_host_key: bool,
+    ) -> Result<()> {
+        let (key, key_type) = session
+            .host_key()
+            .ok_or_else(|| anyhow!("SSH host {} sent no host key", host))?;
+
+        let mut known_hosts = session.known_hosts()?;
+        let known_hosts_path = expand_home("~/.ssh/known_hosts");
+
+        if Path::new(&known_hosts_path).exists() {
+            known_hosts.read_file(&known_hosts_path, KnownHostFileKind::OpenSSH)?;
+        }
+
+        verify_host_key(
+            &mut known_hosts,
+            &known_hosts_path,
+            host,
+            port,
+            key,
+            key_type.into(),
+            accept_new_host_key,
+        )
+    }
+
+    /// Rejects hosts whose key changed, and unknown hosts unless `accept_new_host_key` is set.
+    /// An accepted key is added to known_hosts, later connections are verified against it.
+    fn verify_host_key(
+        known_hosts: &mut KnownHosts,
+        known_hosts_path: &Path,
+        host: &str,
+        port: u16,
+        key: &[u8],
+        key_format: KnownHostKeyFormat,
+        accept_new_host_key: bool,
+    ) -> Result<()> {
+        match known_hosts.check_port(host, port, key) {
+            CheckResult::Match => Ok(()),
+            CheckResult::Mismatch => Err(anyhow!(
+                "SSH host key of {} does not match {}",
+                host,
+                known_hosts_path.display()
+            )),
+            CheckResult::NotFound if accept_new_host_key => {
+                add_host_key(known_hosts, known_hosts_path, host, port, key, key_format)?;
+                warn!(
+                    "SSH host {} was not in {}, its key has been added",
+                    host,
+                    known_hosts_path.display()
+                );
+                Ok(())
+            }
+            CheckResult::NotFound => Err(anyhow!(
+                "SSH host {} is not in {}, add its key with `ssh-keyscan -p {} {}` or accept new host keys explicitly",
+                host,
+                known_hosts_path.display(),
+                port,
+                host
+            )),
+            CheckResult::Failure => Err(anyhow!(
+                "Failed to check the SSH host key of {} against {}",
+                host,
+                known_hosts_path.display()
+            )),
+        }
+    }
+
+    /// Appends the key to known_hosts rather than rewriting it, libssh2 skips the entries it
+    /// can't parse and they would be lost.
+    fn add_host_key(
+        known_hosts: &mut KnownHosts,
+        known_hosts_path: &Path,
+        host: &str,
+        port: u16,
+        key: &[u8],
+        key_format: KnownHostKeyFormat,
+    ) -> Result<()> {
+        let name = match port {
+            22 => host.to_string(),
+            _ => format!("[{}]:{}", host, port),
+        };
+        known_hosts.add(&name, key, "", key_format)?;
+
+        let added = known_hosts
+            .hosts()?
+            .into_iter()
+            .rev()
+            .find(|known_host| known_host.name() == Some(name.as_str()))
+            .ok_or_else(|| anyhow!("SSH host {} wasn't added to the known hosts", host))?;
+        let line = known_hosts.write_string(&added, KnownHostFileKind::OpenSSH)?;
+
+        let add_error = |e: io::Error| {
+            anyhow!(
+                "Failed to add SSH host {} to {}: {}",
+                host,
+                known_hosts_path.display(),
+                e
+            )
+        };
+        if let Some(directory) = known_hosts_path.parent() {
+            fs::create_dir_all(directory).map_err(add_error)?;
+        }
+        let ends_with_newline = match fs::read(known_hosts_path) {
+            Ok(content) => content.is_empty() || content.ends_with(b"\n"),
+            Err(_) => true,
+        };
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(known_hosts_path)
+            .map_err(add_error)?;
+        if !ends_with_newline {
+            writeln!(file).map_err(add_error)?;
+        }
+        writeln!(file, "{}", line.trim_end()).map_err(add_error)?;
+
+        Ok(())
+    }
+
+    struct Forward {
+        stream: TcpStream,
+        channel: Channel,
+        to_remote: Vec<u8>,
+        to_local: Vec<u8>,
+        local_eof: bool,
+        closed: bool,
+    }
+
+    fn would_block(e: &io::Error) -> bool {
+        e.kind() == ErrorKind::WouldBlock
+    }
+
+    fn open_channel(session: &Session, host: &str, port: u16) -> Result<Channel> {
+        loop {
+            match session.channel_direct_tcpip(host, port, None) {
+                Ok(channel) => return Ok(channel),
+                Err(e) => {
+                    let e = io::Error::from(e);
+                    if !would_block(&e) {
+                        return Err(anyhow!(
+                            "Failed to open SSH channel to {}:{}: {}",
+                            host,
+                            port,
+                            e
+                        ));
+                    }
+                    thread::sleep(Duration::from_millis(1));
+                }
+            }
+        }
+    }
+
+    fn forward(
+        session: &Session,
+        listener: &TcpListener,
+        remote_host: &str,
+        remote_port: u16,
+        shutdown: &AtomicBool,
+    ) {
+        let mut forwards: Vec<Forward> = vec![];
+        let mut buffer = vec![0u8; BUFFER_SIZE];
+
+        while !shutdown.load(Ordering::Relaxed) {
+            let mut progress = false;
+
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    progress = true;
+
+                    let channel = match open_channel(session, remote_host, remote_port) {
+                        Ok(channel) => channel,
+                        Err(e) => {
+                            warn!("{}", e);
+                            let _ = stream.shutdown(Shutdown::Both);
+                            continue;
+                        }
+                    };
+
+                    if let Err(e) = stream.set_nonblocking(true) {
+                        warn!("Failed to configure tunneled connection: {}", e);
+                        continue;
+                    }
+
+                    forwards.push(Forward {
+                        stream,
+                        channel,
+                        to_remote: vec![],
+                        to_local: vec![],
+                        local_eof: false,
+                        closed: false,
+                    });
+                }
+                Err(e) if would_block(&e) => {}
+                Err(e) => warn!("Failed to accept tunneled connection: {}", e),
+            }
+
+            for forward in forwards.iter_mut() {
+                progress |= pump(forward, &mut buffer);
+            }
+
+            forwards.retain_mut(|forward| {
+                if forward.closed {
+                    let _ = forward.stream.shutdown(Shutdown::Both);
+                    let _ = forward.channel.close();
+                }
+                !forward.closed
+            });
+
+            if !progress {
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+    }
+
+    /// Moves whatever data is ready in both directions, returns whether any did.
+    fn pump(forward: &mut Forward, buffer: &mut [u8]) -> bool {
+        let mut progress = false;
+
+        if forward.to_remote.is_empty() && !forward.local_eof {
+            match forward.stream.read(buffer) {
+                Ok(0) => {
+                    forward.local_eof = true;
+                    let _ = forward.channel.send_eof();
+                    progress = true;
+                }
+                Ok(n) => {
+                    forward.to_remote.extend_from_slice(&buffer[..n]);
+                    progress = true;
+                }
+                Err(e) if would_block(&e) => {}
+                Err(_) => forward.closed = true,
+            }
+        }
+
+        if !forward.to_remote.is_empty() {
+            match forward.channel.write(&forward.to_remote) {
+                Ok(n) => {
+                    forward.to_remote.drain(..n);
+                    progress = true;
+                }
+                Err(e) if would_block(&e) => {}
+                Err(_) => forward.closed = true,
+            }
+        }
+
+        if forward.to_local.is_empty() {
+            match forward.channel.read(buffer) {
+                Ok(0) => {}
+                Ok(n) => {
+                    forward.to_local.extend_from_slice(&buffer[..n]);
+                    progress = true;
+                }
+                Err(e) if would_block(&e) => {}
+                Err(_) => forward.closed = true,
+            }
+        }
+
+        if !forward.to_local.is_empty() {
+            match forward.stream.write(&forward.to_local) {
+                Ok(n) => {
+                    forward.to_local.drain(..n);
+                    progress = true;
+                }
+                Err(e) if would_block(&e) => {}
+                Err(_) => forward.closed = true,
+            }
+        }
+
+        if forward.channel.eof() && forward.to_local.is_empty() {
+            forward.closed = true;
+        }
+
+        progress
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use tempfile::tempdir;
+
+        use super::*;
+
+        #[test]
+        fn test_verify_host_key() {
+            let directory = tempdir().unwrap();
+            let path = directory.path().join(".ssh").join("known_hosts");
+            let session = Session::new().unwrap();
+            let mut known_hosts = session.known_hosts().unwrap();
+            known_hosts
+                .add(
+                    "known.example",
+                    b"known key",
+                    "",
+                    KnownHostKeyFormat::SshRsa,
+                )
+                .unwrap();
+            let rsa = KnownHostKeyFormat::SshRsa;
+
+            assert!(verify_host_key(
+                &mut known_hosts,
+                &path,
+                "known.example",
+                22,
+                b"known key",
+                rsa,
+                false
+            )
+            .is_ok());
+
+            let error = verify_host_key(
+                &mut known_hosts,
+                &path,
+                "new.example",
+                2222,
+                b"new key",
+                rsa,
+                false,
+            )
+            .expect_err("Unknown hosts should be rejected by default");
+            assert!(error.to_string().contains("is not in"), "{}", error);
+            assert!(!path.exists());
+
+            assert!(verify_host_key(
+                &mut known_hosts,
+                &path,
+                "new.example",
+                2222,
+                b"new key",
+                rsa,
+                true
+            )
+            .is_ok());
+
+            // The accepted key is remembered, a later change is caught
+            let mut reloaded = session.known_hosts().unwrap();
+            reloaded
+                .read_file(&path, KnownHostFileKind::OpenSSH)
+                .unwrap();
+            assert!(matches!(
+                reloaded.check_port("new.example", 2222, b"new key"),
+                CheckResult::Match
+            ));
+            assert!(verify_host_key(
+                &mut reloaded,
+                &path,
+                "new.example",
+                2222,
+                b"changed key",
+                rsa,
+                true
+            )
+            .is_err());
+
+            for accept_new_host_key in [false, true] {
+                assert!(
+                    verify_host_key(
+                        &mut known_hosts,
+                        &path,
+                        "known.example",
+                        22,
+                        b"changed key",
+                        rsa,
+                        accept_new_host_key
+                    )
+                    .is_err(),
+                    "A changed key is never accepted"
+                );
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "ssh"))]
+pub mod tunnel {
+    use anyhow::{anyhow, Result};
+
+    use super::SshConfig;
+
+    pub struct SshTunnel;
+
+    impl SshTunnel {
+        pub fn open(
+            _ssh_config: &SshConfig,
+            _remote_host: &str,
+            _remote_port: u16,
+        ) -> Result<Self> {
+            Err(anyhow!("dbkp was built without SSH support"))
+        }
+
+        pub fn local_port(&self) -> u16 {
+            0
+        }
+    }
+}
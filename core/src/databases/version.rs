@@ -1,11 +1,19 @@
 use serde::{Deserialize, Serialize};
 
-use super::{mysql::version::MySqlVersion, postgres::version::PostgreSQLVersion};
+use super::{
+    mariadb::version::MariaDBVersion, mongo::version::MongoDbVersion, mysql::version::MySqlVersion,
+    postgres::version::PostgreSQLVersion, redis::version::RedisVersion,
+    sqlite::version::SqliteVersion,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Version {
     PostgreSQL(PostgreSQLVersion),
     MySql(MySqlVersion),
+    MariaDB(MariaDBVersion),
+    Sqlite(SqliteVersion),
+    MongoDb(MongoDbVersion),
+    Redis(RedisVersion),
 }
 
 pub trait VersionTrait: Sized + ToString {
@@ -0,0 +1,617 @@
+use age::x25519;
+use anyhow::{anyhow, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::stream::{DecryptorBE32, EncryptorBE32};
+use chacha20poly1305::aead::{KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{self, BufRead, Read, Write};
+
+/// Header of the archives encrypted with a passphrase, followed by the version byte.
+pub const PASSPHRASE_MAGIC: &[u8; 8] = b"DBKPENC\x01";
+const AGE_MAGIC: &[u8] = b"age-encryption.org/v1";
+
+const SALT_SIZE: usize = 16;
+const NONCE_SIZE: usize = 7;
+const CHUNK_SIZE: usize = 64 * 1024;
+const TAG_SIZE: usize = 16;
+/// The argon2 costs come from the backup, costs this far above the ones backups are written with
+/// are refused rather than spending the memory and time they ask for.
+const MAX_COST_FACTOR: u32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EncryptionFormat {
+    /// Argon2id derived key with ChaCha20-Poly1305 in STREAM mode.
+    Passphrase,
+    /// age file encrypted to X25519 recipients.
+    Age,
+}
+
+impl EncryptionFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            EncryptionFormat::Passphrase => "enc",
+            EncryptionFormat::Age => "age",
+        }
+    }
+
+    pub fn from_extension(path: &str) -> Option<EncryptionFormat> {
+        if path.ends_with(".enc") {
+            Some(EncryptionFormat::Passphrase)
+        } else if path.ends_with(".age") {
+            Some(EncryptionFormat::Age)
+        } else {
+            None
+        }
+    }
+
+    /// Removes the encryption extension so the compression extension can be read.
+    pub fn strip_extension(path: &str) -> &str {
+        path.strip_suffix(".enc")
+            .or_else(|| path.strip_suffix(".age"))
+            .unwrap_or(path)
+    }
+
+    pub fn from_signature(signature: &[u8]) -> Option<EncryptionFormat> {
+        if signature.starts_with(PASSPHRASE_MAGIC) {
+            Some(EncryptionFormat::Passphrase)
+        } else if signature.starts_with(AGE_MAGIC) {
+            Some(EncryptionFormat::Age)
+        } else {
+            None
+        }
+    }
+}
+
+/// Keys used to encrypt new backups and decrypt existing ones.
+/// Identities are either `AGE-SECRET-KEY-` strings or paths to age identity files.
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncryptionConfig {
+    #[serde(default)]
+    pub passphrase: Option<String>,
+    #[serde(default)]
+    pub recipients: Vec<String>,
+    #[serde(default)]
+    pub identities: Vec<String>,
+}
+
+impl fmt::Debug for EncryptionConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptionConfig")
+            .field("passphrase", &self.passphrase.as_ref().map(|_| "********"))
+            .field("recipients", &self.recipients)
+            .field(
+                "identities",
+                &format!("{} identities", self.identities.len()),
+            )
+            .finish()
+    }
+}
+
+impl EncryptionConfig {
+    pub fn is_empty(&self) -> bool {
+        self.passphrase.is_none() && self.recipients.is_empty() && self.identities.is_empty()
+    }
+
+    /// Format used to encrypt new backups, `None` when no key is configured. Identities only
+    /// decrypt, a backup isn't left unencrypted because they are the only keys.
+    pub fn format(&self) -> Result<Option<EncryptionFormat>> {
+        match (&self.passphrase, self.recipients.is_empty()) {
+            (Some(_), false) => Err(anyhow!(
+                "Use either a passphrase or age recipients to encrypt backups, not both"
+            )),
+            (Some(_), true) => Ok(Some(EncryptionFormat::Passphrase)),
+            (None, false) => Ok(Some(EncryptionFormat::Age)),
+            (None, true) if !self.identities.is_empty() => Err(anyhow!(
+                "Identities only decrypt backups, add age recipients or a passphrase to encrypt them"
+            )),
+            (None, true) => Ok(None),
+        }
+    }
+
+    fn parse_recipients(&self) -> Result<Vec<x25519::Recipient>> {
+        self.recipients
+            .iter()
+            .map(|recipient| {
+                recipient
+                    .trim()
+                    .parse::<x25519::Recipient>()
+                    .map_err(|e| anyhow!("Invalid age recipient {}: {}", recipient, e))
+            })
+            .collect()
+    }
+
+    fn parse_identities(&self) -> Result<Vec<x25519::Identity>> {
+        let mut identities = vec![];
+
+        for identity in &self.identities {
+            let content = if identity.trim().starts_with("AGE-SECRET-KEY-") {
+                identity.clone()
+            } else {
+                std::fs::read_to_string(identity)
+                    .map_err(|e| anyhow!("Failed to read identity file {}: {}", identity, e))?
+            };
+
+            for line in content.lines() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+
+                let parsed = line
+                    .parse::<x25519::Identity>()
+                    .map_err(|e| anyhow!("Invalid age identity in {}: {}", identity, e))?;
+                identities.push(parsed);
+            }
+        }
+
+        Ok(identities)
+    }
+}
+
+fn derive_key(
+    passphrase: &str,
+    salt: &[u8],
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+) -> io::Result<Key> {
+    let params = Params::new(m_cost, t_cost, p_cost, Some(32))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    let mut key = Key::default();
+
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| io::Error::other(e.to_string()))?;
+
+    Ok(key)
+}
+
+/// Encrypts a stream with a passphrase.
+/// Layout: magic, argon2 costs (3 x u32 LE), salt, nonce, then 64KiB chunks with their tag.
+pub struct PassphraseWriter<W: Write + Send + Unpin> {
+    inner: W,
+    stream: Option<EncryptorBE32<ChaCha20Poly1305>>,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write + Send + Unpin> PassphraseWriter<W> {
+    pub fn new(mut inner: W, passphrase: &str) -> io::Result<Self> {
+        let mut salt = [0u8; SALT_SIZE];
+        let mut nonce = [0u8; NONCE_SIZE];
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(&mut nonce);
+
+        let params = Params::default();
+        let key = derive_key(
+            passphrase,
+            &salt,
+            params.m_cost(),
+            params.t_cost(),
+            params.p_cost(),
+        )?;
+
+        inner.write_all(PASSPHRASE_MAGIC)?;
+        inner.write_all(&params.m_cost().to_le_bytes())?;
+        inner.write_all(&params.t_cost().to_le_bytes())?;
+        inner.write_all(&params.p_cost().to_le_bytes())?;
+        inner.write_all(&salt)?;
+        inner.write_all(&nonce)?;
+
+        let cipher = ChaCha20Poly1305::new(&key);
+
+        Ok(Self {
+            inner,
+            stream: Some(EncryptorBE32::from_aead(cipher, (&nonce).into())),
+            buffer: Vec::with_capacity(CHUNK_SIZE * 2),
+        })
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        let stream = self
+            .stream
+            .take()
+            .ok_or_else(|| io::Error::other("Encryptor already finished"))?;
+
+        let ciphertext = stream
+            .encrypt_last(self.buffer.as_slice())
+            .map_err(|_| io::Error::other("Failed to encrypt chunk"))?;
+        self.inner.write_all(&ciphertext)?;
+
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write + Send + Unpin> Write for PassphraseWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+
+        // Keep at least one byte back so the last chunk is always written by `finish`
+        while self.buffer.len() > CHUNK_SIZE {
+            let stream = self
+                .stream
+                .as_mut()
+                .ok_or_else(|| io::Error::other("Encryptor already finished"))?;
+
+            let ciphertext = stream
+                .encrypt_next(&self.buffer[..CHUNK_SIZE])
+                .map_err(|_| io::Error::other("Failed to encrypt chunk"))?;
+            self.inner.write_all(&ciphertext)?;
+            self.buffer.drain(..CHUNK_SIZE);
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Decrypts a stream written by [`PassphraseWriter`].
+pub struct PassphraseReader<R: Read + Send + Unpin> {
+    inner: R,
+    stream: Option<DecryptorBE32<ChaCha20Poly1305>>,
+    input: Vec<u8>,
+    output: Vec<u8>,
+    position: usize,
+}
+
+impl<R: Read + Send + Unpin> PassphraseReader<R> {
+    /// Reads the header and decrypts the first chunk so a wrong passphrase fails right away.
+    pub fn new(mut inner: R, passphrase: &str) -> io::Result<Self> {
+        let mut magic = [0u8; 8];
+        inner.read_exact(&mut magic)?;
+        if &magic != PASSPHRASE_MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Backup is not encrypted with a passphrase",
+            ));
+        }
+
+        let mut cost = [0u8; 4];
+        inner.read_exact(&mut cost)?;
+        let m_cost = u32::from_le_bytes(cost);
+        inner.read_exact(&mut cost)?;
+        let t_cost = u32::from_le_bytes(cost);
+        inner.read_exact(&mut cost)?;
+        let p_cost = u32::from_le_bytes(cost);
+
+        if m_cost > Params::DEFAULT_M_COST * MAX_COST_FACTOR
+            || t_cost > Params::DEFAULT_T_COST * MAX_COST_FACTOR
+            || p_cost > Params::DEFAULT_P_COST * MAX_COST_FACTOR
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Backup asks for argon2 costs m={} t={} p={}, above the accepted maximum",
+                    m_cost, t_cost, p_cost
+                ),
+            ));
+        }
+
+        let mut salt = [0u8; SALT_SIZE];
+        let mut nonce = [0u8; NONCE_SIZE];
+        inner.read_exact(&mut salt)?;
+        inner.read_exact(&mut nonce)?;
+
+        let key = derive_key(passphrase, &salt, m_cost, t_cost, p_cost)?;
+        let cipher = ChaCha20Poly1305::new(&key);
+
+        let mut reader = Self {
+            inner,
+            stream: Some(DecryptorBE32::from_aead(cipher, (&nonce).into())),
+            input: vec![],
+            output: vec![],
+            position: 0,
+        };
+        reader.next_chunk()?;
+
+        Ok(reader)
+    }
+
+    fn next_chunk(&mut self) -> io::Result<()> {
+        // One byte past a full chunk tells whether more chunks follow
+        let capacity = CHUNK_SIZE + TAG_SIZE + 1;
+        let mut filled = self.input.len();
+        self.input.resize(capacity, 0);
+
+        while filled < capacity {
+            let n = self.inner.read(&mut self.input[filled..])?;
+            if n == 0 {
+                break;
+            }
+            filled += n;
+        }
+        self.input.truncate(filled);
+
+        let decrypt_error = || {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Failed to decrypt backup, the passphrase is wrong or the archive is corrupted",
+            )
+        };
+
+        if filled == capacity {
+            let stream = self.stream.as_mut().ok_or_else(decrypt_error)?;
+            self.output = stream
+                .decrypt_next(&self.input[..CHUNK_SIZE + TAG_SIZE])
+                .map_err(|_| decrypt_error())?;
+            self.input.drain(..CHUNK_SIZE + TAG_SIZE);
+        } else {
+            let stream = self.stream.take().ok_or_else(decrypt_error)?;
+            self.output = stream
+                .decrypt_last(self.input.as_slice())
+                .map_err(|_| decrypt_error())?;
+            self.input.clear();
+        }
+
+        self.position = 0;
+
+        Ok(())
+    }
+}
+
+impl<R: Read + Send + Unpin> Read for PassphraseReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if self.position < self.output.len() {
+                let n = buf.len().min(self.output.len() - self.position);
+                buf[..n].copy_from_slice(&self.output[self.position..self.position + n]);
+                self.position += n;
+                return Ok(n);
+            }
+
+            if self.stream.is_none() {
+                return Ok(0);
+            }
+
+            self.next_chunk()?;
+        }
+    }
+}
+
+pub enum Encryptor<W: Write + Send + Unpin> {
+    Passphrase(PassphraseWriter<W>),
+    Age(age::stream::StreamWriter<W>),
+    None(W),
+}
+
+impl<W: Write + Send + Unpin> Encryptor<W> {
+    pub fn new(writer: W, config: &EncryptionConfig) -> Result<Self> {
+        match config.format()? {
+            Some(EncryptionFormat::Passphrase) => {
+                let passphrase = config.passphrase.as_deref().unwrap_or_default();
+                Ok(Encryptor::Passphrase(PassphraseWriter::new(
+                    writer, passphrase,
+                )?))
+            }
+            Some(EncryptionFormat::Age) => {
+                let recipients = config.parse_recipients()?;
+                let encryptor = age::Encryptor::with_recipients(
+                    recipients.iter().map(|r| r as &dyn age::Recipient),
+                )
+                .map_err(|e| anyhow!("Failed to encrypt for age recipients: {}", e))?;
+
+                Ok(Encryptor::Age(encryptor.wrap_output(writer)?))
+            }
+            None => Ok(Encryptor::None(writer)),
+        }
+    }
+
+    pub fn finish(self) -> io::Result<W> {
+        match self {
+            Encryptor::Passphrase(writer) => writer.finish(),
+            Encryptor::Age(writer) => writer.finish(),
+            Encryptor::None(writer) => Ok(writer),
+        }
+    }
+}
+
+impl<W: Write + Send + Unpin> Write for Encryptor<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encryptor::Passphrase(ref mut writer) => writer.write(buf),
+            Encryptor::Age(ref mut writer) => writer.write(buf),
+            Encryptor::None(ref mut writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encryptor::Passphrase(ref mut writer) => writer.flush(),
+            Encryptor::Age(ref mut writer) => writer.flush(),
+            Encryptor::None(ref mut writer) => writer.flush(),
+        }
+    }
+}
+
+pub enum Decryptor<R: Read + Send + Unpin> {
+    Passphrase(PassphraseReader<R>),
+    Age(age::stream::StreamReader<R>),
+    None(R),
+}
+
+impl<R: Read + Send + Unpin> Decryptor<R> {
+    pub fn new(
+        reader: R,
+        format: Option<EncryptionFormat>,
+        config: &EncryptionConfig,
+    ) -> Result<Self> {
+        match format {
+            Some(EncryptionFormat::Passphrase) => {
+                let passphrase = config.passphrase.as_deref().ok_or_else(|| {
+                    anyhow!("Backup is encrypted with a passphrase, provide it to restore")
+                })?;
+
+                Ok(Decryptor::Passphrase(PassphraseReader::new(
+                    reader, passphrase,
+                )?))
+            }
+            Some(EncryptionFormat::Age) => {
+                let identities = config.parse_identities()?;
+                if identities.is_empty() {
+                    return Err(anyhow!(
+                        "Backup is encrypted for age recipients, provide an identity to restore"
+                    ));
+                }
+
+                let decryptor = age::Decryptor::new(reader)
+                    .map_err(|e| anyhow!("Failed to read encrypted backup: {}", e))?;
+                let reader = decryptor
+                    .decrypt(identities.iter().map(|i| i as &dyn age::Identity))
+                    .map_err(|e| anyhow!("Failed to decrypt backup: {}", e))?;
+
+                Ok(Decryptor::Age(reader))
+            }
+            None => Ok(Decryptor::None(reader)),
+        }
+    }
+
+    /// Peeks at the buffered bytes of the reader to detect encryption without consuming them.
+    pub fn sniff_format(reader: &mut R) -> io::Result<Option<EncryptionFormat>>
+    where
+        R: BufRead,
+    {
+        let signature = reader.fill_buf()?;
+        Ok(EncryptionFormat::from_signature(signature))
+    }
+}
+
+impl<R: Read + Send + Unpin> Read for Decryptor<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Decryptor::Passphrase(ref mut reader) => reader.read(buf),
+            Decryptor::Age(ref mut reader) => reader.read(buf),
+            Decryptor::None(ref mut reader) => reader.read(buf),
+        }
+    }
+}
+
+#[cfg(test)]
+mod encryption_test {
+    use std::io::{BufReader, Cursor, Read, Write};
+
+    use age::secrecy::ExposeSecret;
+
+    use super::{Decryptor, EncryptionConfig, EncryptionFormat, Encryptor, CHUNK_SIZE};
+
+    fn encrypt(config: &EncryptionConfig, plaintext: &[u8]) -> Vec<u8> {
+        let mut encryptor = Encryptor::new(vec![], config).expect("Failed to create encryptor");
+        encryptor.write_all(plaintext).expect("Failed to write");
+        encryptor.finish().expect("Failed to finish encryptor")
+    }
+
+    fn decrypt(config: &EncryptionConfig, ciphertext: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        let mut reader = BufReader::new(Cursor::new(ciphertext));
+        let format = Decryptor::sniff_format(&mut reader)?;
+        let mut decryptor = Decryptor::new(reader, format, config)?;
+
+        let mut plaintext = vec![];
+        decryptor.read_to_end(&mut plaintext)?;
+        Ok(plaintext)
+    }
+
+    #[test]
+    fn passphrase_round_trip() {
+        let config = EncryptionConfig {
+            passphrase: Some("correct horse battery staple".into()),
+            ..Default::default()
+        };
+
+        for size in [0, 10, CHUNK_SIZE, CHUNK_SIZE * 2 + 3] {
+            let plaintext: Vec<u8> = (0..size).map(|i| (i % 251) as u8).collect();
+            let ciphertext = encrypt(&config, &plaintext);
+
+            assert_eq!(
+                EncryptionFormat::from_signature(&ciphertext),
+                Some(EncryptionFormat::Passphrase)
+            );
+            assert_eq!(decrypt(&config, ciphertext).unwrap(), plaintext);
+        }
+    }
+
+    #[test]
+    fn wrong_passphrase() {
+        let config = EncryptionConfig {
+            passphrase: Some("secret".into()),
+            ..Default::default()
+        };
+        let ciphertext = encrypt(&config, b"SELECT 1;");
+
+        let wrong = EncryptionConfig {
+            passphrase: Some("not the secret".into()),
+            ..Default::default()
+        };
+        let error = decrypt(&wrong, ciphertext.clone()).unwrap_err();
+        assert!(error.to_string().contains("passphrase is wrong"));
+
+        let missing = decrypt(&EncryptionConfig::default(), ciphertext).unwrap_err();
+        assert!(missing.to_string().contains("provide it"));
+    }
+
+    #[test]
+    fn excessive_costs() {
+        let config = EncryptionConfig {
+            passphrase: Some("secret".into()),
+            ..Default::default()
+        };
+        let ciphertext = encrypt(&config, b"SELECT 1;");
+
+        // m_cost, t_cost and p_cost follow the magic
+        for offset in [8, 12, 16] {
+            let mut tampered = ciphertext.clone();
+            tampered[offset..offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+
+            let error = decrypt(&config, tampered).unwrap_err();
+            assert!(
+                error.to_string().contains("above the accepted maximum"),
+                "{}",
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn identities_only() {
+        let identity = age::x25519::Identity::generate();
+        let config = EncryptionConfig {
+            identities: vec![identity.to_string().expose_secret().to_string()],
+            ..Default::default()
+        };
+
+        assert!(
+            Encryptor::new(vec![], &config).is_err(),
+            "Identities alone shouldn't write an unencrypted backup"
+        );
+        assert_eq!(EncryptionConfig::default().format().unwrap(), None);
+    }
+
+    #[test]
+    fn age_round_trip() {
+        let identity = age::x25519::Identity::generate();
+        let config = EncryptionConfig {
+            recipients: vec![identity.to_public().to_string()],
+            identities: vec![identity.to_string().expose_secret().to_string()],
+            ..Default::default()
+        };
+
+        let ciphertext = encrypt(&config, b"SELECT 1;");
+        assert_eq!(
+            EncryptionFormat::from_signature(&ciphertext),
+            Some(EncryptionFormat::Age)
+        );
+        assert_eq!(decrypt(&config, ciphertext.clone()).unwrap(), b"SELECT 1;");
+
+        let other = EncryptionConfig {
+            identities: vec![age::x25519::Identity::generate()
+                .to_string()
+                .expose_secret()
+                .to_string()],
+            ..Default::default()
+        };
+        assert!(decrypt(&other, ciphertext).is_err());
+    }
+}
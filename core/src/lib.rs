@@ -1,29 +1,54 @@
-use std::io::Write;
+use std::io::{BufReader, Read, Write};
 
 use anyhow::{anyhow, Result};
-use common::get_default_backup_name;
+use checksum::{verify_checksum, ChecksumAlgorithm, CountingWriter, HashingWriter};
+use chrono::Utc;
+use common::get_default_backup_path;
 use compression::{CompressionFormat, Compressor, Decompressor};
-use databases::DatabaseConnection;
-use flate2::Compression;
+use copy::{check_copy_target, connect_target, pipe, stream_into, CopyOptions, TeeWriter};
+use databases::{ConnectionType, DatabaseConfig, DatabaseConnection, DumpFilters, DumpFormat};
+use encryption::{Decryptor, EncryptionConfig, Encryptor};
+use manifest::BackupManifest;
+use masking::{sql::SqlDialect, Masker, MaskingConfig, MaskingReader, MaskingWriter};
 use serde::{Deserialize, Serialize};
 use storage::provider::{ListOptions, StorageProvider};
+use verify::{compare_table_stats, scratch_database_name, RowCountingWriter, VerifyReport};
 
 use crate::storage::Entry;
 
 pub mod archives;
+pub mod checksum;
 pub mod common;
 pub mod compression;
+pub mod copy;
 pub mod databases;
+pub mod encryption;
 pub mod folders;
+pub mod manifest;
+pub mod masking;
+pub mod retention;
 pub mod storage;
 mod test_utils;
 mod tests;
+pub mod verify;
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct BackupOptions {
-    name: Option<String>,
-    compression_format: Option<CompressionFormat>,
-    compression_level: Option<u32>,
+    pub name: Option<String>,
+    pub compression_format: Option<CompressionFormat>,
+    pub compression_level: Option<u32>,
+    /// Workers compressing the backup, one when not set. Only zstd supports several.
+    pub compression_threads: Option<u32>,
+    pub encryption: Option<EncryptionConfig>,
+    pub checksum_algorithm: Option<ChecksumAlgorithm>,
+    /// Plain SQL when not set, archive formats are only supported by PostgreSQL.
+    pub dump_format: Option<DumpFormat>,
+    /// Parallel dump jobs, only supported by the directory format.
+    pub jobs: Option<u16>,
+    /// Everything is dumped when not set.
+    pub filters: Option<DumpFilters>,
+    /// Masks the dump before it's compressed, only for plain SQL dumps.
+    pub masking: Option<MaskingConfig>,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -31,8 +56,26 @@ pub struct RestoreOptions {
     pub name: String,
     pub compression_format: Option<CompressionFormat>,
     pub drop_database_first: Option<bool>,
+    pub encryption: Option<EncryptionConfig>,
+    /// Parallel restore jobs for PostgreSQL archives.
+    pub jobs: Option<u16>,
+    /// Masks the dump before it's restored, only for plain SQL dumps.
+    pub masking: Option<MaskingConfig>,
+    /// Server to restore into, the one of the backed up database when not set.
+    pub target: Option<DatabaseConfig>,
+    /// Database to restore into, created when missing. Defaults to the one of the target.
+    pub target_database: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct VerifyOptions {
+    pub name: String,
+    /// Server to restore into, the one of the backed up database when not set.
+    pub target: Option<DatabaseConfig>,
+    pub encryption: Option<EncryptionConfig>,
 }
 
+#[derive(Clone)]
 pub struct DbBkp {
     database_connection: DatabaseConnection,
     storage_provider: StorageProvider,
@@ -60,39 +103,154 @@ impl DbBkp {
     }
 
     pub async fn backup_with(&self, options: Option<BackupOptions>) -> Result<String> {
-        let options = match options {
-            Some(options) => options,
-            None => BackupOptions {
-                name: None,
-                compression_format: None,
-                compression_level: None,
-            },
-        };
+        self.backup_to(options.unwrap_or_default(), None).await
+    }
+
+    /// Stores a backup, the dump is also written to `tee` once masked.
+    async fn backup_to(
+        &self,
+        options: BackupOptions,
+        tee: Option<&mut (dyn Write + Send + Unpin)>,
+    ) -> Result<String> {
+        let encryption = options.encryption.unwrap_or_default();
+        let encryption_format = encryption.format()?;
 
         let compression_format = options
             .compression_format
             .unwrap_or(CompressionFormat::Gzip);
-        let compression_level = options.compression_level.unwrap_or(9);
+        let compression_level = compression_format.check_level(
+            options
+                .compression_level
+                .unwrap_or(compression_format.default_level()),
+        )?;
+        let compression_threads = options.compression_threads.unwrap_or(1);
+        if compression_threads == 0 {
+            return Err(anyhow!("At least one compression thread is needed"));
+        }
+        if compression_threads > 1 && compression_format != CompressionFormat::Zstd {
+            return Err(anyhow!("Only zstd compression can use several threads"));
+        }
+        let dump_format = options.dump_format.unwrap_or_default();
+        if dump_format != DumpFormat::Plain
+            && self.database_connection.config.connection_type != ConnectionType::PostgreSql
+        {
+            return Err(anyhow!(
+                "The {} dump format is only supported by PostgreSQL",
+                dump_format
+            ));
+        }
+        if options.jobs.is_some() && dump_format != DumpFormat::Directory {
+            return Err(anyhow!(
+                "Parallel dump jobs are only supported by the directory format"
+            ));
+        }
+
+        let masker = match options.masking {
+            Some(masking) if dump_format == DumpFormat::Plain => Some(Masker::new(
+                masking,
+                SqlDialect::for_connection_type(&self.database_connection.config.connection_type)?,
+            )?),
+            Some(_) => return Err(anyhow!("Only plain dumps can be masked")),
+            None => None,
+        };
+        let masked = masker.is_some();
+
         let name = match options.name {
             Some(name) => name,
-            None => get_default_backup_name(&self.database_connection.config, &compression_format),
+            None => get_default_backup_path(
+                &self.database_connection.config,
+                &compression_format,
+                encryption_format.as_ref(),
+            ),
         };
 
-        let writer = self.storage_provider.create_writer(&name).await?;
-        let mut compressed_writed = Compressor::new(
+        let started_at = Utc::now();
+        let metadata = self.database_connection.connection.get_metadata().await?;
+        let filters = options.filters.unwrap_or_default();
+
+        let checksum_algorithm = options.checksum_algorithm.unwrap_or_default();
+        let writer = HashingWriter::with_algorithm(
+            self.storage_provider.create_writer(&name).await?,
+            checksum_algorithm,
+        );
+        let writer = Encryptor::new(writer, &encryption)?;
+        let mut compressed_writed = CountingWriter::new(Compressor::new(
             writer,
             compression_format,
-            Compression::new(compression_level),
-        );
+            compression_level,
+            compression_threads,
+        )?);
 
-        self.database_connection
-            .connection
-            .backup(&mut compressed_writed)
-            .await?;
+        let dump_options = databases::BackupOptions {
+            format: dump_format,
+            jobs: options.jobs,
+            filters: filters.clone(),
+        };
+
+        let dump_writer: Box<dyn Write + Send + Unpin + '_> = match tee {
+            Some(tee) => Box::new(TeeWriter::new(&mut compressed_writed, tee)),
+            None => Box::new(&mut compressed_writed),
+        };
+        // Only plain SQL dumps have rows to count for verify
+        let counted_dialect = match dump_format {
+            DumpFormat::Plain => {
+                SqlDialect::for_connection_type(&self.database_connection.config.connection_type)
+                    .ok()
+            }
+            _ => None,
+        };
+        let mut dump_writer = RowCountingWriter::new(dump_writer, counted_dialect);
 
-        let mut writer = compressed_writed.finish()?;
+        match masker {
+            Some(masker) => {
+                let mut masking_writer = MaskingWriter::new(&mut dump_writer, masker);
+                self.database_connection
+                    .connection
+                    .backup_with_options(&mut masking_writer, dump_options)
+                    .await?;
+                masking_writer.finish()?;
+            }
+            None => {
+                self.database_connection
+                    .connection
+                    .backup_with_options(&mut dump_writer, dump_options)
+                    .await?
+            }
+        }
+        let table_stats = dump_writer.finish();
+
+        let uncompressed_size = compressed_writed.bytes_written();
+        let (mut writer, compressed_size, checksum) =
+            compressed_writed.into_inner().finish()?.finish()?.finish();
         writer.flush()?;
 
+        let manifest = BackupManifest {
+            name: name.clone(),
+            dbkp_version: env!("CARGO_PKG_VERSION").to_string(),
+            database: BackupManifest::redact(&self.database_connection.config),
+            server_version: metadata.version,
+            dump_format,
+            filters,
+            masked,
+            compression_format,
+            compression_level,
+            encryption: encryption_format,
+            uncompressed_size,
+            compressed_size,
+            checksum_algorithm,
+            checksum,
+            table_stats,
+            started_at,
+            finished_at: Utc::now(),
+        };
+
+        let mut manifest_writer = self
+            .storage_provider
+            .create_writer(&BackupManifest::path_for(&name))
+            .await?;
+        manifest_writer.write_all(&manifest.to_bytes()?)?;
+        manifest_writer.flush()?;
+
         Ok(name)
     }
 
@@ -100,23 +258,122 @@ impl DbBkp {
         self.backup_with(None).await
     }
 
+    /// Copies the database into another one and stores the dump as a backup on the way. The
+    /// filters and masking of the copy apply to the backup too, it must be a plain dump.
+    pub async fn copy(
+        &self,
+        options: CopyOptions,
+        backup: Option<BackupOptions>,
+    ) -> Result<String> {
+        let backup = BackupOptions {
+            filters: options.filters.clone(),
+            masking: options.masking.clone(),
+            ..backup.unwrap_or_default()
+        };
+        if backup.dump_format.unwrap_or_default() != DumpFormat::Plain {
+            return Err(anyhow!("Only plain dumps can be copied"));
+        }
+
+        let target = connect_target(
+            &self.database_connection,
+            options.target.as_ref(),
+            options.target_database.as_deref(),
+        )
+        .await?;
+        check_copy_target(&self.database_connection.config, &target.config)?;
+
+        let (mut writer, reader) = pipe();
+        let this = self.clone();
+        let dump = async move {
+            let name = this.backup_to(backup, Some(&mut writer)).await?;
+            writer.close();
+
+            Ok(name)
+        };
+
+        stream_into(
+            &target,
+            databases::RestoreOptions {
+                drop_database_first: options.drop_database_first.unwrap_or(false),
+                jobs: None,
+            },
+            reader,
+            dump,
+        )
+        .await
+    }
+
     pub async fn restore(&self, options: RestoreOptions) -> Result<()> {
-        let compression_format = options
-            .compression_format
-            .unwrap_or(CompressionFormat::Gzip);
+        let target_connection = connect_target(
+            &self.database_connection,
+            options.target.as_ref(),
+            options.target_database.as_deref(),
+        )
+        .await?;
+
+        self.restore_to(&target_connection, options).await
+    }
 
-        let reader = self.storage_provider.create_reader(&options.name).await?;
-        let mut compressed_reader = Decompressor::new(reader, compression_format);
+    async fn restore_to(
+        &self,
+        database_connection: &DatabaseConnection,
+        options: RestoreOptions,
+    ) -> Result<()> {
+        let mut reader = BufReader::new(self.storage_provider.create_reader(&options.name).await?);
+        let manifest = self.storage_provider.read_manifest(&options.name).await?;
+        let encryption = options.encryption.unwrap_or_default();
 
-        self.database_connection
+        let encryption_format = match &manifest {
+            Some(manifest) => manifest.encryption,
+            None => Decryptor::sniff_format(&mut reader)?,
+        };
+        let mut reader = BufReader::new(Decryptor::new(reader, encryption_format, &encryption)?);
+
+        // Explicit option first, then the manifest, then the magic bytes and finally the extension.
+        let compression_format = match options.compression_format {
+            Some(compression_format) => compression_format,
+            None => match &manifest {
+                Some(manifest) => manifest.compression_format,
+                None => match Decompressor::sniff_format(&mut reader)? {
+                    Some(compression_format) => compression_format,
+                    // Raw deflate has no magic bytes, only the extension tells it apart from plain SQL
+                    None => CompressionFormat::from_extension(&options.name)
+                        .filter(|format| *format == CompressionFormat::Deflate)
+                        .unwrap_or(CompressionFormat::None),
+                },
+            },
+        };
+
+        let compressed_reader = Decompressor::new(reader, compression_format)?;
+
+        let mut reader: Box<dyn Read + Send + Unpin> = match options.masking {
+            Some(masking) => {
+                if manifest.is_some_and(|manifest| manifest.dump_format != DumpFormat::Plain) {
+                    return Err(anyhow!("Only plain dumps can be masked"));
+                }
+
+                let masker = Masker::new(
+                    masking,
+                    SqlDialect::for_connection_type(&database_connection.config.connection_type)?,
+                )?;
+                Box::new(MaskingReader::new(
+                    BufReader::new(compressed_reader),
+                    masker,
+                ))
+            }
+            None => Box::new(compressed_reader),
+        };
+
+        database_connection
             .connection
             .restore_with_options(
-                &mut compressed_reader,
+                &mut reader,
                 databases::RestoreOptions {
                     drop_database_first: match options.drop_database_first {
                         Some(drop) => drop,
                         None => false,
                     },
+                    jobs: options.jobs,
                 },
             )
             .await?;
@@ -124,6 +381,93 @@ impl DbBkp {
         Ok(())
     }
 
+    /// Restores a backup into a scratch database and compares its row counts with the manifest.
+    /// The scratch database is always dropped, even when the restore fails.
+    pub async fn verify(&self, options: VerifyOptions) -> Result<VerifyReport> {
+        let manifest = self.storage_provider.read_manifest(&options.name).await?;
+
+        let checksum = match manifest {
+            Some(_) => Some(verify_checksum(&self.storage_provider, &options.name).await?),
+            None => None,
+        };
+
+        if let Some(checksum) = &checksum {
+            if !checksum.is_valid() {
+                return Ok(VerifyReport {
+                    name: options.name,
+                    scratch_database: String::new(),
+                    checksum: Some(checksum.clone()),
+                    has_table_stats: false,
+                    tables: vec![],
+                });
+            }
+        }
+
+        let target_connection = match &options.target {
+            Some(target) => {
+                &self
+                    .database_connection
+                    .connect_server(target.clone())
+                    .await?
+            }
+            None => &self.database_connection,
+        };
+
+        let source_database = match &manifest {
+            Some(manifest) => manifest.database.database.clone(),
+            None => self.database_connection.config.database.clone(),
+        };
+        let scratch_database = scratch_database_name(&source_database);
+
+        target_connection
+            .connection
+            .create_database(&scratch_database)
+            .await?;
+
+        let mut scratch_config = target_connection.config.clone();
+        scratch_config.database = scratch_database.clone();
+
+        let restored_stats = async {
+            let scratch_connection = target_connection.connect_server(scratch_config).await?;
+
+            self.restore_to(
+                &scratch_connection,
+                RestoreOptions {
+                    name: options.name.clone(),
+                    compression_format: None,
+                    drop_database_first: Some(false),
+                    encryption: options.encryption.clone(),
+                    jobs: None,
+                    masking: None,
+                    target: None,
+                    target_database: None,
+                },
+            )
+            .await?;
+
+            scratch_connection.connection.get_table_stats().await
+        }
+        .await;
+
+        let dropped = target_connection
+            .connection
+            .drop_database(&scratch_database)
+            .await;
+
+        let restored_stats = restored_stats?;
+        dropped?;
+
+        let expected_stats = manifest.map(|m| m.table_stats).unwrap_or_default();
+
+        Ok(VerifyReport {
+            name: options.name,
+            scratch_database,
+            checksum,
+            has_table_stats: !expected_stats.is_empty(),
+            tables: compare_table_stats(&expected_stats, &restored_stats),
+        })
+    }
+
     pub async fn list_with_options(&self, options: ListOptions) -> Result<Vec<Entry>> {
         let entries = self.storage_provider.list_with_options(options).await?;
         Ok(entries)
@@ -0,0 +1,70 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    checksum::ChecksumAlgorithm,
+    compression::CompressionFormat,
+    databases::{version::Version, DatabaseConfig, DumpFilters, DumpFormat, TableStats},
+    encryption::EncryptionFormat,
+};
+
+pub const MANIFEST_SUFFIX: &str = ".manifest.json";
+
+/// Sidecar document written next to every backup archive.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub name: String,
+    pub dbkp_version: String,
+    pub database: DatabaseConfig,
+    pub server_version: Version,
+    #[serde(default)]
+    pub dump_format: DumpFormat,
+    /// Filters the dump was restricted with, the manifest has no table stats when there are any.
+    #[serde(default)]
+    pub filters: DumpFilters,
+    /// Whether the dump went through a masking config, which isn't recorded.
+    #[serde(default)]
+    pub masked: bool,
+    pub compression_format: CompressionFormat,
+    pub compression_level: u32,
+    #[serde(default)]
+    pub encryption: Option<EncryptionFormat>,
+    pub uncompressed_size: u64,
+    pub compressed_size: u64,
+    #[serde(default)]
+    pub checksum_algorithm: ChecksumAlgorithm,
+    pub checksum: String,
+    /// Rows of each table counted in the dump, used by `DbBkp::verify`. Only plain SQL dumps
+    /// have them.
+    #[serde(default)]
+    pub table_stats: Vec<TableStats>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+}
+
+impl BackupManifest {
+    pub fn path_for(backup_path: &str) -> String {
+        format!("{}{}", backup_path, MANIFEST_SUFFIX)
+    }
+
+    pub fn is_manifest(path: &str) -> bool {
+        path.ends_with(MANIFEST_SUFFIX)
+    }
+
+    /// Copies the database configuration without any secret.
+    pub fn redact(database_config: &DatabaseConfig) -> DatabaseConfig {
+        let mut database_config = database_config.clone();
+        database_config.password = None;
+        database_config.ssh = database_config.ssh.map(|ssh| ssh.redact());
+        database_config
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serde_json::to_vec_pretty(self).map_err(|e| anyhow!("Failed to serialize manifest: {}", e))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        serde_json::from_slice(bytes).map_err(|e| anyhow!("Failed to parse manifest: {}", e))
+    }
+}
@@ -0,0 +1,389 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::{self, BufRead, Read, Write},
+    path::Path,
+};
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use sql::{
+    decode_copy_field, decode_literal, encode_copy_field, encode_literal, parse_column_definition,
+    parse_copy, parse_create_table, parse_insert_header, parse_insert_values, SqlDialect,
+    StatementScanner,
+};
+
+pub mod sql;
+mod tests;
+
+/// How the values of a column are replaced. `NULL` values are always kept.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaskRule {
+    /// `user_<hash>@example.com`.
+    Email,
+    /// Hex digest of the value.
+    Hash,
+    Null,
+    /// Digits and letters are replaced by other digits and letters of the same case, everything
+    /// else is kept, so phone numbers or postal codes keep their shape.
+    KeepFormat,
+    Fixed(String),
+}
+
+impl MaskRule {
+    fn apply(&self, value: Option<&[u8]>, key: &[u8; 32]) -> Option<Vec<u8>> {
+        let value = value?;
+
+        match self {
+            MaskRule::Email => {
+                Some(format!("user_{}@example.com", &digest(key, value)[..12]).into())
+            }
+            MaskRule::Hash => Some(digest(key, value)[..32].into()),
+            MaskRule::Null => None,
+            MaskRule::KeepFormat => {
+                let mut random = vec![0u8; value.len()];
+                blake3::Hasher::new_keyed(key)
+                    .update(value)
+                    .finalize_xof()
+                    .fill(&mut random);
+
+                Some(
+                    value
+                        .iter()
+                        .zip(random)
+                        .map(|(byte, random)| match byte {
+                            b'0'..=b'9' => b'0' + random % 10,
+                            b'a'..=b'z' => b'a' + random % 26,
+                            b'A'..=b'Z' => b'A' + random % 26,
+                            _ => *byte,
+                        })
+                        .collect(),
+                )
+            }
+            MaskRule::Fixed(fixed) => Some(fixed.clone().into_bytes()),
+        }
+    }
+}
+
+/// Only depends on the value, so a value masks the same way in every table and foreign keys
+/// still match.
+fn digest(key: &[u8; 32], value: &[u8]) -> String {
+    blake3::keyed_hash(key, value).to_hex().to_string()
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MaskingConfig {
+    /// Secret the masked values are derived from, the same seed gives the same values.
+    #[serde(default)]
+    pub seed: Option<String>,
+    /// Rules by table then column. Tables are matched with or without their schema.
+    pub tables: BTreeMap<String, BTreeMap<String, MaskRule>>,
+}
+
+impl MaskingConfig {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read masking config {}", path.display()))?;
+
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid masking config {}", path.display()))
+    }
+
+    fn table_rules(&self, table: &str) -> Option<&BTreeMap<String, MaskRule>> {
+        self.tables.get(table).or_else(|| {
+            let (_, name) = table.rsplit_once('.')?;
+            self.tables.get(name)
+        })
+    }
+}
+
+enum State {
+    Sql,
+    CreateTable {
+        table: String,
+        columns: Vec<String>,
+    },
+    /// Rule of every column of the `COPY` block, empty when nothing is masked.
+    Copy(Vec<Option<MaskRule>>),
+    Insert {
+        statement: Vec<u8>,
+        scanner: StatementScanner,
+    },
+}
+
+/// Rewrites a plain SQL dump line by line, replacing the values of the masked columns.
+pub struct Masker {
+    config: MaskingConfig,
+    key: [u8; 32],
+    dialect: SqlDialect,
+    /// Columns of the tables created earlier in the dump, for inserts that don't list them.
+    columns: HashMap<String, Vec<String>>,
+    state: State,
+}
+
+impl Masker {
+    pub fn new(config: MaskingConfig, dialect: SqlDialect) -> Result<Self> {
+        let seed = match config.seed.as_deref() {
+            Some(seed) if !seed.is_empty() => seed,
+            _ => {
+                return Err(anyhow!(
+                    "Masking needs a seed, the same seed keeps masked values consistent"
+                ))
+            }
+        };
+
+        Ok(Self {
+            key: blake3::derive_key("dbkp masking", seed.as_bytes()),
+            config,
+            dialect,
+            columns: HashMap::new(),
+            state: State::Sql,
+        })
+    }
+
+    fn column_rules(&self, table: &str, columns: &[String]) -> Vec<Option<MaskRule>> {
+        let rules = match self.config.table_rules(table) {
+            Some(rules) => rules,
+            None => return vec![],
+        };
+
+        columns
+            .iter()
+            .map(|column| rules.get(column).cloned())
+            .collect()
+    }
+
+    /// Masks one line, with its newline if it has one, into `out`.
+    pub fn process_line(&mut self, line: &[u8], out: &mut Vec<u8>) -> Result<()> {
+        match &mut self.state {
+            State::Sql => {}
+            State::CreateTable { table, columns } => {
+                let text = String::from_utf8_lossy(line);
+                if text.trim_start().starts_with(')') {
+                    self.columns
+                        .insert(std::mem::take(table), std::mem::take(columns));
+                    self.state = State::Sql;
+                } else if let Some(column) = parse_column_definition(&text) {
+                    columns.push(column);
+                }
+                out.extend_from_slice(line);
+                return Ok(());
+            }
+            State::Copy(rules) => {
+                let content = line.strip_suffix(b"\n").unwrap_or(line);
+                if content == b"\\." {
+                    self.state = State::Sql;
+                } else if rules.iter().any(Option::is_some) {
+                    for (index, field) in content.split(|b| *b == b'\t').enumerate() {
+                        if index > 0 {
+                            out.push(b'\t');
+                        }
+                        match rules.get(index).and_then(Option::as_ref) {
+                            Some(rule) => encode_copy_field(
+                                rule.apply(decode_copy_field(field).as_deref(), &self.key)
+                                    .as_deref(),
+                                out,
+                            ),
+                            None => out.extend_from_slice(field),
+                        }
+                    }
+                    if content.len() < line.len() {
+                        out.push(b'\n');
+                    }
+                    return Ok(());
+                }
+                out.extend_from_slice(line);
+                return Ok(());
+            }
+            State::Insert { statement, scanner } => {
+                statement.extend_from_slice(line);
+                if scanner.feed(line, self.dialect) {
+                    let statement = std::mem::take(statement);
+                    self.state = State::Sql;
+                    self.mask_insert(&statement, out)?;
+                }
+                return Ok(());
+            }
+        }
+
+        if line.starts_with(b"COPY ") {
+            if let Some((table, columns)) = parse_copy(&String::from_utf8_lossy(line)) {
+                self.state = State::Copy(self.column_rules(&table, &columns));
+            }
+        } else if line.starts_with(b"CREATE TABLE ") {
+            if let Some(table) = parse_create_table(&String::from_utf8_lossy(line)) {
+                self.state = State::CreateTable {
+                    table,
+                    columns: vec![],
+                };
+            }
+        } else if line.starts_with(b"INSERT INTO ") {
+            let mut scanner = StatementScanner::default();
+            if scanner.feed(line, self.dialect) {
+                return self.mask_insert(line, out);
+            }
+            self.state = State::Insert {
+                statement: line.to_vec(),
+                scanner,
+            };
+            return Ok(());
+        }
+
+        out.extend_from_slice(line);
+        Ok(())
+    }
+
+    /// Fails when the dump stops in the middle of a statement, it can't be masked.
+    pub fn finish(&self) -> Result<()> {
+        match self.state {
+            State::Insert { .. } => Err(anyhow!(
+                "The dump ends in the middle of an INSERT statement"
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn mask_insert(&self, statement: &[u8], out: &mut Vec<u8>) -> Result<()> {
+        let header = match parse_insert_header(statement, self.dialect) {
+            Some(header) => header,
+            None => {
+                out.extend_from_slice(statement);
+                return Ok(());
+            }
+        };
+
+        if self.config.table_rules(&header.table).is_none() {
+            out.extend_from_slice(statement);
+            return Ok(());
+        }
+
+        let columns = match header.columns.as_ref().or(self.columns.get(&header.table)) {
+            Some(columns) => columns,
+            None => {
+                return Err(anyhow!(
+                    "Can't mask the inserts into {}, the dump doesn't tell its columns",
+                    header.table
+                ))
+            }
+        };
+        let rules = self.column_rules(&header.table, columns);
+
+        let mut position = 0;
+        for values in parse_insert_values(statement, header.values_start, self.dialect)? {
+            for (range, rule) in values.into_iter().zip(&rules) {
+                if let Some(rule) = rule {
+                    out.extend_from_slice(&statement[position..range.start]);
+                    let value = decode_literal(&statement[range.clone()], self.dialect);
+                    encode_literal(
+                        rule.apply(value.as_deref(), &self.key).as_deref(),
+                        self.dialect,
+                        out,
+                    );
+                    position = range.end;
+                }
+            }
+        }
+        out.extend_from_slice(&statement[position..]);
+
+        Ok(())
+    }
+}
+
+/// Masks a dump on its way to the backup.
+pub struct MaskingWriter<W: Write> {
+    inner: W,
+    masker: Masker,
+    pending: Vec<u8>,
+    output: Vec<u8>,
+}
+
+impl<W: Write> MaskingWriter<W> {
+    pub fn new(inner: W, masker: Masker) -> Self {
+        Self {
+            inner,
+            masker,
+            pending: vec![],
+            output: vec![],
+        }
+    }
+
+    /// Masks the last line when the dump doesn't end with a newline.
+    pub fn finish(mut self) -> Result<W> {
+        let line = std::mem::take(&mut self.pending);
+        self.masker.process_line(&line, &mut self.output)?;
+        self.masker.finish()?;
+        self.inner.write_all(&self.output)?;
+
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for MaskingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let scanned = self.pending.len();
+        self.pending.extend_from_slice(buf);
+
+        if let Some(last_newline) = self.pending[scanned..].iter().rposition(|b| *b == b'\n') {
+            let lines: Vec<u8> = self.pending.drain(..scanned + last_newline + 1).collect();
+            for line in lines.split_inclusive(|b| *b == b'\n') {
+                self.masker
+                    .process_line(line, &mut self.output)
+                    .map_err(io::Error::other)?;
+            }
+            self.inner.write_all(&self.output)?;
+            self.output.clear();
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Masks a dump on its way from the backup to the database.
+pub struct MaskingReader<R: BufRead> {
+    inner: R,
+    masker: Masker,
+    line: Vec<u8>,
+    output: Vec<u8>,
+    position: usize,
+}
+
+impl<R: BufRead> MaskingReader<R> {
+    pub fn new(inner: R, masker: Masker) -> Self {
+        Self {
+            inner,
+            masker,
+            line: vec![],
+            output: vec![],
+            position: 0,
+        }
+    }
+}
+
+impl<R: BufRead> Read for MaskingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.output.len() {
+            self.line.clear();
+            if self.inner.read_until(b'\n', &mut self.line)? == 0 {
+                self.masker.finish().map_err(io::Error::other)?;
+                return Ok(0);
+            }
+
+            self.output.clear();
+            self.position = 0;
+            self.masker
+                .process_line(&self.line, &mut self.output)
+                .map_err(io::Error::other)?;
+        }
+
+        let n = buf.len().min(self.output.len() - self.position);
+        buf[..n].copy_from_slice(&self.output[self.position..self.position + n]);
+        self.position += n;
+
+        Ok(n)
+    }
+}
@@ -0,0 +1,442 @@
+//! Just enough SQL to find the values of plain dumps: the `COPY` blocks of `pg_dump`, and the
+//! `INSERT` statements of `mysqldump` and `pg_dump --inserts`.
+
+use std::ops::Range;
+
+use anyhow::{anyhow, Result};
+
+use crate::databases::ConnectionType;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SqlDialect {
+    PostgreSql,
+    MySql,
+}
+
+impl SqlDialect {
+    pub fn for_connection_type(connection_type: &ConnectionType) -> Result<Self> {
+        match connection_type {
+            ConnectionType::PostgreSql => Ok(SqlDialect::PostgreSql),
+            ConnectionType::MySql | ConnectionType::MariaDB => Ok(SqlDialect::MySql),
+            _ => Err(anyhow!(
+                "Masking is only supported for PostgreSQL, MySQL and MariaDB plain dumps"
+            )),
+        }
+    }
+
+    /// MySQL strings take backslash escapes, PostgreSQL ones only double their quotes.
+    fn backslash_escapes(&self) -> bool {
+        matches!(self, SqlDialect::MySql)
+    }
+}
+
+const CONSTRAINT_KEYWORDS: [&str; 11] = [
+    "CONSTRAINT",
+    "PRIMARY",
+    "UNIQUE",
+    "KEY",
+    "INDEX",
+    "FULLTEXT",
+    "SPATIAL",
+    "FOREIGN",
+    "CHECK",
+    "EXCLUDE",
+    "LIKE",
+];
+
+/// Identifier without its quotes, `"public"."users"` becomes `public.users`.
+pub fn unquote_identifier(identifier: &str) -> String {
+    identifier
+        .split('.')
+        .map(|part| part.trim().trim_matches(|c| c == '"' || c == '`'))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+fn split_columns(columns: &str) -> Vec<String> {
+    columns
+        .split(',')
+        .map(unquote_identifier)
+        .filter(|column| !column.is_empty())
+        .collect()
+}
+
+/// Table and columns of a `COPY table (columns) FROM stdin;` line.
+pub fn parse_copy(line: &str) -> Option<(String, Vec<String>)> {
+    let rest = line
+        .strip_prefix("COPY ")?
+        .trim_end()
+        .strip_suffix("FROM stdin;")?
+        .trim_end();
+
+    match rest.split_once('(') {
+        Some((table, columns)) => Some((
+            unquote_identifier(table),
+            split_columns(columns.strip_suffix(')')?),
+        )),
+        None => Some((unquote_identifier(rest), vec![])),
+    }
+}
+
+/// Table of a `CREATE TABLE table (` line.
+pub fn parse_create_table(line: &str) -> Option<String> {
+    let rest = line.strip_prefix("CREATE TABLE ")?;
+    let rest = rest.strip_prefix("IF NOT EXISTS ").unwrap_or(rest);
+    let (table, _) = rest.split_once('(')?;
+
+    Some(unquote_identifier(table))
+}
+
+/// Column of a line in the body of a `CREATE TABLE`, `None` for constraints and indexes.
+pub fn parse_column_definition(line: &str) -> Option<String> {
+    let line = line.trim();
+
+    let name = if let Some(rest) = line.strip_prefix('`') {
+        rest.split('`').next()?
+    } else if let Some(rest) = line.strip_prefix('"') {
+        rest.split('"').next()?
+    } else {
+        let word = line.split_whitespace().next()?;
+        if CONSTRAINT_KEYWORDS.contains(&word.to_uppercase().as_str()) {
+            return None;
+        }
+        word
+    };
+
+    Some(name.to_string())
+}
+
+/// Value of a `COPY` text field, `None` for `\N`.
+pub fn decode_copy_field(field: &[u8]) -> Option<Vec<u8>> {
+    if field == b"\\N" {
+        return None;
+    }
+
+    let mut value = Vec::with_capacity(field.len());
+    let mut bytes = field.iter().copied().peekable();
+
+    while let Some(byte) = bytes.next() {
+        if byte != b'\\' {
+            value.push(byte);
+            continue;
+        }
+
+        match bytes.next() {
+            Some(b'b') => value.push(0x08),
+            Some(b'f') => value.push(0x0c),
+            Some(b'n') => value.push(b'\n'),
+            Some(b'r') => value.push(b'\r'),
+            Some(b't') => value.push(b'\t'),
+            Some(b'v') => value.push(0x0b),
+            Some(b'x') => {
+                let mut code = 0u8;
+                for _ in 0..2 {
+                    match bytes.peek().and_then(|b| (*b as char).to_digit(16)) {
+                        Some(digit) => {
+                            code = code * 16 + digit as u8;
+                            bytes.next();
+                        }
+                        None => break,
+                    }
+                }
+                value.push(code);
+            }
+            Some(digit @ b'0'..=b'7') => {
+                let mut code = digit - b'0';
+                for _ in 0..2 {
+                    match bytes.peek() {
+                        Some(next @ b'0'..=b'7') => {
+                            code = code.wrapping_mul(8) + (next - b'0');
+                            bytes.next();
+                        }
+                        _ => break,
+                    }
+                }
+                value.push(code);
+            }
+            Some(other) => value.push(other),
+            None => value.push(b'\\'),
+        }
+    }
+
+    Some(value)
+}
+
+pub fn encode_copy_field(value: Option<&[u8]>, out: &mut Vec<u8>) {
+    let value = match value {
+        Some(value) => value,
+        None => return out.extend_from_slice(b"\\N"),
+    };
+
+    for byte in value {
+        match byte {
+            b'\\' => out.extend_from_slice(b"\\\\"),
+            b'\n' => out.extend_from_slice(b"\\n"),
+            b'\r' => out.extend_from_slice(b"\\r"),
+            b'\t' => out.extend_from_slice(b"\\t"),
+            _ => out.push(*byte),
+        }
+    }
+}
+
+/// Position right after the literal or quoted identifier opening at `start`, or the end of
+/// `bytes` when it isn't closed yet.
+fn skip_quoted(bytes: &[u8], start: usize, dialect: SqlDialect) -> usize {
+    let quote = bytes[start];
+    let backslash_escapes = quote != b'`' && dialect.backslash_escapes();
+    let mut position = start + 1;
+
+    while position < bytes.len() {
+        let byte = bytes[position];
+        if byte == b'\\' && backslash_escapes {
+            position += 2;
+        } else if byte == quote {
+            // A doubled quote stands for the quote itself
+            if bytes.get(position + 1) == Some(&quote) {
+                position += 2;
+            } else {
+                return position + 1;
+            }
+        } else {
+            position += 1;
+        }
+    }
+
+    bytes.len()
+}
+
+fn is_quote(byte: u8) -> bool {
+    matches!(byte, b'\'' | b'"' | b'`')
+}
+
+/// Follows the quotes of a statement spread over several lines.
+#[derive(Debug, Default)]
+pub struct StatementScanner {
+    quote: Option<u8>,
+    escaped: bool,
+    depth: usize,
+    /// Parentheses opened outside of quotes and other parentheses, the tuples of an `INSERT`
+    /// when fed from its `VALUES`.
+    pub groups: u64,
+}
+
+impl StatementScanner {
+    /// Whether the statement ends in these bytes, with a semicolon outside of any quote.
+    pub fn feed(&mut self, bytes: &[u8], dialect: SqlDialect) -> bool {
+        for &byte in bytes {
+            match self.quote {
+                Some(_) if self.escaped => self.escaped = false,
+                Some(quote) if byte == b'\\' && quote != b'`' && dialect.backslash_escapes() => {
+                    self.escaped = true
+                }
+                // A doubled quote closes and reopens the literal, which ends up the same
+                Some(quote) if byte == quote => self.quote = None,
+                Some(_) => {}
+                None if is_quote(byte) => self.quote = Some(byte),
+                None if byte == b';' => return true,
+                None if byte == b'(' => {
+                    if self.depth == 0 {
+                        self.groups += 1;
+                    }
+                    self.depth += 1;
+                }
+                None if byte == b')' => self.depth = self.depth.saturating_sub(1),
+                None => {}
+            }
+        }
+
+        false
+    }
+}
+
+/// Target of an `INSERT` statement, with the position of its `VALUES` tuples.
+#[derive(Debug, PartialEq)]
+pub struct InsertHeader {
+    pub table: String,
+    /// Only when the statement lists them, `mysqldump` doesn't by default.
+    pub columns: Option<Vec<String>>,
+    pub values_start: usize,
+}
+
+fn skip_whitespace(bytes: &[u8], mut position: usize) -> usize {
+    while position < bytes.len() && bytes[position].is_ascii_whitespace() {
+        position += 1;
+    }
+    position
+}
+
+pub fn parse_insert_header(statement: &[u8], dialect: SqlDialect) -> Option<InsertHeader> {
+    let mut position = statement
+        .strip_prefix(b"INSERT INTO ")
+        .map(|_| "INSERT INTO ".len())?;
+
+    let table_start = position;
+    while position < statement.len()
+        && !statement[position].is_ascii_whitespace()
+        && statement[position] != b'('
+    {
+        if is_quote(statement[position]) {
+            position = skip_quoted(statement, position, dialect);
+        } else {
+            position += 1;
+        }
+    }
+    let table = unquote_identifier(&String::from_utf8_lossy(&statement[table_start..position]));
+
+    position = skip_whitespace(statement, position);
+    let columns = if statement.get(position) == Some(&b'(') {
+        let columns_start = position + 1;
+        while position < statement.len() && statement[position] != b')' {
+            if is_quote(statement[position]) {
+                position = skip_quoted(statement, position, dialect);
+            } else {
+                position += 1;
+            }
+        }
+        let columns = String::from_utf8_lossy(statement.get(columns_start..position)?);
+        position = skip_whitespace(statement, position + 1);
+        Some(split_columns(&columns))
+    } else {
+        None
+    };
+
+    let keyword = statement.get(position..position + 6)?;
+    if !keyword.eq_ignore_ascii_case(b"VALUES") {
+        return None;
+    }
+
+    Some(InsertHeader {
+        table,
+        columns,
+        values_start: position + 6,
+    })
+}
+
+/// Positions of the values of every tuple of an `INSERT` statement.
+pub fn parse_insert_values(
+    statement: &[u8],
+    values_start: usize,
+    dialect: SqlDialect,
+) -> Result<Vec<Vec<Range<usize>>>> {
+    let mut tuples = vec![];
+    let mut position = skip_whitespace(statement, values_start);
+
+    while statement.get(position) == Some(&b'(') {
+        let mut values = vec![];
+        let mut value_start = position + 1;
+        let mut depth = 0;
+        position += 1;
+
+        loop {
+            let byte = match statement.get(position) {
+                Some(byte) => *byte,
+                None => return Err(anyhow!("Unterminated INSERT statement")),
+            };
+
+            match byte {
+                _ if is_quote(byte) => {
+                    position = skip_quoted(statement, position, dialect);
+                    continue;
+                }
+                b'(' => depth += 1,
+                b')' if depth > 0 => depth -= 1,
+                b',' | b')' if depth == 0 => {
+                    values.push(trim_range(statement, value_start..position));
+                    value_start = position + 1;
+                    if byte == b')' {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+            position += 1;
+        }
+
+        tuples.push(values);
+        position = skip_whitespace(statement, position + 1);
+        if statement.get(position) == Some(&b',') {
+            position = skip_whitespace(statement, position + 1);
+        }
+    }
+
+    Ok(tuples)
+}
+
+fn trim_range(bytes: &[u8], mut range: Range<usize>) -> Range<usize> {
+    while range.start < range.end && bytes[range.start].is_ascii_whitespace() {
+        range.start += 1;
+    }
+    while range.end > range.start && bytes[range.end - 1].is_ascii_whitespace() {
+        range.end -= 1;
+    }
+    range
+}
+
+fn unescape_string(content: &[u8], quote: u8, backslash_escapes: bool) -> Vec<u8> {
+    let mut value = Vec::with_capacity(content.len());
+    let mut position = 0;
+
+    while position < content.len() {
+        let byte = content[position];
+        if byte == b'\\' && backslash_escapes && position + 1 < content.len() {
+            position += 1;
+            value.push(match content[position] {
+                b'0' => 0,
+                b'b' => 0x08,
+                b'n' => b'\n',
+                b'r' => b'\r',
+                b't' => b'\t',
+                b'Z' => 0x1a,
+                other => other,
+            });
+        } else if byte == quote && content.get(position + 1) == Some(&quote) {
+            value.push(quote);
+            position += 1;
+        } else {
+            value.push(byte);
+        }
+        position += 1;
+    }
+
+    value
+}
+
+/// Value of a SQL literal, `None` for `NULL`. Numbers and other bare words are kept as written.
+pub fn decode_literal(literal: &[u8], dialect: SqlDialect) -> Option<Vec<u8>> {
+    if literal.eq_ignore_ascii_case(b"NULL") {
+        return None;
+    }
+
+    // Charset introducers like `_binary '...'` and PostgreSQL `E'...'` escape strings
+    let (literal, backslash_escapes) = match literal.iter().position(|b| *b == b'\'') {
+        Some(0) => (literal, dialect.backslash_escapes()),
+        Some(quote) if literal[0] == b'_' => (&literal[quote..], dialect.backslash_escapes()),
+        Some(1) if literal[0].eq_ignore_ascii_case(&b'E') => (&literal[1..], true),
+        _ => return Some(literal.to_vec()),
+    };
+
+    let content = &literal[1..literal.len().saturating_sub(1).max(1)];
+    Some(unescape_string(content, b'\'', backslash_escapes))
+}
+
+pub fn encode_literal(value: Option<&[u8]>, dialect: SqlDialect, out: &mut Vec<u8>) {
+    let value = match value {
+        Some(value) => value,
+        None => return out.extend_from_slice(b"NULL"),
+    };
+
+    out.push(b'\'');
+    for byte in value {
+        match byte {
+            b'\'' if dialect.backslash_escapes() => out.extend_from_slice(b"\\'"),
+            b'\'' => out.extend_from_slice(b"''"),
+            b'\\' if dialect.backslash_escapes() => out.extend_from_slice(b"\\\\"),
+            b'\n' if dialect.backslash_escapes() => out.extend_from_slice(b"\\n"),
+            b'\r' if dialect.backslash_escapes() => out.extend_from_slice(b"\\r"),
+            0 if dialect.backslash_escapes() => out.extend_from_slice(b"\\0"),
+            0x1a if dialect.backslash_escapes() => out.extend_from_slice(b"\\Z"),
+            _ => out.push(*byte),
+        }
+    }
+    out.push(b'\'');
+}
@@ -0,0 +1,231 @@
+#[cfg(test)]
+mod masking_tests {
+    use std::io::{BufReader, Cursor, Read, Write};
+
+    use crate::masking::{
+        sql::{decode_literal, SqlDialect},
+        MaskRule, Masker, MaskingConfig, MaskingReader, MaskingWriter,
+    };
+
+    fn get_config(seed: &str) -> MaskingConfig {
+        serde_json::from_str(&format!(
+            r#"{{
+                "seed": "{}",
+                "tables": {{
+                    "public.users": {{
+                        "email": "email",
+                        "phone": "keep_format",
+                        "notes": "null",
+                        "name": {{ "fixed": "Jane Doe" }}
+                    }},
+                    "orders": {{ "email": "email", "token": "hash" }}
+                }}
+            }}"#,
+            seed
+        ))
+        .expect("Failed to parse masking config")
+    }
+
+    fn mask(dump: &str, dialect: SqlDialect) -> String {
+        let mut masker =
+            Masker::new(get_config("secret"), dialect).expect("Failed to create masker");
+        let mut out = vec![];
+
+        for line in dump.as_bytes().split_inclusive(|b| *b == b'\n') {
+            masker
+                .process_line(line, &mut out)
+                .expect("Failed to mask line");
+        }
+        masker.finish().expect("Dump should be complete");
+
+        String::from_utf8(out).expect("Masked dump should be UTF-8")
+    }
+
+    #[test]
+    fn test_01_rules() {
+        let key = [7u8; 32];
+
+        let email = MaskRule::Email.apply(Some(b"john@corp.com"), &key).unwrap();
+        assert_eq!(
+            email,
+            MaskRule::Email.apply(Some(b"john@corp.com"), &key).unwrap()
+        );
+        assert_ne!(
+            email,
+            MaskRule::Email
+                .apply(Some(b"john@corp.com"), &[8u8; 32])
+                .unwrap()
+        );
+        assert!(String::from_utf8(email).unwrap().ends_with("@example.com"));
+
+        let phone = MaskRule::KeepFormat
+            .apply(Some(b"+1 (555) 010-9999 ext A"), &key)
+            .unwrap();
+        let phone = String::from_utf8(phone).unwrap();
+        assert_ne!(phone, "+1 (555) 010-9999 ext A");
+        assert!(phone.starts_with("+"));
+        assert_eq!(
+            phone
+                .chars()
+                .map(|c| c.is_ascii_digit())
+                .collect::<Vec<_>>(),
+            "+1 (555) 010-9999 ext A"
+                .chars()
+                .map(|c| c.is_ascii_digit())
+                .collect::<Vec<_>>()
+        );
+
+        assert_eq!(
+            MaskRule::Hash.apply(Some(b"token"), &key).unwrap().len(),
+            32
+        );
+        assert_eq!(MaskRule::Null.apply(Some(b"note"), &key), None);
+        assert_eq!(MaskRule::Fixed("x".into()).apply(None, &key), None);
+    }
+
+    #[test]
+    fn test_02_mask_copy() {
+        let dump = "SET client_encoding = 'UTF8';\n\
+            COPY public.users (id, name, email, notes) FROM stdin;\n\
+            1\tJohn\tjohn@corp.com\tline\\none\n\
+            2\t\\N\tjane@corp.com\t\\N\n\
+            \\.\n\
+            COPY public.items (id, email) FROM stdin;\n\
+            1\tjohn@corp.com\n\
+            \\.\n";
+
+        let masked = mask(dump, SqlDialect::PostgreSql);
+        let lines: Vec<&str> = masked.lines().collect();
+
+        assert_eq!(lines[0], "SET client_encoding = 'UTF8';");
+        let john: Vec<&str> = lines[2].split('\t').collect();
+        assert_eq!(john[0], "1");
+        assert_eq!(john[1], "Jane Doe");
+        assert!(john[2].starts_with("user_") && john[2].ends_with("@example.com"));
+        assert_eq!(john[3], "\\N");
+
+        let jane: Vec<&str> = lines[3].split('\t').collect();
+        assert_eq!(jane[1], "\\N", "NULL values should be kept");
+        assert_ne!(jane[2], john[2]);
+
+        assert_eq!(lines[4], "\\.");
+        assert_eq!(
+            lines[6], "1\tjohn@corp.com",
+            "Tables without rules should be kept"
+        );
+    }
+
+    #[test]
+    fn test_03_mask_mysql_inserts() {
+        let dump = "CREATE TABLE `orders` (\n\
+            \x20 `id` int NOT NULL,\n\
+            \x20 `email` varchar(255) DEFAULT NULL,\n\
+            \x20 `token` blob,\n\
+            \x20 PRIMARY KEY (`id`)\n\
+            ) ENGINE=InnoDB;\n\
+            INSERT INTO `orders` VALUES (1,'john@corp.com',_binary 'a\\'b'),(2,NULL,'x,(y)'),(3,'o\\'neil@corp.com','z');\n";
+
+        let masked = mask(dump, SqlDialect::MySql);
+        let insert = masked.lines().last().unwrap();
+
+        assert!(masked.starts_with("CREATE TABLE `orders` (\n"));
+        assert!(insert.starts_with("INSERT INTO `orders` VALUES (1,'user_"));
+        assert!(insert.contains("(2,NULL,'"));
+        assert!(!insert.contains("corp.com"));
+        assert!(!insert.contains("x,(y)"));
+        assert!(insert.ends_with("');"));
+
+        // The same email masks the same way in every table
+        let users = mask(
+            "COPY public.users (email) FROM stdin;\njohn@corp.com\n\\.\n",
+            SqlDialect::PostgreSql,
+        );
+        let masked_email = users.lines().nth(1).unwrap();
+        assert!(insert.contains(&format!("(1,'{}',", masked_email)));
+    }
+
+    #[test]
+    fn test_04_mask_postgresql_inserts() {
+        let dump =
+            "INSERT INTO public.users (id, email, notes) VALUES (1, 'it''s\nme', E'a\\\\b');\n\
+            INSERT INTO public.users (id, email) VALUES (2, 'jane@corp.com');\n";
+
+        let masked = mask(dump, SqlDialect::PostgreSql);
+        let lines: Vec<&str> = masked.lines().collect();
+
+        assert_eq!(lines.len(), 2, "Multi line strings should be masked whole");
+        assert!(
+            lines[0].starts_with("INSERT INTO public.users (id, email, notes) VALUES (1, 'user_")
+        );
+        assert!(lines[0].ends_with("@example.com', NULL);"));
+        assert!(lines[1].starts_with("INSERT INTO public.users (id, email) VALUES (2, 'user_"));
+
+        assert_eq!(
+            decode_literal(b"'it''s'", SqlDialect::PostgreSql),
+            Some(b"it's".to_vec())
+        );
+        assert_eq!(
+            decode_literal(b"E'a\\\\b'", SqlDialect::PostgreSql),
+            Some(b"a\\b".to_vec())
+        );
+        assert_eq!(
+            decode_literal(b"42", SqlDialect::MySql),
+            Some(b"42".to_vec())
+        );
+        assert_eq!(decode_literal(b"null", SqlDialect::MySql), None);
+    }
+
+    #[test]
+    fn test_05_writer_and_reader() {
+        let dump = "CREATE TABLE `orders` (\n  `id` int,\n  `email` text\n);\n\
+            INSERT INTO `orders` VALUES (1,'john@corp.com'),(2,'jane@corp.com');\n\
+            INSERT INTO `orders` VALUES (3,'line\none');";
+
+        let masker = Masker::new(get_config("secret"), SqlDialect::MySql).unwrap();
+        let mut writer = MaskingWriter::new(vec![], masker);
+        for chunk in dump.as_bytes().chunks(7) {
+            writer.write_all(chunk).expect("Failed to write");
+        }
+        let written = writer.finish().expect("Failed to finish");
+
+        let masker = Masker::new(get_config("secret"), SqlDialect::MySql).unwrap();
+        let mut reader = MaskingReader::new(BufReader::new(Cursor::new(dump)), masker);
+        let mut read = vec![];
+        reader.read_to_end(&mut read).expect("Failed to read");
+
+        assert_eq!(written, read);
+        assert!(!String::from_utf8_lossy(&written).contains("corp.com"));
+
+        let masker = Masker::new(get_config("secret"), SqlDialect::MySql).unwrap();
+        let mut writer = MaskingWriter::new(vec![], masker);
+        writer
+            .write_all(b"INSERT INTO `orders` VALUES (1,'john@corp.com')")
+            .unwrap();
+        assert!(
+            writer.finish().is_err(),
+            "Truncated statements can't be masked"
+        );
+    }
+
+    #[test]
+    fn test_06_config() {
+        let config = get_config("");
+        assert!(Masker::new(config, SqlDialect::PostgreSql).is_err());
+
+        let config = get_config("secret");
+        assert_eq!(
+            config.tables["orders"]["token"],
+            MaskRule::Hash,
+            "Rules should be parsed from their snake case names"
+        );
+
+        let mut masker = Masker::new(config, SqlDialect::MySql).unwrap();
+        let mut out = vec![];
+        assert!(
+            masker
+                .process_line(b"INSERT INTO `orders` VALUES (1,'a','b');\n", &mut out)
+                .is_err(),
+            "Inserts into masked tables need their columns"
+        );
+    }
+}
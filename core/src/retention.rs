@@ -0,0 +1,303 @@
+use std::{collections::BTreeMap, fmt};
+
+use chrono::{DateTime, Datelike, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::storage::Entry;
+
+/// Grandfather-father-son retention policy. Within each period the newest backup is the
+/// one kept, and the most recent successful backup is always kept.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    pub keep_last: u32,
+    pub keep_daily: u32,
+    pub keep_weekly: u32,
+    pub keep_monthly: u32,
+    pub keep_yearly: u32,
+}
+
+impl RetentionPolicy {
+    pub fn is_empty(&self) -> bool {
+        self.keep_last == 0
+            && self.keep_daily == 0
+            && self.keep_weekly == 0
+            && self.keep_monthly == 0
+            && self.keep_yearly == 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RetentionReason {
+    LatestSuccessful,
+    Last(u32),
+    Daily(String),
+    Weekly(String),
+    Monthly(String),
+    Yearly(String),
+    /// The backup date could not be determined, it is never deleted.
+    UnknownDate,
+}
+
+impl fmt::Display for RetentionReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RetentionReason::LatestSuccessful => write!(f, "latest successful backup"),
+            RetentionReason::Last(position) => write!(f, "last #{}", position),
+            RetentionReason::Daily(day) => write!(f, "daily {}", day),
+            RetentionReason::Weekly(week) => write!(f, "weekly {}", week),
+            RetentionReason::Monthly(month) => write!(f, "monthly {}", month),
+            RetentionReason::Yearly(year) => write!(f, "yearly {}", year),
+            RetentionReason::UnknownDate => write!(f, "unknown backup date"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionDecision {
+    pub path: String,
+    pub name: String,
+    pub database: Option<String>,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub size: u64,
+    /// Why the backup is kept, empty when it is deleted.
+    pub reasons: Vec<RetentionReason>,
+    /// Why deleting the backup failed, it is still in the storage.
+    #[serde(default)]
+    pub delete_error: Option<String>,
+}
+
+impl RetentionDecision {
+    pub fn is_kept(&self) -> bool {
+        !self.reasons.is_empty()
+    }
+}
+
+/// Number of periods to keep, period of a backup and the reason it is kept for.
+type Period = (
+    u32,
+    fn(&DateTime<Utc>) -> String,
+    fn(String) -> RetentionReason,
+);
+
+struct Candidate {
+    timestamp: DateTime<Utc>,
+    successful: bool,
+}
+
+/// Applies the policy to the backups of each database separately. Decisions are sorted by
+/// database, newest backup first.
+pub fn apply_policy(policy: &RetentionPolicy, entries: Vec<Entry>) -> Vec<RetentionDecision> {
+    let mut databases: BTreeMap<Option<String>, Vec<Entry>> = BTreeMap::new();
+
+    for entry in entries {
+        databases
+            .entry(entry.database_name())
+            .or_default()
+            .push(entry);
+    }
+
+    let mut decisions = vec![];
+
+    for (database, mut entries) in databases {
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.timestamp()));
+
+        let (dated, undated): (Vec<Entry>, Vec<Entry>) = entries
+            .into_iter()
+            .partition(|entry| entry.timestamp().is_some());
+
+        let candidates: Vec<Candidate> = dated
+            .iter()
+            .map(|entry| Candidate {
+                timestamp: entry.timestamp().unwrap_or_default(),
+                // The manifest is only written once the upload completed
+                successful: entry.manifest.is_some(),
+            })
+            .collect();
+
+        let reasons = select(policy, &candidates);
+
+        for (entry, reasons) in dated.into_iter().zip(reasons) {
+            decisions.push(decision(&entry, database.clone(), reasons));
+        }
+
+        for entry in undated {
+            decisions.push(decision(
+                &entry,
+                database.clone(),
+                vec![RetentionReason::UnknownDate],
+            ));
+        }
+    }
+
+    decisions
+}
+
+fn decision(
+    entry: &Entry,
+    database: Option<String>,
+    reasons: Vec<RetentionReason>,
+) -> RetentionDecision {
+    RetentionDecision {
+        path: entry.path.clone(),
+        name: entry.metadata.name.clone(),
+        database,
+        timestamp: entry.timestamp(),
+        size: entry.metadata.content_length,
+        reasons,
+        delete_error: None,
+    }
+}
+
+/// Candidates must be sorted newest first.
+fn select(policy: &RetentionPolicy, candidates: &[Candidate]) -> Vec<Vec<RetentionReason>> {
+    let mut reasons: Vec<Vec<RetentionReason>> = vec![vec![]; candidates.len()];
+
+    // Backups without a manifest predate manifests or failed, only fall back on them
+    // when no backup of the database has one
+    let latest = candidates
+        .iter()
+        .position(|c| c.successful)
+        .or(if candidates.is_empty() { None } else { Some(0) });
+
+    if let Some(latest) = latest {
+        reasons[latest].push(RetentionReason::LatestSuccessful);
+    }
+
+    for (position, reasons) in reasons
+        .iter_mut()
+        .take(policy.keep_last as usize)
+        .enumerate()
+    {
+        reasons.push(RetentionReason::Last(position as u32 + 1));
+    }
+
+    let periods: [Period; 4] = [
+        (
+            policy.keep_daily,
+            |t| t.format("%Y-%m-%d").to_string(),
+            RetentionReason::Daily,
+        ),
+        (
+            policy.keep_weekly,
+            |t| format!("{}-W{:02}", t.iso_week().year(), t.iso_week().week()),
+            RetentionReason::Weekly,
+        ),
+        (
+            policy.keep_monthly,
+            |t| t.format("%Y-%m").to_string(),
+            RetentionReason::Monthly,
+        ),
+        (
+            policy.keep_yearly,
+            |t| t.format("%Y").to_string(),
+            RetentionReason::Yearly,
+        ),
+    ];
+
+    for (count, period_of, reason) in periods {
+        let mut kept = 0;
+        let mut last_period: Option<String> = None;
+
+        for (index, candidate) in candidates.iter().enumerate() {
+            if kept >= count {
+                break;
+            }
+
+            let period = period_of(&candidate.timestamp);
+            if last_period.as_ref() == Some(&period) {
+                continue;
+            }
+
+            last_period = Some(period.clone());
+            reasons[index].push(reason(period));
+            kept += 1;
+        }
+    }
+
+    reasons
+}
+
+#[cfg(test)]
+mod retention_test {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use super::{select, Candidate, RetentionPolicy, RetentionReason};
+
+    fn candidates(hours: &[i64]) -> Vec<Candidate> {
+        let now = Utc.with_ymd_and_hms(2024, 3, 15, 23, 0, 0).unwrap();
+
+        hours
+            .iter()
+            .map(|hours| Candidate {
+                timestamp: now - Duration::hours(*hours),
+                successful: true,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn gfs() {
+        // Two backups a day for 60 days, newest first
+        let hours: Vec<i64> = (0..120).map(|i| i * 12).collect();
+        let policy = RetentionPolicy {
+            keep_last: 2,
+            keep_daily: 7,
+            keep_weekly: 4,
+            keep_monthly: 3,
+            keep_yearly: 0,
+        };
+
+        let reasons = select(&policy, &candidates(&hours));
+        let kept: Vec<usize> = (0..reasons.len())
+            .filter(|i| !reasons[*i].is_empty())
+            .collect();
+
+        assert_eq!(
+            reasons[0],
+            vec![
+                RetentionReason::LatestSuccessful,
+                RetentionReason::Last(1),
+                RetentionReason::Daily("2024-03-15".into()),
+                RetentionReason::Weekly("2024-W11".into()),
+                RetentionReason::Monthly("2024-03".into()),
+            ]
+        );
+        assert_eq!(reasons[1], vec![RetentionReason::Last(2)]);
+
+        // The newest backup of each of the last 7 days
+        let daily = reasons
+            .iter()
+            .filter(|r| r.iter().any(|r| matches!(r, RetentionReason::Daily(_))))
+            .count();
+        assert_eq!(daily, 7);
+        assert_eq!(
+            reasons[2],
+            vec![RetentionReason::Daily("2024-03-14".into())]
+        );
+
+        // Last backup of February and January
+        assert!(reasons[30].contains(&RetentionReason::Monthly("2024-02".into())));
+        assert!(reasons[88].contains(&RetentionReason::Monthly("2024-01".into())));
+
+        assert!(kept.len() < 20);
+        assert!(reasons[119].is_empty());
+    }
+
+    #[test]
+    fn latest_successful() {
+        let mut candidates = candidates(&[0, 24, 48]);
+        candidates[0].successful = false;
+
+        let reasons = select(&RetentionPolicy::default(), &candidates);
+
+        assert!(reasons[0].is_empty());
+        assert_eq!(reasons[1], vec![RetentionReason::LatestSuccessful]);
+        assert!(reasons[2].is_empty());
+
+        // Without any manifest the newest backup is kept
+        candidates.iter_mut().for_each(|c| c.successful = false);
+        let reasons = select(&RetentionPolicy::default(), &candidates);
+        assert_eq!(reasons[0], vec![RetentionReason::LatestSuccessful]);
+    }
+}
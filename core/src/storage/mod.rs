@@ -1,9 +1,16 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    common::{extract_database_name_from_filename, extract_timestamp_from_filename},
+    manifest::BackupManifest,
+};
+
 pub mod io;
 pub mod provider;
+mod sftp;
 mod test;
+mod webdav;
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum EntryMode {
@@ -34,6 +41,27 @@ pub struct EntryMetadata {
 pub struct Entry {
     pub path: String,
     pub metadata: EntryMetadata,
+    pub manifest: Option<BackupManifest>,
+}
+
+impl Entry {
+    /// Backup creation time, read from the manifest when there is one and from the
+    /// file name otherwise.
+    pub fn timestamp(&self) -> Option<DateTime<Utc>> {
+        match &self.manifest {
+            Some(manifest) => Some(manifest.started_at),
+            None => extract_timestamp_from_filename(&self.metadata.name).ok(),
+        }
+    }
+
+    /// Name of the database configuration the backup was made from, read from the
+    /// manifest when there is one and from the file name otherwise.
+    pub fn database_name(&self) -> Option<String> {
+        match &self.manifest {
+            Some(manifest) => Some(manifest.database.name.clone()),
+            None => extract_database_name_from_filename(&self.metadata.name).ok(),
+        }
+    }
 }
 
 impl From<&opendal::Entry> for Entry {
@@ -63,6 +91,7 @@ impl From<&opendal::Entry> for Entry {
                 },
                 version: metadata.version().map(|it| it.to_string()),
             },
+            manifest: None,
         }
     }
 }
@@ -4,13 +4,15 @@ use futures::StreamExt;
 use log::{debug, error, info, warn};
 use opendal::{
     layers::LoggingLayer,
-    services::{Fs, S3},
+    services::{Fs, Webdav, S3},
     BufferStream, Metadata, Operator, Writer,
 };
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fs,
+    future::Future,
+    io::SeekFrom,
     path::Path,
     sync::{
         mpsc::{channel, Sender},
@@ -19,11 +21,27 @@ use std::{
     thread::{self, JoinHandle},
     time::{Duration, SystemTime},
 };
-use tokio::{runtime::Runtime, sync::oneshot};
+use tokio::{
+    io::{AsyncSeekExt, AsyncWriteExt},
+    runtime::Runtime,
+    sync::oneshot,
+};
 
-use crate::{common::extract_timestamp_from_filename, storage::Entry};
+use crate::{
+    common::slugify,
+    manifest::BackupManifest,
+    retention::{apply_policy, RetentionDecision, RetentionPolicy},
+    storage::Entry,
+};
 
-use super::io::{StorageReader, StorageWriter};
+use super::{
+    io::{StorageReader, StorageWriter},
+    sftp,
+    webdav::WebDavUploader,
+};
+
+/// Manifests read at once when listing backups.
+const MANIFEST_READ_CONCURRENCY: usize = 16;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum StorageCredentials {
@@ -47,8 +65,8 @@ pub enum StorageCredentials {
 pub enum StorageType {
     FileSystem,
     S3,
-    // WebDAV,
-    // SFTP,
+    WebDAV,
+    SFTP,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -70,16 +88,67 @@ pub struct S3StorageConfig {
     pub location: String,
 }
 
+fn default_sftp_port() -> u16 {
+    22
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SftpStorageConfig {
+    pub id: String,
+    pub name: String,
+    pub host: String,
+    #[serde(default = "default_sftp_port")]
+    pub port: u16,
+    /// `Basic` for password authentication or `PrivateKey`.
+    pub credentials: StorageCredentials,
+    pub location: String,
+    /// Adds the key of a server missing from `~/.ssh/known_hosts` instead of rejecting it.
+    #[serde(default)]
+    pub accept_new_host_key: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebDavStorageConfig {
+    pub id: String,
+    pub name: String,
+    /// e.g. `https://cloud.example.com/remote.php/dav/files/<user>` for Nextcloud.
+    pub endpoint: String,
+    /// `Basic`, or `None` when the server doesn't require authentication.
+    pub credentials: StorageCredentials,
+    pub location: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum StorageConfig {
     Local(LocalStorageConfig),
     S3(S3StorageConfig),
+    Sftp(SftpStorageConfig),
+    WebDav(WebDavStorageConfig),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ListOptions {
     pub latest_only: Option<bool>,
     pub limit: Option<usize>,
+    /// Only list backups of this database configuration, those under its prefix or at the root.
+    #[serde(default)]
+    pub database: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CleanupOptions {
+    pub retention_days: u64,
+    pub dry_run: bool,
+    /// Only delete backups of this database configuration.
+    pub database: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionOptions {
+    pub policy: RetentionPolicy,
+    pub dry_run: bool,
+    /// Only apply the policy to backups of this database configuration.
+    pub database: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -122,19 +191,69 @@ pub enum StorageProviderCommand {
         path: String,
         response: oneshot::Sender<Result<()>>,
     },
+    ReadManifest {
+        path: String,
+        response: oneshot::Sender<Result<Option<BackupManifest>>>,
+    },
     Test {
         response: oneshot::Sender<Result<bool>>,
     },
     Cleanup {
-        retention_days: u64,
-        dry_run: bool,
+        options: CleanupOptions,
         response: oneshot::Sender<Result<(usize, u64)>>,
     },
+    ApplyRetention {
+        options: RetentionOptions,
+        response: oneshot::Sender<Result<Vec<RetentionDecision>>>,
+    },
     Shutdown {
         response: oneshot::Sender<Result<()>>,
     },
 }
 
+/// Backends that can't append to an upload in progress, i.e. WebDAV, get the object spooled
+/// to a temporary file and streamed from it when the writer is closed, so a backup is never
+/// held in memory.
+enum OpenWriter {
+    Streaming(Writer),
+    Spooled { path: String, file: tokio::fs::File },
+}
+
+impl OpenWriter {
+    async fn write(&mut self, data: Vec<u8>) -> Result<()> {
+        match self {
+            OpenWriter::Streaming(writer) => writer.write(data).await.map_err(|e| anyhow!("{}", e)),
+            OpenWriter::Spooled { file, .. } => Ok(file.write_all(&data).await?),
+        }
+    }
+
+    async fn close(
+        self,
+        operator: &Operator,
+        uploader: Option<&WebDavUploader>,
+    ) -> Result<Metadata> {
+        match self {
+            OpenWriter::Streaming(mut writer) => writer.close().await.map_err(|e| anyhow!("{}", e)),
+            OpenWriter::Spooled { path, mut file } => {
+                let uploader = uploader.ok_or_else(|| anyhow!("Storage can't upload {}", path))?;
+
+                file.flush().await?;
+                file.seek(SeekFrom::Start(0)).await?;
+
+                if let Some((parent, _)) = path.rsplit_once('/') {
+                    operator
+                        .create_dir(&format!("{}/", parent))
+                        .await
+                        .map_err(|e| anyhow!("{}", e))?;
+                }
+                uploader.upload(&path, file).await?;
+
+                operator.stat(&path).await.map_err(|e| anyhow!("{}", e))
+            }
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct StorageProvider {
     command_tx: Sender<StorageProviderCommand>,
@@ -173,9 +292,36 @@ impl StorageProvider {
                             .layer(LoggingLayer::default())
                             .finish()
                     }
+                    StorageConfig::Sftp(config) => sftp::new_operator(config)?,
+                    StorageConfig::WebDav(config) => {
+                        let mut builder = Webdav::default()
+                            .endpoint(&config.endpoint)
+                            .root(&config.location);
+
+                        builder = match &config.credentials {
+                            StorageCredentials::Basic { username, password } => {
+                                builder.username(username).password(password)
+                            }
+                            StorageCredentials::None => builder,
+                            _ => {
+                                return Err(anyhow!(
+                                    "WebDAV storage only supports basic authentication"
+                                ))
+                            }
+                        };
+
+                        Operator::new(builder)?
+                            .layer(LoggingLayer::default())
+                            .finish()
+                    }
+                };
+
+                let uploader = match &config_clone {
+                    StorageConfig::WebDav(config) => Some(WebDavUploader::new(config)?),
+                    _ => None,
                 };
 
-                let mut writers: HashMap<u64, Writer> = HashMap::new();
+                let mut writers: HashMap<u64, OpenWriter> = HashMap::new();
                 let mut next_writer_id = 1u64;
 
                 let mut streams: HashMap<u64, BufferStream> = HashMap::new();
@@ -193,12 +339,17 @@ impl StorageProvider {
                             let limit = options.limit.unwrap_or(1000);
                             let latest_only = options.latest_only.unwrap_or(false);
 
-                            let result =
-                                operator.list_with(&path).recursive(true).limit(limit).await;
+                            let result = list_backup_files(
+                                &operator,
+                                &path,
+                                options.database.as_deref(),
+                                limit,
+                            )
+                            .await;
 
-                            let _ = response.send(match result {
+                            let result = match result {
                                 Ok(entries) => {
-                                    let mut filtered_results: Vec<Entry> = entries
+                                    let files: Vec<Entry> = entries
                                         .into_iter()
                                         .map(|opendal_entry| {
                                             let mut entry = Entry::from(&opendal_entry);
@@ -217,18 +368,26 @@ impl StorageProvider {
                                         .filter(|entry| entry.metadata.is_file)
                                         .collect();
 
+                                    let mut filtered_results: Vec<Entry> =
+                                        attach_manifests(&operator, files)
+                                            .await
+                                            .into_iter()
+                                            .filter(|entry| match &options.database {
+                                                Some(database) => {
+                                                    entry.database_name().as_ref() == Some(database)
+                                                }
+                                                None => true,
+                                            })
+                                            .collect();
+
                                     // Sort by timestamp (newest first)
                                     filtered_results.sort_by(|a, b| {
-                                        let a_timestamp =
-                                            extract_timestamp_from_filename(&a.metadata.name)
-                                                .unwrap_or_else(|_| {
-                                                    DateTime::<Utc>::from(SystemTime::UNIX_EPOCH)
-                                                });
-                                        let b_timestamp =
-                                            extract_timestamp_from_filename(&b.metadata.name)
-                                                .unwrap_or_else(|_| {
-                                                    DateTime::<Utc>::from(SystemTime::UNIX_EPOCH)
-                                                });
+                                        let a_timestamp = a.timestamp().unwrap_or_else(|| {
+                                            DateTime::<Utc>::from(SystemTime::UNIX_EPOCH)
+                                        });
+                                        let b_timestamp = b.timestamp().unwrap_or_else(|| {
+                                            DateTime::<Utc>::from(SystemTime::UNIX_EPOCH)
+                                        });
                                         b_timestamp.cmp(&a_timestamp)
                                     });
 
@@ -242,7 +401,9 @@ impl StorageProvider {
                                     }
                                 }
                                 Err(error) => Err(anyhow!("{}", error)),
-                            });
+                            };
+
+                            let _ = response.send(result);
                         }
 
                         StorageProviderCommand::CreateWriter {
@@ -251,7 +412,23 @@ impl StorageProvider {
                             response,
                         } => {
                             debug!("Processing CreateWriter command for path: {}", path);
-                            match operator.writer_with(&path).concurrent(concurrency).await {
+                            let writer = if operator.info().full_capability().write_can_multi {
+                                operator
+                                    .writer_with(&path)
+                                    .concurrent(concurrency)
+                                    .await
+                                    .map(OpenWriter::Streaming)
+                                    .map_err(|e| anyhow!("{}", e))
+                            } else {
+                                tempfile::tempfile()
+                                    .map(|file| OpenWriter::Spooled {
+                                        path,
+                                        file: tokio::fs::File::from_std(file),
+                                    })
+                                    .map_err(|e| anyhow!("Failed to create spool file: {}", e))
+                            };
+
+                            match writer {
                                 Ok(writer) => {
                                     let writer_id = next_writer_id;
                                     next_writer_id += 1;
@@ -259,7 +436,7 @@ impl StorageProvider {
                                     let _ = response.send(Ok(writer_id));
                                 }
                                 Err(e) => {
-                                    let _ = response.send(Err(anyhow!("{}", e)));
+                                    let _ = response.send(Err(e));
                                 }
                             }
                         }
@@ -276,7 +453,7 @@ impl StorageProvider {
                             );
                             if let Some(writer) = writers.get_mut(&writer_id) {
                                 let result = writer.write(data).await;
-                                let _ = response.send(result.map_err(|e| anyhow!("{}", e)));
+                                let _ = response.send(result);
                             } else {
                                 let _ =
                                     response.send(Err(anyhow!("Writer {} not found", writer_id)));
@@ -288,9 +465,9 @@ impl StorageProvider {
                             response,
                         } => {
                             debug!("Processing CloseWriter command for writer {}", writer_id);
-                            if let Some(mut writer) = writers.remove(&writer_id) {
-                                let result = writer.close().await;
-                                let _ = response.send(result.map_err(|e| anyhow!("{}", e)));
+                            if let Some(writer) = writers.remove(&writer_id) {
+                                let result = writer.close(&operator, uploader.as_ref()).await;
+                                let _ = response.send(result);
                             } else {
                                 let _ =
                                     response.send(Err(anyhow!("Writer {} not found", writer_id)));
@@ -377,6 +554,22 @@ impl StorageProvider {
                             let _ = response.send(result.map_err(|e| anyhow!("{}", e)));
                         }
 
+                        StorageProviderCommand::ReadManifest { path, response } => {
+                            debug!("Processing ReadManifest command for path: {}", path);
+                            let manifest_path = BackupManifest::path_for(&path);
+                            let result = match operator.exists(&manifest_path).await {
+                                Ok(true) => match operator.read(&manifest_path).await {
+                                    Ok(buffer) => {
+                                        BackupManifest::from_bytes(&buffer.to_vec()).map(Some)
+                                    }
+                                    Err(e) => Err(anyhow!("{}", e)),
+                                },
+                                Ok(false) => Ok(None),
+                                Err(e) => Err(anyhow!("{}", e)),
+                            };
+                            let _ = response.send(result);
+                        }
+
                         StorageProviderCommand::Test { response } => {
                             debug!("Processing Test command");
                             let result = operator.list_with("/").recursive(true).limit(1).await;
@@ -386,21 +579,23 @@ impl StorageProvider {
                             });
                         }
 
-                        StorageProviderCommand::Cleanup {
-                            retention_days,
-                            dry_run,
-                            response,
-                        } => {
+                        StorageProviderCommand::Cleanup { options, response } => {
                             debug!("Processing Cleanup command");
 
-                            // Get all files
-                            let list_result =
-                                operator.list_with("").recursive(true).limit(10000).await;
+                            let list_result = list_backup_files(
+                                &operator,
+                                "",
+                                options.database.as_deref(),
+                                10000,
+                            )
+                            .await;
 
                             let result = match list_result {
                                 Ok(entries) => {
                                     let cutoff = SystemTime::now()
-                                        .checked_sub(Duration::from_secs(retention_days * 86400))
+                                        .checked_sub(Duration::from_secs(
+                                            options.retention_days * 86400,
+                                        ))
                                         .ok_or_else(|| {
                                             anyhow!("Failed to calculate cutoff date")
                                         })?;
@@ -409,39 +604,60 @@ impl StorageProvider {
 
                                     let mut deleted_count = 0;
                                     let mut deleted_size = 0;
+                                    let mut failed = vec![];
 
-                                    for opendal_entry in entries {
-                                        let entry = Entry::from(&opendal_entry);
-                                        if !entry.metadata.is_file {
-                                            continue;
+                                    let files: Vec<Entry> = entries
+                                        .iter()
+                                        .map(Entry::from)
+                                        .filter(|entry| entry.metadata.is_file)
+                                        .collect();
+
+                                    for entry in attach_manifests(&operator, files).await {
+                                        if let Some(database) = &options.database {
+                                            if entry.database_name().as_ref() != Some(database) {
+                                                continue;
+                                            }
                                         }
 
-                                        match extract_timestamp_from_filename(&entry.metadata.name)
-                                        {
-                                            Ok(timestamp) => {
+                                        match entry.timestamp() {
+                                            Some(timestamp) => {
                                                 if timestamp < cutoff_datetime {
                                                     let size = entry.metadata.content_length;
-                                                    deleted_size += size;
-                                                    deleted_count += 1;
 
-                                                    if !dry_run {
+                                                    if options.dry_run {
+                                                        deleted_size += size;
+                                                        deleted_count += 1;
+                                                    } else if let Err(e) =
+                                                        operator.delete(&entry.path).await
+                                                    {
+                                                        error!(
+                                                            "Failed to delete {}: {}",
+                                                            entry.path, e
+                                                        );
+                                                        failed.push(entry.path.clone());
+                                                    } else {
+                                                        deleted_size += size;
+                                                        deleted_count += 1;
+
+                                                        info!(
+                                                            "Successfully deleted {}",
+                                                            entry.path
+                                                        );
+
+                                                        let manifest_path =
+                                                            BackupManifest::path_for(&entry.path);
                                                         if let Err(e) =
-                                                            operator.delete(&entry.path).await
+                                                            operator.delete(&manifest_path).await
                                                         {
                                                             error!(
                                                                 "Failed to delete {}: {}",
-                                                                entry.path, e
-                                                            );
-                                                        } else {
-                                                            info!(
-                                                                "Successfully deleted {}",
-                                                                entry.path
+                                                                manifest_path, e
                                                             );
                                                         }
                                                     }
                                                 }
                                             }
-                                            Err(_) => {
+                                            None => {
                                                 warn!(
                                                     "Failed to extract timestamp from {}",
                                                     entry.metadata.name
@@ -450,7 +666,63 @@ impl StorageProvider {
                                         }
                                     }
 
-                                    Ok((deleted_count, deleted_size))
+                                    if failed.is_empty() {
+                                        Ok((deleted_count, deleted_size))
+                                    } else {
+                                        Err(anyhow!(
+                                            "Deleted {} backup(s) but failed to delete {}",
+                                            deleted_count,
+                                            failed.join(", ")
+                                        ))
+                                    }
+                                }
+                                Err(e) => Err(anyhow!("{}", e)),
+                            };
+
+                            let _ = response.send(result);
+                        }
+
+                        StorageProviderCommand::ApplyRetention { options, response } => {
+                            debug!("Processing ApplyRetention command");
+
+                            let list_result = list_backup_files(
+                                &operator,
+                                "",
+                                options.database.as_deref(),
+                                10000,
+                            )
+                            .await;
+
+                            let result = match list_result {
+                                Ok(entries) => {
+                                    let files: Vec<Entry> = entries
+                                        .iter()
+                                        .map(Entry::from)
+                                        .filter(|entry| entry.metadata.is_file)
+                                        .collect();
+
+                                    let backups: Vec<Entry> = attach_manifests(&operator, files)
+                                        .await
+                                        .into_iter()
+                                        .filter(|entry| match &options.database {
+                                            Some(database) => {
+                                                entry.database_name().as_ref() == Some(database)
+                                            }
+                                            None => true,
+                                        })
+                                        .collect();
+
+                                    let mut decisions = apply_policy(&options.policy, backups);
+
+                                    if !options.dry_run {
+                                        delete_backups(&mut decisions, |path| {
+                                            let operator = operator.clone();
+                                            async move { operator.delete(&path).await }
+                                        })
+                                        .await;
+                                    }
+
+                                    Ok(decisions)
                                 }
                                 Err(e) => Err(anyhow!("{}", e)),
                             };
@@ -462,9 +734,9 @@ impl StorageProvider {
                             debug!("Processing Shutdown command");
 
                             // Close all remaining writers
-                            for (writer_id, mut writer) in writers.drain() {
+                            for (writer_id, writer) in writers.drain() {
                                 debug!("Closing remaining writer {}", writer_id);
-                                if let Err(e) = writer.close().await {
+                                if let Err(e) = writer.close(&operator, uploader.as_ref()).await {
                                     error!("Error closing writer {}: {}", writer_id, e);
                                 }
                             }
@@ -504,6 +776,7 @@ impl StorageProvider {
         self.list_with_options(ListOptions {
             latest_only: None,
             limit: None,
+            database: None,
         })
         .await
     }
@@ -556,18 +829,54 @@ impl StorageProvider {
         response_rx.await?
     }
 
-    pub async fn cleanup(&self, retention_days: u64, dry_run: bool) -> Result<(usize, u64)> {
+    pub async fn read_manifest(&self, path: &str) -> Result<Option<BackupManifest>> {
         let (response_tx, response_rx) = oneshot::channel();
 
-        self.command_tx.send(StorageProviderCommand::Cleanup {
+        self.command_tx.send(StorageProviderCommand::ReadManifest {
+            path: path.to_string(),
+            response: response_tx,
+        })?;
+
+        response_rx.await?
+    }
+
+    pub async fn cleanup(&self, retention_days: u64, dry_run: bool) -> Result<(usize, u64)> {
+        self.cleanup_with_options(CleanupOptions {
             retention_days,
             dry_run,
+            database: None,
+        })
+        .await
+    }
+
+    pub async fn cleanup_with_options(&self, options: CleanupOptions) -> Result<(usize, u64)> {
+        let (response_tx, response_rx) = oneshot::channel();
+
+        self.command_tx.send(StorageProviderCommand::Cleanup {
+            options,
             response: response_tx,
         })?;
 
         response_rx.await?
     }
 
+    /// Applies a retention policy and returns what was kept and why. Nothing is deleted
+    /// when `dry_run` is set.
+    pub async fn apply_retention(
+        &self,
+        options: RetentionOptions,
+    ) -> Result<Vec<RetentionDecision>> {
+        let (response_tx, response_rx) = oneshot::channel();
+
+        self.command_tx
+            .send(StorageProviderCommand::ApplyRetention {
+                options,
+                response: response_tx,
+            })?;
+
+        response_rx.await?
+    }
+
     pub async fn shutdown(&self) -> Result<()> {
         let (response_tx, response_rx) = oneshot::channel();
 
@@ -579,6 +888,98 @@ impl StorageProvider {
     }
 }
 
+/// Deletes the backups that are not kept along with their manifests. When a backup cannot be
+/// deleted its manifest is left in place and the error is recorded on the decision.
+pub(crate) async fn delete_backups<F, Fut>(decisions: &mut [RetentionDecision], delete: F)
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = opendal::Result<()>>,
+{
+    for decision in decisions.iter_mut().filter(|d| !d.is_kept()) {
+        if let Err(e) = delete(decision.path.clone()).await {
+            error!("Failed to delete {}: {}", decision.path, e);
+            decision.delete_error = Some(e.to_string());
+            continue;
+        }
+
+        info!("Successfully deleted {}", decision.path);
+
+        let manifest_path = BackupManifest::path_for(&decision.path);
+        if let Err(e) = delete(manifest_path.clone()).await {
+            error!("Failed to delete {}: {}", manifest_path, e);
+        }
+    }
+}
+
+/// Lists the files under `root` that can be backups of the database, or of any database.
+/// Backups are stored under the prefix of their database and older ones at the root, so only
+/// those are listed when filtering by database.
+async fn list_backup_files(
+    operator: &Operator,
+    root: &str,
+    database: Option<&str>,
+    limit: usize,
+) -> opendal::Result<Vec<opendal::Entry>> {
+    let Some(database) = database else {
+        return operator.list_with(root).recursive(true).limit(limit).await;
+    };
+
+    let mut entries = operator.list_with(root).limit(limit).await?;
+
+    let prefix = slugify(database);
+    if !prefix.is_empty() {
+        entries.extend(
+            operator
+                .list_with(&format!("{}{}/", root, prefix))
+                .recursive(true)
+                .limit(limit)
+                .await?,
+        );
+    }
+
+    Ok(entries)
+}
+
+/// Hides manifest files from the entries and attaches them to the backup they describe.
+async fn attach_manifests(operator: &Operator, entries: Vec<Entry>) -> Vec<Entry> {
+    let (manifests, backups): (Vec<Entry>, Vec<Entry>) = entries
+        .into_iter()
+        .partition(|entry| BackupManifest::is_manifest(&entry.path));
+
+    let manifest_paths: HashSet<String> = manifests.into_iter().map(|entry| entry.path).collect();
+
+    futures::stream::iter(backups)
+        .map(|mut backup| {
+            let manifest_path = BackupManifest::path_for(&backup.path);
+            let has_manifest = manifest_paths.contains(&manifest_path);
+
+            async move {
+                if !has_manifest {
+                    return backup;
+                }
+
+                backup.manifest = match operator.read(&manifest_path).await {
+                    Ok(buffer) => match BackupManifest::from_bytes(&buffer.to_vec()) {
+                        Ok(manifest) => Some(manifest),
+                        Err(e) => {
+                            warn!("Ignoring manifest {}: {}", manifest_path, e);
+                            None
+                        }
+                    },
+                    Err(e) => {
+                        warn!("Failed to read manifest {}: {}", manifest_path, e);
+                        None
+                    }
+                };
+
+                backup
+            }
+        })
+        .buffer_unordered(MANIFEST_READ_CONCURRENCY)
+        .collect()
+        .await
+}
+
 impl Drop for StorageProvider {
     fn drop(&mut self) {
         // Attempt graceful shutdown
@@ -0,0 +1,540 @@
+//! SFTP storage. opendal's sftp service drives the openssh binary and can't authenticate
+//! with a password, so the accessor is implemented on top of libssh2 instead.
+
+#[cfg(feature = "ssh")]
+pub use backend::new_operator;
+
+#[cfg(not(feature = "ssh"))]
+pub fn new_operator(
+    _config: &super::provider::SftpStorageConfig,
+) -> anyhow::Result<opendal::Operator> {
+    Err(anyhow::anyhow!("dbkp was built without SFTP support"))
+}
+
+#[cfg(feature = "ssh")]
+// opendal's error type is what its accessor traits return
+#[allow(clippy::result_large_err)]
+mod backend {
+    use std::{
+        collections::VecDeque,
+        fmt::{self, Debug},
+        io::{Read, Seek, SeekFrom, Write},
+        path::{Path, PathBuf},
+        sync::{Arc, Mutex},
+    };
+
+    use chrono::{DateTime, Utc};
+    use opendal::{
+        layers::LoggingLayer,
+        raw::{
+            oio, Access, AccessorInfo, OpCreateDir, OpDelete, OpList, OpRead, OpStat, OpWrite,
+            RpCreateDir, RpDelete, RpList, RpRead, RpStat, RpWrite,
+        },
+        Buffer, Capability, EntryMode, Error, ErrorKind, Metadata, Operator, OperatorBuilder,
+        Result, Scheme,
+    };
+    use ssh2::{ErrorCode, FileStat, OpenFlags, OpenType, Sftp};
+
+    use crate::{
+        databases::ssh::tunnel::{expand_home, open_session},
+        storage::provider::{SftpStorageConfig, StorageCredentials},
+    };
+
+    const READ_CHUNK_SIZE: usize = 2 * 1024 * 1024;
+    // SFTP status codes, see draft-ietf-secsh-filexfer-02
+    const SSH_FX_NO_SUCH_FILE: i32 = 2;
+    const SSH_FX_PERMISSION_DENIED: i32 = 3;
+
+    pub fn new_operator(config: &SftpStorageConfig) -> anyhow::Result<Operator> {
+        let root = match config.location.as_str() {
+            "" => String::new(),
+            location if location.ends_with('/') => location.to_string(),
+            location => format!("{}/", location),
+        };
+
+        let info = AccessorInfo::default();
+        info.set_scheme(Scheme::Sftp)
+            .set_root(&root)
+            .set_native_capability(Capability {
+                stat: true,
+                stat_has_content_length: true,
+                stat_has_last_modified: true,
+                read: true,
+                write: true,
+                write_can_empty: true,
+                // Writes are appended to the open remote file as they come
+                write_can_multi: true,
+                create_dir: true,
+                delete: true,
+                list: true,
+                list_has_content_length: true,
+                list_has_last_modified: true,
+                shared: true,
+                ..Default::default()
+            });
+
+        let backend = SftpBackend {
+            core: Arc::new(SftpCore {
+                info: Arc::new(info),
+                config: config.clone(),
+                root,
+                sftp: Mutex::new(None),
+            }),
+        };
+
+        Ok(OperatorBuilder::new(backend)
+            .layer(LoggingLayer::default())
+            .finish())
+    }
+
+    struct SftpCore {
+        info: Arc<AccessorInfo>,
+        config: SftpStorageConfig,
+        root: String,
+        /// Opened on first use and shared by every operation.
+        sftp: Mutex<Option<Arc<Sftp>>>,
+    }
+
+    impl SftpCore {
+        fn sftp(&self) -> Result<Arc<Sftp>> {
+            let mut sftp = self
+                .sftp
+                .lock()
+                .map_err(|_| Error::new(ErrorKind::Unexpected, "SFTP session lock poisoned"))?;
+
+            if let Some(sftp) = sftp.as_ref() {
+                return Ok(sftp.clone());
+            }
+
+            let session = self.connect().map_err(|e| {
+                Error::new(ErrorKind::Unexpected, "failed to open SFTP session").set_source(e)
+            })?;
+            let session = Arc::new(session.sftp().map_err(parse_error)?);
+            *sftp = Some(session.clone());
+
+            Ok(session)
+        }
+
+        fn connect(&self) -> anyhow::Result<ssh2::Session> {
+            let config = &self.config;
+            let session = open_session(&config.host, config.port, config.accept_new_host_key)?;
+
+            let (username, auth_result) = match &config.credentials {
+                StorageCredentials::Basic { username, password } => {
+                    (username, session.userauth_password(username, password))
+                }
+                StorageCredentials::PrivateKey {
+                    username,
+                    key_path,
+                    passphrase,
+                } => (
+                    username,
+                    session.userauth_pubkey_file(
+                        username,
+                        None,
+                        &expand_home(key_path),
+                        passphrase.as_deref(),
+                    ),
+                ),
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "SFTP storage requires a password or a private key"
+                    ))
+                }
+            };
+
+            auth_result.map_err(|e| {
+                anyhow::anyhow!(
+                    "SFTP authentication as {} on {} failed: {}",
+                    username,
+                    config.host,
+                    e
+                )
+            })?;
+
+            if !session.authenticated() {
+                return Err(anyhow::anyhow!(
+                    "SFTP authentication as {} on {} failed",
+                    username,
+                    config.host
+                ));
+            }
+
+            Ok(session)
+        }
+
+        /// Remote path of a path relative to the storage location.
+        fn remote_path(&self, path: &str) -> PathBuf {
+            let path = format!("{}{}", self.root, path.trim_start_matches('/'));
+            match path.trim_end_matches('/') {
+                "" if path.starts_with('/') => PathBuf::from("/"),
+                "" => PathBuf::from("."),
+                path => PathBuf::from(path),
+            }
+        }
+
+        /// Creates the directory and its parents, the storage location included, like
+        /// `mkdir -p`.
+        fn create_dir_all(&self, sftp: &Sftp, path: &str) -> Result<()> {
+            let remote_path = self.remote_path(path);
+            let missing: Vec<&Path> = remote_path
+                .ancestors()
+                .filter(|dir| !dir.as_os_str().is_empty())
+                .take_while(|dir| sftp.stat(dir).is_err())
+                .collect();
+
+            for dir in missing.into_iter().rev() {
+                if let Err(e) = sftp.mkdir(dir, 0o755) {
+                    // Another writer may have created it in the meantime
+                    if sftp.stat(dir).is_err() {
+                        return Err(parse_error(e));
+                    }
+                }
+            }
+
+            Ok(())
+        }
+    }
+
+    /// Runs blocking libssh2 calls off the async runtime.
+    async fn blocking<T, F>(f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> Result<T> + Send + 'static,
+    {
+        tokio::task::spawn_blocking(f).await.map_err(|e| {
+            Error::new(ErrorKind::Unexpected, "SFTP task failed").set_source(anyhow::anyhow!(e))
+        })?
+    }
+
+    fn parse_error(e: ssh2::Error) -> Error {
+        let kind = match e.code() {
+            ErrorCode::SFTP(SSH_FX_NO_SUCH_FILE) => ErrorKind::NotFound,
+            ErrorCode::SFTP(SSH_FX_PERMISSION_DENIED) => ErrorKind::PermissionDenied,
+            _ => ErrorKind::Unexpected,
+        };
+
+        Error::new(kind, e.message().to_string()).set_source(anyhow::anyhow!(e))
+    }
+
+    fn parse_io_error(e: std::io::Error) -> Error {
+        Error::new(ErrorKind::Unexpected, "SFTP transfer failed").set_source(e)
+    }
+
+    fn metadata(stat: &FileStat) -> Metadata {
+        let mode = if stat.is_dir() {
+            EntryMode::DIR
+        } else if stat.is_file() {
+            EntryMode::FILE
+        } else {
+            EntryMode::Unknown
+        };
+
+        let mut metadata = Metadata::new(mode);
+
+        if let Some(size) = stat.size {
+            metadata.set_content_length(size);
+        }
+
+        if let Some(modified) = stat
+            .mtime
+            .and_then(|mtime| DateTime::<Utc>::from_timestamp(mtime as i64, 0))
+        {
+            metadata.set_last_modified(modified);
+        }
+
+        metadata
+    }
+
+    #[derive(Clone)]
+    struct SftpBackend {
+        core: Arc<SftpCore>,
+    }
+
+    impl Debug for SftpBackend {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("SftpBackend")
+                .field("host", &self.core.config.host)
+                .field("port", &self.core.config.port)
+                .field("root", &self.core.root)
+                .finish()
+        }
+    }
+
+    impl Access for SftpBackend {
+        type Reader = SftpReader;
+        type Writer = SftpWriter;
+        type Lister = Option<SftpLister>;
+        type Deleter = oio::OneShotDeleter<SftpDeleter>;
+        type BlockingReader = ();
+        type BlockingWriter = ();
+        type BlockingLister = ();
+        type BlockingDeleter = ();
+
+        fn info(&self) -> Arc<AccessorInfo> {
+            self.core.info.clone()
+        }
+
+        async fn create_dir(&self, path: &str, _: OpCreateDir) -> Result<RpCreateDir> {
+            let core = self.core.clone();
+            let path = path.to_string();
+
+            blocking(move || {
+                let sftp = core.sftp()?;
+                core.create_dir_all(&sftp, &path)
+            })
+            .await?;
+
+            Ok(RpCreateDir::default())
+        }
+
+        async fn stat(&self, path: &str, _: OpStat) -> Result<RpStat> {
+            let core = self.core.clone();
+            let path = path.to_string();
+
+            let stat = blocking(move || {
+                let sftp = core.sftp()?;
+                sftp.stat(&core.remote_path(&path)).map_err(parse_error)
+            })
+            .await?;
+
+            Ok(RpStat::new(metadata(&stat)))
+        }
+
+        async fn read(&self, path: &str, args: OpRead) -> Result<(RpRead, Self::Reader)> {
+            let core = self.core.clone();
+            let path = path.to_string();
+            let offset = args.range().offset();
+
+            let file = blocking(move || {
+                let sftp = core.sftp()?;
+                let mut file = sftp.open(core.remote_path(&path)).map_err(parse_error)?;
+                if offset != 0 {
+                    file.seek(SeekFrom::Start(offset)).map_err(parse_io_error)?;
+                }
+                Ok(file)
+            })
+            .await?;
+
+            Ok((
+                RpRead::default(),
+                SftpReader {
+                    file: Some(file),
+                    remaining: args.range().size(),
+                },
+            ))
+        }
+
+        async fn write(&self, path: &str, _: OpWrite) -> Result<(RpWrite, Self::Writer)> {
+            let core = self.core.clone();
+            let remote = path.to_string();
+
+            let file = blocking(move || {
+                let path = remote;
+                let sftp = core.sftp()?;
+                let dir = path
+                    .rsplit_once('/')
+                    .map(|(dir, _)| dir)
+                    .unwrap_or_default();
+                core.create_dir_all(&sftp, dir)?;
+
+                sftp.open_mode(
+                    core.remote_path(&path),
+                    OpenFlags::WRITE | OpenFlags::CREATE | OpenFlags::TRUNCATE,
+                    0o644,
+                    OpenType::File,
+                )
+                .map_err(parse_error)
+            })
+            .await?;
+
+            Ok((
+                RpWrite::default(),
+                SftpWriter {
+                    core: self.core.clone(),
+                    path: path.to_string(),
+                    file: Some(file),
+                },
+            ))
+        }
+
+        async fn delete(&self) -> Result<(RpDelete, Self::Deleter)> {
+            Ok((
+                RpDelete::default(),
+                oio::OneShotDeleter::new(SftpDeleter {
+                    core: self.core.clone(),
+                }),
+            ))
+        }
+
+        async fn list(&self, path: &str, _: OpList) -> Result<(RpList, Self::Lister)> {
+            let core = self.core.clone();
+            let path = path.to_string();
+
+            let lister = blocking(move || {
+                let sftp = core.sftp()?;
+                let entries = match sftp.readdir(core.remote_path(&path)) {
+                    Ok(entries) => entries,
+                    Err(e) => {
+                        let e = parse_error(e);
+                        return match e.kind() {
+                            ErrorKind::NotFound => Ok(None),
+                            _ => Err(e),
+                        };
+                    }
+                };
+
+                let prefix = path.trim_start_matches('/');
+                let entries = entries
+                    .iter()
+                    .filter_map(|(remote_path, stat)| {
+                        let name = remote_path.file_name()?.to_str()?;
+                        if name == "." || name == ".." {
+                            return None;
+                        }
+
+                        let suffix = if stat.is_dir() { "/" } else { "" };
+                        Some(oio::Entry::new(
+                            &format!("{}{}{}", prefix, name, suffix),
+                            metadata(stat),
+                        ))
+                    })
+                    .collect();
+
+                Ok(Some(SftpLister { entries }))
+            })
+            .await?;
+
+            Ok((RpList::default(), lister))
+        }
+    }
+
+    struct SftpReader {
+        file: Option<ssh2::File>,
+        remaining: Option<u64>,
+    }
+
+    impl oio::Read for SftpReader {
+        async fn read(&mut self) -> Result<Buffer> {
+            let size = match self.remaining {
+                Some(0) => return Ok(Buffer::new()),
+                Some(remaining) => (remaining as usize).min(READ_CHUNK_SIZE),
+                None => READ_CHUNK_SIZE,
+            };
+
+            let Some(mut file) = self.file.take() else {
+                return Ok(Buffer::new());
+            };
+
+            let (file, data) = blocking(move || {
+                let mut data = vec![0u8; size];
+                let mut read = 0;
+
+                while read < size {
+                    match file.read(&mut data[read..]).map_err(parse_io_error)? {
+                        0 => break,
+                        n => read += n,
+                    }
+                }
+
+                data.truncate(read);
+                Ok((file, data))
+            })
+            .await?;
+
+            if !data.is_empty() {
+                self.file = Some(file);
+            }
+            if let Some(remaining) = self.remaining.as_mut() {
+                *remaining -= data.len() as u64;
+            }
+
+            Ok(Buffer::from(data))
+        }
+    }
+
+    struct SftpWriter {
+        core: Arc<SftpCore>,
+        path: String,
+        file: Option<ssh2::File>,
+    }
+
+    impl oio::Write for SftpWriter {
+        async fn write(&mut self, bs: Buffer) -> Result<()> {
+            let Some(mut file) = self.file.take() else {
+                return Err(Error::new(ErrorKind::Unexpected, "SFTP writer is closed"));
+            };
+
+            let file = blocking(move || {
+                file.write_all(&bs.to_bytes()).map_err(parse_io_error)?;
+                Ok(file)
+            })
+            .await?;
+
+            self.file = Some(file);
+
+            Ok(())
+        }
+
+        async fn close(&mut self) -> Result<Metadata> {
+            if let Some(mut file) = self.file.take() {
+                blocking(move || file.close().map_err(parse_error)).await?;
+            }
+
+            Ok(Metadata::default())
+        }
+
+        async fn abort(&mut self) -> Result<()> {
+            self.file = None;
+
+            let core = self.core.clone();
+            let path = self.path.clone();
+
+            blocking(move || {
+                let sftp = core.sftp()?;
+                match sftp.unlink(&core.remote_path(&path)).map_err(parse_error) {
+                    Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+                    _ => Ok(()),
+                }
+            })
+            .await
+        }
+    }
+
+    struct SftpLister {
+        entries: VecDeque<oio::Entry>,
+    }
+
+    impl oio::List for SftpLister {
+        async fn next(&mut self) -> Result<Option<oio::Entry>> {
+            Ok(self.entries.pop_front())
+        }
+    }
+
+    struct SftpDeleter {
+        core: Arc<SftpCore>,
+    }
+
+    impl oio::OneShotDelete for SftpDeleter {
+        async fn delete_once(&self, path: String, _: OpDelete) -> Result<()> {
+            let core = self.core.clone();
+
+            blocking(move || {
+                let sftp = core.sftp()?;
+                let remote_path = core.remote_path(&path);
+
+                let result = if path.ends_with('/') {
+                    sftp.rmdir(&remote_path)
+                } else {
+                    sftp.unlink(&remote_path)
+                };
+
+                match result.map_err(parse_error) {
+                    Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+                    _ => Ok(()),
+                }
+            })
+            .await
+        }
+    }
+}
@@ -8,9 +8,13 @@
 //! - Empty file handling
 //! - Large file operations (10KB test files)
 //! - List operations with timestamp sorting
-//! - List operations with options (latest_only, limit)
+//! - List operations with options (latest_only, limit, database)
 //! - Delete operations
-//! - Cleanup operations with retention policies
+//! - Cleanup operations with retention policies, optionally restricted to one database
+//! - Grandfather-father-son retention with the reason each backup is kept
+//! - Retention deletes that fail are recorded on the decision instead of reported as deleted
+//! - Backup manifests attached to listed entries and used by cleanup
+//! - Checksum verification against the manifest
 //! - Error handling for edge cases
 //!
 //! ## S3 Storage Tests (`s3_storage_tests`)
@@ -18,6 +22,10 @@
 //! - Write and read operations
 //! - List operations with cleanup
 //!
+//! ## SFTP Storage Tests (`sftp_storage_tests`)
+//! - Write, read, list and delete in nested directories (skips if SFTP_HOST is unset)
+//! - Cleanup operations with retention policies
+//!
 //! ## Edge Cases and Integration Tests (`edge_cases_and_integration_tests`)
 //! - Concurrent operations testing
 //! - Filename timestamp extraction validation
@@ -34,15 +42,30 @@
 #[cfg(test)]
 mod storage_tests {
     use crate::{
+        checksum::{verify_checksum, HashingWriter},
         common::extract_timestamp_from_filename,
+        compression::CompressionFormat,
+        databases::{
+            postgres::version::PostgreSQLVersion, version::Version, ConnectionType, DatabaseConfig,
+            DumpFormat,
+        },
+        manifest::BackupManifest,
+        retention::{RetentionDecision, RetentionPolicy, RetentionReason},
         storage::{
-            provider::{ListOptions, StorageProvider},
+            provider::{
+                delete_backups, CleanupOptions, ListOptions, RetentionOptions, StorageProvider,
+            },
             Entry,
         },
-        test_utils::test_utils::{get_local_provider, get_s3_provider, initialize_test},
+        test_utils::test_utils::{
+            get_local_provider, get_s3_provider, get_sftp_provider, initialize_test,
+        },
+    };
+    use chrono::{DateTime, TimeZone, Utc};
+    use std::{
+        io::{Cursor, Read, Write},
+        sync::Mutex,
     };
-    use chrono::Utc;
-    use std::io::{Cursor, Read, Write};
 
     const TEST_CONTENT: &[u8] = b"This is test content for storage operations";
     const TEST_FILENAME: &str = "test_backup_2024-01-15-120000-abc123.dump";
@@ -83,6 +106,42 @@ mod storage_tests {
         Ok(())
     }
 
+    // Helper function to build a manifest for a backup
+    fn create_test_manifest(name: &str, started_at: DateTime<Utc>) -> BackupManifest {
+        BackupManifest {
+            name: name.to_string(),
+            dbkp_version: "test".into(),
+            database: DatabaseConfig {
+                id: "test".into(),
+                name: "test".into(),
+                connection_type: ConnectionType::PostgreSql,
+                host: "localhost".into(),
+                port: 5432,
+                database: "test".into(),
+                username: "test".into(),
+                password: None,
+                ssh: None,
+            },
+            server_version: Version::PostgreSQL(PostgreSQLVersion {
+                major: 17,
+                minor: 0,
+            }),
+            dump_format: DumpFormat::Plain,
+            filters: Default::default(),
+            masked: false,
+            compression_format: CompressionFormat::Gzip,
+            compression_level: 9,
+            encryption: None,
+            uncompressed_size: 0,
+            compressed_size: 0,
+            checksum_algorithm: Default::default(),
+            checksum: String::new(),
+            table_stats: vec![],
+            started_at,
+            finished_at: started_at,
+        }
+    }
+
     // Helper function to read content from storage
     async fn read_test_content(
         provider: &StorageProvider,
@@ -243,6 +302,7 @@ mod storage_tests {
                 .list_with_options(ListOptions {
                     latest_only: Some(true),
                     limit: None,
+                    database: None,
                 })
                 .await;
 
@@ -260,6 +320,7 @@ mod storage_tests {
                 .list_with_options(ListOptions {
                     latest_only: None,
                     limit: Some(10), // Use a higher limit since filtering happens after
+                    database: None,
                 })
                 .await
                 .expect("Failed to list with limit");
@@ -368,6 +429,307 @@ mod storage_tests {
             assert!(new_file_exists, "New file should remain");
         }
 
+        #[tokio::test]
+        async fn test_cleanup_by_database() {
+            initialize_test();
+            let provider = get_local_provider().expect("Failed to create local provider");
+
+            // "shop-eu" shares a prefix with "shop" but is a different database
+            let shop_backup = "shop-2023-01-01-120000-abc123.gz";
+            let other_backup = "shop-eu-2023-01-01-120000-def456.gz";
+
+            for file in [shop_backup, other_backup] {
+                write_test_content(&provider, file, b"old content")
+                    .await
+                    .expect("Failed to write backup");
+            }
+
+            let (deleted_count, _) = provider
+                .cleanup_with_options(CleanupOptions {
+                    retention_days: 30,
+                    dry_run: false,
+                    database: Some("shop".into()),
+                })
+                .await
+                .expect("Failed to perform cleanup");
+
+            assert_eq!(deleted_count, 1, "Only the shop backup should be deleted");
+
+            let entries = provider.list().await.expect("Failed to list entries");
+            assert!(!entries.iter().any(|e| e.metadata.name == shop_backup));
+            assert!(entries.iter().any(|e| e.metadata.name == other_backup));
+        }
+
+        #[tokio::test]
+        async fn test_list_by_database() {
+            initialize_test();
+            let provider = get_local_provider().expect("Failed to create local provider");
+
+            let shop_backup = "shop/shop-2024-01-02-120000-abc123.gz";
+            let legacy_shop_backup = "shop-2024-01-01-120000-def456.gz";
+            let other_backup = "shop-eu/shop-eu-2024-01-03-120000-fed789.gz";
+            // Only the prefix of the database and the root are listed
+            let elsewhere_backup = "archive/shop-2024-01-04-120000-bad000.gz";
+
+            for file in [
+                shop_backup,
+                legacy_shop_backup,
+                other_backup,
+                elsewhere_backup,
+            ] {
+                write_test_content(&provider, file, b"content")
+                    .await
+                    .expect("Failed to write backup");
+            }
+
+            let entries = provider
+                .list_with_options(ListOptions {
+                    latest_only: None,
+                    limit: None,
+                    database: Some("shop".into()),
+                })
+                .await
+                .expect("Failed to list entries");
+
+            let paths: Vec<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+            assert_eq!(paths, vec![shop_backup, legacy_shop_backup]);
+        }
+
+        #[tokio::test]
+        async fn test_apply_retention() {
+            initialize_test();
+            let provider = get_local_provider().expect("Failed to create local provider");
+
+            let latest = "shop-2023-06-01-120000-abc123.gz";
+            let same_year = "shop-2023-01-01-120000-def456.gz";
+            let previous_year = "shop-2022-01-01-120000-fed789.gz";
+
+            for file in [latest, same_year, previous_year] {
+                write_test_content(&provider, file, b"content")
+                    .await
+                    .expect("Failed to write backup");
+            }
+
+            let options = RetentionOptions {
+                policy: RetentionPolicy {
+                    keep_yearly: 2,
+                    ..Default::default()
+                },
+                dry_run: true,
+                database: Some("shop".into()),
+            };
+
+            let decisions = provider
+                .apply_retention(options.clone())
+                .await
+                .expect("Failed to apply retention");
+
+            let reasons = |name: &str| {
+                decisions
+                    .iter()
+                    .find(|d| d.name == name)
+                    .map(|d| d.reasons.clone())
+                    .expect("Missing decision")
+            };
+
+            assert_eq!(
+                reasons(latest),
+                vec![
+                    RetentionReason::LatestSuccessful,
+                    RetentionReason::Yearly("2023".into())
+                ]
+            );
+            assert!(reasons(same_year).is_empty());
+            assert_eq!(
+                reasons(previous_year),
+                vec![RetentionReason::Yearly("2022".into())]
+            );
+
+            // Dry run leaves every backup in place
+            let entries = provider.list().await.expect("Failed to list entries");
+            assert!(entries.iter().any(|e| e.metadata.name == same_year));
+
+            provider
+                .apply_retention(RetentionOptions {
+                    dry_run: false,
+                    ..options
+                })
+                .await
+                .expect("Failed to apply retention");
+
+            let entries = provider.list().await.expect("Failed to list entries");
+            assert!(!entries.iter().any(|e| e.metadata.name == same_year));
+            assert!(entries.iter().any(|e| e.metadata.name == latest));
+            assert!(entries.iter().any(|e| e.metadata.name == previous_year));
+        }
+
+        #[tokio::test]
+        async fn test_apply_retention_failed_delete() {
+            let backup = |name: &str, kept: bool| RetentionDecision {
+                path: name.into(),
+                name: name.into(),
+                database: Some("shop".into()),
+                timestamp: None,
+                size: 7,
+                reasons: if kept {
+                    vec![RetentionReason::LatestSuccessful]
+                } else {
+                    vec![]
+                },
+                delete_error: None,
+            };
+
+            let mut decisions = vec![
+                backup("shop-2023-06-01-120000-abc123.gz", true),
+                backup("shop-2023-01-01-120000-def456.gz", false),
+                backup("shop-2022-01-01-120000-fed789.gz", false),
+            ];
+
+            let deleted = Mutex::new(vec![]);
+            delete_backups(&mut decisions, |path| {
+                let result = if path.starts_with("shop-2023-01-01") {
+                    Err(opendal::Error::new(
+                        opendal::ErrorKind::PermissionDenied,
+                        "permission denied",
+                    ))
+                } else {
+                    deleted.lock().unwrap().push(path);
+                    Ok(())
+                };
+                async move { result }
+            })
+            .await;
+
+            assert!(decisions[0].delete_error.is_none());
+            assert!(decisions[1]
+                .delete_error
+                .as_ref()
+                .is_some_and(|e| e.contains("permission denied")));
+            assert!(decisions[2].delete_error.is_none());
+
+            // The manifest of the backup that failed to delete is left in place
+            assert_eq!(
+                *deleted.lock().unwrap(),
+                vec![
+                    "shop-2022-01-01-120000-fed789.gz".to_string(),
+                    BackupManifest::path_for("shop-2022-01-01-120000-fed789.gz"),
+                ]
+            );
+        }
+
+        #[tokio::test]
+        async fn test_manifest_operations() {
+            initialize_test();
+            let provider = get_local_provider().expect("Failed to create local provider");
+
+            // The file name says 2020 but the manifest says the backup is recent
+            let recent_backup = "test-2020-01-01-120000-abc123.gz";
+            let old_backup = "test-2024-01-01-120000-def456.gz";
+            let old_timestamp = Utc.with_ymd_and_hms(2020, 1, 1, 12, 0, 0).unwrap();
+
+            for (backup, started_at) in [(recent_backup, Utc::now()), (old_backup, old_timestamp)] {
+                let manifest = create_test_manifest(backup, started_at);
+                write_test_content(&provider, backup, TEST_CONTENT)
+                    .await
+                    .expect("Failed to write backup");
+                write_test_content(
+                    &provider,
+                    &BackupManifest::path_for(backup),
+                    &manifest.to_bytes().expect("Failed to serialize manifest"),
+                )
+                .await
+                .expect("Failed to write manifest");
+            }
+
+            let entries = provider.list().await.expect("Failed to list entries");
+
+            assert_eq!(entries.len(), 2, "Manifests should not be listed");
+            assert_eq!(entries[0].metadata.name, recent_backup);
+            assert!(entries.iter().all(|e| e.manifest.is_some()));
+
+            let manifest = provider
+                .read_manifest(recent_backup)
+                .await
+                .expect("Failed to read manifest")
+                .expect("Manifest should exist");
+            assert_eq!(manifest.name, recent_backup);
+
+            let missing = provider
+                .read_manifest("missing.gz")
+                .await
+                .expect("Failed to read missing manifest");
+            assert!(missing.is_none());
+
+            let (deleted_count, _) = provider
+                .cleanup(30, false)
+                .await
+                .expect("Failed to cleanup");
+
+            assert_eq!(deleted_count, 1, "Only the old backup should be deleted");
+
+            let old_manifest = provider
+                .read_manifest(old_backup)
+                .await
+                .expect("Failed to read manifest");
+            assert!(old_manifest.is_none(), "Manifest should be deleted too");
+
+            let entries = provider.list().await.expect("Failed to list entries");
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].metadata.name, recent_backup);
+        }
+
+        #[tokio::test]
+        async fn test_verify_checksum() {
+            initialize_test();
+            let provider = get_local_provider().expect("Failed to create local provider");
+            let backup = "test-2024-01-01-120000-abc123.gz";
+
+            let mut hashing_writer = HashingWriter::new(Vec::new());
+            hashing_writer
+                .write_all(TEST_CONTENT)
+                .expect("Failed to hash content");
+            let (_, size, checksum) = hashing_writer.finish();
+
+            let mut manifest = create_test_manifest(backup, Utc::now());
+            manifest.compressed_size = size;
+            manifest.checksum = checksum;
+
+            write_test_content(&provider, backup, TEST_CONTENT)
+                .await
+                .expect("Failed to write backup");
+            write_test_content(
+                &provider,
+                &BackupManifest::path_for(backup),
+                &manifest.to_bytes().expect("Failed to serialize manifest"),
+            )
+            .await
+            .expect("Failed to write manifest");
+
+            let verification = verify_checksum(&provider, backup)
+                .await
+                .expect("Failed to verify checksum");
+            assert!(verification.is_valid());
+
+            // Same size, different bytes
+            let mut corrupted = TEST_CONTENT.to_vec();
+            corrupted[0] ^= 0xFF;
+            write_test_content(&provider, backup, &corrupted)
+                .await
+                .expect("Failed to overwrite backup");
+
+            let verification = verify_checksum(&provider, backup)
+                .await
+                .expect("Failed to verify checksum");
+            assert!(!verification.is_valid());
+            assert_eq!(verification.actual_size, verification.expected_size);
+
+            let missing = verify_checksum(&provider, "missing.gz").await;
+            assert!(
+                missing.is_err(),
+                "A backup without manifest can't be verified"
+            );
+        }
+
         #[tokio::test]
         async fn test_error_handling() {
             initialize_test();
@@ -387,6 +749,7 @@ mod storage_tests {
                 .list_with_options(ListOptions {
                     latest_only: Some(true),
                     limit: None,
+                    database: None,
                 })
                 .await;
 
@@ -495,6 +858,101 @@ mod storage_tests {
         }
     }
 
+    mod sftp_storage_tests {
+        use super::*;
+
+        #[tokio::test]
+        async fn test_write_read_list_and_delete() {
+            initialize_test();
+            let provider = match get_sftp_provider().expect("Unable to get sftp provider") {
+                Some(provider) => provider,
+                None => {
+                    println!("SFTP_HOST not set, skipping SFTP tests");
+                    return;
+                }
+            };
+
+            assert!(provider.test().await.expect("SFTP connection failed"));
+
+            let test_file = "sftp_db/sftp_db-2024-01-15-120000-abc123.gz";
+            let content = create_test_content(3 * 1024 * 1024);
+
+            write_test_content(&provider, test_file, &content)
+                .await
+                .expect("Failed to write test file");
+
+            let read_content = read_test_content(&provider, test_file)
+                .await
+                .expect("Failed to read test file");
+            assert_eq!(read_content, content, "Read content should match");
+
+            let entries = provider
+                .list_with_options(ListOptions {
+                    latest_only: None,
+                    limit: None,
+                    database: Some("sftp_db".into()),
+                })
+                .await
+                .expect("Failed to list entries");
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].path, test_file);
+            assert_eq!(entries[0].metadata.content_length, content.len() as u64);
+            assert!(entries[0].metadata.last_modified.is_some());
+
+            provider
+                .delete(test_file)
+                .await
+                .expect("Failed to delete test file");
+
+            let entries = provider.list().await.expect("Failed to list entries");
+            assert!(entries.iter().all(|e| e.path != test_file));
+
+            // Deleting a missing file is not an error, like the other backends
+            provider
+                .delete(test_file)
+                .await
+                .expect("Failed to delete missing file");
+        }
+
+        #[tokio::test]
+        async fn test_cleanup_operations() {
+            initialize_test();
+            let provider = match get_sftp_provider().expect("Unable to get sftp provider") {
+                Some(provider) => provider,
+                None => {
+                    println!("SFTP_HOST not set, skipping SFTP tests");
+                    return;
+                }
+            };
+
+            let old_file = "backup/backup-2023-01-01-120000-abc123.dump";
+            let new_file = format!(
+                "backup/backup-{}-def456.dump",
+                Utc::now().format("%Y-%m-%d-%H%M%S")
+            );
+
+            write_test_content(&provider, old_file, b"old content")
+                .await
+                .expect("Failed to write old file");
+            write_test_content(&provider, &new_file, b"new content")
+                .await
+                .expect("Failed to write new file");
+
+            let (count, size) = provider
+                .cleanup(30, false)
+                .await
+                .expect("Failed to perform cleanup");
+            assert_eq!(count, 1);
+            assert_eq!(size, b"old content".len() as u64);
+
+            let entries = provider.list().await.expect("Failed to list entries");
+            assert!(entries.iter().all(|e| e.path != old_file));
+            assert!(entries.iter().any(|e| e.path == new_file));
+
+            let _ = provider.delete(&new_file).await;
+        }
+    }
+
     mod edge_cases_and_integration_tests {
         use super::*;
 
@@ -0,0 +1,266 @@
+//! WebDAV uploads. opendal's webdav service can only PUT a body held in memory, so backups are
+//! spooled to a temporary file and streamed from it in a single PUT once complete.
+
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use reqwest::{header::CONTENT_LENGTH, Body, Client};
+use std::time::Duration;
+use tokio::{fs::File, io::AsyncReadExt};
+
+use super::provider::{StorageCredentials, WebDavStorageConfig};
+
+const UPLOAD_CHUNK_SIZE: usize = 1024 * 1024;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+/// Slowest upload, in bytes per second, waited for before giving up on it.
+const MIN_UPLOAD_RATE: u64 = 64 * 1024;
+
+pub struct WebDavUploader {
+    client: Client,
+    endpoint: String,
+    root: String,
+    credentials: Option<(String, String)>,
+}
+
+impl WebDavUploader {
+    pub fn new(config: &WebDavStorageConfig) -> Result<Self> {
+        let credentials = match &config.credentials {
+            StorageCredentials::Basic { username, password } => {
+                Some((username.clone(), password.clone()))
+            }
+            StorageCredentials::None => None,
+            _ => return Err(anyhow!("WebDAV storage only supports basic authentication")),
+        };
+
+        let client = Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+
+        Ok(Self {
+            client,
+            endpoint: config.endpoint.trim_end_matches('/').to_string(),
+            root: normalize_root(&config.location),
+            credentials,
+        })
+    }
+
+    /// URL of a path relative to the storage location, built like opendal's webdav service does.
+    fn url(&self, path: &str) -> String {
+        format!(
+            "{}{}",
+            self.endpoint,
+            percent_encode_path(&format!("{}{}", self.root, path.trim_start_matches('/')))
+        )
+    }
+
+    /// Streams `file` to `path`, whose parent collection must exist.
+    pub async fn upload(&self, path: &str, file: File) -> Result<()> {
+        let size = file.metadata().await?.len();
+
+        let body = futures::stream::unfold(Some(file), |file| async move {
+            let mut file = file?;
+            let mut chunk = vec![0u8; UPLOAD_CHUNK_SIZE];
+
+            match file.read(&mut chunk).await {
+                Ok(0) => None,
+                Ok(n) => {
+                    chunk.truncate(n);
+                    Some((Ok(Bytes::from(chunk)), Some(file)))
+                }
+                // Ends the stream after the error, reqwest aborts the upload on it
+                Err(e) => Some((Err(e), None)),
+            }
+        });
+
+        let mut request = self
+            .client
+            .put(self.url(path))
+            .timeout(REQUEST_TIMEOUT + Duration::from_secs(size / MIN_UPLOAD_RATE))
+            .header(CONTENT_LENGTH, size)
+            .body(Body::wrap_stream(body));
+        if let Some((username, password)) = &self.credentials {
+            request = request.basic_auth(username, Some(password));
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| anyhow!("WebDAV upload of {} failed: {}", path, e))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow!(
+                "WebDAV upload of {} failed with {}: {}",
+                path,
+                status,
+                body.trim()
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// The location as an absolute collection path, `/` when empty.
+fn normalize_root(location: &str) -> String {
+    let segments: Vec<&str> = location
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+
+    if segments.is_empty() {
+        "/".to_string()
+    } else {
+        format!("/{}/", segments.join("/"))
+    }
+}
+
+/// Percent-encodes everything but `/` and the characters `encodeURIComponent` leaves alone.
+fn percent_encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z'
+            | b'a'..=b'z'
+            | b'0'..=b'9'
+            | b'/'
+            | b'-'
+            | b'_'
+            | b'.'
+            | b'!'
+            | b'~'
+            | b'*'
+            | b'\''
+            | b'('
+            | b')' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Seek, SeekFrom, Write};
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    /// Accepts one request and answers it with `status`, returns the request head and body.
+    async fn serve_once(listener: TcpListener, status: &'static str) -> (String, Vec<u8>) {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = vec![];
+        let mut buffer = [0u8; 64 * 1024];
+
+        let head_end = loop {
+            let n = stream.read(&mut buffer).await.unwrap();
+            request.extend_from_slice(&buffer[..n]);
+            if let Some(position) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                break position + 4;
+            }
+        };
+
+        let head = String::from_utf8_lossy(&request[..head_end]).to_string();
+        let length: usize = head
+            .lines()
+            .find_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                name.eq_ignore_ascii_case("content-length")
+                    .then(|| value.trim().parse().unwrap())
+            })
+            .expect("The upload should have a content length");
+
+        let mut body = request[head_end..].to_vec();
+        while body.len() < length {
+            let n = stream.read(&mut buffer).await.unwrap();
+            body.extend_from_slice(&buffer[..n]);
+        }
+
+        stream
+            .write_all(format!("HTTP/1.1 {}\r\nContent-Length: 0\r\n\r\n", status).as_bytes())
+            .await
+            .unwrap();
+
+        (head, body)
+    }
+
+    fn uploader(endpoint: String) -> WebDavUploader {
+        WebDavUploader::new(&WebDavStorageConfig {
+            id: "test".into(),
+            name: "test".into(),
+            endpoint,
+            credentials: StorageCredentials::Basic {
+                username: "user".into(),
+                password: "secret".into(),
+            },
+            location: "backups".into(),
+        })
+        .unwrap()
+    }
+
+    fn spooled(content: &[u8]) -> File {
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(content).unwrap();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        File::from_std(file)
+    }
+
+    #[tokio::test]
+    async fn test_streamed_upload() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/dav/", listener.local_addr().unwrap());
+        let server = tokio::spawn(serve_once(listener, "201 Created"));
+
+        let content: Vec<u8> = (0..3 * UPLOAD_CHUNK_SIZE + 17)
+            .map(|i| (i % 251) as u8)
+            .collect();
+        uploader(endpoint)
+            .upload("app db/app-2024-01-15-120000-abc123.gz", spooled(&content))
+            .await
+            .expect("Upload failed");
+
+        let (head, body) = server.await.unwrap();
+        assert!(
+            head.starts_with("PUT /dav/backups/app%20db/app-2024-01-15-120000-abc123.gz HTTP/1.1"),
+            "Unexpected request: {}",
+            head
+        );
+        assert!(head.to_lowercase().contains("authorization: basic"));
+        assert_eq!(body, content);
+    }
+
+    #[test]
+    fn test_url() {
+        let uploader = uploader("https://dav.example.com/remote.php/".into());
+        assert_eq!(
+            uploader.url("/shop/shop (eu) é#1.gz"),
+            "https://dav.example.com/remote.php/backups/shop/shop%20(eu)%20%C3%A9%231.gz"
+        );
+
+        assert_eq!(normalize_root(""), "/");
+        assert_eq!(normalize_root("/a//b/"), "/a/b/");
+    }
+
+    #[tokio::test]
+    async fn test_failed_upload() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(serve_once(listener, "507 Insufficient Storage"));
+
+        let error = uploader(endpoint)
+            .upload("app/backup.gz", spooled(b"data"))
+            .await
+            .expect_err("The upload should fail");
+        assert!(error.to_string().contains("507"), "{}", error);
+
+        server.await.unwrap();
+    }
+}
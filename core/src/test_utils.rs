@@ -13,7 +13,10 @@ pub mod test_utils {
 
     use crate::{
         databases::{postgres::connection::PostgreSqlConnection, ConnectionType, DatabaseConfig},
-        storage::provider::{LocalStorageConfig, S3StorageConfig, StorageConfig, StorageProvider},
+        storage::provider::{
+            LocalStorageConfig, S3StorageConfig, SftpStorageConfig, StorageConfig,
+            StorageCredentials, StorageProvider,
+        },
     };
 
     pub fn initialize_test() {
@@ -71,6 +74,29 @@ pub mod test_utils {
         Ok(pool)
     }
 
+    pub async fn get_mariadb_pool() -> Result<Pool<MySql>> {
+        let port: u16 = env::var("MARIADB_PORT").unwrap_or("0".into()).parse()?;
+        let password = env::var("MARIADB_PASSWORD").unwrap_or_default();
+        let host: String = env::var("MARIADB_HOST").unwrap_or_default();
+        let username: String = env::var("MARIADB_USERNAME").unwrap_or_default();
+        let database: String = env::var("MARIADB_NAME").unwrap_or_default();
+
+        let connect_options = MySqlConnectOptions::new()
+            .host(&host)
+            .username(&username)
+            .database(&database)
+            .password(&password)
+            .port(port);
+
+        let pool = MySqlPoolOptions::new()
+            .max_connections(5)
+            .acquire_timeout(Duration::from_secs(30))
+            .connect_with(connect_options)
+            .await?;
+
+        Ok(pool)
+    }
+
     pub async fn get_postgresql_connection(admin_connection: bool) -> Result<PostgreSqlConnection> {
         initialize_test();
 
@@ -82,6 +108,7 @@ pub mod test_utils {
             connection_type: ConnectionType::PostgreSql,
             host: env::var("POSTGRESQL_HOST").unwrap_or_default(),
             password: Some(password),
+            ssh: None,
             username: env::var("POSTGRESQL_USERNAME").unwrap_or_default(),
             database: if admin_connection {
                 "postgres".into()
@@ -127,4 +154,43 @@ pub mod test_utils {
 
         Ok(provider)
     }
+
+    /// Returns `None` when `SFTP_HOST` is not set, e.g. the `sftp` service of
+    /// dev/docker-compose.yaml.
+    pub fn get_sftp_provider() -> Result<Option<StorageProvider>> {
+        let host = match env::var("SFTP_HOST") {
+            Ok(host) => host,
+            Err(_) => return Ok(None),
+        };
+
+        let username = env::var("SFTP_USERNAME").unwrap_or_default();
+        let credentials = match env::var("SFTP_KEY_PATH") {
+            Ok(key_path) => StorageCredentials::PrivateKey {
+                username,
+                key_path,
+                passphrase: env::var("SFTP_KEY_PASSPHRASE").ok(),
+            },
+            Err(_) => StorageCredentials::Basic {
+                username,
+                password: env::var("SFTP_PASSWORD").unwrap_or_default(),
+            },
+        };
+
+        let config = StorageConfig::Sftp(SftpStorageConfig {
+            id: "test".into(),
+            name: "sftp".into(),
+            host,
+            port: env::var("SFTP_PORT").unwrap_or("22".into()).parse()?,
+            credentials,
+            location: format!(
+                "{}/sftp_provider_test_{}",
+                env::var("SFTP_LOCATION").unwrap_or_else(|_| "upload".into()),
+                chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+            ),
+            // Test servers are usually not in known_hosts
+            accept_new_host_key: true,
+        });
+
+        Ok(Some(StorageProvider::new(config)?))
+    }
 }
@@ -1,14 +1,23 @@
 #[cfg(test)]
 mod vprdbbkp_tests {
     use anyhow::Result;
-    use std::env;
+    use sqlx::{sqlite::SqliteConnectOptions, ConnectOptions, Connection};
+    use std::{env, path::Path};
     use tempfile::tempdir;
 
     use crate::{
-        databases::{ConnectionType, DatabaseConfig, DatabaseConnection},
+        copy::{copy, CopyOptions},
+        databases::{
+            ssh::{SshAuth, SshConfig},
+            ConnectionType, DatabaseConfig, DatabaseConnection, DumpFilters, DumpFormat,
+            TableStats,
+        },
+        masking::MaskingConfig,
         storage::provider::{LocalStorageConfig, S3StorageConfig, StorageConfig, StorageProvider},
-        test_utils::test_utils::{get_mysql_pool, get_postgresql_pool, initialize_test},
-        DbBkp, RestoreOptions,
+        test_utils::test_utils::{
+            get_mariadb_pool, get_mysql_pool, get_postgresql_pool, initialize_test,
+        },
+        BackupOptions, DbBkp, RestoreOptions, VerifyOptions,
     };
 
     fn get_local_provider() -> Result<StorageProvider> {
@@ -60,6 +69,7 @@ mod vprdbbkp_tests {
             connection_type: ConnectionType::PostgreSql,
             host: env::var("POSTGRESQL_HOST").unwrap_or_default(),
             password: Some(password),
+            ssh: None,
             username: env::var("POSTGRESQL_USERNAME").unwrap_or_default(),
             database: env::var("POSTGRESQL_NAME").unwrap_or_default(),
             port,
@@ -80,6 +90,7 @@ mod vprdbbkp_tests {
             connection_type: ConnectionType::MySql,
             host: env::var("MYSQL_HOST").unwrap_or_default(),
             password: Some(password),
+            ssh: None,
             username: env::var("MYSQL_USERNAME").unwrap_or_default(),
             database: env::var("MYSQL_NAME").unwrap_or_default(),
             port,
@@ -88,6 +99,27 @@ mod vprdbbkp_tests {
         Ok(config)
     }
 
+    fn get_mariadb_config() -> Result<DatabaseConfig> {
+        initialize_test();
+
+        let port: u16 = env::var("MARIADB_PORT").unwrap_or("0".into()).parse()?;
+        let password = env::var("MARIADB_PASSWORD").unwrap_or_default();
+
+        let config = DatabaseConfig {
+            id: "test".to_string(),
+            name: "test".to_string(),
+            connection_type: ConnectionType::MariaDB,
+            host: env::var("MARIADB_HOST").unwrap_or_default(),
+            password: Some(password),
+            ssh: None,
+            username: env::var("MARIADB_USERNAME").unwrap_or_default(),
+            database: env::var("MARIADB_NAME").unwrap_or_default(),
+            port,
+        };
+
+        Ok(config)
+    }
+
     #[tokio::test]
     async fn test_01_postgresql_backup() {
         initialize_test();
@@ -153,6 +185,11 @@ mod vprdbbkp_tests {
                 name: backup_name,
                 compression_format: None,
                 drop_database_first: Some(true),
+                encryption: None,
+                jobs: None,
+                masking: None,
+                target: None,
+                target_database: None,
             })
             .await
             .expect("Failed to restore");
@@ -179,6 +216,104 @@ mod vprdbbkp_tests {
         assert!(test3_exists, "test3 should be restored");
     }
 
+    #[tokio::test]
+    async fn test_02_mariadb_backup() {
+        initialize_test();
+        let config = get_mariadb_config().expect("Failed to get mariadb config");
+        let db_pool = get_mariadb_pool().await.expect("Failed to get db pool");
+
+        let database_connection = DatabaseConnection::new(config.clone())
+            .await
+            .expect("Failed to get database connection");
+
+        let storage_provider = get_local_provider().expect("Failed to get local storage provider");
+
+        let engine = DbBkp::new(database_connection, storage_provider);
+
+        sqlx::query("DROP TABLE IF EXISTS backup_test_table")
+            .execute(&db_pool)
+            .await
+            .expect("Failed to drop test table");
+
+        sqlx::query(
+            "CREATE TABLE backup_test_table (id SERIAL PRIMARY KEY, name TEXT, value INTEGER)",
+        )
+        .execute(&db_pool)
+        .await
+        .expect("Failed to create test table");
+
+        sqlx::query("INSERT INTO backup_test_table (name, value) VALUES ('test1', 100), ('test2', 200), ('test3', 300)")
+        .execute(&db_pool)
+        .await
+        .expect("Failed to insert test data");
+
+        let rows: Vec<(String, i32)> =
+            sqlx::query_as("SELECT name, value FROM backup_test_table ORDER BY id")
+                .fetch_all(&db_pool)
+                .await
+                .expect("Failed to fetch test data");
+
+        assert_eq!(rows.len(), 3, "Should have 3 rows before backup");
+
+        let backup_name = engine.backup().await.expect("Failed to backup");
+
+        sqlx::query("UPDATE backup_test_table SET value = 999 WHERE name = 'test1'")
+            .execute(&db_pool)
+            .await
+            .expect("Failed to update test data");
+
+        sqlx::query("DELETE FROM backup_test_table WHERE name = 'test3'")
+            .execute(&db_pool)
+            .await
+            .expect("Failed to delete test data");
+
+        let modified_rows: Vec<(String, i32)> =
+            sqlx::query_as("SELECT name, value FROM backup_test_table ORDER BY id")
+                .fetch_all(&db_pool)
+                .await
+                .expect("Failed to fetch modified data");
+
+        assert_eq!(modified_rows.len(), 2, "Should have 2 rows after deletion");
+        assert_eq!(modified_rows[0].1, 999, "Value should be modified");
+
+        engine
+            .restore(RestoreOptions {
+                name: backup_name,
+                compression_format: None,
+                drop_database_first: Some(true),
+                encryption: None,
+                jobs: None,
+                masking: None,
+                target: None,
+                target_database: None,
+            })
+            .await
+            .expect("Failed to restore");
+
+        let db_pool = get_mariadb_pool().await.expect("Failed to get db pool");
+
+        let restored_rows: Vec<(String, i32)> =
+            sqlx::query_as("SELECT name, value FROM backup_test_table ORDER BY id")
+                .fetch_all(&db_pool)
+                .await
+                .expect("Failed to fetch restored data");
+
+        assert_eq!(restored_rows.len(), 3, "Should have 3 rows after restore");
+
+        let test1_row = restored_rows
+            .iter()
+            .find(|(name, _)| name == "test1")
+            .expect("Should have test1 row after restore");
+
+        assert_eq!(
+            test1_row.1, 100,
+            "test1 value should be restored to original"
+        );
+
+        let test3_exists = restored_rows.iter().any(|(name, _)| name == "test3");
+        assert!(test3_exists, "test3 should be restored");
+    }
+
     #[tokio::test]
     async fn test_03_mysql_backup() {
         initialize_test();
@@ -244,6 +379,11 @@ mod vprdbbkp_tests {
                 name: backup_name,
                 compression_format: None,
                 drop_database_first: Some(true),
+                encryption: None,
+                jobs: None,
+                masking: None,
+                target: None,
+                target_database: None,
             })
             .await
             .expect("Failed to restore");
@@ -271,4 +411,780 @@ mod vprdbbkp_tests {
         let test3_exists = restored_rows.iter().any(|(name, _)| name == "test3");
         assert!(test3_exists, "test3 should be restored");
     }
+
+    #[tokio::test]
+    async fn test_04_postgresql_verify() {
+        initialize_test();
+        let config = get_postgresql_config().expect("Failed to get postgresql config");
+        let db_pool = get_postgresql_pool().await.expect("Failed to get db pool");
+
+        let database_connection = DatabaseConnection::new(config.clone())
+            .await
+            .expect("Failed to get database connection");
+
+        let storage_provider = get_local_provider().expect("Failed to get local storage provider");
+
+        let engine = DbBkp::new(database_connection, storage_provider);
+
+        sqlx::query("DROP TABLE IF EXISTS verify_test_table")
+            .execute(&db_pool)
+            .await
+            .expect("Failed to drop test table");
+
+        sqlx::query("CREATE TABLE verify_test_table (id SERIAL PRIMARY KEY, name TEXT)")
+            .execute(&db_pool)
+            .await
+            .expect("Failed to create test table");
+
+        sqlx::query("INSERT INTO verify_test_table (name) VALUES ('test1'), ('test2')")
+            .execute(&db_pool)
+            .await
+            .expect("Failed to insert test data");
+
+        let backup_name = engine.backup().await.expect("Failed to backup");
+
+        let report = engine
+            .verify(VerifyOptions {
+                name: backup_name,
+                target: None,
+                encryption: None,
+            })
+            .await
+            .expect("Failed to verify");
+
+        assert!(report.is_valid(), "The restore should be complete");
+        assert!(
+            report.mismatched_tables().is_empty(),
+            "Restored row counts should match"
+        );
+        assert!(report.has_table_stats);
+
+        let table = report
+            .tables
+            .iter()
+            .find(|t| t.name == "public.verify_test_table")
+            .expect("Should have verified the test table");
+        assert_eq!(table.actual_rows, Some(2));
+
+        let scratch_exists: Option<(i32,)> =
+            sqlx::query_as("SELECT 1 FROM pg_database WHERE datname = $1")
+                .bind(&report.scratch_database)
+                .fetch_optional(&db_pool)
+                .await
+                .expect("Failed to look for scratch database");
+        assert!(
+            scratch_exists.is_none(),
+            "Scratch database should be dropped"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_05_postgresql_ssh_tunnel() {
+        initialize_test();
+
+        // Needs a bastion, e.g. the `bastion` service of dev/docker-compose.yaml
+        let ssh_host = match env::var("SSH_HOST") {
+            Ok(host) => host,
+            Err(_) => {
+                println!("SSH_HOST not set, skipping SSH tunnel test");
+                return;
+            }
+        };
+
+        let mut config = get_postgresql_config().expect("Failed to get postgresql config");
+        config.host = env::var("SSH_DATABASE_HOST").unwrap_or(config.host);
+        config.ssh = Some(SshConfig {
+            host: ssh_host,
+            port: env::var("SSH_PORT")
+                .unwrap_or("22".into())
+                .parse()
+                .expect("Invalid SSH_PORT"),
+            username: env::var("SSH_USERNAME").unwrap_or_default(),
+            auth: match env::var("SSH_KEY_PATH") {
+                Ok(key_path) => SshAuth::PrivateKey {
+                    key_path,
+                    passphrase: env::var("SSH_KEY_PASSPHRASE").ok(),
+                },
+                Err(_) => SshAuth::Agent,
+            },
+            // Test servers are usually not in known_hosts
+            accept_new_host_key: true,
+        });
+
+        let database_connection = DatabaseConnection::new(config.clone())
+            .await
+            .expect("Failed to connect through the SSH tunnel");
+
+        let engine = DbBkp::new(
+            database_connection,
+            get_local_provider().expect("Failed to get local storage provider"),
+        );
+
+        engine.test().await.expect("Connection test failed");
+
+        let backup_name = engine.backup().await.expect("Failed to backup");
+
+        engine
+            .restore(RestoreOptions {
+                name: backup_name.clone(),
+                compression_format: None,
+                drop_database_first: Some(true),
+                encryption: None,
+                jobs: None,
+                masking: None,
+                target: None,
+                target_database: None,
+            })
+            .await
+            .expect("Failed to restore");
+
+        // Other databases of the server are reached through the same tunnel
+        let target_database = format!("{}_ssh_copy", config.database);
+        engine
+            .restore(RestoreOptions {
+                name: backup_name,
+                compression_format: None,
+                drop_database_first: Some(true),
+                encryption: None,
+                jobs: None,
+                masking: None,
+                target: Some(config),
+                target_database: Some(target_database.clone()),
+            })
+            .await
+            .expect("Failed to restore into another database through the SSH tunnel");
+
+        let target_connection = engine
+            .database_connection
+            .connect_database(&target_database)
+            .await
+            .expect("Failed to connect to the target database through the SSH tunnel");
+        let restored = target_connection.connection.test().await;
+        drop(target_connection);
+
+        engine
+            .database_connection
+            .connection
+            .drop_database(&target_database)
+            .await
+            .expect("Failed to drop target database");
+        assert!(restored.expect("Failed to test the target database"));
+    }
+
+    #[tokio::test]
+    async fn test_06_sqlite_backup_and_verify() {
+        initialize_test();
+
+        let directory = tempdir().expect("Failed to create temp dir");
+        let path = directory.path().join("app.db");
+
+        let mut connection = SqliteConnectOptions::new()
+            .filename(&path)
+            .create_if_missing(true)
+            .connect()
+            .await
+            .expect("Failed to create database");
+
+        sqlx::query("CREATE TABLE verify_test_table (id INTEGER PRIMARY KEY, name TEXT)")
+            .execute(&mut connection)
+            .await
+            .expect("Failed to create test table");
+
+        sqlx::query("INSERT INTO verify_test_table (name) VALUES ('test1'), ('test2')")
+            .execute(&mut connection)
+            .await
+            .expect("Failed to insert test data");
+
+        let config = DatabaseConfig {
+            id: "test".to_string(),
+            name: "app".to_string(),
+            connection_type: ConnectionType::Sqlite,
+            host: "".into(),
+            password: None,
+            ssh: None,
+            username: "".into(),
+            database: path.to_string_lossy().to_string(),
+            port: 0,
+        };
+
+        let database_connection = DatabaseConnection::new(config)
+            .await
+            .expect("Failed to get database connection");
+
+        let storage_provider = get_local_provider().expect("Failed to get local storage provider");
+
+        let engine = DbBkp::new(database_connection, storage_provider);
+
+        let backup_name = engine.backup().await.expect("Failed to backup");
+
+        let report = engine
+            .verify(VerifyOptions {
+                name: backup_name.clone(),
+                target: None,
+                encryption: None,
+            })
+            .await
+            .expect("Failed to verify");
+
+        assert!(report.is_valid(), "The restore should succeed");
+        assert!(
+            !report.has_table_stats,
+            "Only plain SQL dumps have their rows counted"
+        );
+        assert!(
+            !Path::new(&report.scratch_database).exists(),
+            "Scratch database should be dropped"
+        );
+
+        sqlx::query("DELETE FROM verify_test_table")
+            .execute(&mut connection)
+            .await
+            .expect("Failed to delete test data");
+        connection
+            .close()
+            .await
+            .expect("Failed to close connection");
+
+        engine
+            .restore(RestoreOptions {
+                name: backup_name,
+                compression_format: None,
+                drop_database_first: Some(true),
+                encryption: None,
+                jobs: None,
+                masking: None,
+                target: None,
+                target_database: None,
+            })
+            .await
+            .expect("Failed to restore");
+
+        let mut connection = SqliteConnectOptions::new()
+            .filename(&path)
+            .connect()
+            .await
+            .expect("Failed to open restored database");
+
+        let (rows,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM verify_test_table")
+            .fetch_one(&mut connection)
+            .await
+            .expect("Failed to count restored rows");
+        assert_eq!(rows, 2, "Should have 2 rows after restore");
+    }
+
+    #[tokio::test]
+    async fn test_07_postgresql_archive_formats() {
+        initialize_test();
+        let config = get_postgresql_config().expect("Failed to get postgresql config");
+        let db_pool = get_postgresql_pool().await.expect("Failed to get db pool");
+
+        let database_connection = DatabaseConnection::new(config.clone())
+            .await
+            .expect("Failed to get database connection");
+
+        let storage_provider = get_local_provider().expect("Failed to get local storage provider");
+
+        let engine = DbBkp::new(database_connection, storage_provider);
+
+        sqlx::query("DROP TABLE IF EXISTS dump_format_test_table")
+            .execute(&db_pool)
+            .await
+            .expect("Failed to drop test table");
+
+        sqlx::query("CREATE TABLE dump_format_test_table (id SERIAL PRIMARY KEY, name TEXT)")
+            .execute(&db_pool)
+            .await
+            .expect("Failed to create test table");
+
+        sqlx::query(
+            "INSERT INTO dump_format_test_table (name) VALUES ('test1'), ('test2'), ('test3')",
+        )
+        .execute(&db_pool)
+        .await
+        .expect("Failed to insert test data");
+
+        let parallel_custom = engine
+            .backup_with(Some(BackupOptions {
+                dump_format: Some(DumpFormat::Custom),
+                jobs: Some(2),
+                ..Default::default()
+            }))
+            .await;
+        assert!(
+            parallel_custom.is_err(),
+            "Only directory dumps can run in parallel"
+        );
+
+        for (dump_format, jobs) in [(DumpFormat::Custom, None), (DumpFormat::Directory, Some(2))] {
+            let backup_name = engine
+                .backup_with(Some(BackupOptions {
+                    dump_format: Some(dump_format),
+                    jobs,
+                    ..Default::default()
+                }))
+                .await
+                .expect("Failed to backup");
+
+            sqlx::query("DELETE FROM dump_format_test_table WHERE name = 'test3'")
+                .execute(&db_pool)
+                .await
+                .expect("Failed to delete test data");
+
+            engine
+                .restore(RestoreOptions {
+                    name: backup_name,
+                    compression_format: None,
+                    drop_database_first: Some(false),
+                    encryption: None,
+                    jobs: Some(2),
+                    masking: None,
+                    target: None,
+                    target_database: None,
+                })
+                .await
+                .expect("Failed to restore");
+
+            let (rows,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM dump_format_test_table")
+                .fetch_one(&db_pool)
+                .await
+                .expect("Failed to count restored rows");
+            assert_eq!(rows, 3, "{} dump should restore 3 rows", dump_format);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_08_postgresql_backup_filters() {
+        initialize_test();
+        let config = get_postgresql_config().expect("Failed to get postgresql config");
+        let db_pool = get_postgresql_pool().await.expect("Failed to get db pool");
+
+        let database_connection = DatabaseConnection::new(config.clone())
+            .await
+            .expect("Failed to get database connection");
+
+        let storage_provider = get_local_provider().expect("Failed to get local storage provider");
+
+        let engine = DbBkp::new(database_connection, storage_provider);
+
+        for table in [
+            "filter_kept_table",
+            "filter_skipped_table",
+            "filter_empty_table",
+        ] {
+            sqlx::query(&format!("DROP TABLE IF EXISTS {}", table))
+                .execute(&db_pool)
+                .await
+                .expect("Failed to drop test table");
+
+            sqlx::query(&format!(
+                "CREATE TABLE {} (id SERIAL PRIMARY KEY, name TEXT)",
+                table
+            ))
+            .execute(&db_pool)
+            .await
+            .expect("Failed to create test table");
+
+            sqlx::query(&format!(
+                "INSERT INTO {} (name) VALUES ('test1'), ('test2')",
+                table
+            ))
+            .execute(&db_pool)
+            .await
+            .expect("Failed to insert test data");
+        }
+
+        let backup_name = engine
+            .backup_with(Some(BackupOptions {
+                filters: Some(DumpFilters {
+                    exclude_tables: vec!["filter_skipped_table".into()],
+                    exclude_table_data: vec!["filter_empty_table".into()],
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }))
+            .await
+            .expect("Failed to backup");
+
+        let report = engine
+            .verify(VerifyOptions {
+                name: backup_name.clone(),
+                target: None,
+                encryption: None,
+            })
+            .await
+            .expect("Failed to verify");
+        assert!(report.is_valid(), "Filtered backups should verify");
+        let verified: Vec<&str> = report.tables.iter().map(|t| t.name.as_str()).collect();
+        assert!(verified.contains(&"public.filter_kept_table"));
+        assert!(
+            !verified.contains(&"public.filter_skipped_table"),
+            "Only the tables of the dump should be compared"
+        );
+
+        for table in [
+            "filter_kept_table",
+            "filter_skipped_table",
+            "filter_empty_table",
+        ] {
+            sqlx::query(&format!("DROP TABLE {}", table))
+                .execute(&db_pool)
+                .await
+                .expect("Failed to drop test table");
+        }
+
+        engine
+            .restore(RestoreOptions {
+                name: backup_name,
+                compression_format: None,
+                drop_database_first: Some(false),
+                encryption: None,
+                jobs: None,
+                masking: None,
+                target: None,
+                target_database: None,
+            })
+            .await
+            .expect("Failed to restore");
+
+        let (rows,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM filter_kept_table")
+            .fetch_one(&db_pool)
+            .await
+            .expect("Failed to count restored rows");
+        assert_eq!(rows, 2, "Kept table should be restored with its rows");
+
+        let (rows,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM filter_empty_table")
+            .fetch_one(&db_pool)
+            .await
+            .expect("Failed to count restored rows");
+        assert_eq!(rows, 0, "Table without data should be restored empty");
+
+        let skipped: Option<(String,)> =
+            sqlx::query_as("SELECT tablename::text FROM pg_tables WHERE tablename = $1")
+                .bind("filter_skipped_table")
+                .fetch_optional(&db_pool)
+                .await
+                .expect("Failed to look for skipped table");
+        assert!(skipped.is_none(), "Excluded table should not be restored");
+
+        let rejected = engine
+            .backup_with(Some(BackupOptions {
+                filters: Some(DumpFilters {
+                    include_tables: vec!["missing_table_*".into()],
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }))
+            .await;
+        assert!(
+            rejected.is_err(),
+            "pg_dump should fail when no table matches"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_09_postgresql_masked_backup() {
+        initialize_test();
+        let config = get_postgresql_config().expect("Failed to get postgresql config");
+        let db_pool = get_postgresql_pool().await.expect("Failed to get db pool");
+
+        let database_connection = DatabaseConnection::new(config.clone())
+            .await
+            .expect("Failed to get database connection");
+
+        let storage_provider = get_local_provider().expect("Failed to get local storage provider");
+
+        let engine = DbBkp::new(database_connection, storage_provider);
+
+        let masking: MaskingConfig = serde_json::from_str(
+            r#"{
+                "seed": "test",
+                "tables": {
+                    "masking_users": { "email": "email", "phone": "keep_format" },
+                    "masking_orders": { "user_email": "email" }
+                }
+            }"#,
+        )
+        .expect("Failed to parse masking config");
+
+        sqlx::query("DROP TABLE IF EXISTS masking_orders, masking_users")
+            .execute(&db_pool)
+            .await
+            .expect("Failed to drop test tables");
+
+        sqlx::query("CREATE TABLE masking_users (email TEXT PRIMARY KEY, phone TEXT)")
+            .execute(&db_pool)
+            .await
+            .expect("Failed to create test table");
+
+        sqlx::query(
+            "CREATE TABLE masking_orders (id SERIAL PRIMARY KEY, user_email TEXT REFERENCES masking_users (email))",
+        )
+        .execute(&db_pool)
+        .await
+        .expect("Failed to create test table");
+
+        sqlx::query(
+            "INSERT INTO masking_users VALUES ('john@corp.com', '555-0100'), ('jane@corp.com', NULL)",
+        )
+        .execute(&db_pool)
+        .await
+        .expect("Failed to insert test data");
+
+        sqlx::query(
+            "INSERT INTO masking_orders (user_email) VALUES ('john@corp.com'), ('jane@corp.com'), ('john@corp.com')",
+        )
+        .execute(&db_pool)
+        .await
+        .expect("Failed to insert test data");
+
+        let masked_backup = engine
+            .backup_with(Some(BackupOptions {
+                masking: Some(masking.clone()),
+                ..Default::default()
+            }))
+            .await
+            .expect("Failed to backup");
+
+        let plain_backup = engine.backup().await.expect("Failed to backup");
+
+        let restores = [(masked_backup, None), (plain_backup, Some(masking.clone()))];
+
+        for (backup_name, restore_masking) in restores {
+            sqlx::query("DROP TABLE IF EXISTS masking_orders, masking_users")
+                .execute(&db_pool)
+                .await
+                .expect("Failed to drop test tables");
+
+            engine
+                .restore(RestoreOptions {
+                    name: backup_name,
+                    compression_format: None,
+                    drop_database_first: Some(false),
+                    encryption: None,
+                    jobs: None,
+                    masking: restore_masking,
+                    target: None,
+                    target_database: None,
+                })
+                .await
+                .expect("Failed to restore");
+
+            let users: Vec<(String, Option<String>)> =
+                sqlx::query_as("SELECT email, phone FROM masking_users ORDER BY phone NULLS LAST")
+                    .fetch_all(&db_pool)
+                    .await
+                    .expect("Failed to read restored users");
+
+            assert_eq!(users.len(), 2);
+            assert!(users
+                .iter()
+                .all(|(email, _)| email.ends_with("@example.com")));
+            let phone = users[0]
+                .1
+                .as_deref()
+                .expect("Phone should be masked, not nulled");
+            assert_ne!(phone, "555-0100");
+            assert_eq!(phone.len(), 8);
+            assert_eq!(&phone[3..4], "-");
+            assert_eq!(users[1].1, None);
+
+            let (matching_orders,): (i64,) = sqlx::query_as(
+                "SELECT COUNT(*) FROM masking_orders o JOIN masking_users u ON u.email = o.user_email",
+            )
+            .fetch_one(&db_pool)
+            .await
+            .expect("Failed to join restored tables");
+            assert_eq!(matching_orders, 3, "Masked foreign keys should still match");
+        }
+
+        let rejected = engine
+            .backup_with(Some(BackupOptions {
+                dump_format: Some(DumpFormat::Custom),
+                masking: Some(masking),
+                ..Default::default()
+            }))
+            .await;
+        assert!(rejected.is_err(), "Archives can't be masked");
+    }
+
+    #[tokio::test]
+    async fn test_10_postgresql_restore_into_other_database() {
+        initialize_test();
+        let config = get_postgresql_config().expect("Failed to get postgresql config");
+        let db_pool = get_postgresql_pool().await.expect("Failed to get db pool");
+
+        let database_connection = DatabaseConnection::new(config.clone())
+            .await
+            .expect("Failed to get database connection");
+
+        let storage_provider = get_local_provider().expect("Failed to get local storage provider");
+
+        let engine = DbBkp::new(database_connection, storage_provider);
+
+        let target_database = format!("{}_copy", config.database);
+
+        sqlx::query("DROP TABLE IF EXISTS target_items")
+            .execute(&db_pool)
+            .await
+            .expect("Failed to drop test table");
+
+        sqlx::query("CREATE TABLE target_items (id SERIAL PRIMARY KEY, name TEXT)")
+            .execute(&db_pool)
+            .await
+            .expect("Failed to create test table");
+
+        sqlx::query("INSERT INTO target_items (name) VALUES ('one'), ('two')")
+            .execute(&db_pool)
+            .await
+            .expect("Failed to insert test data");
+
+        let backup_name = engine.backup().await.expect("Failed to backup");
+
+        engine
+            .database_connection
+            .connection
+            .drop_database(&target_database)
+            .await
+            .expect("Failed to drop target database");
+
+        // The first restore creates the database, the second one restores over it
+        for drop_database_first in [false, true] {
+            engine
+                .restore(RestoreOptions {
+                    name: backup_name.clone(),
+                    compression_format: None,
+                    drop_database_first: Some(drop_database_first),
+                    encryption: None,
+                    jobs: None,
+                    masking: None,
+                    target: Some(config.clone()),
+                    target_database: Some(target_database.clone()),
+                })
+                .await
+                .expect("Failed to restore into the target database");
+        }
+
+        let mut target_config = config.clone();
+        target_config.database = target_database.clone();
+        let target_connection = DatabaseConnection::new(target_config)
+            .await
+            .expect("Failed to connect to the target database");
+
+        let stats = target_connection
+            .connection
+            .get_table_stats()
+            .await
+            .expect("Failed to get table stats");
+        target_connection
+            .connection
+            .drop_database(&target_database)
+            .await
+            .expect("Failed to drop target database");
+
+        assert!(stats.contains(&TableStats {
+            name: "public.target_items".into(),
+            rows: 2,
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_11_postgresql_copy() {
+        initialize_test();
+        let config = get_postgresql_config().expect("Failed to get postgresql config");
+        let db_pool = get_postgresql_pool().await.expect("Failed to get db pool");
+
+        let database_connection = DatabaseConnection::new(config.clone())
+            .await
+            .expect("Failed to get database connection");
+
+        let storage_provider = get_local_provider().expect("Failed to get local storage provider");
+
+        let engine = DbBkp::new(database_connection.clone(), storage_provider);
+
+        let target_database = format!("{}_staging", config.database);
+
+        sqlx::query("DROP TABLE IF EXISTS copy_items, copy_audit")
+            .execute(&db_pool)
+            .await
+            .expect("Failed to drop test tables");
+
+        sqlx::query("CREATE TABLE copy_items (id SERIAL PRIMARY KEY, name TEXT)")
+            .execute(&db_pool)
+            .await
+            .expect("Failed to create test table");
+
+        sqlx::query("CREATE TABLE copy_audit (id SERIAL PRIMARY KEY)")
+            .execute(&db_pool)
+            .await
+            .expect("Failed to create test table");
+
+        sqlx::query("INSERT INTO copy_items (name) VALUES ('one'), ('two'), ('three')")
+            .execute(&db_pool)
+            .await
+            .expect("Failed to insert test data");
+
+        database_connection
+            .connection
+            .drop_database(&target_database)
+            .await
+            .expect("Failed to drop target database");
+
+        let options = CopyOptions {
+            target_database: Some(target_database.clone()),
+            drop_database_first: Some(true),
+            filters: Some(DumpFilters {
+                exclude_tables: vec!["copy_audit".into()],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        copy(&database_connection, options.clone())
+            .await
+            .expect("Failed to copy");
+
+        let backup_name = engine
+            .copy(options, None)
+            .await
+            .expect("Failed to copy and store the backup");
+
+        let manifest = engine
+            .storage_provider
+            .read_manifest(&backup_name)
+            .await
+            .expect("Failed to read manifest")
+            .expect("The stored copy should have a manifest");
+        assert_eq!(manifest.filters.exclude_tables, vec!["copy_audit"]);
+
+        let target_connection = database_connection
+            .connect_database(&target_database)
+            .await
+            .expect("Failed to connect to the target database");
+
+        let stats = target_connection
+            .connection
+            .get_table_stats()
+            .await
+            .expect("Failed to get table stats");
+        database_connection
+            .connection
+            .drop_database(&target_database)
+            .await
+            .expect("Failed to drop target database");
+
+        assert!(stats.contains(&TableStats {
+            name: "public.copy_items".into(),
+            rows: 3,
+        }));
+        assert!(
+            !stats.iter().any(|table| table.name == "public.copy_audit"),
+            "Excluded tables shouldn't be copied"
+        );
+
+        let same_database = copy(&database_connection, CopyOptions::default()).await;
+        assert!(
+            same_database.is_err(),
+            "A database can't be copied into itself"
+        );
+    }
 }
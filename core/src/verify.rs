@@ -0,0 +1,346 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+};
+
+use uuid::Uuid;
+
+use crate::{
+    checksum::ChecksumVerification,
+    databases::TableStats,
+    masking::sql::{parse_copy, parse_insert_header, SqlDialect, StatementScanner},
+};
+
+/// Longest identifier accepted by both PostgreSQL (63) and MySQL (64).
+const MAX_DATABASE_NAME_LENGTH: usize = 63;
+
+#[derive(Debug, Clone)]
+pub struct TableVerification {
+    pub name: String,
+    pub expected_rows: Option<u64>,
+    pub actual_rows: Option<u64>,
+}
+
+impl TableVerification {
+    pub fn rows_match(&self) -> bool {
+        self.expected_rows == self.actual_rows
+    }
+
+    /// Recorded at backup time but not created by the restore.
+    pub fn is_missing(&self) -> bool {
+        self.expected_rows.is_some() && self.actual_rows.is_none()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VerifyReport {
+    pub name: String,
+    pub scratch_database: String,
+    pub checksum: Option<ChecksumVerification>,
+    /// False when no rows were counted, for backups that aren't plain SQL dumps, only the
+    /// restore itself is checked.
+    pub has_table_stats: bool,
+    pub tables: Vec<TableVerification>,
+}
+
+impl VerifyReport {
+    /// Row counts come from the dump itself, every table must be restored with all its rows.
+    pub fn is_valid(&self) -> bool {
+        let checksum_valid = self
+            .checksum
+            .as_ref()
+            .map(|checksum| checksum.is_valid())
+            .unwrap_or(true);
+
+        checksum_valid
+            && (!self.has_table_stats
+                || (self.missing_tables().is_empty() && self.mismatched_tables().is_empty()))
+    }
+
+    pub fn missing_tables(&self) -> Vec<&TableVerification> {
+        self.tables.iter().filter(|t| t.is_missing()).collect()
+    }
+
+    /// Restored tables whose row count differs from the recorded one.
+    pub fn mismatched_tables(&self) -> Vec<&TableVerification> {
+        self.tables
+            .iter()
+            .filter(|t| !t.is_missing() && !t.rows_match())
+            .collect()
+    }
+}
+
+enum CountState {
+    Sql,
+    /// Table of the `COPY` block, one row per line.
+    Copy(String),
+    Insert {
+        table: Option<String>,
+        scanner: StatementScanner,
+    },
+}
+
+/// Counts the rows of a plain dump while it's written, the lines of its `COPY` blocks and the
+/// tuples of its `INSERT` statements, so they match the snapshot the dump was taken from.
+/// Other dumps pass through without being counted.
+pub struct RowCountingWriter<W: Write> {
+    inner: W,
+    dialect: Option<SqlDialect>,
+    pending: Vec<u8>,
+    state: CountState,
+    rows: BTreeMap<String, u64>,
+}
+
+impl<W: Write> RowCountingWriter<W> {
+    pub fn new(inner: W, dialect: Option<SqlDialect>) -> Self {
+        Self {
+            inner,
+            dialect,
+            pending: vec![],
+            state: CountState::Sql,
+            rows: BTreeMap::new(),
+        }
+    }
+
+    /// Rows counted by table, empty when the dump wasn't counted.
+    pub fn finish(mut self) -> Vec<TableStats> {
+        let line = std::mem::take(&mut self.pending);
+        self.count_line(&line);
+
+        self.rows
+            .into_iter()
+            .map(|(name, rows)| TableStats { name, rows })
+            .collect()
+    }
+
+    fn count_line(&mut self, line: &[u8]) {
+        let dialect = match self.dialect {
+            Some(dialect) => dialect,
+            None => return,
+        };
+
+        match &mut self.state {
+            CountState::Sql => {}
+            CountState::Copy(table) => {
+                if line.strip_suffix(b"\n").unwrap_or(line) == b"\\." {
+                    self.state = CountState::Sql;
+                } else if let Some(rows) = self.rows.get_mut(table) {
+                    *rows += 1;
+                }
+                return;
+            }
+            CountState::Insert { table, scanner } => {
+                if scanner.feed(line, dialect) {
+                    if let Some(table) = table {
+                        *self.rows.entry(table.clone()).or_default() += scanner.groups;
+                    }
+                    self.state = CountState::Sql;
+                }
+                return;
+            }
+        }
+
+        if line.starts_with(b"COPY ") {
+            if let Some((table, _)) = parse_copy(&String::from_utf8_lossy(line)) {
+                self.rows.entry(table.clone()).or_default();
+                self.state = CountState::Copy(table);
+            }
+        } else if line.starts_with(b"INSERT INTO ") {
+            // Only the tuples after `VALUES` are counted, not the column list
+            let (table, values) = match parse_insert_header(line, dialect) {
+                Some(header) => (Some(header.table), &line[header.values_start..]),
+                None => (None, line),
+            };
+
+            let mut scanner = StatementScanner::default();
+            if scanner.feed(values, dialect) {
+                if let Some(table) = table {
+                    *self.rows.entry(table).or_default() += scanner.groups;
+                }
+            } else {
+                self.state = CountState::Insert { table, scanner };
+            }
+        }
+    }
+}
+
+impl<W: Write> Write for RowCountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write_all(buf)?;
+
+        if self.dialect.is_none() {
+            return Ok(buf.len());
+        }
+
+        let scanned = self.pending.len();
+        self.pending.extend_from_slice(buf);
+
+        if let Some(last_newline) = self.pending[scanned..].iter().rposition(|b| *b == b'\n') {
+            let lines: Vec<u8> = self.pending.drain(..scanned + last_newline + 1).collect();
+            for line in lines.split_inclusive(|b| *b == b'\n') {
+                self.count_line(line);
+            }
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Name of the throwaway database a backup is restored into, e.g. `shop_dbkp_verify_1a2b3c4d`.
+/// SQLite databases are paths, their scratch file goes in the same directory.
+pub fn scratch_database_name(database: &str) -> String {
+    let uuid_string = Uuid::new_v4().to_string();
+    let uuid = uuid_string.split('-').next().unwrap_or("verify");
+    let suffix = format!("_dbkp_verify_{}", uuid);
+
+    let (directory, database) = match database.rfind(['/', '\\']) {
+        Some(index) => database.split_at(index + 1),
+        None => ("", database),
+    };
+
+    // The limit is in bytes, a multibyte character can't be split
+    let mut prefix_length = database.len().min(MAX_DATABASE_NAME_LENGTH - suffix.len());
+    while !database.is_char_boundary(prefix_length) {
+        prefix_length -= 1;
+    }
+    let prefix = &database[..prefix_length];
+
+    format!("{}{}{}", directory, prefix, suffix)
+}
+
+pub fn compare_table_stats(
+    expected: &[TableStats],
+    actual: &[TableStats],
+) -> Vec<TableVerification> {
+    let mut tables: BTreeMap<&str, TableVerification> = BTreeMap::new();
+
+    for stats in expected {
+        tables.insert(
+            &stats.name,
+            TableVerification {
+                name: stats.name.clone(),
+                expected_rows: Some(stats.rows),
+                actual_rows: None,
+            },
+        );
+    }
+
+    // Tables without rows in the dump, like empty MySQL tables, aren't compared
+    for stats in actual {
+        if let Some(table) = tables.get_mut(stats.name.as_str()) {
+            table.actual_rows = Some(stats.rows);
+        }
+    }
+
+    tables.into_values().collect()
+}
+
+#[cfg(test)]
+mod verify_test {
+    use std::io::Write;
+
+    use crate::{databases::TableStats, masking::sql::SqlDialect};
+
+    use super::{compare_table_stats, scratch_database_name, RowCountingWriter, VerifyReport};
+
+    fn stats(name: &str, rows: u64) -> TableStats {
+        TableStats {
+            name: name.into(),
+            rows,
+        }
+    }
+
+    fn count_rows(dump: &str, dialect: SqlDialect) -> Vec<TableStats> {
+        let mut writer = RowCountingWriter::new(vec![], Some(dialect));
+        // Small writes split lines and statements like a real dump does
+        for chunk in dump.as_bytes().chunks(7) {
+            writer.write_all(chunk).expect("Failed to write");
+        }
+        writer.finish()
+    }
+
+    #[test]
+    fn compare() {
+        let expected = vec![stats("public.users", 10), stats("public.orders", 5)];
+        let actual = vec![stats("public.users", 10), stats("public.orders", 4)];
+
+        let tables = compare_table_stats(&expected, &actual);
+        let mut report = VerifyReport {
+            name: "backup".into(),
+            scratch_database: "scratch".into(),
+            checksum: None,
+            has_table_stats: true,
+            tables,
+        };
+
+        assert!(!report.is_valid(), "Lost rows are a failure");
+        assert_eq!(report.mismatched_tables().len(), 1);
+        assert_eq!(report.mismatched_tables()[0].name, "public.orders");
+
+        report.tables = compare_table_stats(&expected, &expected);
+        assert!(report.is_valid());
+        assert!(report.mismatched_tables().is_empty());
+
+        // A table missing from the restored database is a failure
+        report.tables = compare_table_stats(&expected, &actual[..1]);
+        assert!(!report.is_valid());
+        assert_eq!(report.missing_tables()[0].name, "public.orders");
+        assert!(report.mismatched_tables().is_empty());
+
+        report.tables = compare_table_stats(&expected[..1], &actual);
+        assert!(
+            report.is_valid(),
+            "Tables without rows in the dump are skipped"
+        );
+        assert_eq!(report.tables.len(), 1);
+    }
+
+    #[test]
+    fn count_dump_rows() {
+        let dump = "CREATE TABLE public.users (\n    id integer,\n    name text\n);\n\
+            COPY public.users (id, name) FROM stdin;\n\
+            1\tAlice\n\
+            2\tINSERT INTO users VALUES (3);\n\
+            \\.\n\
+            COPY public.\"Empty\" (id) FROM stdin;\n\
+            \\.\n";
+        assert_eq!(
+            count_rows(dump, SqlDialect::PostgreSql),
+            vec![stats("public.Empty", 0), stats("public.users", 2)]
+        );
+
+        let dump = "INSERT INTO `orders` VALUES (1,'a (b'),(2,'it\\'s'),(3,CONCAT('x','y'));\n\
+            INSERT INTO `orders` (`id`, `note`) VALUES\n(4,'multi\nline;'),\n(5,NULL);\n\
+            INSERT INTO `items` VALUES (1,'(');";
+        assert_eq!(
+            count_rows(dump, SqlDialect::MySql),
+            vec![stats("items", 1), stats("orders", 5)]
+        );
+
+        let mut writer = RowCountingWriter::new(vec![], None);
+        writer
+            .write_all(b"INSERT INTO t VALUES (1);\n")
+            .expect("Failed to write");
+        assert!(writer.finish().is_empty(), "Other dumps aren't counted");
+    }
+
+    #[test]
+    fn scratch_name() {
+        let name = scratch_database_name("shop");
+        assert!(name.starts_with("shop_dbkp_verify_"));
+
+        let name = scratch_database_name(&"a".repeat(100));
+        assert_eq!(name.len(), 63);
+
+        let name = scratch_database_name(&"é".repeat(40));
+        assert!(name.len() <= 63, "{} is {} bytes", name, name.len());
+        assert!(name.starts_with(&"é".repeat(20)));
+
+        let name = scratch_database_name(&format!("/srv/{}/app.db", "a".repeat(100)));
+        assert!(name.starts_with(&format!("/srv/{}/app.db_dbkp_verify_", "a".repeat(100))));
+    }
+}
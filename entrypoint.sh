@@ -13,6 +13,7 @@ show_usage() {
     echo "  backup    Perform database backup (default)"
     echo "  restore   Restore database from backup"
     echo "  list      List available backups"
+    echo "  daemon    Run the scheduled jobs of JOBS_FILE"
     echo "  version   Show dbkp version"
     echo "  help      Show this help"
     echo ""
@@ -34,6 +35,8 @@ show_usage() {
     echo "  FORMAT           Backup format [default: custom]"
     echo "  COMPRESS         Enable compression [default: true]"
     echo "  VERBOSE          Enable verbose output [default: false]"
+    echo "  JOBS_FILE        Job file of the daemon [default: /etc/dbkp/jobs.toml]"
+    echo "  STATE_FILE       Last runs of the daemon jobs [default: /backups/.dbkp/daemon_state.json]"
     echo ""
     echo "Example:"
     echo "  docker run -e DATABASE=mydb -e USERNAME=user -e PASSWORD=pass \\"
@@ -193,6 +196,22 @@ run_list() {
     eval "$cmd"
 }
 
+# Function to run the scheduler daemon
+run_daemon() {
+    local jobs_file="${JOBS_FILE:-/etc/dbkp/jobs.toml}"
+    local state_file="${STATE_FILE:-/backups/.dbkp/daemon_state.json}"
+
+    if [ ! -f "$jobs_file" ]; then
+        echo "❌ Error: Job file not found: $jobs_file"
+        exit 1
+    fi
+
+    echo "⏰ Starting dbkp daemon with $jobs_file"
+    echo ""
+
+    exec /usr/local/bin/dbkp daemon "$jobs_file" --state "$state_file"
+}
+
 # Function to show version
 show_version() {
     echo "dbkp Docker Container"
@@ -223,6 +242,9 @@ case "${1:-backup}" in
     list)
         run_list
         ;;
+    daemon)
+        run_daemon
+        ;;
     version)
         show_version
         ;;